@@ -0,0 +1,93 @@
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest};
+use rocket::serde::json::Json;
+use rocket::{Request, State};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::openapi;
+use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
+use sea_orm::{ConnectionTrait, DatabaseConnection};
+use service::audit::{AuditEntry, AuditFilter, NewAuditEntry};
+use service::error::GenericError;
+
+use crate::authenticate::UserAuthentication;
+
+/// Who made a request and from where, to log privileged actions with.
+///
+/// The action is the name of the endpoint, so handlers only give what the action was done to.
+#[derive(Debug)]
+pub struct Audit {
+    actor: Option<i32>,
+    action: String,
+    ip: Option<String>,
+}
+
+impl Audit {
+    /// Logs the action. Give it the transaction the action was done in, so it is only logged when
+    /// the action is committed.
+    pub async fn record(
+        &self,
+        db: &impl ConnectionTrait,
+        fantasy_tournament_id: Option<i32>,
+        target_user: Option<i32>,
+        summary: Option<String>,
+    ) -> Result<(), GenericError> {
+        service::audit::record(
+            db,
+            NewAuditEntry {
+                actor: self.actor,
+                action: self.action.clone(),
+                fantasy_tournament_id,
+                target_user,
+                summary,
+                ip: self.ip.clone(),
+            },
+        )
+        .await
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Audit {
+    type Error = GenericError;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let actor = match request.guard::<UserAuthentication>().await {
+            Outcome::Success(user) => user.to_user_model().ok().map(|u| u.id),
+            _ => None,
+        };
+        let action = request
+            .route()
+            .and_then(|route| route.name.as_deref())
+            .unwrap_or("unknown")
+            .to_string();
+        Outcome::Success(Self {
+            actor,
+            action,
+            ip: request.client_ip().map(|ip| ip.to_string()),
+        })
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for Audit {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+/// # Audit log
+///
+/// Privileged actions taken by admins, moderators and tournament managers, newest first. Admins only.
+#[openapi(tag = "Admin")]
+#[get("/audit-log?<filter..>")]
+pub(crate) async fn get_audit_log(
+    auth: UserAuthentication,
+    db: &State<DatabaseConnection>,
+    filter: AuditFilter,
+) -> Result<Json<Vec<AuditEntry>>, GenericError> {
+    auth.assure_admin()?;
+    service::audit::get_entries(db.inner(), filter).await.map(Json)
+}
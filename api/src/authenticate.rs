@@ -1,8 +1,10 @@
 use entity::prelude::User;
 use entity::{user, user_cookies};
+use service::roles::TournamentRole;
+use std::marker::PhantomData;
 
 use rocket::http::{CookieJar, Status};
-use rocket::outcome::{IntoOutcome, Outcome};
+use rocket::outcome::Outcome;
 use rocket::serde::json::Json;
 use rocket::{
     get,
@@ -10,11 +12,13 @@ use rocket::{
     Request, State,
 };
 
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::request::RequestHeaderInput;
 use rocket_okapi::{openapi, request::OpenApiFromRequest};
 use sea_orm::{ConnectionTrait, DatabaseConnection, EntityTrait, ModelTrait, TransactionTrait};
 
 use crate::error;
-use crate::error::{GenericError, UserError};
+use crate::error::GenericError;
 use error::AuthError;
 use sea_orm::ColumnTrait;
 use sea_orm::QueryFilter;
@@ -24,10 +28,61 @@ use user_cookies::Model as CookieModel;
 #[derive(OpenApiFromRequest, Debug)]
 pub struct UserAuthentication(Authentication);
 
+/// Requires the user to have at least the role `R` in the tournament of the route,
+/// the tournament id being the second segment of the path.
+#[derive(Debug)]
+pub struct RequireRole<R: role::RequiredRole> {
+    pub user: UserAuthentication,
+    pub role: TournamentRole,
+    required: PhantomData<R>,
+}
+
+/// Requires the tournament of the route to be readable by the user, or by anyone when it is public.
+/// The user doesn't have to be signed in.
 #[derive(OpenApiFromRequest, Debug)]
-pub struct TournamentAuthentication {
+pub struct ViewTournament {
     pub user: UserAuthentication,
-    is_owner: bool,
+    /// `None` when the user isn't a member
+    pub role: Option<TournamentRole>,
+}
+
+/// The roles `RequireRole` can require
+pub mod role {
+    use service::roles::TournamentRole;
+
+    pub trait RequiredRole: Send + Sync + 'static {
+        const ROLE: TournamentRole;
+        const DENIED: &'static str;
+    }
+
+    #[derive(Debug)]
+    pub struct Spectator;
+    #[derive(Debug)]
+    pub struct Member;
+    #[derive(Debug)]
+    pub struct CoOwner;
+    #[derive(Debug)]
+    pub struct Owner;
+
+    impl RequiredRole for Spectator {
+        const ROLE: TournamentRole = TournamentRole::Spectator;
+        const DENIED: &'static str = "You are not part of this tournament";
+    }
+
+    impl RequiredRole for Member {
+        const ROLE: TournamentRole = TournamentRole::Member;
+        const DENIED: &'static str = "Spectators can not do that";
+    }
+
+    impl RequiredRole for CoOwner {
+        const ROLE: TournamentRole = TournamentRole::CoOwner;
+        const DENIED: &'static str = "Only the owner and co-owners of this tournament can do that";
+    }
+
+    impl RequiredRole for Owner {
+        const ROLE: TournamentRole = TournamentRole::Owner;
+        const DENIED: &'static str = "You are not the owner of this tournament";
+    }
 }
 
 #[derive(OpenApiFromRequest, Debug)]
@@ -101,13 +156,6 @@ enum Authentication {
 }
 
 impl Authentication {
-    pub fn is_authenticated(&self, user_id: i32) -> bool {
-        match self {
-            Self::Authenticated { user, .. } => user.admin || user.id == user_id,
-            _ => false,
-        }
-    }
-
     pub fn is_admin(&self) -> bool {
         match self {
             Self::Authenticated { user, .. } => user.admin,
@@ -116,52 +164,44 @@ impl Authentication {
     }
 }
 
-impl TournamentAuthentication {
-    async fn new(
-        user_auth: UserAuthentication,
-        db: &DatabaseConnection,
-        tournament_id: i32,
-    ) -> Result<Self, GenericError> {
-        Ok(Self {
-            is_owner: Self::get_internal_authentication(&user_auth, db, tournament_id).await?,
-            user: user_auth,
-        })
-    }
+#[rocket::async_trait]
+impl<'r, R: role::RequiredRole> FromRequest<'r> for RequireRole<R> {
+    type Error = GenericError;
 
-    pub fn assure_ownership(&self) -> Result<(), GenericError> {
-        if self.is_owner || self.user.0.is_admin() {
-            Ok(())
-        } else {
-            Err(AuthError::Invalid("You are not the owner of this tournament").into())
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let db = request
+            .rocket()
+            .state::<DatabaseConnection>()
+            .expect("Database not found");
+        let user = match request.guard::<UserAuthentication>().await {
+            Outcome::Success(user) => user,
+            _ => return Outcome::Error((Status::Unauthorized, AuthError::Missing("No cookie found").into())),
+        };
+        let user_model = match user.to_user_model() {
+            Ok(user) => user,
+            Err(e) => return Outcome::Error((Status::Unauthorized, e)),
+        };
+        let Some(Ok(tournament_id)) = request.param::<i32>(1) else {
+            return Outcome::Error((
+                Status::BadRequest,
+                AuthError::Invalid("Invalid tournament id").into(),
+            ));
+        };
+        match service::roles::role_in_tournament(db, user_model, tournament_id).await {
+            Ok(Some(role)) if role >= R::ROLE => Outcome::Success(Self {
+                user,
+                role,
+                required: PhantomData,
+            }),
+            Ok(_) => Outcome::Error((Status::Forbidden, GenericError::NotPermitted(R::DENIED))),
+            Err(e @ GenericError::NotFound(_)) => Outcome::Error((Status::NotFound, e)),
+            Err(e) => Outcome::Error((Status::InternalServerError, e)),
         }
     }
-
-    pub async fn is_authenticated(&self) -> bool {
-        self.user.0.is_admin() || matches!(self.user.0, Authentication::Authenticated { .. })
-    }
-
-    async fn get_internal_authentication(
-        user: &UserAuthentication,
-        db: &DatabaseConnection,
-        tournament_id: i32,
-    ) -> Result<bool, GenericError> {
-        Ok(user.0.is_admin()
-            | user
-                .0
-                .is_authenticated(Self::get_owner_id(db, tournament_id).await?))
-    }
-
-    async fn get_owner_id(db: &DatabaseConnection, tournament_id: i32) -> Result<i32, GenericError> {
-        entity::fantasy_tournament::Entity::find_by_id(tournament_id)
-            .one(db)
-            .await
-            .map_err(|_| GenericError::UnknownError("Unable to find tournament by id"))?
-            .map(|c| c.owner)
-            .ok_or(UserError::InvalidUserId("User not found").into())
-    }
 }
+
 #[rocket::async_trait]
-impl<'r> FromRequest<'r> for TournamentAuthentication {
+impl<'r> FromRequest<'r> for ViewTournament {
     type Error = GenericError;
 
     async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
@@ -169,26 +209,39 @@ impl<'r> FromRequest<'r> for TournamentAuthentication {
             .rocket()
             .state::<DatabaseConnection>()
             .expect("Database not found");
-        let cookie: request::Outcome<UserAuthentication, _> = request.guard::<UserAuthentication>().await;
-
-        if let Some(cookie) = cookie.succeeded() {
-            if let Some(Ok(t_id)) = request.param::<i32>(1) {
-                match TournamentAuthentication::new(cookie, db, t_id).await {
-                    Ok(success) => Outcome::Success(success),
-                    Err(e) => Outcome::Error((Status::BadRequest, e)),
-                }
-            } else {
-                None.or_error((
-                    Status::BadRequest,
-                    AuthError::Invalid("Invalid tournament id").into(),
-                ))
+        let user = match request.guard::<UserAuthentication>().await {
+            Outcome::Success(user) => user,
+            _ => return Outcome::Error((Status::Unauthorized, AuthError::Missing("No cookie found").into())),
+        };
+        let Some(Ok(tournament_id)) = request.param::<i32>(1) else {
+            return Outcome::Error((
+                Status::BadRequest,
+                AuthError::Invalid("Invalid tournament id").into(),
+            ));
+        };
+        let user_model = user.to_user_model().ok();
+        match service::visibility::assure_can_view(db, user_model, tournament_id).await {
+            Ok(role) => Outcome::Success(Self { user, role }),
+            Err(e @ GenericError::NotPermitted(_)) if user_model.is_none() => {
+                Outcome::Error((Status::Unauthorized, e))
             }
-        } else {
-            None.or_error((Status::Unauthorized, AuthError::Missing("No cookie found").into()))
+            Err(e @ GenericError::NotPermitted(_)) => Outcome::Error((Status::Forbidden, e)),
+            Err(e @ GenericError::NotFound(_)) => Outcome::Error((Status::NotFound, e)),
+            Err(e) => Outcome::Error((Status::InternalServerError, e)),
         }
     }
 }
 
+impl<'r, R: role::RequiredRole> OpenApiFromRequest<'r> for RequireRole<R> {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
 impl UserAuthentication {
     fn new_invalid_cookie() -> Self {
         Self(Authentication::InvalidCookie)
@@ -254,7 +307,7 @@ impl UserAuthentication {
         }
     }
 
-    fn remove_from_jar(cookies: &CookieJar<'_>) {
+    pub(crate) fn remove_from_jar(cookies: &CookieJar<'_>) {
         cookies.remove_private("auth");
     }
 
@@ -0,0 +1,131 @@
+use rocket::futures::stream;
+use rocket::http::ContentType;
+use rocket::response::stream::ByteStream;
+use rocket::response::{self, Responder};
+use rocket::{Request, State};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::{MediaType, RefOr, Response as OpenApiResponse, Responses};
+use rocket_okapi::okapi::schemars::Map;
+use rocket_okapi::openapi;
+use rocket_okapi::response::OpenApiResponderInner;
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use service::error::GenericError;
+use service::export::ExportFormat;
+
+use crate::authenticate;
+
+/// League data streamed as JSON or CSV, downloaded as a file
+pub(crate) struct Export {
+    format: ExportFormat,
+    filename: String,
+    chunks: Vec<Vec<u8>>,
+}
+
+impl Export {
+    fn new<T: Serialize>(
+        rows: Vec<T>,
+        format: ExportFormat,
+        name: &str,
+        tournament_id: i32,
+    ) -> Result<Self, GenericError> {
+        Ok(Self {
+            format,
+            filename: format!("tournament-{tournament_id}-{name}.{}", format.extension()),
+            chunks: service::export::encode(&rows, format)?,
+        })
+    }
+}
+
+impl<'r> Responder<'r, 'r> for Export {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'r> {
+        let mut response = ByteStream::from(stream::iter(self.chunks)).respond_to(request)?;
+        response.set_header(match self.format {
+            ExportFormat::Json => ContentType::JSON,
+            ExportFormat::Csv => ContentType::CSV,
+        });
+        response.set_raw_header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", self.filename),
+        );
+        Ok(response)
+    }
+}
+
+impl OpenApiResponderInner for Export {
+    fn responses(_: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut content = Map::new();
+        content.insert("application/json".to_string(), MediaType::default());
+        content.insert("text/csv".to_string(), MediaType::default());
+        let mut responses = Responses::default();
+        responses.responses.insert(
+            "200".to_string(),
+            RefOr::Object(OpenApiResponse {
+                description: "The export, as JSON or CSV depending on `format`".to_string(),
+                content,
+                ..Default::default()
+            }),
+        );
+        Ok(responses)
+    }
+}
+
+/// # Export standings
+///
+/// The total score and rank of every user in the tournament.
+#[openapi(tag = "Export")]
+#[get("/fantasy-tournament/<tournament_id>/export/standings?<format>")]
+pub(crate) async fn export_standings(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+    format: Option<ExportFormat>,
+) -> Result<Export, GenericError> {
+    let db = db.inner();
+    let rows = service::export::standings(db, tournament_id).await?;
+    Export::new(rows, format.unwrap_or_default(), "standings", tournament_id)
+}
+
+/// # Export competition scores
+///
+/// The points every pick earned each user in each competition.
+#[openapi(tag = "Export")]
+#[get("/fantasy-tournament/<tournament_id>/export/scores?<format>")]
+pub(crate) async fn export_scores(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+    format: Option<ExportFormat>,
+) -> Result<Export, GenericError> {
+    let db = db.inner();
+    let rows = service::export::competition_scores(db, tournament_id).await?;
+    Export::new(rows, format.unwrap_or_default(), "scores", tournament_id)
+}
+
+/// # Export rosters
+#[openapi(tag = "Export")]
+#[get("/fantasy-tournament/<tournament_id>/export/rosters?<format>")]
+pub(crate) async fn export_rosters(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+    format: Option<ExportFormat>,
+) -> Result<Export, GenericError> {
+    let db = db.inner();
+    let rows = service::export::rosters(db, tournament_id).await?;
+    Export::new(rows, format.unwrap_or_default(), "rosters", tournament_id)
+}
+
+/// # Export the trade log
+#[openapi(tag = "Export")]
+#[get("/fantasy-tournament/<tournament_id>/export/trades?<format>")]
+pub(crate) async fn export_trades(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+    format: Option<ExportFormat>,
+) -> Result<Export, GenericError> {
+    let db = db.inner();
+    let rows = service::export::trades(db, tournament_id).await?;
+    Export::new(rows, format.unwrap_or_default(), "trades", tournament_id)
+}
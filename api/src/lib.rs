@@ -13,18 +13,27 @@ use rocket_okapi::settings::UrlObject;
 use rocket_okapi::swagger_ui::{make_swagger_ui, SwaggerUIConfig};
 use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 
+use audit::*;
 use authenticate::*;
+use exports::*;
+use monitoring::*;
 use mutation::*;
 use query::*;
 use service::*;
 
+pub mod audit;
 pub mod authenticate;
+pub mod exports;
+pub mod monitoring;
 pub mod mutation;
 pub mod query;
 pub mod utils;
 
 pub mod endpoints {
+    pub use super::audit;
     pub use super::authenticate;
+    pub use super::exports;
+    pub use super::monitoring;
     pub use super::mutation;
     pub use super::query;
     pub use super::utils;
@@ -77,9 +86,72 @@ pub fn routes() -> Vec<Route> {
         get_competitions,
         proxy_image,
         get_competition_scores,
+        get_competition,
+        get_competition_leaderboard,
+        get_round_scorecard,
+        get_projections,
+        simulate_lineup,
+        get_captains,
+        set_captain,
+        remove_captain,
+        get_substitutions,
+        get_roster_rules,
+        get_roster_violations,
+        get_player_prices,
+        get_performance_index,
+        search_players,
+        get_competition_prices,
+        get_budget,
+        set_player_price,
+        clear_player_price,
+        add_placeholder,
+        remove_placeholder,
+        link_placeholder,
+        get_placeholders,
+        import_schedule,
+        backup_tournament,
+        restore_tournament,
+        export_my_account,
+        delete_my_account,
+        export_standings,
+        export_scores,
+        export_rosters,
+        export_trades,
         change_passsword,
         get_exchange_window,
         get_trade_log,
+        revert_trade,
+        revert_trades_after,
+        update_tournament_settings,
+        archive_tournament,
+        unarchive_tournament,
+        delete_tournament,
+        remove_competition,
+        kick_user,
+        leave_tournament,
+        create_invite_code,
+        delete_invite_code,
+        get_invite_codes,
+        join_with_code,
+        request_to_join,
+        get_join_requests,
+        answer_join_request,
+        get_tournament_members,
+        get_public_tournament,
+        set_member_role,
+        set_moderator,
+        get_audit_log,
+        see_invitations,
+        get_notifications,
+        mark_notification_read,
+        mark_all_notifications_read,
+        create_webhook,
+        delete_webhook,
+        test_webhook,
+        get_webhooks,
+        get_webhook_deliveries,
+        health_check,
+        readiness_check,
         get_all_picks
     ]
 }
@@ -120,7 +192,9 @@ pub async fn launch() -> Rocket<Build> {
                 .to_cors()
                 .unwrap(),
         )
+        .attach(RequestMetrics)
         .register("/api", catchers![general_not_found])
+        .mount("/", rocket::routes![prometheus_metrics])
         .mount("/", FileServer::from(flutter_path))
 
     //.configure(conf)
@@ -0,0 +1,124 @@
+use std::time::Instant;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Status};
+use rocket::request::{self, FromRequest};
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::{Data, Request, Response, State};
+use rocket_okapi::openapi;
+use sea_orm::DatabaseConnection;
+use service::error::GenericError;
+use service::health::HealthStatus;
+
+use crate::authenticate::UserAuthentication;
+
+/// Counts and times every handled request, labelled by the route that handled it.
+pub struct RequestMetrics;
+
+struct RequestStart(Option<Instant>);
+
+#[rocket::async_trait]
+impl Fairing for RequestMetrics {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _: &mut Data<'_>) {
+        req.local_cache(|| RequestStart(Some(Instant::now())));
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let Some(start) = req.local_cache(|| RequestStart(None)).0 else {
+            return;
+        };
+        let route = req
+            .route()
+            .map(|r| r.uri.to_string())
+            .unwrap_or_else(|| "unmatched".to_string());
+        service::metrics::observe_request(
+            req.method().as_str(),
+            &route,
+            res.status().code,
+            start.elapsed().as_secs_f64(),
+        );
+    }
+}
+
+fn with_status(health: HealthStatus) -> status::Custom<Json<HealthStatus>> {
+    let status = if health.ok {
+        Status::Ok
+    } else {
+        Status::ServiceUnavailable
+    };
+    status::Custom(status, Json(health))
+}
+
+/// # Check that the server and database are up
+///
+/// Responds with 503 if the database can't be reached.
+#[openapi(tag = "Monitoring")]
+#[get("/health")]
+pub(crate) async fn health_check(db: &State<DatabaseConnection>) -> status::Custom<Json<HealthStatus>> {
+    with_status(service::health::check_health(db.inner()).await)
+}
+
+/// # Check that the server is ready to serve up-to-date scores
+///
+/// Responds with 503 if the database can't be reached or the score refresh loop has stalled.
+#[openapi(tag = "Monitoring")]
+#[get("/ready")]
+pub(crate) async fn readiness_check(db: &State<DatabaseConnection>) -> status::Custom<Json<HealthStatus>> {
+    with_status(service::health::check_readiness(db.inner()).await)
+}
+
+/// Whether the request carries the bearer token set in `METRICS_TOKEN`, for scrapers that can't log in.
+pub(crate) struct ScraperToken(bool);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ScraperToken {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let configured = std::env::var("METRICS_TOKEN").ok().filter(|t| !t.is_empty());
+        let given = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|h| h.strip_prefix("Bearer "));
+        let valid = match (configured, given) {
+            // Compared in full, so the time taken doesn't tell how much of the token was right
+            (Some(configured), Some(given)) => {
+                configured.len() == given.len()
+                    && configured
+                        .bytes()
+                        .zip(given.bytes())
+                        .fold(0, |diff, (a, b)| diff | (a ^ b))
+                        == 0
+            }
+            _ => false,
+        };
+        request::Outcome::Success(Self(valid))
+    }
+}
+
+/// Metrics in the Prometheus text format, mounted outside of `/api`.
+///
+/// Needs the bearer token in `METRICS_TOKEN`, or an admin to be logged in.
+#[get("/metrics")]
+pub(crate) async fn prometheus_metrics(
+    token: ScraperToken,
+    auth: UserAuthentication,
+    db: &State<DatabaseConnection>,
+) -> Result<(ContentType, String), GenericError> {
+    if !token.0 {
+        auth.assure_admin()?;
+    }
+    let body = service::metrics::render(db.inner()).await?;
+    Ok((
+        ContentType::new("text", "plain").with_params(("version", "0.0.4")),
+        body,
+    ))
+}
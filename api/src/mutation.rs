@@ -1,15 +1,19 @@
 use rocket::http::CookieJar;
-use rocket::serde::json::Json;
+use rocket::serde::json::{json, Json};
 use rocket::State;
 use rocket_okapi::openapi;
 use sea_orm::DatabaseConnection;
 use sea_orm::TransactionTrait;
 
 use error::GenericError;
+use service::audit::summarize;
 use service::dto::{forms, FantasyPick, FantasyPicks, UserLogin};
+use service::roles::TournamentRole;
 use service::update_password;
 
+use crate::audit::Audit;
 use crate::authenticate;
+use crate::authenticate::role;
 use crate::authenticate::AllowedToExchangeGuard;
 use crate::error;
 use crate::error::UserError;
@@ -81,52 +85,74 @@ pub async fn create_user(
 /// # Returns
 ///
 /// A string indicating success
+///
+/// Co-owners and moderators can change the picks of other users, outside of exchange windows.
 #[openapi(tag = "Fantasy Tournament")]
 #[allow(clippy::too_many_arguments)]
 #[put("/fantasy-tournament/<fantasy_tournament_id>/user/<user_id>/picks/div/<division>/<slot>/<pdga_number>")]
 pub(crate) async fn add_pick(
-    user: authenticate::UserAuthentication,
+    auth: authenticate::RequireRole<role::Member>,
     exchange: AllowedToExchangeGuard,
     db: &State<DatabaseConnection>,
+    audit: Audit,
     user_id: i32,
     fantasy_tournament_id: i32,
     slot: i32,
     pdga_number: i32,
     division: service::dto::Division,
 ) -> Result<&'static str, GenericError> {
-    let not_permitted = UserError::NotPermitted("You are not permitted to add picks for this user");
-    if !exchange.is_allowed() {
-        Err(GenericError::NotPermitted(
-            "You are not allowed to exchange picks at this time",
-        ))?
-    }
     let db = db.inner();
-    let user = user.to_user_model()?;
-    if service::check_if_user_in_tournament(db, user.id, fantasy_tournament_id)
-        .await
-        .unwrap_or(false)
-    {
-        if user.id != user_id {
-            return Err(not_permitted.into());
-        }
-        let pick = FantasyPick {
-            slot,
-            pdga_number,
-            name: None,
-            benched: false,
-        };
+    assure_allowed_to_pick(&auth, &exchange, db, user_id, fantasy_tournament_id).await?;
+    let pick = FantasyPick {
+        slot,
+        pdga_number,
+        name: None,
+        benched: false,
+    };
 
-        let txn = db
-            .begin()
-            .await
-            .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
-        pick.change_or_insert(&txn, user.id, fantasy_tournament_id, division)
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    FantasyPick::change_or_insert_all(&[pick], &txn, user_id, fantasy_tournament_id, division).await?;
+    if auth.user.to_user_model()?.id != user_id {
+        let summary = summarize(&json!({ "division": division, "slot": slot, "pdga_number": pdga_number }));
+        audit
+            .record(&txn, Some(fantasy_tournament_id), Some(user_id), summary)
             .await?;
-        txn.commit()
-            .await
-            .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    }
+    service::roster_rules::validate_roster(&txn, user_id, fantasy_tournament_id).await?;
+    service::notifications::notify_trade(&txn, fantasy_tournament_id, user_id).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+
+    Ok("Successfully added pick")
+}
 
-        Ok("Successfully added pick")
+/// Users pick for themselves during exchange windows, co-owners can fix the picks of anyone
+/// picking in the tournament at any time.
+async fn assure_allowed_to_pick<R: role::RequiredRole>(
+    auth: &authenticate::RequireRole<R>,
+    exchange: &AllowedToExchangeGuard,
+    db: &DatabaseConnection,
+    user_id: i32,
+    fantasy_tournament_id: i32,
+) -> Result<(), GenericError> {
+    let not_permitted = UserError::NotPermitted("You are not permitted to add picks for this user");
+    let picker = service::roles::participant_role(db, fantasy_tournament_id, user_id).await?;
+    if picker < Some(TournamentRole::Member) {
+        return Err(not_permitted.into());
+    }
+    if auth.user.to_user_model()?.id == user_id {
+        if !exchange.is_allowed() {
+            Err(GenericError::NotPermitted(
+                "You are not allowed to exchange picks at this time",
+            ))?
+        }
+        Ok(())
+    } else if auth.role >= TournamentRole::CoOwner {
+        Ok(())
     } else {
         Err(not_permitted.into())
     }
@@ -138,31 +164,35 @@ pub(crate) async fn add_pick(
     format = "json",
     data = "<json_picks>"
 )]
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn add_picks(
-    user: authenticate::UserAuthentication,
+    auth: authenticate::RequireRole<role::Member>,
     exchange: AllowedToExchangeGuard,
     db: &State<DatabaseConnection>,
+    audit: Audit,
     user_id: i32,
     fantasy_tournament_id: i32,
     json_picks: Json<Vec<FantasyPick>>,
     division: service::dto::Division,
 ) -> Result<String, GenericError> {
     let db = db.inner();
-    let user = user.to_user_model()?;
-    if user.id != user_id && !user.admin {
-        return Err(UserError::NotPermitted("You are not permitted to add picks for this user").into());
+    let user = auth.user.to_user_model()?;
+    let fixing_other_user = user.id != user_id;
+    if fixing_other_user {
+        assure_allowed_to_pick(&auth, &exchange, db, user_id, fantasy_tournament_id).await?;
     }
 
     let current_picks: FantasyPicks = service::query::get_user_picks_in_tournament_division(
         db,
-        user.id,
-        user.id,
+        Some(user_id),
+        user_id,
         fantasy_tournament_id,
         &division,
     )
     .await?;
 
     let picks = json_picks.into_inner();
+    let summary = fixing_other_user.then(|| summarize(&picks)).flatten();
     let all_picks_match = picks.iter().all(|p| {
         current_picks
             .picks
@@ -172,13 +202,17 @@ pub(crate) async fn add_picks(
 
     let move_allowed = exchange.is_move_allowed(db, fantasy_tournament_id).await && all_picks_match;
 
-    if exchange.is_allowed() || move_allowed {
+    if fixing_other_user || exchange.is_allowed() || move_allowed {
         let txn = db
             .begin()
             .await
             .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
-        for pick in picks {
-            pick.change_or_insert(&txn, user.id, fantasy_tournament_id, division)
+        FantasyPick::change_or_insert_all(&picks, &txn, user_id, fantasy_tournament_id, division).await?;
+        service::roster_rules::validate_roster(&txn, user_id, fantasy_tournament_id).await?;
+        service::notifications::notify_trade(&txn, fantasy_tournament_id, user_id).await?;
+        if fixing_other_user {
+            audit
+                .record(&txn, Some(fantasy_tournament_id), Some(user_id), summary)
                 .await?;
         }
         txn.commit()
@@ -195,16 +229,25 @@ pub(crate) async fn add_picks(
 #[openapi(tag = "Fantasy Tournament")]
 #[post("/fantasy-tournament/<fantasy_tournament_id>/invite/<invited_user>")]
 pub(crate) async fn invite_user(
-    auth: authenticate::TournamentAuthentication,
+    _auth: authenticate::RequireRole<role::CoOwner>,
     db: &State<DatabaseConnection>,
+    audit: Audit,
     fantasy_tournament_id: i32,
     invited_user: String,
 ) -> Result<String, GenericError> {
-    auth.assure_ownership()?;
-    match service::create_invite(db, invited_user, fantasy_tournament_id).await {
-        Ok(_) => Ok("Successfully invited user".to_string()),
-        Err(e) => Err(e.into()),
-    }
+    let summary = summarize(&json!({ "invited_user": invited_user }));
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::create_invite(&txn, invited_user, fantasy_tournament_id).await?;
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully invited user".to_string())
 }
 
 #[openapi(tag = "Fantasy Tournament")]
@@ -216,7 +259,7 @@ pub(crate) async fn answer_invite(
     accepted: bool,
 ) -> Result<String, GenericError> {
     let user = user.to_user_model()?;
-    match service::answer_invite(db, user, fantasy_tournament_id, accepted).await {
+    match service::answer_invite(db.inner(), user, fantasy_tournament_id, accepted).await {
         Ok(()) => Ok("Successfully answered invite".to_string()),
         Err(e) => Err(e.into()),
     }
@@ -227,12 +270,12 @@ pub(crate) async fn answer_invite(
     data = "<competition>"
 )]
 pub(crate) async fn add_competition(
-    auth: authenticate::TournamentAuthentication,
+    _auth: authenticate::RequireRole<role::CoOwner>,
     db: &State<DatabaseConnection>,
+    audit: Audit,
     fantasy_tournament_id: u32,
     competition: Json<forms::AddCompetition>,
 ) -> Result<String, GenericError> {
-    auth.assure_ownership()?;
     let db = db.inner();
     let txn = db.begin().await.map_err(|_| {
         GenericError::UnknownError("internal error, please try again or contact support if problem persists")
@@ -244,12 +287,852 @@ pub(crate) async fn add_competition(
         competition.level.clone(),
     )
     .await?;
+    audit
+        .record(
+            &txn,
+            Some(fantasy_tournament_id as i32),
+            None,
+            summarize(&*competition),
+        )
+        .await?;
     txn.commit()
         .await
         .map_err(|_| GenericError::UnknownError("Unknown error while trying to commit transaction"))?;
     Ok("Successfully added competition".to_string())
 }
 
+/// # Add a placeholder competition
+///
+/// Schedules a competition that PDGA hasn't published yet. It is replaced with the PDGA event
+/// once an admin links it, or once a stored competition matches its name and date.
+#[openapi(tag = "Fantasy Tournament")]
+#[post(
+    "/fantasy-tournament/<fantasy_tournament_id>/placeholder",
+    format = "json",
+    data = "<placeholder>"
+)]
+pub(crate) async fn add_placeholder(
+    _auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    placeholder: Json<forms::AddPlaceholder>,
+) -> Result<Json<i32>, GenericError> {
+    let placeholder = placeholder.into_inner();
+    let summary = summarize(&placeholder);
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    let id = service::placeholders::add_placeholder(
+        &txn,
+        fantasy_tournament_id,
+        &placeholder.name,
+        placeholder.date,
+        placeholder.level,
+    )
+    .await?;
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok(Json(id))
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[delete("/fantasy-tournament/<fantasy_tournament_id>/placeholder/<placeholder_id>")]
+pub(crate) async fn remove_placeholder(
+    _auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    placeholder_id: i32,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::placeholders::remove_placeholder(&txn, fantasy_tournament_id, placeholder_id).await?;
+    let summary = summarize(&json!({ "placeholder_id": placeholder_id }));
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully removed placeholder".to_string())
+}
+
+/// # Link a placeholder to its PDGA event
+///
+/// Adds the PDGA event to every tournament the placeholder is scheduled in.
+#[openapi(tag = "Admin")]
+#[put("/placeholder/<placeholder_id>/link/<competition_id>")]
+pub(crate) async fn link_placeholder(
+    auth: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    placeholder_id: i32,
+    competition_id: i32,
+) -> Result<String, GenericError> {
+    auth.assure_admin()?;
+    let fetched = service::placeholders::fetch_unstored_competition(db.inner(), competition_id).await?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::placeholders::link_placeholder(&txn, placeholder_id, competition_id, fetched).await?;
+    let summary = summarize(&json!({ "placeholder_id": placeholder_id, "competition_id": competition_id }));
+    audit.record(&txn, None, None, summary).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully linked placeholder".to_string())
+}
+
+/// # Import a season schedule
+///
+/// Adds a list of PDGA events and placeholders to the tournament, as JSON or CSV. Every row is
+/// checked and fetched from PDGA first, nothing is added unless all rows are valid.
+#[openapi(tag = "Fantasy Tournament")]
+#[post(
+    "/fantasy-tournament/<fantasy_tournament_id>/schedule/import?<format>",
+    data = "<schedule>"
+)]
+pub(crate) async fn import_schedule(
+    _auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    format: Option<service::schedule_import::ScheduleFormat>,
+    schedule: String,
+) -> Result<Json<service::schedule_import::ImportReport>, GenericError> {
+    let rows = service::schedule_import::parse_schedule(&schedule, format.unwrap_or_default())?;
+    let prepared =
+        service::schedule_import::prepare_schedule(db.inner(), fantasy_tournament_id, rows).await?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    let report = service::schedule_import::import_schedule(&txn, prepared).await?;
+    if report.imported {
+        audit
+            .record(&txn, Some(fantasy_tournament_id), None, summarize(&report))
+            .await?;
+    }
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok(Json(report))
+}
+
+/// # Restore a tournament
+///
+/// Creates a new tournament from a backup archive and returns its id. Users are matched by name,
+/// the ones that don't exist here are created without a password until an admin sets one. Admins only.
+#[openapi(tag = "Admin")]
+#[post("/fantasy-tournament/restore?<name>", data = "<archive>")]
+pub(crate) async fn restore_tournament(
+    auth: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    name: Option<String>,
+    archive: String,
+) -> Result<Json<i32>, GenericError> {
+    auth.assure_admin()?;
+    let archive = service::backup::parse_archive(&archive)?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    let tournament_id = service::backup::restore_tournament(&txn, archive, name).await?;
+    audit.record(&txn, Some(tournament_id), None, None).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok(Json(tournament_id))
+}
+
+/// # Change tournament settings
+///
+/// Refuses changes that existing picks don't fit, unless `trim_excess` is set.
+#[openapi(tag = "Fantasy Tournament")]
+#[put(
+    "/fantasy-tournament/<fantasy_tournament_id>/settings",
+    format = "json",
+    data = "<settings>"
+)]
+pub(crate) async fn update_tournament_settings(
+    _auth: authenticate::RequireRole<role::Owner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    settings: Json<forms::UpdateTournamentSettings>,
+) -> Result<String, GenericError> {
+    let summary = summarize(&*settings);
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::tournament_management::update_settings(&txn, fantasy_tournament_id, settings.into_inner())
+        .await?;
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully updated tournament settings".to_string())
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[post("/fantasy-tournament/<fantasy_tournament_id>/archive")]
+pub(crate) async fn archive_tournament(
+    _auth: authenticate::RequireRole<role::Owner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::tournament_management::set_archived(&txn, fantasy_tournament_id, true).await?;
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, None)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully archived tournament".to_string())
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[post("/fantasy-tournament/<fantasy_tournament_id>/unarchive")]
+pub(crate) async fn unarchive_tournament(
+    _auth: authenticate::RequireRole<role::Owner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::tournament_management::set_archived(&txn, fantasy_tournament_id, false).await?;
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, None)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully unarchived tournament".to_string())
+}
+
+/// # Delete a tournament
+///
+/// Deletes the tournament along with all picks, scores, trades and participants in it.
+#[openapi(tag = "Fantasy Tournament")]
+#[delete("/fantasy-tournament/<fantasy_tournament_id>")]
+pub(crate) async fn delete_tournament(
+    _auth: authenticate::RequireRole<role::Owner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::tournament_management::delete_tournament(&txn, fantasy_tournament_id).await?;
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, None)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully deleted tournament".to_string())
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[delete("/fantasy-tournament/<fantasy_tournament_id>/competition/<competition_id>")]
+pub(crate) async fn remove_competition(
+    _auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    competition_id: i32,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::tournament_management::remove_competition(&txn, fantasy_tournament_id, competition_id).await?;
+    let summary = summarize(&json!({ "competition_id": competition_id }));
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully removed competition".to_string())
+}
+
+/// # Kick a user from a tournament
+///
+/// Removes the user's picks and scores, their trades are kept in the trade log.
+/// Only users with a lower role than yours can be removed.
+#[openapi(tag = "Fantasy Tournament")]
+#[delete("/fantasy-tournament/<fantasy_tournament_id>/user/<user_id>")]
+pub(crate) async fn kick_user(
+    auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    user_id: i32,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::tournament_management::kick_user(&txn, fantasy_tournament_id, auth.role, user_id).await?;
+    audit
+        .record(&txn, Some(fantasy_tournament_id), Some(user_id), None)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully removed user from tournament".to_string())
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[post("/fantasy-tournament/<fantasy_tournament_id>/leave")]
+pub(crate) async fn leave_tournament(
+    user: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    fantasy_tournament_id: i32,
+) -> Result<String, GenericError> {
+    let user = user.to_user_model()?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::tournament_management::remove_user(&txn, fantasy_tournament_id, user.id).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully left tournament".to_string())
+}
+
+/// # Create an invite code
+///
+/// Anyone with the code can join the tournament through `/join/<code>`,
+/// until it expires or runs out of uses.
+#[openapi(tag = "Fantasy Tournament")]
+#[post(
+    "/fantasy-tournament/<fantasy_tournament_id>/invite-code",
+    format = "json",
+    data = "<options>"
+)]
+pub(crate) async fn create_invite_code(
+    auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    options: Json<forms::CreateInviteCode>,
+) -> Result<Json<service::invites::InviteCode>, GenericError> {
+    let user = auth.user.to_user_model()?;
+    let options = options.into_inner();
+    let summary = summarize(&options);
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    let code = service::invites::create_invite_code(&txn, fantasy_tournament_id, user.id, options).await?;
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok(Json(code))
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[delete("/fantasy-tournament/<fantasy_tournament_id>/invite-code/<code>")]
+pub(crate) async fn delete_invite_code(
+    _auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    code: String,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::invites::delete_invite_code(&txn, fantasy_tournament_id, &code).await?;
+    let summary = summarize(&json!({ "code": code }));
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully deleted invite code".to_string())
+}
+
+/// # Join a tournament with an invite code
+///
+/// # Returns
+///
+/// The id of the joined tournament
+#[openapi(tag = "Fantasy Tournament")]
+#[post("/join/<code>")]
+pub(crate) async fn join_with_code(
+    user: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    code: String,
+) -> Result<Json<i32>, GenericError> {
+    let user = user.to_user_model()?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    let tournament_id = service::invites::join_with_code(&txn, &code, user.id).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok(Json(tournament_id))
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[post("/fantasy-tournament/<fantasy_tournament_id>/request-join")]
+pub(crate) async fn request_to_join(
+    user: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    fantasy_tournament_id: i32,
+) -> Result<String, GenericError> {
+    let user = user.to_user_model()?;
+    service::invites::request_to_join(db.inner(), fantasy_tournament_id, user.id).await?;
+    Ok("Successfully asked to join tournament".to_string())
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[post("/fantasy-tournament/<fantasy_tournament_id>/join-request/<user_id>/<accepted>")]
+pub(crate) async fn answer_join_request(
+    _auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    user_id: i32,
+    accepted: bool,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::invites::answer_join_request(&txn, fantasy_tournament_id, user_id, accepted).await?;
+    let summary = summarize(&json!({ "accepted": accepted }));
+    audit
+        .record(&txn, Some(fantasy_tournament_id), Some(user_id), summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully answered join request".to_string())
+}
+
+/// # Change the role of a member
+///
+/// Giving someone the `Owner` role hands the tournament over to them,
+/// and the current owner becomes a co-owner.
+#[openapi(tag = "Fantasy Tournament")]
+#[put("/fantasy-tournament/<fantasy_tournament_id>/user/<user_id>/role/<new_role>")]
+pub(crate) async fn set_member_role(
+    _auth: authenticate::RequireRole<role::Owner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    user_id: i32,
+    new_role: TournamentRole,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::roles::set_role(&txn, fantasy_tournament_id, user_id, new_role).await?;
+    let summary = summarize(&json!({ "role": new_role }));
+    audit
+        .record(&txn, Some(fantasy_tournament_id), Some(user_id), summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully changed role".to_string())
+}
+
+/// # Make a user a moderator
+///
+/// Moderators have the powers of a co-owner in every tournament. Admins only.
+#[openapi(tag = "Admin")]
+#[put("/user/<user_id>/moderator/<moderator>")]
+pub(crate) async fn set_moderator(
+    auth: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    user_id: i32,
+    moderator: bool,
+) -> Result<String, GenericError> {
+    auth.assure_admin()?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::roles::set_moderator(&txn, user_id, moderator).await?;
+    let summary = summarize(&json!({ "moderator": moderator }));
+    audit.record(&txn, None, Some(user_id), summary).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully changed moderator status".to_string())
+}
+
+/// # Revert a pick change
+///
+/// Rebuilds the roster of the user that made the change as if it never happened.
+/// Compensating entries are added to the trade log, so the history is kept intact.
+#[openapi(tag = "Pick Exchange")]
+#[post("/fantasy-tournament/<fantasy_tournament_id>/trade-log/<trade_id>/revert")]
+pub(crate) async fn revert_trade(
+    auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    trade_id: i32,
+) -> Result<String, GenericError> {
+    let reverted_by = auth.user.to_user_model()?.id;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::trade_rollback::revert_trade(&txn, fantasy_tournament_id, trade_id, reverted_by).await?;
+    let summary = summarize(&json!({ "trade_id": trade_id }));
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully reverted trade".to_string())
+}
+
+/// # Revert all pick changes of a user after a point in time
+#[openapi(tag = "Pick Exchange")]
+#[post(
+    "/fantasy-tournament/<fantasy_tournament_id>/user/<user_id>/trade-log/revert",
+    format = "json",
+    data = "<revert>"
+)]
+pub(crate) async fn revert_trades_after(
+    auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    user_id: i32,
+    revert: Json<forms::RevertTradesAfter>,
+) -> Result<String, GenericError> {
+    let reverted_by = auth.user.to_user_model()?.id;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    let reverted = service::trade_rollback::revert_trades_after(
+        &txn,
+        fantasy_tournament_id,
+        user_id,
+        revert.after,
+        reverted_by,
+    )
+    .await?;
+    audit
+        .record(
+            &txn,
+            Some(fantasy_tournament_id),
+            Some(user_id),
+            summarize(&*revert),
+        )
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok(format!("Successfully reverted {reverted} trades"))
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[post(
+    "/fantasy-tournament/<fantasy_tournament_id>/webhooks",
+    format = "json",
+    data = "<webhook>"
+)]
+pub(crate) async fn create_webhook(
+    auth: authenticate::RequireRole<role::Owner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    webhook: Json<forms::CreateWebhook>,
+) -> Result<Json<service::webhooks::Webhook>, GenericError> {
+    let user = auth.user.to_user_model()?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    let webhook =
+        service::webhooks::create_webhook(&txn, fantasy_tournament_id, user.id, webhook.into_inner()).await?;
+    // The url and secret of a webhook are credentials, so only its id is logged
+    let summary = summarize(&json!({ "webhook_id": webhook.id }));
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok(Json(webhook))
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[delete("/fantasy-tournament/<fantasy_tournament_id>/webhook/<webhook_id>")]
+pub(crate) async fn delete_webhook(
+    _auth: authenticate::RequireRole<role::Owner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    webhook_id: i32,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::webhooks::delete_webhook(&txn, fantasy_tournament_id, webhook_id).await?;
+    let summary = summarize(&json!({ "webhook_id": webhook_id }));
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully deleted webhook".to_string())
+}
+
+/// # Send a test event to a webhook
+///
+/// Queues a `ping` event, it is delivered with the next batch of webhook deliveries.
+#[openapi(tag = "Fantasy Tournament")]
+#[post("/fantasy-tournament/<fantasy_tournament_id>/webhook/<webhook_id>/test")]
+pub(crate) async fn test_webhook(
+    _auth: authenticate::RequireRole<role::Owner>,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    fantasy_tournament_id: i32,
+    webhook_id: i32,
+) -> Result<String, GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::webhooks::send_test_event(&txn, fantasy_tournament_id, webhook_id).await?;
+    let summary = summarize(&json!({ "webhook_id": webhook_id }));
+    audit
+        .record(&txn, Some(fantasy_tournament_id), None, summary)
+        .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully queued test event".to_string())
+}
+
+#[openapi(tag = "User")]
+#[post("/notifications/<notification_id>/read")]
+pub(crate) async fn mark_notification_read(
+    user: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    notification_id: i32,
+) -> Result<String, GenericError> {
+    let user = user.to_user_model()?;
+    service::notifications::mark_read(db.inner(), user.id, Some(notification_id)).await?;
+    Ok("Successfully marked notification as read".to_string())
+}
+
+#[openapi(tag = "User")]
+#[post("/notifications/read-all")]
+pub(crate) async fn mark_all_notifications_read(
+    user: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+) -> Result<String, GenericError> {
+    let user = user.to_user_model()?;
+    service::notifications::mark_read(db.inner(), user.id, None).await?;
+    Ok("Successfully marked notifications as read".to_string())
+}
+
+/// # Set a captain
+///
+/// Designates the captain, and optionally the vice-captain, of the user's picks in a division
+/// for a competition. Captains are locked once the competition has started.
+#[openapi(tag = "Fantasy Tournament")]
+#[put(
+    "/fantasy-tournament/<fantasy_tournament_id>/competition/<competition_id>/captain",
+    format = "json",
+    data = "<captain>"
+)]
+pub(crate) async fn set_captain(
+    auth: authenticate::RequireRole<role::Member>,
+    db: &State<DatabaseConnection>,
+    fantasy_tournament_id: i32,
+    competition_id: i32,
+    captain: Json<forms::SetCaptain>,
+) -> Result<String, GenericError> {
+    let db = db.inner();
+    let user = auth.user.to_user_model()?;
+    let captain = captain.into_inner();
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::captains::set_captain(
+        &txn,
+        user.id,
+        fantasy_tournament_id,
+        competition_id,
+        captain.division,
+        captain.captain,
+        captain.vice_captain,
+    )
+    .await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully set captain".to_string())
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[delete("/fantasy-tournament/<fantasy_tournament_id>/competition/<competition_id>/captain/div/<division>")]
+pub(crate) async fn remove_captain(
+    auth: authenticate::RequireRole<role::Member>,
+    db: &State<DatabaseConnection>,
+    fantasy_tournament_id: i32,
+    competition_id: i32,
+    division: service::dto::Division,
+) -> Result<String, GenericError> {
+    let user = auth.user.to_user_model()?;
+    service::captains::remove_captain(
+        db.inner(),
+        user.id,
+        fantasy_tournament_id,
+        competition_id,
+        division,
+    )
+    .await?;
+    Ok("Successfully removed captain".to_string())
+}
+
+/// # Override the price of a player
+///
+/// Sets the price of a player in a competition by hand, it is no longer recomputed between events.
+#[openapi(tag = "Admin")]
+#[put(
+    "/competition/<competition_id>/player/<pdga_number>/price",
+    format = "json",
+    data = "<price>"
+)]
+pub(crate) async fn set_player_price(
+    auth: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    competition_id: i32,
+    pdga_number: i32,
+    price: Json<forms::SetPlayerPrice>,
+) -> Result<String, GenericError> {
+    auth.assure_admin()?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::salary_cap::override_price(&txn, competition_id, pdga_number, Some(price.price)).await?;
+    let summary = summarize(
+        &json!({ "competition_id": competition_id, "pdga_number": pdga_number, "price": Some(price.price) }),
+    );
+    audit.record(&txn, None, None, summary).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully set price".to_string())
+}
+
+/// # Clear a price override
+///
+/// Goes back to the price derived from rating and recent results.
+#[openapi(tag = "Admin")]
+#[delete("/competition/<competition_id>/player/<pdga_number>/price")]
+pub(crate) async fn clear_player_price(
+    auth: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    competition_id: i32,
+    pdga_number: i32,
+) -> Result<String, GenericError> {
+    auth.assure_admin()?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::salary_cap::override_price(&txn, competition_id, pdga_number, None).await?;
+    let summary = summarize(&json!({ "competition_id": competition_id, "pdga_number": pdga_number }));
+    audit.record(&txn, None, None, summary).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok("Successfully cleared price override".to_string())
+}
+
+/// # Delete your account
+///
+/// Anonymises the account by default, which keeps your picks, trades and scores in league history
+/// under a new name. Tournaments you own are handed over to another participant, or deleted when
+/// nobody else is in them.
+#[openapi(tag = "User")]
+#[delete("/my-account", format = "json", data = "<deletion>")]
+pub(crate) async fn delete_my_account(
+    user: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    cookies: &CookieJar<'_>,
+    deletion: Json<forms::DeleteAccount>,
+) -> Result<&'static str, GenericError> {
+    let user = user.to_user_model()?;
+    let deletion = deletion.into_inner();
+    let confirmed = service::query::authenticate(
+        db.inner(),
+        user.name.clone(),
+        service::query::Auth::Password(deletion.password),
+    )
+    .await
+    .map_err(|_| GenericError::UnknownError("Unable to check password"))?;
+    if !confirmed {
+        Err(GenericError::NotPermitted("Wrong password"))?
+    }
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    service::account::delete_account(&txn, user.id, deletion.mode, deletion.new_owner).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    authenticate::UserAuthentication::remove_from_jar(cookies);
+    Ok("Account deleted")
+}
+
 #[openapi(tag = "Admin")]
 #[post("/user/<user_id>/change_password", data = "<password>")]
 pub(crate) async fn change_passsword(
@@ -257,8 +1140,18 @@ pub(crate) async fn change_passsword(
     password: String,
     auth: authenticate::UserAuthentication,
     db: &State<DatabaseConnection>,
+    audit: Audit,
 ) -> Result<&'static str, Json<GenericError>> {
     auth.assure_admin()?;
-    update_password(db, user_id, password).await?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    update_password(&txn, user_id, password).await?;
+    // The password itself is never logged
+    audit.record(&txn, None, Some(user_id), None).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
     Ok("Success!")
 }
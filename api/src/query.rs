@@ -1,4 +1,6 @@
+use crate::audit::Audit;
 use crate::authenticate;
+use crate::authenticate::role;
 use crate::error::UserError;
 use crate::error::{GenericError, TournamentError};
 use dto::{FantasyPick, FantasyPicks};
@@ -7,14 +9,43 @@ use rocket::fs::NamedFile;
 use rocket::serde::json::Json;
 use rocket::State;
 use rocket_okapi::openapi;
-use sea_orm::DatabaseConnection;
+use sea_orm::{DatabaseConnection, TransactionTrait};
 use service::dto::Division;
 use service::dto::UserDataCombination;
+use service::roles::TournamentRole;
 use service::{dto, make_dto_user_attribute, SimpleFantasyTournament};
 use std::collections::HashMap;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/my-invitations")]
+pub(crate) async fn see_invitations(
+    db: &State<DatabaseConnection>,
+    user: authenticate::UserAuthentication,
+) -> Result<Json<Vec<SimpleFantasyTournament>>, GenericError> {
+    let user_model = user.to_user_model()?;
+    service::get_pending_invitations(db.inner(), user_model.id)
+        .await
+        .map(Json)
+}
+
+/// # See your notifications
+///
+/// Returns the latest notifications, newest first.
+#[openapi(tag = "User")]
+#[get("/notifications?<unread_only>")]
+pub(crate) async fn get_notifications(
+    db: &State<DatabaseConnection>,
+    user: authenticate::UserAuthentication,
+    unread_only: Option<bool>,
+) -> Result<Json<Vec<service::notifications::Notification>>, GenericError> {
+    let user_model = user.to_user_model()?;
+    service::notifications::get_notifications(db.inner(), user_model.id, unread_only.unwrap_or(false))
+        .await
+        .map(Json)
+}
+
 #[openapi(tag = "Fantasy Tournament")]
 #[get("/my-tournaments")]
 pub(crate) async fn see_tournaments(
@@ -32,18 +63,15 @@ pub(crate) async fn see_tournaments(
 #[get("/fantasy-tournament/<id>")]
 pub(crate) async fn get_tournament(
     db: &State<DatabaseConnection>,
-    auth: authenticate::UserAuthentication,
+    viewer: authenticate::ViewTournament,
     id: i32,
 ) -> Result<Json<SimpleFantasyTournament>, GenericError> {
-    if let Ok(model) = auth.to_user_model() {
-        if model.admin {
-            return match service::get_fantasy_tournament(db.inner(), id, Some(model.id)).await {
-                Ok(Some(tournament)) => Ok(Json(tournament)),
-                Ok(None) | Err(_) => Err(TournamentError::NotFound("Tournament not found").into()),
-            };
-        }
-    }
-    match service::get_fantasy_tournament(db.inner(), id, None).await {
+    // Admins manage the tournament as if they owned it
+    let acting_owner = match (viewer.user.to_user_model(), viewer.role) {
+        (Ok(user), Some(TournamentRole::Owner)) => Some(user.id),
+        _ => None,
+    };
+    match service::get_fantasy_tournament(db.inner(), id, acting_owner).await {
         Ok(Some(tournament)) => Ok(Json(tournament)),
         Ok(None) | Err(_) => Err(TournamentError::NotFound("Tournament not found").into()),
     }
@@ -54,6 +82,7 @@ make_dto_user_attribute!(Score, i32);
 #[openapi(tag = "Fantasy Tournament")]
 #[get("/fantasy-tournament/<id>/users")]
 pub(crate) async fn see_participants(
+    _viewer: authenticate::ViewTournament,
     db: &State<DatabaseConnection>,
     id: i32,
 ) -> Result<Json<Vec<UserDataCombination<AttributeScore>>>, GenericError> {
@@ -75,41 +104,31 @@ pub(crate) async fn see_participants(
 #[get("/fantasy-tournament/<tournament_id>/user/<user_id>/picks/div/<division>/pick/<pick_slot>")]
 pub(crate) async fn get_user_pick(
     db: &State<DatabaseConnection>,
-    requester: authenticate::UserAuthentication,
+    _viewer: authenticate::ViewTournament,
     tournament_id: i32,
     user_id: i32,
     pick_slot: i32,
     division: Division,
 ) -> Result<Json<FantasyPick>, GenericError> {
-    if requester.to_user_model()?.id != user_id {
-        Err(UserError::NotPermitted("You are not permitted to view this pick").into())
-    } else {
-        match service::get_user_pick_in_tournament(
-            db.inner(),
-            user_id,
-            tournament_id,
-            pick_slot,
-            division.into(),
-        )
+    match service::get_user_pick_in_tournament(db.inner(), user_id, tournament_id, pick_slot, division.into())
         .await
-        {
-            Ok(pick) => Ok(Json(pick)),
-            Err(_) => Err(GenericError::NotFound("Pick not found")),
-        }
+    {
+        Ok(pick) => Ok(Json(pick)),
+        Err(_) => Err(GenericError::NotFound("Pick not found")),
     }
 }
 #[openapi(tag = "Fantasy Tournament")]
 #[get("/fantasy-tournament/<tournament_id>/user/<user_id>/picks/div/<division>")]
 pub(crate) async fn get_user_picks(
     db: &State<DatabaseConnection>,
-    requester: authenticate::UserAuthentication,
+    viewer: authenticate::ViewTournament,
     tournament_id: i32,
     user_id: i32,
     division: dto::Division,
 ) -> Result<Json<FantasyPicks>, GenericError> {
     let res = service::get_user_picks_in_tournament_division(
         db.inner(),
-        requester.to_user_model()?.id,
+        viewer.user.to_user_model().ok().map(|u| u.id),
         user_id,
         tournament_id,
         &division,
@@ -126,6 +145,7 @@ make_dto_user_attribute!(FantasyPick, Vec<FantasyPick>);
 #[openapi(tag = "Fantasy Tournament")]
 #[get("/fantasy-tournament/<tournament_id>/picks")]
 pub(crate) async fn get_all_picks(
+    _viewer: authenticate::ViewTournament,
     db: &State<DatabaseConnection>,
     tournament_id: i32,
 ) -> Result<Json<Vec<UserDataCombination<AttributeFantasyPick>>>, GenericError> {
@@ -155,6 +175,7 @@ pub(crate) async fn get_all_picks(
 #[openapi(tag = "Fantasy Tournament")]
 #[get("/fantasy-tournament/<tournament_id>/divisions")]
 pub(crate) async fn get_divisions(
+    _viewer: authenticate::ViewTournament,
     db: &State<DatabaseConnection>,
     tournament_id: i32,
 ) -> Result<Json<Vec<dto::Division>>, GenericError> {
@@ -170,9 +191,24 @@ pub(crate) async fn get_my_id(user: authenticate::UserAuthentication) -> Result<
     Ok(Json(user.to_user_model()?.id))
 }
 
+/// # Export your data
+///
+/// Everything stored about your account: profile, tournaments, picks, trades, scores and sessions.
+#[openapi(tag = "User")]
+#[get("/my-account/export")]
+pub(crate) async fn export_my_account(
+    user: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+) -> Result<Json<service::account::AccountExport>, GenericError> {
+    service::account::export_account(db.inner(), user.to_user_model()?.id)
+        .await
+        .map(Json)
+}
+
 #[openapi(tag = "Fantasy Tournament")]
 #[get("/fantasy-tournament/<tournament_id>/max-picks")]
 pub(crate) async fn get_max_picks(
+    _viewer: authenticate::ViewTournament,
     db: &State<DatabaseConnection>,
     tournament_id: i32,
 ) -> Result<Json<i32>, GenericError> {
@@ -185,6 +221,7 @@ pub(crate) async fn get_max_picks(
 #[openapi(tag = "Fantasy Tournament")]
 #[get("/fantasy-tournament/<tournament_id>/competitions")]
 pub(crate) async fn get_competitions(
+    _viewer: authenticate::ViewTournament,
     db: &State<DatabaseConnection>,
     tournament_id: i32,
 ) -> Result<Json<Vec<dto::Competition>>, GenericError> {
@@ -235,6 +272,7 @@ pub(crate) async fn proxy_image(
 #[openapi(tag = "Fantasy Tournament")]
 #[get("/fantasy-tournament/<tournament_id>/competition/<competition_id>/scores")]
 pub(crate) async fn get_competition_scores(
+    _viewer: authenticate::ViewTournament,
     db: &State<DatabaseConnection>,
     tournament_id: i32,
     competition_id: i32,
@@ -244,9 +282,282 @@ pub(crate) async fn get_competition_scores(
         .map(Json)
 }
 
+/// # Competition details
+///
+/// Status, dates, rounds and divisions of a competition.
+#[openapi(tag = "Competition")]
+#[get("/competition/<competition_id>")]
+pub(crate) async fn get_competition(
+    db: &State<DatabaseConnection>,
+    competition_id: i32,
+) -> Result<Json<dto::CompetitionDetails>, GenericError> {
+    dto::CompetitionDetails::from_db(db.inner(), competition_id)
+        .await
+        .map(Json)
+}
+
+/// # Competition leaderboard
+///
+/// Placements, throws per round and score relative to par. Give a fantasy tournament
+/// to see which of its users have each player on their roster.
+#[openapi(tag = "Competition")]
+#[get("/competition/<competition_id>/leaderboard?<division>&<fantasy_tournament_id>")]
+pub(crate) async fn get_competition_leaderboard(
+    user: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    competition_id: i32,
+    division: Option<Division>,
+    fantasy_tournament_id: Option<i32>,
+) -> Result<Json<dto::Leaderboard>, GenericError> {
+    if let Some(tournament_id) = fantasy_tournament_id {
+        service::visibility::assure_can_view(db.inner(), user.to_user_model().ok(), tournament_id).await?;
+    }
+    dto::Leaderboard::from_db(db.inner(), competition_id, division, fantasy_tournament_id)
+        .await
+        .map(Json)
+}
+
+#[openapi(tag = "Competition")]
+#[get("/competition/<competition_id>/round/<round>/scorecard")]
+pub(crate) async fn get_round_scorecard(
+    db: &State<DatabaseConnection>,
+    competition_id: i32,
+    round: i32,
+) -> Result<Json<dto::Scorecard>, GenericError> {
+    dto::Scorecard::from_db(db.inner(), competition_id, round)
+        .await
+        .map(Json)
+}
+
+/// # Projected standings
+///
+/// Expected final scores and their likely range, based on the current placements in
+/// running competitions and how much each player's placement tends to move per round.
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/projections")]
+pub(crate) async fn get_projections(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+) -> Result<Json<service::projections::TournamentProjection>, GenericError> {
+    service::projections::project_tournament(db.inner(), tournament_id)
+        .await
+        .map(Json)
+}
+
+/// # Simulate a lineup
+///
+/// Scores a hypothetical set of picks in a competition with the tournament's bench rules and captains,
+/// and the best total the same players could have reached. Nothing is saved.
+#[openapi(tag = "Fantasy Tournament")]
+#[post(
+    "/fantasy-tournament/<tournament_id>/competition/<competition_id>/simulate",
+    format = "json",
+    data = "<lineup>"
+)]
+pub(crate) async fn simulate_lineup(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+    competition_id: i32,
+    lineup: Json<dto::forms::SimulateLineup>,
+) -> Result<Json<service::simulation::LineupSimulation>, GenericError> {
+    let lineup = lineup.into_inner();
+    service::simulation::simulate_lineup(
+        db.inner(),
+        tournament_id,
+        competition_id,
+        lineup.picks,
+        lineup.captains,
+    )
+    .await
+    .map(Json)
+}
+
+/// # Captains in a competition
+///
+/// The captains of every user in the tournament, along with the multiplier and whether they are locked.
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/competition/<competition_id>/captains")]
+pub(crate) async fn get_captains(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+    competition_id: i32,
+) -> Result<Json<service::captains::Captains>, GenericError> {
+    service::captains::get_captains(db.inner(), tournament_id, competition_id)
+        .await
+        .map(Json)
+}
+
+/// # Placeholder competitions
+///
+/// Competitions scheduled before PDGA published them, along with the event each was linked to.
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/placeholders")]
+pub(crate) async fn get_placeholders(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+) -> Result<Json<Vec<service::placeholders::Placeholder>>, GenericError> {
+    service::placeholders::get_placeholders(db.inner(), tournament_id)
+        .await
+        .map(Json)
+}
+
+/// # Back up a tournament
+///
+/// A versioned archive of the tournament with its participants, competitions, scores, picks and trades,
+/// which can be restored on this or another server. Admins only.
+#[openapi(tag = "Admin")]
+#[get("/fantasy-tournament/<tournament_id>/backup")]
+pub(crate) async fn backup_tournament(
+    auth: authenticate::UserAuthentication,
+    db: &State<DatabaseConnection>,
+    audit: Audit,
+    tournament_id: i32,
+) -> Result<Json<service::backup::TournamentArchive>, GenericError> {
+    auth.assure_admin()?;
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    let archive = service::backup::backup_tournament(&txn, tournament_id).await?;
+    audit.record(&txn, Some(tournament_id), None, None).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    Ok(Json(archive))
+}
+
+/// # Substitutions in a competition
+///
+/// Bench players that were automatically substituted in for starters that didn't start.
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/competition/<competition_id>/substitutions")]
+pub(crate) async fn get_substitutions(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+    competition_id: i32,
+) -> Result<Json<Vec<service::substitutions::Substitution>>, GenericError> {
+    service::substitutions::get_substitutions(db.inner(), tournament_id, competition_id)
+        .await
+        .map(Json)
+}
+
+/// # Roster rules
+///
+/// Division quotas and per-player limits that every roster in the tournament has to follow.
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/roster-rules")]
+pub(crate) async fn get_roster_rules(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+) -> Result<Json<service::roster_rules::RosterRules>, GenericError> {
+    service::roster_rules::get_roster_rules(db.inner(), tournament_id)
+        .await
+        .map(Json)
+}
+
+/// # Roster violations
+///
+/// The rules a user's roster currently breaks, counting unfilled FPO slots against the minimum.
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/user/<user_id>/roster-violations")]
+pub(crate) async fn get_roster_violations(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<Json<Vec<service::roster_rules::RosterViolation>>, GenericError> {
+    service::roster_rules::roster_violations(db.inner(), user_id, tournament_id, false)
+        .await
+        .map(Json)
+}
+
+/// # Price history of a player
+///
+/// The price of a player in every competition it was set for, newest competition first.
+#[openapi(tag = "Player")]
+#[get("/player/<pdga_number>/prices")]
+pub(crate) async fn get_player_prices(
+    db: &State<DatabaseConnection>,
+    pdga_number: i32,
+) -> Result<Json<Vec<service::salary_cap::PlayerPrice>>, GenericError> {
+    service::salary_cap::price_history(db.inner(), pdga_number)
+        .await
+        .map(Json)
+}
+
+/// # Performance index of a player
+///
+/// The rolling performance index of a player after each finished competition, newest first.
+#[openapi(tag = "Player")]
+#[get("/player/<pdga_number>/performance-index")]
+pub(crate) async fn get_performance_index(
+    db: &State<DatabaseConnection>,
+    pdga_number: i32,
+) -> Result<Json<Vec<service::performance_index::PerformanceIndex>>, GenericError> {
+    service::performance_index::performance_history(db.inner(), pdga_number)
+        .await
+        .map(Json)
+}
+
+/// # Search players
+///
+/// Players whose name contains all the words in `name`, sorted by name, rating or performance index.
+#[openapi(tag = "Player")]
+#[get("/players?<name>&<sort>&<limit>")]
+pub(crate) async fn search_players(
+    db: &State<DatabaseConnection>,
+    name: Option<String>,
+    sort: Option<service::performance_index::PlayerSort>,
+    limit: Option<u64>,
+) -> Result<Json<Vec<service::performance_index::PlayerSearchResult>>, GenericError> {
+    service::performance_index::search_players(
+        db.inner(),
+        name.as_deref(),
+        sort.unwrap_or_default(),
+        limit.unwrap_or(50).min(500),
+    )
+    .await
+    .map(Json)
+}
+
+/// # Player prices in a competition
+#[openapi(tag = "Competition")]
+#[get("/competition/<competition_id>/prices")]
+pub(crate) async fn get_competition_prices(
+    db: &State<DatabaseConnection>,
+    competition_id: i32,
+) -> Result<Json<Vec<service::salary_cap::PlayerPrice>>, GenericError> {
+    service::salary_cap::competition_prices(db.inner(), competition_id)
+        .await
+        .map(Json)
+}
+
+/// # Budget of a user
+///
+/// How much of the budget a user has spent in each division, empty when the tournament has no salary cap.
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/user/<user_id>/budget")]
+pub(crate) async fn get_budget(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<Json<Vec<service::salary_cap::DivisionBudget>>, GenericError> {
+    service::salary_cap::budget_status(db.inner(), user_id, tournament_id)
+        .await
+        .map(Json)
+}
+
 #[openapi(tag = "Pick Exchange")]
 #[get("/fantasy-tournament/<tournament_id>/user/<user_id>/exchange_window")]
 pub(crate) async fn get_exchange_window(
+    _viewer: authenticate::ViewTournament,
     db: &State<DatabaseConnection>,
     tournament_id: u32,
     user_id: u32,
@@ -258,7 +569,11 @@ pub(crate) async fn get_exchange_window(
 
 #[openapi(tag = "Fantasy Tournament")]
 #[get("/fantasy-tournament/<tournament_id>/trade-log")]
-pub(crate) async fn get_trade_log(db: &State<DatabaseConnection>, tournament_id: i32) -> Json<Vec<String>> {
+pub(crate) async fn get_trade_log(
+    _viewer: authenticate::ViewTournament,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+) -> Json<Vec<String>> {
     let db = db.inner();
     dto::PlayerTradesLog::get(db, tournament_id)
         .await
@@ -266,3 +581,87 @@ pub(crate) async fn get_trade_log(db: &State<DatabaseConnection>, tournament_id:
         .await
         .into()
 }
+
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/invite-codes")]
+pub(crate) async fn get_invite_codes(
+    _auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+) -> Result<Json<Vec<service::invites::InviteCode>>, GenericError> {
+    service::invites::get_invite_codes(db.inner(), tournament_id)
+        .await
+        .map(Json)
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/webhooks")]
+pub(crate) async fn get_webhooks(
+    _auth: authenticate::RequireRole<role::Owner>,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+) -> Result<Json<Vec<service::webhooks::Webhook>>, GenericError> {
+    service::webhooks::get_webhooks(db.inner(), tournament_id)
+        .await
+        .map(Json)
+}
+
+/// # Get the delivery log of a webhook
+///
+/// # Returns
+///
+/// The 100 most recent deliveries, newest first
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/webhook/<webhook_id>/deliveries")]
+pub(crate) async fn get_webhook_deliveries(
+    _auth: authenticate::RequireRole<role::Owner>,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+    webhook_id: i32,
+) -> Result<Json<Vec<service::webhooks::WebhookDelivery>>, GenericError> {
+    service::webhooks::get_deliveries(db.inner(), tournament_id, webhook_id)
+        .await
+        .map(Json)
+}
+
+/// # Public tournament page
+///
+/// Standings, rosters and competitions of a public tournament, readable without signing in.
+/// The slug is found in the tournament details once it is made public.
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/public/<slug>")]
+pub(crate) async fn get_public_tournament(
+    db: &State<DatabaseConnection>,
+    slug: &str,
+) -> Result<Json<service::visibility::PublicTournament>, GenericError> {
+    service::visibility::get_public_tournament(db.inner(), slug)
+        .await
+        .map(Json)
+}
+
+/// # Get the members of a tournament and their roles
+///
+/// Ordered by role, the owner first.
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/roles")]
+pub(crate) async fn get_tournament_members(
+    _auth: authenticate::RequireRole<role::Spectator>,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+) -> Result<Json<Vec<service::roles::Member>>, GenericError> {
+    service::roles::get_members(db.inner(), tournament_id)
+        .await
+        .map(Json)
+}
+
+#[openapi(tag = "Fantasy Tournament")]
+#[get("/fantasy-tournament/<tournament_id>/join-requests")]
+pub(crate) async fn get_join_requests(
+    _auth: authenticate::RequireRole<role::CoOwner>,
+    db: &State<DatabaseConnection>,
+    tournament_id: i32,
+) -> Result<Json<Vec<dto::User>>, GenericError> {
+    service::invites::get_join_requests(db.inner(), tournament_id)
+        .await
+        .map(Json)
+}
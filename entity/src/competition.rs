@@ -15,18 +15,35 @@ pub struct Model {
     pub level: CompetitionLevel,
     pub ended_at: Option<DateTimeWithTimeZone>,
     pub start_date: Date,
+    pub timezone: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::competition_in_fantasy_tournament::Entity")]
     CompetitionInFantasyTournament,
+    #[sea_orm(has_many = "super::fantasy_captain::Entity")]
+    FantasyCaptain,
+    #[sea_orm(has_many = "super::phantom_competition::Entity")]
+    PhantomCompetition,
+    #[sea_orm(has_many = "super::pick_substitution::Entity")]
+    PickSubstitution,
+    #[sea_orm(has_many = "super::player_hole_score::Entity")]
+    PlayerHoleScore,
     #[sea_orm(has_many = "super::player_in_competition::Entity")]
     PlayerInCompetition,
+    #[sea_orm(has_many = "super::player_performance_index::Entity")]
+    PlayerPerformanceIndex,
+    #[sea_orm(has_many = "super::player_price::Entity")]
+    PlayerPrice,
     #[sea_orm(has_many = "super::player_round_score::Entity")]
     PlayerRoundScore,
+    #[sea_orm(has_many = "super::player_withdrawal::Entity")]
+    PlayerWithdrawal,
     #[sea_orm(has_many = "super::round::Entity")]
     Round,
+    #[sea_orm(has_many = "super::round_hole::Entity")]
+    RoundHole,
     #[sea_orm(has_many = "super::user_competition_score_in_fantasy_tournament::Entity")]
     UserCompetitionScoreInFantasyTournament,
 }
@@ -37,24 +54,72 @@ impl Related<super::competition_in_fantasy_tournament::Entity> for Entity {
     }
 }
 
+impl Related<super::fantasy_captain::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FantasyCaptain.def()
+    }
+}
+
+impl Related<super::phantom_competition::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PhantomCompetition.def()
+    }
+}
+
+impl Related<super::pick_substitution::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PickSubstitution.def()
+    }
+}
+
+impl Related<super::player_hole_score::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayerHoleScore.def()
+    }
+}
+
 impl Related<super::player_in_competition::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PlayerInCompetition.def()
     }
 }
 
+impl Related<super::player_performance_index::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayerPerformanceIndex.def()
+    }
+}
+
+impl Related<super::player_price::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayerPrice.def()
+    }
+}
+
 impl Related<super::player_round_score::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PlayerRoundScore.def()
     }
 }
 
+impl Related<super::player_withdrawal::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayerWithdrawal.def()
+    }
+}
+
 impl Related<super::round::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Round.def()
     }
 }
 
+impl Related<super::round_hole::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RoundHole.def()
+    }
+}
+
 impl Related<super::user_competition_score_in_fantasy_tournament::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UserCompetitionScoreInFantasyTournament.def()
@@ -1,5 +1,7 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+use super::sea_orm_active_enums::SubstitutionPolicy;
+use super::sea_orm_active_enums::TournamentVisibility;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -11,18 +13,40 @@ pub struct Model {
     pub owner: i32,
     pub max_picks_per_user: i32,
     pub bench_size: i32,
+    pub archived_at: Option<DateTimeWithTimeZone>,
+    pub join_requests_enabled: bool,
+    pub captain_multiplier_percent: i32,
+    pub vice_captains_enabled: bool,
+    pub substitution_policy: SubstitutionPolicy,
+    pub min_fpo_picks: Option<i32>,
+    pub max_fpo_picks: Option<i32>,
+    pub max_picks_per_country: Option<i32>,
+    pub top_rated_pool_size: Option<i32>,
+    pub max_top_rated_picks: Option<i32>,
+    pub budget_per_division: Option<i32>,
+    pub visibility: TournamentVisibility,
+    #[sea_orm(unique)]
+    pub public_slug: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::competition_in_fantasy_tournament::Entity")]
     CompetitionInFantasyTournament,
+    #[sea_orm(has_many = "super::fantasy_captain::Entity")]
+    FantasyCaptain,
     #[sea_orm(has_many = "super::fantasy_pick::Entity")]
     FantasyPick,
     #[sea_orm(has_many = "super::fantasy_tournament_division::Entity")]
     FantasyTournamentDivision,
+    #[sea_orm(has_many = "super::fantasy_tournament_invite_code::Entity")]
+    FantasyTournamentInviteCode,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
     #[sea_orm(has_many = "super::phantom_competition_in_fantasy_tournament::Entity")]
     PhantomCompetitionInFantasyTournament,
+    #[sea_orm(has_many = "super::pick_substitution::Entity")]
+    PickSubstitution,
     #[sea_orm(has_many = "super::player_division_in_fantasy_tournament::Entity")]
     PlayerDivisionInFantasyTournament,
     #[sea_orm(has_many = "super::player_trade::Entity")]
@@ -39,6 +63,8 @@ pub enum Relation {
     UserCompetitionScoreInFantasyTournament,
     #[sea_orm(has_many = "super::user_in_fantasy_tournament::Entity")]
     UserInFantasyTournament,
+    #[sea_orm(has_many = "super::webhook_subscription::Entity")]
+    WebhookSubscription,
 }
 
 impl Related<super::competition_in_fantasy_tournament::Entity> for Entity {
@@ -47,6 +73,12 @@ impl Related<super::competition_in_fantasy_tournament::Entity> for Entity {
     }
 }
 
+impl Related<super::fantasy_captain::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FantasyCaptain.def()
+    }
+}
+
 impl Related<super::fantasy_pick::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::FantasyPick.def()
@@ -59,12 +91,30 @@ impl Related<super::fantasy_tournament_division::Entity> for Entity {
     }
 }
 
+impl Related<super::fantasy_tournament_invite_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FantasyTournamentInviteCode.def()
+    }
+}
+
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
 impl Related<super::phantom_competition_in_fantasy_tournament::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PhantomCompetitionInFantasyTournament.def()
     }
 }
 
+impl Related<super::pick_substitution::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PickSubstitution.def()
+    }
+}
+
 impl Related<super::player_division_in_fantasy_tournament::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PlayerDivisionInFantasyTournament.def()
@@ -95,4 +145,10 @@ impl Related<super::user_in_fantasy_tournament::Entity> for Entity {
     }
 }
 
+impl Related<super::webhook_subscription::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WebhookSubscription.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
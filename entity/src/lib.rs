@@ -2,22 +2,35 @@
 
 pub mod prelude;
 
+pub mod audit_log;
 pub mod competition;
 pub mod competition_in_fantasy_tournament;
+pub mod fantasy_captain;
 pub mod fantasy_pick;
 pub mod fantasy_tournament;
 pub mod fantasy_tournament_division;
+pub mod fantasy_tournament_invite_code;
+pub mod notification;
 pub mod phantom_competition;
 pub mod phantom_competition_in_fantasy_tournament;
+pub mod pick_substitution;
 pub mod player;
 pub mod player_division_in_fantasy_tournament;
+pub mod player_hole_score;
 pub mod player_in_competition;
+pub mod player_performance_index;
+pub mod player_price;
 pub mod player_round_score;
 pub mod player_trade;
+pub mod player_trade_revert;
+pub mod player_withdrawal;
 pub mod round;
+pub mod round_hole;
 pub mod sea_orm_active_enums;
 pub mod user;
 pub mod user_authentication;
 pub mod user_competition_score_in_fantasy_tournament;
 pub mod user_cookies;
 pub mod user_in_fantasy_tournament;
+pub mod webhook_delivery;
+pub mod webhook_subscription;
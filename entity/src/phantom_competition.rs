@@ -6,19 +6,34 @@ use sea_orm::entity::prelude::*;
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "phantom_competition")]
 pub struct Model {
-    #[sea_orm(primary_key, auto_increment = false)]
+    #[sea_orm(primary_key)]
     pub id: i32,
     pub name: String,
     pub date: Date,
     pub level: CompetitionLevel,
+    pub linked_competition_id: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::competition::Entity",
+        from = "Column::LinkedCompetitionId",
+        to = "super::competition::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Competition,
     #[sea_orm(has_many = "super::phantom_competition_in_fantasy_tournament::Entity")]
     PhantomCompetitionInFantasyTournament,
 }
 
+impl Related<super::competition::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Competition.def()
+    }
+}
+
 impl Related<super::phantom_competition_in_fantasy_tournament::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PhantomCompetitionInFantasyTournament.def()
@@ -10,6 +10,8 @@ pub struct Model {
     pub first_name: String,
     pub last_name: String,
     pub avatar: Option<String>,
+    pub country: Option<String>,
+    pub rating: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -18,10 +20,18 @@ pub enum Relation {
     FantasyPick,
     #[sea_orm(has_many = "super::player_division_in_fantasy_tournament::Entity")]
     PlayerDivisionInFantasyTournament,
+    #[sea_orm(has_many = "super::player_hole_score::Entity")]
+    PlayerHoleScore,
     #[sea_orm(has_many = "super::player_in_competition::Entity")]
     PlayerInCompetition,
+    #[sea_orm(has_many = "super::player_performance_index::Entity")]
+    PlayerPerformanceIndex,
+    #[sea_orm(has_many = "super::player_price::Entity")]
+    PlayerPrice,
     #[sea_orm(has_many = "super::player_round_score::Entity")]
     PlayerRoundScore,
+    #[sea_orm(has_many = "super::player_withdrawal::Entity")]
+    PlayerWithdrawal,
     #[sea_orm(has_many = "super::user_competition_score_in_fantasy_tournament::Entity")]
     UserCompetitionScoreInFantasyTournament,
 }
@@ -38,18 +48,42 @@ impl Related<super::player_division_in_fantasy_tournament::Entity> for Entity {
     }
 }
 
+impl Related<super::player_hole_score::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayerHoleScore.def()
+    }
+}
+
 impl Related<super::player_in_competition::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PlayerInCompetition.def()
     }
 }
 
+impl Related<super::player_performance_index::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayerPerformanceIndex.def()
+    }
+}
+
+impl Related<super::player_price::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayerPrice.def()
+    }
+}
+
 impl Related<super::player_round_score::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PlayerRoundScore.def()
     }
 }
 
+impl Related<super::player_withdrawal::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayerWithdrawal.def()
+    }
+}
+
 impl Related<super::user_competition_score_in_fantasy_tournament::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UserCompetitionScoreInFantasyTournament.def()
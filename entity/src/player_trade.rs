@@ -15,6 +15,8 @@ pub struct Model {
     pub is_local_swap: bool,
     pub other_player: Option<i32>,
     pub other_slot: Option<i32>,
+    pub is_removal: bool,
+    pub is_revert: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -43,6 +45,8 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Player1,
+    #[sea_orm(has_many = "super::player_trade_revert::Entity")]
+    PlayerTradeRevert,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::User",
@@ -59,6 +63,12 @@ impl Related<super::fantasy_tournament::Entity> for Entity {
     }
 }
 
+impl Related<super::player_trade_revert::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayerTradeRevert.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
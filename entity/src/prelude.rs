@@ -1,20 +1,33 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub use super::audit_log::Entity as AuditLog;
 pub use super::competition::Entity as Competition;
 pub use super::competition_in_fantasy_tournament::Entity as CompetitionInFantasyTournament;
+pub use super::fantasy_captain::Entity as FantasyCaptain;
 pub use super::fantasy_pick::Entity as FantasyPick;
 pub use super::fantasy_tournament::Entity as FantasyTournament;
 pub use super::fantasy_tournament_division::Entity as FantasyTournamentDivision;
+pub use super::fantasy_tournament_invite_code::Entity as FantasyTournamentInviteCode;
+pub use super::notification::Entity as Notification;
 pub use super::phantom_competition::Entity as PhantomCompetition;
 pub use super::phantom_competition_in_fantasy_tournament::Entity as PhantomCompetitionInFantasyTournament;
+pub use super::pick_substitution::Entity as PickSubstitution;
 pub use super::player::Entity as Player;
 pub use super::player_division_in_fantasy_tournament::Entity as PlayerDivisionInFantasyTournament;
+pub use super::player_hole_score::Entity as PlayerHoleScore;
 pub use super::player_in_competition::Entity as PlayerInCompetition;
+pub use super::player_performance_index::Entity as PlayerPerformanceIndex;
+pub use super::player_price::Entity as PlayerPrice;
 pub use super::player_round_score::Entity as PlayerRoundScore;
 pub use super::player_trade::Entity as PlayerTrade;
+pub use super::player_trade_revert::Entity as PlayerTradeRevert;
+pub use super::player_withdrawal::Entity as PlayerWithdrawal;
 pub use super::round::Entity as Round;
+pub use super::round_hole::Entity as RoundHole;
 pub use super::user::Entity as User;
 pub use super::user_authentication::Entity as UserAuthentication;
 pub use super::user_competition_score_in_fantasy_tournament::Entity as UserCompetitionScoreInFantasyTournament;
 pub use super::user_cookies::Entity as UserCookies;
 pub use super::user_in_fantasy_tournament::Entity as UserInFantasyTournament;
+pub use super::webhook_delivery::Entity as WebhookDelivery;
+pub use super::webhook_subscription::Entity as WebhookSubscription;
@@ -49,6 +49,22 @@ pub enum FantasyTournamentInvitationStatus {
     Declined,
     #[sea_orm(string_value = "Pending")]
     Pending,
+    #[sea_orm(string_value = "Requested")]
+    Requested,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "notification_kind")]
+pub enum NotificationKind {
+    #[sea_orm(string_value = "CompetitionFinished")]
+    CompetitionFinished,
+    #[sea_orm(string_value = "ExchangeWindowOpened")]
+    ExchangeWindowOpened,
+    #[sea_orm(string_value = "Invitation")]
+    Invitation,
+    #[sea_orm(string_value = "JoinRequest")]
+    JoinRequest,
+    #[sea_orm(string_value = "Trade")]
+    Trade,
 }
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "round_type_enum")]
@@ -62,3 +78,63 @@ pub enum RoundTypeEnum {
     #[sea_orm(string_value = "unknown")]
     Unknown,
 }
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "substitution_policy")]
+pub enum SubstitutionPolicy {
+    #[sea_orm(string_value = "AnyDivision")]
+    AnyDivision,
+    #[sea_orm(string_value = "Off")]
+    Off,
+    #[sea_orm(string_value = "SameDivision")]
+    SameDivision,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "tournament_role")]
+pub enum TournamentRole {
+    #[sea_orm(string_value = "CoOwner")]
+    CoOwner,
+    #[sea_orm(string_value = "Member")]
+    Member,
+    #[sea_orm(string_value = "Owner")]
+    Owner,
+    #[sea_orm(string_value = "Spectator")]
+    Spectator,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "tournament_visibility")]
+pub enum TournamentVisibility {
+    #[sea_orm(string_value = "MembersOnly")]
+    MembersOnly,
+    #[sea_orm(string_value = "Private")]
+    Private,
+    #[sea_orm(string_value = "Public")]
+    Public,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "webhook_delivery_status")]
+pub enum WebhookDeliveryStatus {
+    #[sea_orm(string_value = "Delivered")]
+    Delivered,
+    #[sea_orm(string_value = "Failed")]
+    Failed,
+    #[sea_orm(string_value = "Pending")]
+    Pending,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "webhook_format")]
+pub enum WebhookFormat {
+    #[sea_orm(string_value = "Discord")]
+    Discord,
+    #[sea_orm(string_value = "Generic")]
+    Generic,
+    #[sea_orm(string_value = "Slack")]
+    Slack,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "withdrawal_reason")]
+pub enum WithdrawalReason {
+    #[sea_orm(string_value = "DidNotFinish")]
+    DidNotFinish,
+    #[sea_orm(string_value = "DidNotStart")]
+    DidNotStart,
+}
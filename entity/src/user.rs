@@ -10,16 +10,29 @@ pub struct Model {
     #[sea_orm(unique)]
     pub name: String,
     pub admin: bool,
+    pub moderator: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::audit_log::Entity")]
+    AuditLog,
+    #[sea_orm(has_many = "super::fantasy_captain::Entity")]
+    FantasyCaptain,
     #[sea_orm(has_many = "super::fantasy_pick::Entity")]
     FantasyPick,
     #[sea_orm(has_many = "super::fantasy_tournament::Entity")]
     FantasyTournament,
+    #[sea_orm(has_many = "super::fantasy_tournament_invite_code::Entity")]
+    FantasyTournamentInviteCode,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
+    #[sea_orm(has_many = "super::pick_substitution::Entity")]
+    PickSubstitution,
     #[sea_orm(has_many = "super::player_trade::Entity")]
     PlayerTrade,
+    #[sea_orm(has_many = "super::player_trade_revert::Entity")]
+    PlayerTradeRevert,
     #[sea_orm(has_many = "super::user_authentication::Entity")]
     UserAuthentication,
     #[sea_orm(has_many = "super::user_competition_score_in_fantasy_tournament::Entity")]
@@ -28,6 +41,20 @@ pub enum Relation {
     UserCookies,
     #[sea_orm(has_many = "super::user_in_fantasy_tournament::Entity")]
     UserInFantasyTournament,
+    #[sea_orm(has_many = "super::webhook_subscription::Entity")]
+    WebhookSubscription,
+}
+
+impl Related<super::audit_log::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AuditLog.def()
+    }
+}
+
+impl Related<super::fantasy_captain::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FantasyCaptain.def()
+    }
 }
 
 impl Related<super::fantasy_pick::Entity> for Entity {
@@ -42,12 +69,36 @@ impl Related<super::fantasy_tournament::Entity> for Entity {
     }
 }
 
+impl Related<super::fantasy_tournament_invite_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FantasyTournamentInviteCode.def()
+    }
+}
+
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
+impl Related<super::pick_substitution::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PickSubstitution.def()
+    }
+}
+
 impl Related<super::player_trade::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PlayerTrade.def()
     }
 }
 
+impl Related<super::player_trade_revert::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlayerTradeRevert.def()
+    }
+}
+
 impl Related<super::user_authentication::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UserAuthentication.def()
@@ -72,4 +123,10 @@ impl Related<super::user_in_fantasy_tournament::Entity> for Entity {
     }
 }
 
+impl Related<super::webhook_subscription::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WebhookSubscription.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
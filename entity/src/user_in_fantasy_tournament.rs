@@ -1,6 +1,7 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
 use super::sea_orm_active_enums::FantasyTournamentInvitationStatus;
+use super::sea_orm_active_enums::TournamentRole;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -11,6 +12,7 @@ pub struct Model {
     pub user_id: i32,
     pub fantasy_tournament_id: i32,
     pub invitation_status: FantasyTournamentInvitationStatus,
+    pub role: TournamentRole,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -8,6 +8,7 @@ pub(crate) enum User {
     Id,
     Name,
     Admin,
+    Moderator,
 }
 
 #[derive(DeriveIden)]
@@ -17,6 +18,8 @@ pub(crate) enum Player {
     FirstName,
     LastName,
     Avatar,
+    Country,
+    Rating,
 }
 
 #[derive(DeriveIden)]
@@ -46,6 +49,7 @@ pub(crate) enum Competition {
     Level,
     EndedAt,
     StartDate,
+    Timezone,
 }
 
 #[derive(Iden, EnumIter)]
@@ -70,6 +74,7 @@ pub(crate) enum PhantomCompetition {
     Name,
     Date,
     Level,
+    LinkedCompetitionId,
 }
 
 #[derive(DeriveIden)]
@@ -149,6 +154,19 @@ pub(crate) enum FantasyTournament {
     Owner,
     MaxPicksPerUser,
     BenchSize,
+    ArchivedAt,
+    JoinRequestsEnabled,
+    CaptainMultiplierPercent,
+    ViceCaptainsEnabled,
+    SubstitutionPolicy,
+    MinFpoPicks,
+    MaxFpoPicks,
+    MaxPicksPerCountry,
+    TopRatedPoolSize,
+    MaxTopRatedPicks,
+    BudgetPerDivision,
+    Visibility,
+    PublicSlug,
 }
 
 #[derive(DeriveIden)]
@@ -175,6 +193,17 @@ pub(crate) enum PlayerTrade {
     OtherSlot,
     Timestamp,
     IsLocalSwap,
+    IsRemoval,
+    IsRevert,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum PlayerTradeRevert {
+    Table,
+    Id,
+    TradeId,
+    RevertedBy,
+    Timestamp,
 }
 
 #[derive(DeriveIden)]
@@ -220,6 +249,20 @@ pub(crate) enum UserInFantasyTournament {
     UserId,
     FantasyTournamentId,
     InvitationStatus,
+    Role,
+}
+
+#[derive(Iden, EnumIter)]
+pub(crate) enum TournamentRole {
+    Table,
+    #[iden = "Owner"]
+    Owner,
+    #[iden = "CoOwner"]
+    CoOwner,
+    #[iden = "Member"]
+    Member,
+    #[iden = "Spectator"]
+    Spectator,
 }
 
 #[derive(DeriveIden)]
@@ -229,3 +272,219 @@ pub(crate) enum FantasyTournamentDivision {
     FantasyTournamentId,
     Division,
 }
+
+#[derive(DeriveIden)]
+pub(crate) enum FantasyTournamentInviteCode {
+    Table,
+    Id,
+    FantasyTournamentId,
+    Code,
+    CreatedBy,
+    CreatedAt,
+    ExpiresAt,
+    MaxUses,
+    Uses,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum Notification {
+    Table,
+    Id,
+    UserId,
+    FantasyTournamentId,
+    Kind,
+    Message,
+    DedupKey,
+    CreatedAt,
+    ReadAt,
+}
+
+#[derive(Iden, EnumIter)]
+pub(crate) enum NotificationKind {
+    Table,
+    #[iden = "Invitation"]
+    Invitation,
+    #[iden = "JoinRequest"]
+    JoinRequest,
+    #[iden = "ExchangeWindowOpened"]
+    ExchangeWindowOpened,
+    #[iden = "Trade"]
+    Trade,
+    #[iden = "CompetitionFinished"]
+    CompetitionFinished,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum WebhookSubscription {
+    Table,
+    Id,
+    FantasyTournamentId,
+    Url,
+    Format,
+    Secret,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(Iden, EnumIter)]
+pub(crate) enum WebhookFormat {
+    Table,
+    #[iden = "Generic"]
+    Generic,
+    #[iden = "Discord"]
+    Discord,
+    #[iden = "Slack"]
+    Slack,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum WebhookDelivery {
+    Table,
+    Id,
+    SubscriptionId,
+    Event,
+    Payload,
+    Status,
+    Attempts,
+    NextAttemptAt,
+    LastStatusCode,
+    LastError,
+    CreatedAt,
+    DeliveredAt,
+}
+
+#[derive(Iden, EnumIter)]
+pub(crate) enum WebhookDeliveryStatus {
+    Table,
+    #[iden = "Pending"]
+    Pending,
+    #[iden = "Delivered"]
+    Delivered,
+    #[iden = "Failed"]
+    Failed,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum RoundHole {
+    Table,
+    Id,
+    CompetitionId,
+    Round,
+    Division,
+    HoleNumber,
+    Par,
+    Length,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum PlayerHoleScore {
+    Table,
+    Id,
+    PDGANumber,
+    CompetitionId,
+    Round,
+    HoleNumber,
+    Throws,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum FantasyCaptain {
+    Table,
+    Id,
+    FantasyTournamentId,
+    UserId,
+    CompetitionId,
+    Division,
+    Captain,
+    ViceCaptain,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum PlayerWithdrawal {
+    Table,
+    Id,
+    PDGANumber,
+    CompetitionId,
+    Round,
+    Reason,
+}
+
+#[derive(Iden, EnumIter)]
+pub(crate) enum WithdrawalReason {
+    Table,
+    #[iden = "DidNotStart"]
+    DidNotStart,
+    #[iden = "DidNotFinish"]
+    DidNotFinish,
+}
+
+#[derive(Iden, EnumIter)]
+pub(crate) enum SubstitutionPolicy {
+    Table,
+    #[iden = "Off"]
+    Off,
+    #[iden = "SameDivision"]
+    SameDivision,
+    #[iden = "AnyDivision"]
+    AnyDivision,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum PickSubstitution {
+    Table,
+    Id,
+    FantasyTournamentId,
+    CompetitionId,
+    UserId,
+    ReplacedPlayer,
+    SubstitutePlayer,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum PlayerPrice {
+    Table,
+    Id,
+    PDGANumber,
+    CompetitionId,
+    Price,
+    Overridden,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum PlayerPerformanceIndex {
+    Table,
+    Id,
+    PDGANumber,
+    CompetitionId,
+    StrokesGained,
+    Value,
+    CompetitionsCounted,
+    ComputedAt,
+}
+
+#[derive(Iden, EnumIter)]
+pub(crate) enum TournamentVisibility {
+    Table,
+    #[iden = "Private"]
+    Private,
+    #[iden = "MembersOnly"]
+    MembersOnly,
+    #[iden = "Public"]
+    Public,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum AuditLog {
+    Table,
+    Id,
+    Actor,
+    Action,
+    FantasyTournamentId,
+    TargetUser,
+    Summary,
+    Ip,
+    CreatedAt,
+}
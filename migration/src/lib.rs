@@ -8,6 +8,22 @@ mod m20240106_182527_make_usernames_more_unique;
 mod m20240106_191136_users_in_fantasy_tournament;
 mod m20240317_131336_log_exchanges;
 mod m20240506_172259_add_round_type;
+mod m20240521_190412_revertible_trades;
+mod m20240523_101530_tournament_lifecycle;
+mod m20240525_143012_invite_codes;
+mod m20240527_184233_notifications;
+mod m20240529_201145_webhooks;
+mod m20240601_112708_hole_scores;
+mod m20240603_094512_competition_timezone;
+mod m20240605_163021_captains;
+mod m20240607_141950_substitutions;
+mod m20240609_103417_roster_rules;
+mod m20240611_152204_salary_cap;
+mod m20240613_091745_performance_index;
+mod m20240615_134012_phantom_placeholders;
+mod m20240617_102344_roles;
+mod m20240619_141503_tournament_visibility;
+mod m20240621_090317_audit_log;
 mod macros;
 
 pub struct Migrator;
@@ -24,6 +40,22 @@ impl MigratorTrait for Migrator {
             Box::new(m20240106_191136_users_in_fantasy_tournament::Migration),
             Box::new(m20240317_131336_log_exchanges::Migration),
             Box::new(m20240506_172259_add_round_type::Migration),
+            Box::new(m20240521_190412_revertible_trades::Migration),
+            Box::new(m20240523_101530_tournament_lifecycle::Migration),
+            Box::new(m20240525_143012_invite_codes::Migration),
+            Box::new(m20240527_184233_notifications::Migration),
+            Box::new(m20240529_201145_webhooks::Migration),
+            Box::new(m20240601_112708_hole_scores::Migration),
+            Box::new(m20240603_094512_competition_timezone::Migration),
+            Box::new(m20240605_163021_captains::Migration),
+            Box::new(m20240607_141950_substitutions::Migration),
+            Box::new(m20240609_103417_roster_rules::Migration),
+            Box::new(m20240611_152204_salary_cap::Migration),
+            Box::new(m20240613_091745_performance_index::Migration),
+            Box::new(m20240615_134012_phantom_placeholders::Migration),
+            Box::new(m20240617_102344_roles::Migration),
+            Box::new(m20240619_141503_tournament_visibility::Migration),
+            Box::new(m20240621_090317_audit_log::Migration),
         ]
     }
 }
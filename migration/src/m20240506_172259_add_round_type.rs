@@ -1,4 +1,4 @@
-use crate::enums::{FantasyTournamentInvitationStatus, RoundTypeEnum, RoundTypeVariants};
+use crate::enums::{RoundTypeEnum, RoundTypeVariants};
 use sea_orm_migration::prelude::extension::postgres::Type;
 use sea_orm_migration::prelude::*;
 use sea_orm_migration::sea_orm::Iterable;
@@ -0,0 +1,84 @@
+use crate::drop_table;
+use crate::enums::{PlayerTrade, PlayerTradeRevert, User};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PlayerTrade::Table)
+                    .add_column(
+                        ColumnDef::new(PlayerTrade::IsRemoval)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(
+                        ColumnDef::new(PlayerTrade::IsRevert)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlayerTradeRevert::Table)
+                    .col(
+                        ColumnDef::new(PlayerTradeRevert::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PlayerTradeRevert::TradeId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayerTradeRevert::Table, PlayerTradeRevert::TradeId)
+                            .to(PlayerTrade::Table, PlayerTrade::Id),
+                    )
+                    .col(ColumnDef::new(PlayerTradeRevert::RevertedBy).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayerTradeRevert::Table, PlayerTradeRevert::RevertedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .col(
+                        ColumnDef::new(PlayerTradeRevert::Timestamp)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .index(
+                        Index::create()
+                            .name("player_trade_revert_trade")
+                            .col(PlayerTradeRevert::TradeId)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_table!(PlayerTradeRevert, manager);
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PlayerTrade::Table)
+                    .drop_column(PlayerTrade::IsRemoval)
+                    .drop_column(PlayerTrade::IsRevert)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
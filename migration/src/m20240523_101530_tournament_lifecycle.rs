@@ -0,0 +1,36 @@
+use crate::enums::FantasyTournament;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::ArchivedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .drop_column(FantasyTournament::ArchivedAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use crate::drop_table;
+use crate::enums::{FantasyTournament, FantasyTournamentInvitationStatus, FantasyTournamentInviteCode, User};
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(FantasyTournamentInvitationStatus::Table)
+                    .add_value(Alias::new("Requested"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::JoinRequestsEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(FantasyTournamentInviteCode::Table)
+                    .col(
+                        ColumnDef::new(FantasyTournamentInviteCode::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FantasyTournamentInviteCode::FantasyTournamentId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                FantasyTournamentInviteCode::Table,
+                                FantasyTournamentInviteCode::FantasyTournamentId,
+                            )
+                            .to(FantasyTournament::Table, FantasyTournament::Id),
+                    )
+                    .col(
+                        ColumnDef::new(FantasyTournamentInviteCode::Code)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FantasyTournamentInviteCode::CreatedBy)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                FantasyTournamentInviteCode::Table,
+                                FantasyTournamentInviteCode::CreatedBy,
+                            )
+                            .to(User::Table, User::Id),
+                    )
+                    .col(
+                        ColumnDef::new(FantasyTournamentInviteCode::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FantasyTournamentInviteCode::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(FantasyTournamentInviteCode::MaxUses)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(FantasyTournamentInviteCode::Uses)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_table!(FantasyTournamentInviteCode, manager);
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .drop_column(FantasyTournament::JoinRequestsEnabled)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Postgres can't drop a value from an enum, so the type is recreated without it
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+            DELETE FROM user_in_fantasy_tournament WHERE invitation_status = 'Requested';
+            ALTER TYPE fantasy_tournament_invitation_status RENAME TO fantasy_tournament_invitation_status_old;
+            CREATE TYPE fantasy_tournament_invitation_status AS ENUM ('Pending', 'Accepted', 'Declined');
+            ALTER TABLE user_in_fantasy_tournament
+            ALTER COLUMN invitation_status TYPE fantasy_tournament_invitation_status
+            USING invitation_status::text::fantasy_tournament_invitation_status;
+            DROP TYPE fantasy_tournament_invitation_status_old;
+            "#,
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use crate::enums::{FantasyTournament, Notification, NotificationKind, User};
+use crate::extension::postgres::Type;
+use crate::{drop_table, drop_type};
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::Iterable;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(NotificationKind::Table)
+                    .values(NotificationKind::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Notification::Table)
+                    .col(
+                        ColumnDef::new(Notification::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Notification::UserId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Notification::Table, Notification::UserId)
+                            .to(User::Table, User::Id),
+                    )
+                    .col(ColumnDef::new(Notification::FantasyTournamentId).integer().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Notification::Table, Notification::FantasyTournamentId)
+                            .to(FantasyTournament::Table, FantasyTournament::Id),
+                    )
+                    .col(
+                        ColumnDef::new(Notification::Kind)
+                            .custom(NotificationKind::Table)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Notification::Message).string().not_null())
+                    .col(ColumnDef::new(Notification::DedupKey).string().null())
+                    .col(
+                        ColumnDef::new(Notification::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Notification::ReadAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .index(
+                        Index::create()
+                            .name("notification_user_dedup_key")
+                            .col(Notification::UserId)
+                            .col(Notification::DedupKey)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_table!(Notification, manager);
+        drop_type!(NotificationKind, manager);
+        Ok(())
+    }
+}
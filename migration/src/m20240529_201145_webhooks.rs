@@ -0,0 +1,156 @@
+use crate::enums::{
+    FantasyTournament, User, WebhookDelivery, WebhookDeliveryStatus, WebhookFormat, WebhookSubscription,
+};
+use crate::extension::postgres::Type;
+use crate::{drop_table, drop_type};
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::Iterable;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(WebhookFormat::Table)
+                    .values(WebhookFormat::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(WebhookDeliveryStatus::Table)
+                    .values(WebhookDeliveryStatus::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookSubscription::Table)
+                    .col(
+                        ColumnDef::new(WebhookSubscription::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookSubscription::FantasyTournamentId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                WebhookSubscription::Table,
+                                WebhookSubscription::FantasyTournamentId,
+                            )
+                            .to(FantasyTournament::Table, FantasyTournament::Id),
+                    )
+                    .col(ColumnDef::new(WebhookSubscription::Url).string().not_null())
+                    .col(
+                        ColumnDef::new(WebhookSubscription::Format)
+                            .custom(WebhookFormat::Table)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WebhookSubscription::Secret).string().not_null())
+                    .col(
+                        ColumnDef::new(WebhookSubscription::CreatedBy)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(WebhookSubscription::Table, WebhookSubscription::CreatedBy)
+                            .to(User::Table, User::Id),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookSubscription::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookDelivery::Table)
+                    .col(
+                        ColumnDef::new(WebhookDelivery::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDelivery::SubscriptionId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(WebhookDelivery::Table, WebhookDelivery::SubscriptionId)
+                            .to(WebhookSubscription::Table, WebhookSubscription::Id),
+                    )
+                    .col(ColumnDef::new(WebhookDelivery::Event).string().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::Payload).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(WebhookDelivery::Status)
+                            .custom(WebhookDeliveryStatus::Table)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDelivery::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDelivery::NextAttemptAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WebhookDelivery::LastStatusCode).integer().null())
+                    .col(ColumnDef::new(WebhookDelivery::LastError).string().null())
+                    .col(
+                        ColumnDef::new(WebhookDelivery::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDelivery::DeliveredAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("webhook_delivery_status_next_attempt")
+                    .table(WebhookDelivery::Table)
+                    .col(WebhookDelivery::Status)
+                    .col(WebhookDelivery::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_table!(WebhookDelivery, manager);
+        drop_table!(WebhookSubscription, manager);
+        drop_type!(WebhookDeliveryStatus, manager);
+        drop_type!(WebhookFormat, manager);
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::drop_table;
+use crate::enums::{Competition, Division, Player, PlayerHoleScore, RoundHole};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RoundHole::Table)
+                    .col(
+                        ColumnDef::new(RoundHole::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RoundHole::CompetitionId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(RoundHole::Table, RoundHole::CompetitionId)
+                            .to(Competition::Table, Competition::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(RoundHole::Round).integer().not_null())
+                    // Divisions can play different layouts in the same round
+                    .col(
+                        ColumnDef::new(RoundHole::Division)
+                            .custom(Division::Table)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RoundHole::HoleNumber).integer().not_null())
+                    .col(ColumnDef::new(RoundHole::Par).integer().not_null())
+                    .col(ColumnDef::new(RoundHole::Length).integer().null())
+                    .index(
+                        Index::create()
+                            .name("unique_round_hole")
+                            .col(RoundHole::CompetitionId)
+                            .col(RoundHole::Round)
+                            .col(RoundHole::Division)
+                            .col(RoundHole::HoleNumber)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlayerHoleScore::Table)
+                    .col(
+                        ColumnDef::new(PlayerHoleScore::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PlayerHoleScore::PDGANumber).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayerHoleScore::Table, PlayerHoleScore::PDGANumber)
+                            .to(Player::Table, Player::PDGANumber)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(PlayerHoleScore::CompetitionId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayerHoleScore::Table, PlayerHoleScore::CompetitionId)
+                            .to(Competition::Table, Competition::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(PlayerHoleScore::Round).integer().not_null())
+                    .col(ColumnDef::new(PlayerHoleScore::HoleNumber).integer().not_null())
+                    .col(ColumnDef::new(PlayerHoleScore::Throws).integer().not_null())
+                    .index(
+                        Index::create()
+                            .name("unique_player_hole_score")
+                            .col(PlayerHoleScore::PDGANumber)
+                            .col(PlayerHoleScore::CompetitionId)
+                            .col(PlayerHoleScore::Round)
+                            .col(PlayerHoleScore::HoleNumber)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_table!(PlayerHoleScore, manager);
+        drop_table!(RoundHole, manager);
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use crate::enums::Competition;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Competition::Table)
+                    .add_column(ColumnDef::new(Competition::Timezone).string().null())
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Competition::Table)
+                    .drop_column(Competition::Timezone)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
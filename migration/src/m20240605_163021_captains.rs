@@ -0,0 +1,179 @@
+use crate::enums::{
+    Competition, Division, FantasyCaptain, FantasyTournament, Player, PlayerWithdrawal, User,
+    WithdrawalReason,
+};
+use crate::extension::postgres::Type;
+use crate::{drop_table, drop_type};
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::Iterable;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::CaptainMultiplierPercent)
+                            .integer()
+                            .not_null()
+                            .default(100),
+                    )
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::ViceCaptainsEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(FantasyCaptain::Table)
+                    .col(
+                        ColumnDef::new(FantasyCaptain::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FantasyCaptain::FantasyTournamentId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(FantasyCaptain::Table, FantasyCaptain::FantasyTournamentId)
+                            .to(FantasyTournament::Table, FantasyTournament::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(FantasyCaptain::UserId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(FantasyCaptain::Table, FantasyCaptain::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(FantasyCaptain::CompetitionId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(FantasyCaptain::Table, FantasyCaptain::CompetitionId)
+                            .to(Competition::Table, Competition::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(FantasyCaptain::Division)
+                            .custom(Division::Table)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(FantasyCaptain::Captain).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(FantasyCaptain::Table, FantasyCaptain::Captain)
+                            .to(Player::Table, Player::PDGANumber),
+                    )
+                    .col(ColumnDef::new(FantasyCaptain::ViceCaptain).integer().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(FantasyCaptain::Table, FantasyCaptain::ViceCaptain)
+                            .to(Player::Table, Player::PDGANumber),
+                    )
+                    .col(
+                        ColumnDef::new(FantasyCaptain::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .index(
+                        Index::create()
+                            .name("unique_captain_per_division")
+                            .col(FantasyCaptain::FantasyTournamentId)
+                            .col(FantasyCaptain::UserId)
+                            .col(FantasyCaptain::CompetitionId)
+                            .col(FantasyCaptain::Division)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(WithdrawalReason::Table)
+                    .values(WithdrawalReason::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlayerWithdrawal::Table)
+                    .col(
+                        ColumnDef::new(PlayerWithdrawal::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PlayerWithdrawal::PDGANumber).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayerWithdrawal::Table, PlayerWithdrawal::PDGANumber)
+                            .to(Player::Table, Player::PDGANumber)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(PlayerWithdrawal::CompetitionId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayerWithdrawal::Table, PlayerWithdrawal::CompetitionId)
+                            .to(Competition::Table, Competition::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(PlayerWithdrawal::Round).integer().not_null())
+                    .col(
+                        ColumnDef::new(PlayerWithdrawal::Reason)
+                            .custom(WithdrawalReason::Table)
+                            .not_null(),
+                    )
+                    .index(
+                        Index::create()
+                            .name("unique_player_withdrawal")
+                            .col(PlayerWithdrawal::PDGANumber)
+                            .col(PlayerWithdrawal::CompetitionId)
+                            .col(PlayerWithdrawal::Round)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_table!(PlayerWithdrawal, manager);
+        drop_type!(WithdrawalReason, manager);
+        drop_table!(FantasyCaptain, manager);
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .drop_column(FantasyTournament::CaptainMultiplierPercent)
+                    .drop_column(FantasyTournament::ViceCaptainsEnabled)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use crate::enums::{Competition, FantasyTournament, PickSubstitution, Player, SubstitutionPolicy, User};
+use crate::extension::postgres::Type;
+use crate::{drop_table, drop_type};
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::Iterable;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(SubstitutionPolicy::Table)
+                    .values(SubstitutionPolicy::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::SubstitutionPolicy)
+                            .custom(SubstitutionPolicy::Table)
+                            .not_null()
+                            .default("SameDivision"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PickSubstitution::Table)
+                    .col(
+                        ColumnDef::new(PickSubstitution::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PickSubstitution::FantasyTournamentId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PickSubstitution::Table, PickSubstitution::FantasyTournamentId)
+                            .to(FantasyTournament::Table, FantasyTournament::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(PickSubstitution::CompetitionId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PickSubstitution::Table, PickSubstitution::CompetitionId)
+                            .to(Competition::Table, Competition::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(PickSubstitution::UserId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PickSubstitution::Table, PickSubstitution::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(PickSubstitution::ReplacedPlayer)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PickSubstitution::Table, PickSubstitution::ReplacedPlayer)
+                            .to(Player::Table, Player::PDGANumber),
+                    )
+                    .col(
+                        ColumnDef::new(PickSubstitution::SubstitutePlayer)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PickSubstitution::Table, PickSubstitution::SubstitutePlayer)
+                            .to(Player::Table, Player::PDGANumber),
+                    )
+                    .col(
+                        ColumnDef::new(PickSubstitution::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .index(
+                        Index::create()
+                            .name("unique_substitution_per_replaced_pick")
+                            .col(PickSubstitution::FantasyTournamentId)
+                            .col(PickSubstitution::CompetitionId)
+                            .col(PickSubstitution::UserId)
+                            .col(PickSubstitution::ReplacedPlayer)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_table!(PickSubstitution, manager);
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .drop_column(FantasyTournament::SubstitutionPolicy)
+                    .to_owned(),
+            )
+            .await?;
+        drop_type!(SubstitutionPolicy, manager);
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::enums::{FantasyTournament, Player};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Player::Table)
+                    .add_column(ColumnDef::new(Player::Country).string().null())
+                    .add_column(ColumnDef::new(Player::Rating).integer().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .add_column(ColumnDef::new(FantasyTournament::MinFpoPicks).integer().null())
+                    .add_column(ColumnDef::new(FantasyTournament::MaxFpoPicks).integer().null())
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::MaxPicksPerCountry)
+                            .integer()
+                            .null(),
+                    )
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::TopRatedPoolSize)
+                            .integer()
+                            .null(),
+                    )
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::MaxTopRatedPicks)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .drop_column(FantasyTournament::MinFpoPicks)
+                    .drop_column(FantasyTournament::MaxFpoPicks)
+                    .drop_column(FantasyTournament::MaxPicksPerCountry)
+                    .drop_column(FantasyTournament::TopRatedPoolSize)
+                    .drop_column(FantasyTournament::MaxTopRatedPicks)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Player::Table)
+                    .drop_column(Player::Country)
+                    .drop_column(Player::Rating)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use crate::drop_table;
+use crate::enums::{Competition, FantasyTournament, Player, PlayerPrice};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::BudgetPerDivision)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlayerPrice::Table)
+                    .col(
+                        ColumnDef::new(PlayerPrice::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PlayerPrice::PDGANumber).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayerPrice::Table, PlayerPrice::PDGANumber)
+                            .to(Player::Table, Player::PDGANumber)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(PlayerPrice::CompetitionId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayerPrice::Table, PlayerPrice::CompetitionId)
+                            .to(Competition::Table, Competition::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(PlayerPrice::Price).integer().not_null())
+                    .col(
+                        ColumnDef::new(PlayerPrice::Overridden)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(PlayerPrice::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .index(
+                        Index::create()
+                            .name("unique_player_price_per_competition")
+                            .col(PlayerPrice::PDGANumber)
+                            .col(PlayerPrice::CompetitionId)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_table!(PlayerPrice, manager);
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .drop_column(FantasyTournament::BudgetPerDivision)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::drop_table;
+use crate::enums::{Competition, Player, PlayerPerformanceIndex};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PlayerPerformanceIndex::Table)
+                    .col(
+                        ColumnDef::new(PlayerPerformanceIndex::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PlayerPerformanceIndex::PDGANumber)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(PlayerPerformanceIndex::Table, PlayerPerformanceIndex::PDGANumber)
+                            .to(Player::Table, Player::PDGANumber)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(PlayerPerformanceIndex::CompetitionId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                PlayerPerformanceIndex::Table,
+                                PlayerPerformanceIndex::CompetitionId,
+                            )
+                            .to(Competition::Table, Competition::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(PlayerPerformanceIndex::StrokesGained)
+                            .double()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PlayerPerformanceIndex::Value).double().not_null())
+                    .col(
+                        ColumnDef::new(PlayerPerformanceIndex::CompetitionsCounted)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PlayerPerformanceIndex::ComputedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .index(
+                        Index::create()
+                            .name("unique_performance_index_per_competition")
+                            .col(PlayerPerformanceIndex::PDGANumber)
+                            .col(PlayerPerformanceIndex::CompetitionId)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_table!(PlayerPerformanceIndex, manager);
+        Ok(())
+    }
+}
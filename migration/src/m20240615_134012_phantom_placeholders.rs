@@ -0,0 +1,73 @@
+use crate::enums::{Competition, PhantomCompetition};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The ids were never generated since nothing created phantom competitions
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+            CREATE SEQUENCE phantom_competition_id_seq OWNED BY phantom_competition.id;
+            ALTER TABLE phantom_competition
+            ALTER COLUMN id SET DEFAULT nextval('phantom_competition_id_seq');
+            CREATE SEQUENCE phantom_competition_in_fantasy_tournament_id_seq
+            OWNED BY phantom_competition_in_fantasy_tournament.id;
+            ALTER TABLE phantom_competition_in_fantasy_tournament
+            ALTER COLUMN id SET DEFAULT nextval('phantom_competition_in_fantasy_tournament_id_seq');
+            "#,
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PhantomCompetition::Table)
+                    .add_column(
+                        ColumnDef::new(PhantomCompetition::LinkedCompetitionId)
+                            .integer()
+                            .null(),
+                    )
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_phantom_competition_linked_competition")
+                            .from_tbl(PhantomCompetition::Table)
+                            .from_col(PhantomCompetition::LinkedCompetitionId)
+                            .to_tbl(Competition::Table)
+                            .to_col(Competition::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PhantomCompetition::Table)
+                    .drop_foreign_key(Alias::new("fk_phantom_competition_linked_competition"))
+                    .drop_column(PhantomCompetition::LinkedCompetitionId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+            ALTER TABLE phantom_competition ALTER COLUMN id DROP DEFAULT;
+            DROP SEQUENCE phantom_competition_id_seq;
+            ALTER TABLE phantom_competition_in_fantasy_tournament ALTER COLUMN id DROP DEFAULT;
+            DROP SEQUENCE phantom_competition_in_fantasy_tournament_id_seq;
+            "#,
+            )
+            .await?;
+        Ok(())
+    }
+}
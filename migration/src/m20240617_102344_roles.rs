@@ -0,0 +1,79 @@
+use crate::drop_type;
+use crate::enums::{TournamentRole, User, UserInFantasyTournament};
+use crate::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::Iterable;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(
+                        ColumnDef::new(User::Moderator)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(TournamentRole::Table)
+                    .values(TournamentRole::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserInFantasyTournament::Table)
+                    .add_column(
+                        ColumnDef::new(UserInFantasyTournament::Role)
+                            .custom(TournamentRole::Table)
+                            .not_null()
+                            .default("Member"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"UPDATE user_in_fantasy_tournament SET role = 'Owner'
+                FROM fantasy_tournament
+                WHERE fantasy_tournament.id = user_in_fantasy_tournament.fantasy_tournament_id
+                AND fantasy_tournament.owner = user_in_fantasy_tournament.user_id"#,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserInFantasyTournament::Table)
+                    .drop_column(UserInFantasyTournament::Role)
+                    .to_owned(),
+            )
+            .await?;
+        drop_type!(TournamentRole, manager);
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Moderator)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
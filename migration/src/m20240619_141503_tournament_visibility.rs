@@ -0,0 +1,56 @@
+use crate::drop_type;
+use crate::enums::{FantasyTournament, TournamentVisibility};
+use crate::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::Iterable;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(TournamentVisibility::Table)
+                    .values(TournamentVisibility::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::Visibility)
+                            .custom(TournamentVisibility::Table)
+                            .not_null()
+                            .default("MembersOnly"),
+                    )
+                    .add_column(
+                        ColumnDef::new(FantasyTournament::PublicSlug)
+                            .string()
+                            .null()
+                            .unique_key(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FantasyTournament::Table)
+                    .drop_column(FantasyTournament::Visibility)
+                    .drop_column(FantasyTournament::PublicSlug)
+                    .to_owned(),
+            )
+            .await?;
+        drop_type!(TournamentVisibility, manager);
+        Ok(())
+    }
+}
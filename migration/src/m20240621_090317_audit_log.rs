@@ -0,0 +1,81 @@
+use crate::drop_table;
+use crate::enums::{AuditLog, User};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .col(
+                        ColumnDef::new(AuditLog::Id)
+                            .integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AuditLog::Actor).integer().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(AuditLog::Table, AuditLog::Actor)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .col(ColumnDef::new(AuditLog::Action).string().not_null())
+                    // No foreign keys on the targets, entries outlive the tournaments and users they are about
+                    .col(ColumnDef::new(AuditLog::FantasyTournamentId).integer().null())
+                    .col(ColumnDef::new(AuditLog::TargetUser).integer().null())
+                    .col(ColumnDef::new(AuditLog::Summary).text().null())
+                    .col(ColumnDef::new(AuditLog::Ip).string().null())
+                    .col(
+                        ColumnDef::new(AuditLog::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // The log is filtered by these and read newest first, by id
+        manager
+            .create_index(
+                Index::create()
+                    .name("audit_log_actor_id")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::Actor)
+                    .col(AuditLog::Id)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("audit_log_fantasy_tournament_id_id")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::FantasyTournamentId)
+                    .col(AuditLog::Id)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("audit_log_target_user_id")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::TargetUser)
+                    .col(AuditLog::Id)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_table!(AuditLog, manager);
+        Ok(())
+    }
+}
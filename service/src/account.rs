@@ -0,0 +1,459 @@
+use std::collections::HashMap;
+
+use entity::prelude::*;
+use entity::sea_orm_active_enums::FantasyTournamentInvitationStatus;
+use entity::*;
+use itertools::Itertools;
+use rocket_okapi::JsonSchema;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::{Division, InvitationStatus};
+use crate::error::GenericError;
+use crate::roles::TournamentRole;
+
+/// Everything stored about a user
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct AccountExport {
+    pub profile: Profile,
+    pub tournaments: Vec<AccountTournament>,
+    pub picks: Vec<AccountPick>,
+    pub trades: Vec<AccountTrade>,
+    pub scores: Vec<AccountScore>,
+    pub sessions: Vec<Session>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct Profile {
+    pub id: i32,
+    pub name: String,
+    pub admin: bool,
+    pub has_password: bool,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct AccountTournament {
+    pub id: i32,
+    pub name: String,
+    pub owner: bool,
+    pub invitation_status: InvitationStatus,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct AccountPick {
+    pub fantasy_tournament_id: i32,
+    pub division: Division,
+    pub slot: i32,
+    pub pdga_number: i32,
+    pub benched: bool,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct AccountTrade {
+    pub id: i32,
+    pub fantasy_tournament_id: i32,
+    pub timestamp: DateTimeWithTimeZone,
+    pub slot: i32,
+    pub pdga_number: i32,
+    pub other_pdga_number: Option<i32>,
+    pub other_slot: Option<i32>,
+    pub is_local_swap: bool,
+    pub is_removal: bool,
+    pub is_revert: bool,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct AccountScore {
+    pub fantasy_tournament_id: i32,
+    pub competition_id: i32,
+    pub pdga_number: i32,
+    pub score: i32,
+}
+
+/// A logged in session. Only the start of the cookie is shown, it is as good as a password.
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct Session {
+    pub cookie_start: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Default)]
+pub enum DeletionMode {
+    /// Replaces the username and removes the login, keeping picks, trades and scores in league history
+    #[default]
+    Anonymise,
+    /// Removes the user along with their picks, trades and scores
+    Delete,
+}
+
+/// Characters of a session cookie included in an account export
+const COOKIE_START_LENGTH: usize = 6;
+
+pub async fn export_account(db: &impl ConnectionTrait, user_id: i32) -> Result<AccountExport, GenericError> {
+    let user = User::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get user from database"))?
+        .ok_or(GenericError::NotFound("User not found"))?;
+    let has_password = UserAuthentication::find()
+        .filter(user_authentication::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get authentication from database"))?
+        .is_some();
+    let participations = UserInFantasyTournament::find()
+        .filter(user_in_fantasy_tournament::Column::UserId.eq(user_id))
+        .find_also_related(FantasyTournament)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournaments from database"))?;
+    let picks = FantasyPick::find()
+        .filter(fantasy_pick::Column::User.eq(user_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?;
+    let trades = PlayerTrade::find()
+        .filter(player_trade::Column::User.eq(user_id))
+        .order_by_asc(player_trade::Column::Timestamp)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get trades from database"))?;
+    let scores = UserCompetitionScoreInFantasyTournament::find()
+        .filter(user_competition_score_in_fantasy_tournament::Column::User.eq(user_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get scores from database"))?;
+    let sessions = UserCookies::find()
+        .filter(user_cookies::Column::UserId.eq(user_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get sessions from database"))?;
+
+    Ok(AccountExport {
+        profile: Profile {
+            id: user.id,
+            name: user.name,
+            admin: user.admin,
+            has_password,
+        },
+        tournaments: participations
+            .into_iter()
+            .filter_map(|(participation, tournament)| {
+                tournament.map(|t| AccountTournament {
+                    id: t.id,
+                    name: t.name,
+                    owner: t.owner == user_id,
+                    invitation_status: participation.invitation_status.into(),
+                })
+            })
+            .sorted_by_key(|t| t.id)
+            .collect(),
+        picks: picks
+            .into_iter()
+            .map(|p| AccountPick {
+                fantasy_tournament_id: p.fantasy_tournament_id,
+                division: p.division.into(),
+                slot: p.pick_number,
+                pdga_number: p.player,
+                benched: p.benched,
+            })
+            .sorted_by_key(|p| (p.fantasy_tournament_id, p.division as u8, p.slot))
+            .collect(),
+        trades: trades
+            .into_iter()
+            .map(|t| AccountTrade {
+                id: t.id,
+                fantasy_tournament_id: t.fantasy_tournament_id,
+                timestamp: t.timestamp,
+                slot: t.slot,
+                pdga_number: t.player,
+                other_pdga_number: t.other_player,
+                other_slot: t.other_slot,
+                is_local_swap: t.is_local_swap,
+                is_removal: t.is_removal,
+                is_revert: t.is_revert,
+            })
+            .collect(),
+        scores: scores
+            .into_iter()
+            .map(|s| AccountScore {
+                fantasy_tournament_id: s.fantasy_tournament_id,
+                competition_id: s.competition_id,
+                pdga_number: s.pdga_number,
+                score: s.score,
+            })
+            .sorted_by_key(|s| (s.fantasy_tournament_id, s.competition_id, s.pdga_number))
+            .collect(),
+        sessions: sessions
+            .into_iter()
+            .map(|s| Session {
+                cookie_start: s.cookie.chars().take(COOKIE_START_LENGTH).collect(),
+            })
+            .collect(),
+    })
+}
+
+/// The name an anonymised user is renamed to, unique since it contains the id
+fn anonymised_name(user_id: i32) -> String {
+    format!("deleted-user-{user_id}")
+}
+
+/// Deletes or anonymises a user. Should be run inside a transaction.
+///
+/// Tournaments the user owns go to `new_owner`, who has to be an accepted participant in all of them,
+/// or else to a co-owner or the participant that joined first. Tournaments without anyone else in them are deleted.
+pub async fn delete_account(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    mode: DeletionMode,
+    new_owner: Option<i32>,
+) -> Result<(), GenericError> {
+    let user = User::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get user from database"))?
+        .ok_or(GenericError::NotFound("User not found"))?;
+    if new_owner == Some(user_id) {
+        Err(GenericError::BadRequest("Ownership must go to another user"))?
+    }
+    transfer_owned_tournaments(db, user_id, new_owner).await?;
+
+    UserCookies::delete_many()
+        .filter(user_cookies::Column::UserId.eq(user_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete sessions"))?;
+    UserAuthentication::delete_many()
+        .filter(user_authentication::Column::UserId.eq(user_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete login"))?;
+    Notification::delete_many()
+        .filter(notification::Column::UserId.eq(user_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete notifications"))?;
+
+    match mode {
+        DeletionMode::Anonymise => {
+            let mut user = user.into_active_model();
+            user.name = Set(anonymised_name(user_id));
+            user.admin = Set(false);
+            User::update(user)
+                .exec(db)
+                .await
+                .map_err(|_| GenericError::UnknownError("Unable to anonymise user"))?;
+        }
+        DeletionMode::Delete => {
+            hand_over_created_rows(db, user_id).await?;
+            let trade_ids = PlayerTrade::find()
+                .filter(player_trade::Column::User.eq(user_id))
+                .all(db)
+                .await
+                .map_err(|_| GenericError::UnknownError("Unable to get trades from database"))?
+                .into_iter()
+                .map(|t| t.id)
+                .collect_vec();
+            PlayerTradeRevert::delete_many()
+                .filter(player_trade_revert::Column::TradeId.is_in(trade_ids))
+                .exec(db)
+                .await
+                .map_err(|_| GenericError::UnknownError("Unable to delete reverted trades"))?;
+            PlayerTrade::delete_many()
+                .filter(player_trade::Column::User.eq(user_id))
+                .exec(db)
+                .await
+                .map_err(|_| GenericError::UnknownError("Unable to delete trades"))?;
+            FantasyPick::delete_many()
+                .filter(fantasy_pick::Column::User.eq(user_id))
+                .exec(db)
+                .await
+                .map_err(|_| GenericError::UnknownError("Unable to delete picks"))?;
+            UserCompetitionScoreInFantasyTournament::delete_many()
+                .filter(user_competition_score_in_fantasy_tournament::Column::User.eq(user_id))
+                .exec(db)
+                .await
+                .map_err(|_| GenericError::UnknownError("Unable to delete scores"))?;
+            UserInFantasyTournament::delete_many()
+                .filter(user_in_fantasy_tournament::Column::UserId.eq(user_id))
+                .exec(db)
+                .await
+                .map_err(|_| GenericError::UnknownError("Unable to delete participations"))?;
+            // Captains and substitutions are removed along with the user
+            User::delete_by_id(user_id)
+                .exec(db)
+                .await
+                .map_err(|_| GenericError::UnknownError("Unable to delete user"))?;
+        }
+    }
+    Ok(())
+}
+
+async fn transfer_owned_tournaments(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    new_owner: Option<i32>,
+) -> Result<(), GenericError> {
+    let owned = FantasyTournament::find()
+        .filter(fantasy_tournament::Column::Owner.eq(user_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournaments from database"))?;
+    if owned.is_empty() {
+        return Ok(());
+    }
+    let participants = UserInFantasyTournament::find()
+        .filter(
+            user_in_fantasy_tournament::Column::FantasyTournamentId
+                .is_in(owned.iter().map(|t| t.id))
+                .and(user_in_fantasy_tournament::Column::UserId.ne(user_id))
+                .and(
+                    user_in_fantasy_tournament::Column::InvitationStatus
+                        .eq(FantasyTournamentInvitationStatus::Accepted),
+                ),
+        )
+        .order_by_asc(user_in_fantasy_tournament::Column::Id)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get participants from database"))?
+        .into_iter()
+        // Co-owners are first in line, the sort keeps the order they joined in
+        .sorted_by_key(|p| std::cmp::Reverse(TournamentRole::from(p.role.clone())))
+        .into_group_map_by(|p| p.fantasy_tournament_id);
+
+    let mut successors = HashMap::with_capacity(owned.len());
+    for tournament in &owned {
+        let candidates = participants
+            .get(&tournament.id)
+            .map(|p| p.iter().map(|p| p.user_id).collect_vec())
+            .unwrap_or_default();
+        successors.insert(tournament.id, successor(&candidates, new_owner)?);
+    }
+    for tournament in owned {
+        match successors[&tournament.id] {
+            Some(successor) => crate::roles::transfer_ownership(db, tournament.id, successor).await?,
+            None => crate::tournament_management::delete_tournament(db, tournament.id).await?,
+        }
+    }
+    Ok(())
+}
+
+/// Who takes over a tournament, out of its other participants with co-owners first.
+fn successor(candidates: &[i32], new_owner: Option<i32>) -> Result<Option<i32>, GenericError> {
+    match new_owner {
+        Some(new_owner) if candidates.contains(&new_owner) => Ok(Some(new_owner)),
+        Some(_) => Err(GenericError::BadRequest(
+            "The new owner has to be a participant in every tournament you own",
+        )),
+        None => Ok(candidates.first().copied()),
+    }
+}
+
+/// Invite codes, webhooks and trade reverts made by the user are kept, credited to the owner of their tournament.
+async fn hand_over_created_rows(db: &impl ConnectionTrait, user_id: i32) -> Result<(), GenericError> {
+    FantasyTournamentInviteCode::update_many()
+        .col_expr(
+            fantasy_tournament_invite_code::Column::CreatedBy,
+            Expr::cust(
+                "(SELECT owner FROM fantasy_tournament \
+                 WHERE fantasy_tournament.id = fantasy_tournament_invite_code.fantasy_tournament_id)",
+            ),
+        )
+        .filter(fantasy_tournament_invite_code::Column::CreatedBy.eq(user_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to hand over invite codes"))?;
+    WebhookSubscription::update_many()
+        .col_expr(
+            webhook_subscription::Column::CreatedBy,
+            Expr::cust(
+                "(SELECT owner FROM fantasy_tournament \
+                 WHERE fantasy_tournament.id = webhook_subscription.fantasy_tournament_id)",
+            ),
+        )
+        .filter(webhook_subscription::Column::CreatedBy.eq(user_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to hand over webhooks"))?;
+    PlayerTradeRevert::update_many()
+        .col_expr(
+            player_trade_revert::Column::RevertedBy,
+            Expr::cust(
+                "(SELECT fantasy_tournament.owner FROM player_trade JOIN fantasy_tournament \
+                 ON fantasy_tournament.id = player_trade.fantasy_tournament_id \
+                 WHERE player_trade.id = player_trade_revert.trade_id)",
+            ),
+        )
+        .filter(player_trade_revert::Column::RevertedBy.eq(user_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to hand over reverted trades"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+    use entity::sea_orm_active_enums::TournamentRole as EntityRole;
+
+    #[test]
+    fn ownership_goes_to_chosen_or_first_participant() {
+        assert_eq!(successor(&[4, 2], None).unwrap(), Some(4));
+        assert_eq!(successor(&[4, 2], Some(2)).unwrap(), Some(2));
+        assert_eq!(successor(&[], None).unwrap(), None);
+        assert!(successor(&[4], Some(2)).is_err());
+        assert!(successor(&[], Some(2)).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn deleted_owner_hands_tournament_to_participant() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let member = test_db::user(&txn, "member").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        test_db::member(
+            &txn,
+            tournament,
+            member.id,
+            FantasyTournamentInvitationStatus::Accepted,
+            EntityRole::Member,
+        )
+        .await;
+
+        delete_account(&txn, owner.id, DeletionMode::Delete, None)
+            .await
+            .unwrap();
+        let tournament = FantasyTournament::find_by_id(tournament)
+            .one(&txn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tournament.owner, member.id);
+        assert!(User::find_by_id(owner.id).one(&txn).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn anonymised_user_without_login_is_renamed() {
+        let txn = test_db::begin().await;
+        // Like users created by a restore, this one has never had a password
+        let user = test_db::user(&txn, "restored").await;
+        let tournament = test_db::tournament(&txn, user.id).await;
+
+        delete_account(&txn, user.id, DeletionMode::Anonymise, None)
+            .await
+            .unwrap();
+        let user = User::find_by_id(user.id).one(&txn).await.unwrap().unwrap();
+        assert_eq!(user.name, anonymised_name(user.id));
+        // Nobody else was in the tournament
+        assert!(FantasyTournament::find_by_id(tournament)
+            .one(&txn)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
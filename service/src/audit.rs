@@ -0,0 +1,162 @@
+use chrono::{DateTime, Utc};
+use entity::prelude::*;
+use entity::*;
+use itertools::Itertools;
+use log::error;
+use rocket::FromForm;
+use rocket_okapi::JsonSchema;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, NotSet, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::GenericError;
+
+const AUDIT_LIST_LIMIT: u64 = 100;
+const AUDIT_LIST_MAX_LIMIT: u64 = 1000;
+/// Longer payload summaries are cut, the log is meant to show what changed, not to store it
+const SUMMARY_MAX_CHARS: usize = 500;
+
+/// A privileged action about to be logged
+#[derive(Debug, Clone)]
+pub struct NewAuditEntry {
+    pub actor: Option<i32>,
+    /// The endpoint the action was made through
+    pub action: String,
+    pub fantasy_tournament_id: Option<i32>,
+    pub target_user: Option<i32>,
+    pub summary: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct AuditEntry {
+    pub id: i32,
+    /// `None` when the user has since been deleted
+    pub actor: Option<i32>,
+    pub actor_name: Option<String>,
+    pub action: String,
+    pub fantasy_tournament_id: Option<i32>,
+    pub target_user: Option<i32>,
+    pub summary: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+/// Filters for the audit log, all of them are optional
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, FromForm)]
+pub struct AuditFilter {
+    pub actor: Option<i32>,
+    pub action: Option<String>,
+    pub fantasy_tournament_id: Option<i32>,
+    pub target_user: Option<i32>,
+    /// Only entries made at or after this time, in RFC 3339
+    pub since: Option<String>,
+    /// Only entries older than this id, to page through the log
+    pub before: Option<i32>,
+    /// 100 by default, at most 1000
+    pub limit: Option<u64>,
+}
+
+/// Serializes a payload for the log, cutting it if it is long.
+pub fn summarize(payload: &impl Serialize) -> Option<String> {
+    let summary = serde_json::to_string(payload).ok()?;
+    Some(truncate(summary))
+}
+
+fn truncate(summary: String) -> String {
+    match summary.char_indices().nth(SUMMARY_MAX_CHARS) {
+        Some((cut, _)) => format!("{}…", &summary[..cut]),
+        None => summary,
+    }
+}
+
+/// Logs a privileged action. Should be run in the same transaction as the action,
+/// so that only actions that went through are logged.
+pub async fn record(db: &impl ConnectionTrait, entry: NewAuditEntry) -> Result<(), GenericError> {
+    AuditLog::insert(audit_log::ActiveModel {
+        id: NotSet,
+        actor: Set(entry.actor),
+        action: Set(entry.action),
+        fantasy_tournament_id: Set(entry.fantasy_tournament_id),
+        target_user: Set(entry.target_user),
+        summary: Set(entry.summary.map(truncate)),
+        ip: Set(entry.ip),
+        created_at: Set(Utc::now().fixed_offset()),
+    })
+    .exec_without_returning(db)
+    .await
+    .map_err(|e| {
+        error!("Unable to write audit log: {:#?}", e);
+        GenericError::UnknownError("Unable to write audit log")
+    })?;
+    Ok(())
+}
+
+/// The audit log, newest first.
+pub async fn get_entries(
+    db: &impl ConnectionTrait,
+    filter: AuditFilter,
+) -> Result<Vec<AuditEntry>, GenericError> {
+    let since = filter
+        .since
+        .map(|since| DateTime::parse_from_rfc3339(&since))
+        .transpose()
+        .map_err(|_| GenericError::BadRequest("since has to be an RFC 3339 timestamp"))?;
+    let condition = Condition::all()
+        .add_option(filter.actor.map(|actor| audit_log::Column::Actor.eq(actor)))
+        .add_option(filter.action.map(|action| audit_log::Column::Action.eq(action)))
+        .add_option(
+            filter
+                .fantasy_tournament_id
+                .map(|id| audit_log::Column::FantasyTournamentId.eq(id)),
+        )
+        .add_option(
+            filter
+                .target_user
+                .map(|user| audit_log::Column::TargetUser.eq(user)),
+        )
+        .add_option(since.map(|since| audit_log::Column::CreatedAt.gte(since)))
+        .add_option(filter.before.map(|id| audit_log::Column::Id.lt(id)));
+    Ok(AuditLog::find()
+        .filter(condition)
+        .find_also_related(User)
+        .order_by_desc(audit_log::Column::Id)
+        .limit(filter.limit.unwrap_or(AUDIT_LIST_LIMIT).min(AUDIT_LIST_MAX_LIMIT))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get audit log from database"))?
+        .into_iter()
+        .map(|(entry, actor)| AuditEntry {
+            id: entry.id,
+            actor: entry.actor,
+            actor_name: actor.map(|a| a.name),
+            action: entry.action,
+            fantasy_tournament_id: entry.fantasy_tournament_id,
+            target_user: entry.target_user,
+            summary: entry.summary,
+            ip: entry.ip,
+            created_at: entry.created_at,
+        })
+        .collect_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_summaries_are_cut_on_char_boundaries() {
+        assert_eq!(truncate("short".to_string()), "short");
+        let long = "å".repeat(SUMMARY_MAX_CHARS + 10);
+        let cut = truncate(long);
+        assert_eq!(cut.chars().count(), SUMMARY_MAX_CHARS + 1);
+        assert!(cut.ends_with('…'));
+        assert_eq!(
+            summarize(&serde_json::json!({"name": "Spring league"})).as_deref(),
+            Some(r#"{"name":"Spring league"}"#)
+        );
+    }
+}
@@ -0,0 +1,1013 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, Utc};
+use entity::prelude::*;
+use entity::sea_orm_active_enums::*;
+use entity::*;
+use itertools::Itertools;
+use rocket_okapi::JsonSchema;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    ActiveEnum, ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::GenericError;
+use crate::mutation::INSERT_CHUNK_SIZE;
+
+/// Version of the archive format, bumped whenever a field is added, removed or changes meaning
+pub const ARCHIVE_VERSION: u32 = 3;
+/// The oldest version that can still be restored, fields added since then are optional
+const OLDEST_VERSION: u32 = 1;
+
+/// A self-contained copy of a fantasy tournament, with the competitions and players it refers to.
+///
+/// Users are referred to by their id on the server the archive was made on, and matched by name when restored.
+/// Enum values are stored the way the database stores them.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct TournamentArchive {
+    pub version: u32,
+    pub exported_at: DateTimeWithTimeZone,
+    pub tournament: ArchivedTournament,
+    pub users: Vec<ArchivedUser>,
+    pub participants: Vec<ArchivedParticipant>,
+    pub divisions: Vec<String>,
+    pub players: Vec<ArchivedPlayer>,
+    pub player_divisions: Vec<ArchivedPlayerDivision>,
+    pub competitions: Vec<ArchivedCompetition>,
+    pub picks: Vec<ArchivedPick>,
+    pub trades: Vec<ArchivedTrade>,
+    pub user_scores: Vec<ArchivedUserScore>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedTournament {
+    pub name: String,
+    pub owner: i32,
+    pub max_picks_per_user: i32,
+    pub bench_size: i32,
+    pub archived_at: Option<DateTimeWithTimeZone>,
+    pub join_requests_enabled: bool,
+    pub captain_multiplier_percent: i32,
+    pub vice_captains_enabled: bool,
+    pub substitution_policy: String,
+    pub min_fpo_picks: Option<i32>,
+    pub max_fpo_picks: Option<i32>,
+    pub max_picks_per_country: Option<i32>,
+    pub top_rated_pool_size: Option<i32>,
+    pub max_top_rated_picks: Option<i32>,
+    pub budget_per_division: Option<i32>,
+    /// Missing before version 3, where every tournament was readable by members only
+    #[serde(default)]
+    pub visibility: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedUser {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedParticipant {
+    pub user: i32,
+    pub invitation_status: String,
+    /// Missing in version 1, where the owner was the only participant with a role
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedPlayer {
+    pub pdga_number: i32,
+    pub first_name: String,
+    pub last_name: String,
+    pub avatar: Option<String>,
+    pub country: Option<String>,
+    pub rating: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedPlayerDivision {
+    pub pdga_number: i32,
+    pub division: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedCompetition {
+    /// The PDGA event id, which is kept as is
+    pub id: i32,
+    pub name: String,
+    pub status: String,
+    pub round_count: i32,
+    pub level: String,
+    pub ended_at: Option<DateTimeWithTimeZone>,
+    pub start_date: NaiveDate,
+    pub timezone: Option<String>,
+    pub rounds: Vec<ArchivedRound>,
+    pub players: Vec<ArchivedPlayerDivision>,
+    pub scores: Vec<ArchivedRoundScore>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedRound {
+    pub round_number: i32,
+    pub date: DateTimeWithTimeZone,
+    pub status: String,
+    pub round_type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedRoundScore {
+    pub pdga_number: i32,
+    pub round: i32,
+    pub throws: i32,
+    pub division: String,
+    pub placement: i32,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedPick {
+    pub user: i32,
+    pub pdga_number: i32,
+    pub slot: i32,
+    pub division: String,
+    pub benched: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedTrade {
+    pub user: i32,
+    pub pdga_number: i32,
+    pub slot: i32,
+    pub timestamp: DateTimeWithTimeZone,
+    pub is_local_swap: bool,
+    pub other_pdga_number: Option<i32>,
+    pub other_slot: Option<i32>,
+    pub is_removal: bool,
+    pub is_revert: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArchivedUserScore {
+    pub user: i32,
+    pub competition_id: i32,
+    pub pdga_number: i32,
+    pub score: i32,
+}
+
+fn value<E: ActiveEnum<Value = String>>(e: &E) -> String {
+    e.to_value()
+}
+
+fn parse<E: ActiveEnum<Value = String>>(value: &str) -> Result<E, GenericError> {
+    E::try_from_value(&value.to_string())
+        .map_err(|_| GenericError::BadRequest("Archive has an unknown enum value"))
+}
+
+pub async fn backup_tournament(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<TournamentArchive, GenericError> {
+    let tournament = FantasyTournament::find_by_id(tournament_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament from database"))?
+        .ok_or(GenericError::NotFound("Tournament not found"))?;
+    let participants = UserInFantasyTournament::find()
+        .filter(user_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get participants from database"))?;
+    let divisions = FantasyTournamentDivision::find()
+        .filter(fantasy_tournament_division::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get divisions from database"))?;
+    let player_divisions = PlayerDivisionInFantasyTournament::find()
+        .filter(player_division_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get player divisions from database"))?;
+    let picks = FantasyPick::find()
+        .filter(fantasy_pick::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?;
+    let trades = PlayerTrade::find()
+        .filter(player_trade::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get trades from database"))?;
+    let user_scores = UserCompetitionScoreInFantasyTournament::find()
+        .filter(user_competition_score_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get user scores from database"))?;
+    let competition_ids = CompetitionInFantasyTournament::find()
+        .filter(competition_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competitions from database"))?
+        .into_iter()
+        .map(|c| c.competition_id)
+        .collect_vec();
+    let competitions = archive_competitions(db, competition_ids).await?;
+
+    let user_ids: HashSet<i32> = std::iter::once(tournament.owner)
+        .chain(participants.iter().map(|p| p.user_id))
+        .chain(picks.iter().map(|p| p.user))
+        .chain(trades.iter().map(|t| t.user))
+        .chain(user_scores.iter().map(|s| s.user))
+        .collect();
+    let users = User::find()
+        .filter(user::Column::Id.is_in(user_ids))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get users from database"))?
+        .into_iter()
+        .sorted_by_key(|u| u.id)
+        .map(|u| ArchivedUser {
+            id: u.id,
+            name: u.name,
+        })
+        .collect();
+
+    let pdga_numbers: HashSet<i32> = player_divisions
+        .iter()
+        .map(|p| p.player_pdga_number)
+        .chain(picks.iter().map(|p| p.player))
+        .chain(
+            trades
+                .iter()
+                .flat_map(|t| std::iter::once(t.player).chain(t.other_player)),
+        )
+        .chain(user_scores.iter().map(|s| s.pdga_number))
+        .chain(
+            competitions
+                .iter()
+                .flat_map(|c| c.players.iter().map(|p| p.pdga_number)),
+        )
+        .chain(
+            competitions
+                .iter()
+                .flat_map(|c| c.scores.iter().map(|s| s.pdga_number)),
+        )
+        .collect();
+    let players = Player::find()
+        .filter(player::Column::PdgaNumber.is_in(pdga_numbers))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get players from database"))?
+        .into_iter()
+        .sorted_by_key(|p| p.pdga_number)
+        .map(|p| ArchivedPlayer {
+            pdga_number: p.pdga_number,
+            first_name: p.first_name,
+            last_name: p.last_name,
+            avatar: p.avatar,
+            country: p.country,
+            rating: p.rating,
+        })
+        .collect();
+
+    Ok(TournamentArchive {
+        version: ARCHIVE_VERSION,
+        exported_at: Utc::now().fixed_offset(),
+        tournament: ArchivedTournament {
+            name: tournament.name,
+            owner: tournament.owner,
+            max_picks_per_user: tournament.max_picks_per_user,
+            bench_size: tournament.bench_size,
+            archived_at: tournament.archived_at,
+            join_requests_enabled: tournament.join_requests_enabled,
+            captain_multiplier_percent: tournament.captain_multiplier_percent,
+            vice_captains_enabled: tournament.vice_captains_enabled,
+            substitution_policy: value(&tournament.substitution_policy),
+            min_fpo_picks: tournament.min_fpo_picks,
+            max_fpo_picks: tournament.max_fpo_picks,
+            max_picks_per_country: tournament.max_picks_per_country,
+            top_rated_pool_size: tournament.top_rated_pool_size,
+            max_top_rated_picks: tournament.max_top_rated_picks,
+            budget_per_division: tournament.budget_per_division,
+            visibility: Some(value(&tournament.visibility)),
+        },
+        users,
+        participants: participants
+            .into_iter()
+            .sorted_by_key(|p| p.id)
+            .map(|p| ArchivedParticipant {
+                user: p.user_id,
+                invitation_status: value(&p.invitation_status),
+                role: Some(value(&p.role)),
+            })
+            .collect(),
+        divisions: divisions
+            .into_iter()
+            .sorted_by_key(|d| d.id)
+            .map(|d| value(&d.division))
+            .collect(),
+        players,
+        player_divisions: player_divisions
+            .into_iter()
+            .sorted_by_key(|p| p.player_pdga_number)
+            .map(|p| ArchivedPlayerDivision {
+                pdga_number: p.player_pdga_number,
+                division: value(&p.division),
+            })
+            .collect(),
+        competitions,
+        picks: picks
+            .into_iter()
+            .sorted_by_key(|p| p.id)
+            .map(|p| ArchivedPick {
+                user: p.user,
+                pdga_number: p.player,
+                slot: p.pick_number,
+                division: value(&p.division),
+                benched: p.benched,
+            })
+            .collect(),
+        trades: trades
+            .into_iter()
+            .sorted_by_key(|t| t.id)
+            .map(|t| ArchivedTrade {
+                user: t.user,
+                pdga_number: t.player,
+                slot: t.slot,
+                timestamp: t.timestamp,
+                is_local_swap: t.is_local_swap,
+                other_pdga_number: t.other_player,
+                other_slot: t.other_slot,
+                is_removal: t.is_removal,
+                is_revert: t.is_revert,
+            })
+            .collect(),
+        user_scores: user_scores
+            .into_iter()
+            .sorted_by_key(|s| s.id)
+            .map(|s| ArchivedUserScore {
+                user: s.user,
+                competition_id: s.competition_id,
+                pdga_number: s.pdga_number,
+                score: s.score,
+            })
+            .collect(),
+    })
+}
+
+async fn archive_competitions(
+    db: &impl ConnectionTrait,
+    competition_ids: Vec<i32>,
+) -> Result<Vec<ArchivedCompetition>, GenericError> {
+    let competitions = Competition::find()
+        .filter(competition::Column::Id.is_in(competition_ids.clone()))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competitions from database"))?;
+    let mut rounds = Round::find()
+        .filter(round::Column::CompetitionId.is_in(competition_ids.clone()))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get rounds from database"))?
+        .into_iter()
+        .into_group_map_by(|r| r.competition_id);
+    let mut players = PlayerInCompetition::find()
+        .filter(player_in_competition::Column::CompetitionId.is_in(competition_ids.clone()))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competition players from database"))?
+        .into_iter()
+        .into_group_map_by(|p| p.competition_id);
+    let mut scores = PlayerRoundScore::find()
+        .filter(player_round_score::Column::CompetitionId.is_in(competition_ids))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?
+        .into_iter()
+        .into_group_map_by(|s| s.competition_id);
+
+    Ok(competitions
+        .into_iter()
+        .sorted_by_key(|c| (c.start_date, c.id))
+        .map(|c| ArchivedCompetition {
+            rounds: rounds
+                .remove(&c.id)
+                .unwrap_or_default()
+                .into_iter()
+                .sorted_by_key(|r| r.round_number)
+                .map(|r| ArchivedRound {
+                    round_number: r.round_number,
+                    date: r.date,
+                    status: value(&r.status),
+                    round_type: r.round_type.as_ref().map(value),
+                })
+                .collect(),
+            players: players
+                .remove(&c.id)
+                .unwrap_or_default()
+                .into_iter()
+                .sorted_by_key(|p| p.pdga_number)
+                .map(|p| ArchivedPlayerDivision {
+                    pdga_number: p.pdga_number,
+                    division: value(&p.division),
+                })
+                .collect(),
+            scores: scores
+                .remove(&c.id)
+                .unwrap_or_default()
+                .into_iter()
+                .sorted_by_key(|s| (s.round, s.pdga_number))
+                .map(|s| ArchivedRoundScore {
+                    pdga_number: s.pdga_number,
+                    round: s.round,
+                    throws: s.throws,
+                    division: value(&s.division),
+                    placement: s.placement,
+                })
+                .collect(),
+            id: c.id,
+            name: c.name,
+            status: value(&c.status),
+            round_count: c.rounds,
+            level: value(&c.level),
+            ended_at: c.ended_at,
+            start_date: c.start_date,
+            timezone: c.timezone,
+        })
+        .collect())
+}
+
+/// Reads an archive, refusing versions this server doesn't know how to restore.
+pub fn parse_archive(input: &str) -> Result<TournamentArchive, GenericError> {
+    let archive: serde_json::Value =
+        serde_json::from_str(input).map_err(|_| GenericError::BadRequest("Archive is not valid JSON"))?;
+    let version = archive.get("version").and_then(serde_json::Value::as_u64);
+    if !version.is_some_and(|v| (OLDEST_VERSION as u64..=ARCHIVE_VERSION as u64).contains(&v)) {
+        Err(GenericError::BadRequest("Unsupported archive version"))?
+    }
+    serde_json::from_value(archive).map_err(|_| GenericError::BadRequest("Archive is malformed"))
+}
+
+/// Makes sure every user, player and competition the archive refers to is part of it,
+/// so a restore can't fail halfway on a missing reference.
+fn check_references(archive: &TournamentArchive) -> Result<(), GenericError> {
+    let users: HashSet<i32> = archive.users.iter().map(|u| u.id).collect();
+    let players: HashSet<i32> = archive.players.iter().map(|p| p.pdga_number).collect();
+    let competitions: HashSet<i32> = archive.competitions.iter().map(|c| c.id).collect();
+
+    let referenced_users = std::iter::once(archive.tournament.owner)
+        .chain(archive.participants.iter().map(|p| p.user))
+        .chain(archive.picks.iter().map(|p| p.user))
+        .chain(archive.trades.iter().map(|t| t.user))
+        .chain(archive.user_scores.iter().map(|s| s.user));
+    if !referenced_users.into_iter().all(|u| users.contains(&u)) {
+        Err(GenericError::BadRequest(
+            "Archive refers to a user it doesn't contain",
+        ))?
+    }
+    let referenced_players = archive
+        .player_divisions
+        .iter()
+        .map(|p| p.pdga_number)
+        .chain(archive.picks.iter().map(|p| p.pdga_number))
+        .chain(
+            archive
+                .trades
+                .iter()
+                .flat_map(|t| std::iter::once(t.pdga_number).chain(t.other_pdga_number)),
+        )
+        .chain(archive.user_scores.iter().map(|s| s.pdga_number))
+        .chain(archive.competitions.iter().flat_map(|c| {
+            c.players
+                .iter()
+                .map(|p| p.pdga_number)
+                .chain(c.scores.iter().map(|s| s.pdga_number))
+        }));
+    if !referenced_players.into_iter().all(|p| players.contains(&p)) {
+        Err(GenericError::BadRequest(
+            "Archive refers to a player it doesn't contain",
+        ))?
+    }
+    if !archive
+        .user_scores
+        .iter()
+        .all(|s| competitions.contains(&s.competition_id))
+    {
+        Err(GenericError::BadRequest(
+            "Archive refers to a competition it doesn't contain",
+        ))?
+    }
+    if archive
+        .users
+        .iter()
+        .map(|u| &u.name)
+        .duplicates()
+        .next()
+        .is_some()
+    {
+        Err(GenericError::BadRequest(
+            "Archive has two users with the same name",
+        ))?
+    }
+    Ok(())
+}
+
+async fn insert_all<A>(
+    db: &impl ConnectionTrait,
+    models: Vec<A>,
+    error: &'static str,
+) -> Result<(), GenericError>
+where
+    A: ActiveModelTrait + Send,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+{
+    for chunk in models.chunks(INSERT_CHUNK_SIZE) {
+        <A::Entity as EntityTrait>::insert_many(chunk.to_vec())
+            .exec_without_returning(db)
+            .await
+            .map_err(|_| GenericError::UnknownError(error))?;
+    }
+    Ok(())
+}
+
+/// Restores an archive as a new tournament and returns its id.
+/// Should be run in a transaction, so that nothing is left behind when the archive can't be restored.
+///
+/// Users are matched by name, and the ones missing on this server are created without a password,
+/// which [`crate::update_password`] can set.
+/// Competitions, players and player divisions that already exist are left as they are,
+/// the rest are inserted from the archive.
+pub async fn restore_tournament(
+    db: &impl ConnectionTrait,
+    archive: TournamentArchive,
+    name: Option<String>,
+) -> Result<i32, GenericError> {
+    check_references(&archive)?;
+
+    let mut existing_users: HashMap<String, i32> = User::find()
+        .filter(user::Column::Name.is_in(archive.users.iter().map(|u| u.name.clone())))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get users from database"))?
+        .into_iter()
+        .map(|u| (u.name, u.id))
+        .collect();
+    let mut users = HashMap::with_capacity(archive.users.len());
+    for archived in &archive.users {
+        let id = match existing_users.remove(&archived.name) {
+            Some(id) => id,
+            None => {
+                User::insert(user::ActiveModel {
+                    id: NotSet,
+                    name: Set(archived.name.clone()),
+                    admin: Set(false),
+                    moderator: Set(false),
+                })
+                .exec(db)
+                .await
+                .map_err(|_| GenericError::UnknownError("Unable to create user"))?
+                .last_insert_id
+            }
+        };
+        users.insert(archived.id, id);
+    }
+
+    let existing_players: HashSet<i32> = Player::find()
+        .filter(player::Column::PdgaNumber.is_in(archive.players.iter().map(|p| p.pdga_number)))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get players from database"))?
+        .into_iter()
+        .map(|p| p.pdga_number)
+        .collect();
+    let players = archive
+        .players
+        .iter()
+        .filter(|p| !existing_players.contains(&p.pdga_number))
+        .map(|p| player::ActiveModel {
+            pdga_number: Set(p.pdga_number),
+            first_name: Set(p.first_name.clone()),
+            last_name: Set(p.last_name.clone()),
+            avatar: Set(p.avatar.clone()),
+            country: Set(p.country.clone()),
+            rating: Set(p.rating),
+        })
+        .collect_vec();
+    insert_all(db, players, "Unable to insert players").await?;
+
+    let existing_competitions: HashSet<i32> = Competition::find()
+        .filter(competition::Column::Id.is_in(archive.competitions.iter().map(|c| c.id)))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competitions from database"))?
+        .into_iter()
+        .map(|c| c.id)
+        .collect();
+    for archived in archive
+        .competitions
+        .iter()
+        .filter(|c| !existing_competitions.contains(&c.id))
+    {
+        restore_competition(db, archived).await?;
+    }
+
+    let settings = &archive.tournament;
+    let visibility: Option<TournamentVisibility> = settings.visibility.as_deref().map(parse).transpose()?;
+    let tournament_id = FantasyTournament::insert(fantasy_tournament::ActiveModel {
+        id: NotSet,
+        name: Set(name.unwrap_or_else(|| settings.name.clone())),
+        owner: Set(users[&settings.owner]),
+        max_picks_per_user: Set(settings.max_picks_per_user),
+        bench_size: Set(settings.bench_size),
+        archived_at: Set(settings.archived_at),
+        join_requests_enabled: Set(settings.join_requests_enabled),
+        captain_multiplier_percent: Set(settings.captain_multiplier_percent),
+        vice_captains_enabled: Set(settings.vice_captains_enabled),
+        substitution_policy: Set(parse(&settings.substitution_policy)?),
+        min_fpo_picks: Set(settings.min_fpo_picks),
+        max_fpo_picks: Set(settings.max_fpo_picks),
+        max_picks_per_country: Set(settings.max_picks_per_country),
+        top_rated_pool_size: Set(settings.top_rated_pool_size),
+        max_top_rated_picks: Set(settings.max_top_rated_picks),
+        budget_per_division: Set(settings.budget_per_division),
+        // The slug stays with the original, a restored public tournament gets its own
+        public_slug: match visibility {
+            Some(TournamentVisibility::Public) => Set(Some(crate::visibility::new_slug())),
+            _ => NotSet,
+        },
+        visibility: visibility.map_or(NotSet, Set),
+    })
+    .exec(db)
+    .await
+    .map_err(|_| GenericError::UnknownError("Unable to create tournament"))?
+    .last_insert_id;
+
+    let divisions = archive
+        .divisions
+        .iter()
+        .map(|d| {
+            Ok(fantasy_tournament_division::ActiveModel {
+                id: NotSet,
+                fantasy_tournament_id: Set(tournament_id),
+                division: Set(parse(d)?),
+            })
+        })
+        .collect::<Result<Vec<_>, GenericError>>()?;
+    insert_all(db, divisions, "Unable to insert divisions").await?;
+
+    let participants = archive
+        .participants
+        .iter()
+        .map(|p| {
+            Ok(user_in_fantasy_tournament::ActiveModel {
+                id: NotSet,
+                user_id: Set(users[&p.user]),
+                fantasy_tournament_id: Set(tournament_id),
+                invitation_status: Set(parse(&p.invitation_status)?),
+                role: Set(match &p.role {
+                    Some(role) => parse(role)?,
+                    None if p.user == settings.owner => TournamentRole::Owner,
+                    None => TournamentRole::Member,
+                }),
+            })
+        })
+        .collect::<Result<Vec<_>, GenericError>>()?;
+    insert_all(db, participants, "Unable to insert participants").await?;
+
+    // Divisions are stored per player, not per tournament, so players that are already here keep theirs
+    let existing_player_divisions: HashSet<i32> = PlayerDivisionInFantasyTournament::find()
+        .filter(
+            player_division_in_fantasy_tournament::Column::PlayerPdgaNumber
+                .is_in(archive.player_divisions.iter().map(|p| p.pdga_number)),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get player divisions from database"))?
+        .into_iter()
+        .map(|p| p.player_pdga_number)
+        .collect();
+    let player_divisions = archive
+        .player_divisions
+        .iter()
+        .filter(|p| !existing_player_divisions.contains(&p.pdga_number))
+        .map(|p| {
+            Ok(player_division_in_fantasy_tournament::ActiveModel {
+                player_pdga_number: Set(p.pdga_number),
+                fantasy_tournament_id: Set(tournament_id),
+                division: Set(parse(&p.division)?),
+            })
+        })
+        .collect::<Result<Vec<_>, GenericError>>()?;
+    insert_all(db, player_divisions, "Unable to insert player divisions").await?;
+
+    let competitions = archive
+        .competitions
+        .iter()
+        .map(|c| competition_in_fantasy_tournament::ActiveModel {
+            id: NotSet,
+            competition_id: Set(c.id),
+            fantasy_tournament_id: Set(tournament_id),
+        })
+        .collect_vec();
+    insert_all(db, competitions, "Unable to insert tournament competitions").await?;
+
+    let picks = archive
+        .picks
+        .iter()
+        .map(|p| {
+            Ok(fantasy_pick::ActiveModel {
+                id: NotSet,
+                user: Set(users[&p.user]),
+                player: Set(p.pdga_number),
+                fantasy_tournament_id: Set(tournament_id),
+                pick_number: Set(p.slot),
+                division: Set(parse(&p.division)?),
+                benched: Set(p.benched),
+            })
+        })
+        .collect::<Result<Vec<_>, GenericError>>()?;
+    insert_all(db, picks, "Unable to insert picks").await?;
+
+    let trades = archive
+        .trades
+        .iter()
+        .map(|t| player_trade::ActiveModel {
+            id: NotSet,
+            user: Set(users[&t.user]),
+            player: Set(t.pdga_number),
+            slot: Set(t.slot),
+            fantasy_tournament_id: Set(tournament_id),
+            timestamp: Set(t.timestamp),
+            is_local_swap: Set(t.is_local_swap),
+            other_player: Set(t.other_pdga_number),
+            other_slot: Set(t.other_slot),
+            is_removal: Set(t.is_removal),
+            is_revert: Set(t.is_revert),
+        })
+        .collect_vec();
+    insert_all(db, trades, "Unable to insert trades").await?;
+
+    let user_scores = archive
+        .user_scores
+        .iter()
+        .map(|s| user_competition_score_in_fantasy_tournament::ActiveModel {
+            id: NotSet,
+            user: Set(users[&s.user]),
+            fantasy_tournament_id: Set(tournament_id),
+            score: Set(s.score),
+            competition_id: Set(s.competition_id),
+            pdga_number: Set(s.pdga_number),
+        })
+        .collect_vec();
+    insert_all(db, user_scores, "Unable to insert user scores").await?;
+
+    Ok(tournament_id)
+}
+
+async fn restore_competition(
+    db: &impl ConnectionTrait,
+    archived: &ArchivedCompetition,
+) -> Result<(), GenericError> {
+    Competition::insert(competition::ActiveModel {
+        id: Set(archived.id),
+        name: Set(archived.name.clone()),
+        status: Set(parse(&archived.status)?),
+        rounds: Set(archived.round_count),
+        level: Set(parse(&archived.level)?),
+        ended_at: Set(archived.ended_at),
+        start_date: Set(archived.start_date),
+        timezone: Set(archived.timezone.clone()),
+    })
+    .exec_without_returning(db)
+    .await
+    .map_err(|_| GenericError::UnknownError("Unable to insert competition"))?;
+
+    let rounds = archived
+        .rounds
+        .iter()
+        .map(|r| {
+            Ok(round::ActiveModel {
+                id: NotSet,
+                round_number: Set(r.round_number),
+                competition_id: Set(archived.id),
+                date: Set(r.date),
+                status: Set(parse(&r.status)?),
+                round_type: Set(r.round_type.as_deref().map(parse::<RoundTypeEnum>).transpose()?),
+            })
+        })
+        .collect::<Result<Vec<_>, GenericError>>()?;
+    insert_all(db, rounds, "Unable to insert rounds").await?;
+
+    let players = archived
+        .players
+        .iter()
+        .map(|p| {
+            Ok(player_in_competition::ActiveModel {
+                id: NotSet,
+                pdga_number: Set(p.pdga_number),
+                competition_id: Set(archived.id),
+                division: Set(parse(&p.division)?),
+            })
+        })
+        .collect::<Result<Vec<_>, GenericError>>()?;
+    insert_all(db, players, "Unable to insert competition players").await?;
+
+    let scores = archived
+        .scores
+        .iter()
+        .map(|s| {
+            Ok(player_round_score::ActiveModel {
+                id: NotSet,
+                pdga_number: Set(s.pdga_number),
+                competition_id: Set(archived.id),
+                round: Set(s.round),
+                throws: Set(s.throws),
+                division: Set(parse(&s.division)?),
+                placement: Set(s.placement),
+            })
+        })
+        .collect::<Result<Vec<_>, GenericError>>()?;
+    insert_all(db, scores, "Unable to insert round scores").await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive() -> TournamentArchive {
+        let timestamp = DateTimeWithTimeZone::parse_from_rfc3339("2024-06-01T12:00:00+02:00").unwrap();
+        TournamentArchive {
+            version: ARCHIVE_VERSION,
+            exported_at: timestamp,
+            tournament: ArchivedTournament {
+                name: "League".to_string(),
+                owner: 7,
+                max_picks_per_user: 5,
+                bench_size: 0,
+                archived_at: None,
+                join_requests_enabled: false,
+                captain_multiplier_percent: 200,
+                vice_captains_enabled: false,
+                substitution_policy: value(&SubstitutionPolicy::Off),
+                min_fpo_picks: None,
+                max_fpo_picks: None,
+                max_picks_per_country: Some(2),
+                top_rated_pool_size: None,
+                max_top_rated_picks: None,
+                budget_per_division: None,
+                visibility: Some(value(&TournamentVisibility::MembersOnly)),
+            },
+            users: vec![ArchivedUser {
+                id: 7,
+                name: "owner".to_string(),
+            }],
+            participants: vec![ArchivedParticipant {
+                user: 7,
+                invitation_status: value(&FantasyTournamentInvitationStatus::Accepted),
+                role: Some(value(&TournamentRole::Owner)),
+            }],
+            divisions: vec![value(&Division::Mpo)],
+            players: vec![ArchivedPlayer {
+                pdga_number: 27523,
+                first_name: "Paul".to_string(),
+                last_name: "McBeth".to_string(),
+                avatar: None,
+                country: Some("US".to_string()),
+                rating: Some(1050),
+            }],
+            player_divisions: vec![],
+            competitions: vec![ArchivedCompetition {
+                id: 77758,
+                name: "European Open".to_string(),
+                status: value(&CompetitionStatus::Finished),
+                round_count: 1,
+                level: value(&CompetitionLevel::Major),
+                ended_at: Some(timestamp),
+                start_date: NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+                timezone: None,
+                rounds: vec![ArchivedRound {
+                    round_number: 1,
+                    date: timestamp,
+                    status: value(&CompetitionStatus::Finished),
+                    round_type: None,
+                }],
+                players: vec![ArchivedPlayerDivision {
+                    pdga_number: 27523,
+                    division: value(&Division::Mpo),
+                }],
+                scores: vec![ArchivedRoundScore {
+                    pdga_number: 27523,
+                    round: 1,
+                    throws: 54,
+                    division: value(&Division::Mpo),
+                    placement: 1,
+                }],
+            }],
+            picks: vec![ArchivedPick {
+                user: 7,
+                pdga_number: 27523,
+                slot: 1,
+                division: value(&Division::Mpo),
+                benched: false,
+            }],
+            trades: vec![ArchivedTrade {
+                user: 7,
+                pdga_number: 27523,
+                slot: 1,
+                timestamp,
+                is_local_swap: false,
+                other_pdga_number: None,
+                other_slot: None,
+                is_removal: false,
+                is_revert: false,
+            }],
+            user_scores: vec![ArchivedUserScore {
+                user: 7,
+                competition_id: 77758,
+                pdga_number: 27523,
+                score: 100,
+            }],
+        }
+    }
+
+    #[test]
+    fn archive_round_trips_through_json() {
+        let archive = archive();
+        let json = serde_json::to_string(&archive).unwrap();
+        let parsed = parse_archive(&json).unwrap();
+        assert_eq!(parsed, archive);
+        assert!(check_references(&parsed).is_ok());
+        assert_eq!(parse::<Division>(&parsed.divisions[0]).unwrap(), Division::Mpo);
+    }
+
+    #[test]
+    fn unknown_versions_and_dangling_references_are_refused() {
+        let mut older = serde_json::to_value(archive()).unwrap();
+        older["version"] = OLDEST_VERSION.into();
+        older["participants"][0].as_object_mut().unwrap().remove("role");
+        older["tournament"].as_object_mut().unwrap().remove("visibility");
+        assert_eq!(
+            parse_archive(&older.to_string()).unwrap().participants[0].role,
+            None
+        );
+
+        let mut newer = serde_json::to_value(archive()).unwrap();
+        newer["version"] = (ARCHIVE_VERSION + 1).into();
+        assert!(parse_archive(&newer.to_string()).is_err());
+
+        let mut missing_user = archive();
+        missing_user.picks[0].user = 8;
+        assert!(check_references(&missing_user).is_err());
+
+        let mut missing_player = archive();
+        missing_player.trades[0].other_pdga_number = Some(1);
+        assert!(check_references(&missing_player).is_err());
+
+        let mut missing_competition = archive();
+        missing_competition.competitions.clear();
+        assert!(check_references(&missing_competition).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn restores_onto_a_server_that_has_the_players() {
+        let txn = crate::test_db::begin().await;
+        let owner = crate::test_db::user(&txn, "owner").await;
+        let tournament = crate::test_db::tournament(&txn, owner.id).await;
+        crate::test_db::player(&txn, tournament, 1).await;
+        FantasyPick::insert(fantasy_pick::ActiveModel {
+            id: NotSet,
+            user: Set(owner.id),
+            player: Set(99_000_001),
+            fantasy_tournament_id: Set(tournament),
+            pick_number: Set(1),
+            division: Set(Division::Mpo),
+            benched: Set(false),
+        })
+        .exec_without_returning(&txn)
+        .await
+        .unwrap();
+
+        let archive = backup_tournament(&txn, tournament).await.unwrap();
+        assert_eq!(archive.player_divisions.len(), 1);
+        let restored = restore_tournament(&txn, archive, Some(format!("restored-{tournament}")))
+            .await
+            .unwrap();
+
+        let picks = FantasyPick::find()
+            .filter(fantasy_pick::Column::FantasyTournamentId.eq(restored))
+            .all(&txn)
+            .await
+            .unwrap();
+        assert_eq!(picks.len(), 1);
+        assert_eq!((picks[0].user, picks[0].player), (owner.id, 99_000_001));
+        let division = PlayerDivisionInFantasyTournament::find_by_id(99_000_001)
+            .one(&txn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(division.fantasy_tournament_id, tournament);
+    }
+}
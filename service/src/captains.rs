@@ -0,0 +1,427 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use entity::prelude::*;
+use entity::sea_orm_active_enums::CompetitionStatus;
+use entity::*;
+use itertools::Itertools;
+use log::error;
+use rocket_okapi::JsonSchema;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{sea_query, ColumnTrait, ConnectionTrait, EntityTrait, NotSet, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::{Division, UserScore};
+use crate::error::GenericError;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Captain {
+    pub user: i32,
+    pub division: Division,
+    pub captain: i32,
+    /// Takes over the multiplier if the captain doesn't start or finish
+    pub vice_captain: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct Captains {
+    pub competition_id: i32,
+    /// Points of captains in percent, 100 means captains are off
+    pub multiplier_percent: i32,
+    pub vice_captains_enabled: bool,
+    /// Whether the competition has started, after which captains can't be changed
+    pub locked: bool,
+    pub captains: Vec<Captain>,
+}
+
+impl From<fantasy_captain::Model> for Captain {
+    fn from(model: fantasy_captain::Model) -> Self {
+        Self {
+            user: model.user_id,
+            division: model.division.into(),
+            captain: model.captain,
+            vice_captain: model.vice_captain,
+        }
+    }
+}
+
+async fn tournament(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<fantasy_tournament::Model, GenericError> {
+    FantasyTournament::find_by_id(tournament_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament from database"))?
+        .ok_or(GenericError::NotFound("Tournament not found"))
+}
+
+/// The competition, if it is part of the tournament
+async fn competition_in_tournament(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+) -> Result<competition::Model, GenericError> {
+    let added = CompetitionInFantasyTournament::find()
+        .filter(
+            competition_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(competition_in_fantasy_tournament::Column::CompetitionId.eq(competition_id)),
+        )
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competitions from database"))?
+        .is_some();
+    if !added {
+        return Err(GenericError::NotFound(
+            "Competition is not part of the tournament",
+        ));
+    }
+    Competition::find_by_id(competition_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to find competition"))?
+        .ok_or(GenericError::NotFound("Competition not found"))
+}
+
+async fn assure_unlocked(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+) -> Result<(), GenericError> {
+    let competition = competition_in_tournament(db, tournament_id, competition_id).await?;
+    if competition.status != CompetitionStatus::NotStarted {
+        Err(GenericError::NotPermitted(
+            "Captains are locked once the competition has started",
+        ))?
+    }
+    Ok(())
+}
+
+async fn assure_picked(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    tournament_id: i32,
+    division: Division,
+    pdga_number: i32,
+) -> Result<(), GenericError> {
+    FantasyPick::find()
+        .filter(
+            fantasy_pick::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(fantasy_pick::Column::User.eq(user_id))
+                .and(fantasy_pick::Column::Player.eq(pdga_number))
+                .and(fantasy_pick::Column::Division.eq(sea_orm_active_enums::Division::from(division))),
+        )
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?
+        .ok_or(GenericError::BadRequest(
+            "Captains have to be picked by the user in the division",
+        ))?;
+    Ok(())
+}
+
+/// Designates the captain, and optionally the vice-captain, of a user in a division for a competition.
+pub async fn set_captain(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    tournament_id: i32,
+    competition_id: i32,
+    division: Division,
+    captain: i32,
+    vice_captain: Option<i32>,
+) -> Result<(), GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let tournament = tournament(db, tournament_id).await?;
+    if tournament.captain_multiplier_percent <= 100 {
+        Err(GenericError::BadRequest(
+            "Captains are not enabled in this tournament",
+        ))?
+    }
+    if vice_captain.is_some() && !tournament.vice_captains_enabled {
+        Err(GenericError::BadRequest(
+            "Vice-captains are not enabled in this tournament",
+        ))?
+    }
+    if vice_captain == Some(captain) {
+        Err(GenericError::BadRequest(
+            "The vice-captain has to be another player than the captain",
+        ))?
+    }
+    assure_unlocked(db, tournament_id, competition_id).await?;
+    assure_picked(db, user_id, tournament_id, division, captain).await?;
+    if let Some(vice_captain) = vice_captain {
+        assure_picked(db, user_id, tournament_id, division, vice_captain).await?;
+    }
+
+    FantasyCaptain::insert(fantasy_captain::ActiveModel {
+        id: NotSet,
+        fantasy_tournament_id: Set(tournament_id),
+        user_id: Set(user_id),
+        competition_id: Set(competition_id),
+        division: Set(division.into()),
+        captain: Set(captain),
+        vice_captain: Set(vice_captain),
+        updated_at: Set(Utc::now().fixed_offset()),
+    })
+    .on_conflict(
+        sea_query::OnConflict::columns([
+            fantasy_captain::Column::FantasyTournamentId,
+            fantasy_captain::Column::UserId,
+            fantasy_captain::Column::CompetitionId,
+            fantasy_captain::Column::Division,
+        ])
+        .update_columns([
+            fantasy_captain::Column::Captain,
+            fantasy_captain::Column::ViceCaptain,
+            fantasy_captain::Column::UpdatedAt,
+        ])
+        .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await
+    .map_err(|e| {
+        error!("Unable to save captain: {:#?}", e);
+        GenericError::UnknownError("Unable to save captain")
+    })?;
+    Ok(())
+}
+
+pub async fn remove_captain(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    tournament_id: i32,
+    competition_id: i32,
+    division: Division,
+) -> Result<(), GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    assure_unlocked(db, tournament_id, competition_id).await?;
+    FantasyCaptain::delete_many()
+        .filter(
+            fantasy_captain::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(fantasy_captain::Column::UserId.eq(user_id))
+                .and(fantasy_captain::Column::CompetitionId.eq(competition_id))
+                .and(fantasy_captain::Column::Division.eq(sea_orm_active_enums::Division::from(division))),
+        )
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to remove captain"))?;
+    Ok(())
+}
+
+async fn captain_models(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+) -> Result<Vec<fantasy_captain::Model>, GenericError> {
+    FantasyCaptain::find()
+        .filter(
+            fantasy_captain::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(fantasy_captain::Column::CompetitionId.eq(competition_id)),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get captains from database"))
+}
+
+pub async fn get_captains(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+) -> Result<Captains, GenericError> {
+    let tournament = tournament(db, tournament_id).await?;
+    let competition = competition_in_tournament(db, tournament_id, competition_id).await?;
+    let captains = captain_models(db, tournament_id, competition_id)
+        .await?
+        .into_iter()
+        .map(Captain::from)
+        .sorted_by_key(|c| (c.user, c.division as u8))
+        .collect_vec();
+    Ok(Captains {
+        competition_id,
+        multiplier_percent: tournament.captain_multiplier_percent,
+        vice_captains_enabled: tournament.vice_captains_enabled,
+        locked: competition.status != CompetitionStatus::NotStarted,
+        captains,
+    })
+}
+
+/// How captains multiply the counted points in a competition. Shared by the saved user scores,
+/// projections and simulations so they all agree on who is multiplied.
+pub(crate) struct CaptainMultiplier {
+    percent: i32,
+    vice_captains_enabled: bool,
+    captains: Vec<Captain>,
+    /// Players that withdrew or have no score, the vice-captain steps in for them
+    out: HashSet<i32>,
+}
+
+impl CaptainMultiplier {
+    pub(crate) fn new(
+        percent: i32,
+        vice_captains_enabled: bool,
+        captains: Vec<Captain>,
+        out: HashSet<i32>,
+    ) -> Self {
+        Self {
+            percent,
+            vice_captains_enabled,
+            captains,
+            out,
+        }
+    }
+
+    /// The stored captains of every user in the tournament.
+    pub(crate) async fn load(
+        db: &impl ConnectionTrait,
+        tournament_id: i32,
+        competition_id: i32,
+        player_scores: &HashMap<i32, i32>,
+    ) -> Result<Self, GenericError> {
+        let captains = captain_models(db, tournament_id, competition_id)
+            .await?
+            .into_iter()
+            .map(Captain::from)
+            .collect_vec();
+        Self::with_captains(db, tournament_id, competition_id, captains, player_scores).await
+    }
+
+    /// Captains that don't have to be stored, such as those of a simulated lineup.
+    pub(crate) async fn with_captains(
+        db: &impl ConnectionTrait,
+        tournament_id: i32,
+        competition_id: i32,
+        captains: Vec<Captain>,
+        player_scores: &HashMap<i32, i32>,
+    ) -> Result<Self, GenericError> {
+        let tournament = tournament(db, tournament_id).await?;
+        let percent = tournament.captain_multiplier_percent;
+        let vice_captains_enabled = tournament.vice_captains_enabled;
+        if percent <= 100 || captains.is_empty() {
+            return Ok(Self::new(percent, vice_captains_enabled, vec![], HashSet::new()));
+        }
+        let mut out = PlayerWithdrawal::find()
+            .filter(player_withdrawal::Column::CompetitionId.eq(competition_id))
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get withdrawals from database"))?
+            .into_iter()
+            .map(|w| w.pdga_number)
+            .collect::<HashSet<_>>();
+        out.extend(
+            captains
+                .iter()
+                .flat_map(|c| [Some(c.captain), c.vice_captain])
+                .flatten()
+                .filter(|pdga_number| !player_scores.contains_key(pdga_number)),
+        );
+        Ok(Self::new(percent, vice_captains_enabled, captains, out))
+    }
+
+    /// The (user, player) pairs among the counted ones that get the multiplier.
+    ///
+    /// The vice-captain only steps in when the captain didn't start or finish.
+    pub(crate) fn multiplied(&self, counted: &[(i32, i32)]) -> HashSet<(i32, i32)> {
+        if self.percent <= 100 {
+            return HashSet::new();
+        }
+        self.captains
+            .iter()
+            .filter_map(|c| {
+                if !self.out.contains(&c.captain) {
+                    Some(c.captain)
+                } else if self.vice_captains_enabled {
+                    c.vice_captain.filter(|vice| !self.out.contains(vice))
+                } else {
+                    None
+                }
+                .filter(|pdga_number| counted.contains(&(c.user, *pdga_number)))
+                .map(|pdga_number| (c.user, pdga_number))
+            })
+            .collect()
+    }
+
+    pub(crate) fn apply(&self, points: i32) -> i32 {
+        points * self.percent / 100
+    }
+
+    /// [`Self::apply`] for expected points, which aren't whole.
+    pub(crate) fn apply_expected(&self, points: f64) -> f64 {
+        points * self.percent as f64 / 100.0
+    }
+
+    /// Multiplies the counted scores of captains.
+    pub(crate) fn apply_to_scores(&self, scores: &mut [UserScore]) {
+        let counted = scores.iter().map(|s| (s.user, s.pdga_num as i32)).collect_vec();
+        let multiplied = self.multiplied(&counted);
+        for score in scores.iter_mut() {
+            if multiplied.contains(&(score.user, score.pdga_num as i32)) {
+                score.score = self.apply(score.score);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(user: i32, pdga_num: u32) -> UserScore {
+        UserScore {
+            user,
+            score: 50,
+            competition_id: 1,
+            pdga_num,
+            fantasy_tournament_id: 1,
+            benched: false,
+            slot: 1,
+            division: Division::MPO,
+        }
+    }
+
+    fn captain(user: i32, captain: i32, vice_captain: Option<i32>) -> Captain {
+        Captain {
+            user,
+            division: Division::MPO,
+            captain,
+            vice_captain,
+        }
+    }
+
+    fn multiplier(captains: Vec<Captain>, vice_captains_enabled: bool, out: &[i32]) -> CaptainMultiplier {
+        CaptainMultiplier::new(
+            150,
+            vice_captains_enabled,
+            captains,
+            out.iter().copied().collect(),
+        )
+    }
+
+    #[test]
+    fn vice_captain_only_steps_in_when_captain_is_out() {
+        let captains = vec![captain(1, 10, Some(11)), captain(2, 20, Some(21))];
+        let counted = [(1, 10), (1, 11), (2, 20), (2, 21)];
+
+        let multiplied = multiplier(captains.clone(), true, &[20]).multiplied(&counted);
+        assert_eq!(multiplied, HashSet::from([(1, 10), (2, 21)]));
+
+        let multiplied = multiplier(captains, false, &[20]).multiplied(&counted);
+        assert_eq!(multiplied, HashSet::from([(1, 10)]));
+    }
+
+    #[test]
+    fn benched_captains_are_not_multiplied() {
+        let multiplied = multiplier(vec![captain(1, 10, Some(11))], true, &[]).multiplied(&[(1, 11)]);
+        assert!(multiplied.is_empty());
+    }
+
+    #[test]
+    fn only_counted_captain_scores_are_multiplied() {
+        let mut scores = [score(1, 10), score(1, 11), score(2, 10)];
+        multiplier(vec![captain(1, 10, None)], false, &[]).apply_to_scores(&mut scores);
+        assert_eq!(scores.map(|s| s.score), [75, 50, 50]);
+    }
+}
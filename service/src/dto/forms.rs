@@ -1,5 +1,5 @@
-use super::CompetitionLevel;
 use super::JsonSchema;
+use super::{CompetitionLevel, Division};
 use serde_derive::{Deserialize, Serialize};
 
 #[derive(Debug, JsonSchema, Serialize, Deserialize)]
@@ -7,3 +7,83 @@ pub struct AddCompetition {
     pub competition_id: u32,
     pub level: CompetitionLevel,
 }
+
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct RevertTradesAfter {
+    pub after: chrono::DateTime<chrono::FixedOffset>,
+}
+
+/// Changes to the settings of a fantasy tournament, fields left out are kept as is.
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct UpdateTournamentSettings {
+    pub name: Option<String>,
+    pub max_picks_per_user: Option<i32>,
+    pub bench_size: Option<i32>,
+    pub divisions: Option<Vec<Division>>,
+    /// Lets users ask to join the tournament, to be approved by the owner
+    pub join_requests_enabled: Option<bool>,
+    /// Points of captains in percent, 100 turns captains off
+    pub captain_multiplier_percent: Option<i32>,
+    /// Lets a vice-captain take over the multiplier when the captain doesn't start or finish
+    pub vice_captains_enabled: Option<bool>,
+    pub substitution_policy: Option<crate::substitutions::SubstitutionPolicy>,
+    /// Replaces all roster rules of the tournament
+    pub roster_rules: Option<crate::roster_rules::RosterRules>,
+    /// Total price each user can spend on picks in a division, 0 turns the salary cap off
+    pub budget_per_division: Option<i32>,
+    /// Making the tournament public gives it a shareable page, see `public_slug`
+    pub visibility: Option<crate::visibility::Visibility>,
+    /// Removes picks that no longer fit the new settings instead of refusing the change
+    #[serde(default)]
+    pub trim_excess: bool,
+}
+
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct CreateInviteCode {
+    pub expires_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub max_uses: Option<i32>,
+}
+
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct CreateWebhook {
+    pub url: String,
+    pub format: crate::webhooks::WebhookFormat,
+}
+
+/// A hypothetical lineup to score, nothing is saved
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct SimulateLineup {
+    pub picks: Vec<super::FantasyPick>,
+    /// Captains of the lineup, scored with the multiplier of the tournament
+    #[serde(default)]
+    pub captains: Vec<SetCaptain>,
+}
+
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct SetCaptain {
+    pub division: Division,
+    pub captain: i32,
+    pub vice_captain: Option<i32>,
+}
+
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct AddPlaceholder {
+    pub name: String,
+    pub date: chrono::NaiveDate,
+    pub level: CompetitionLevel,
+}
+
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct SetPlayerPrice {
+    pub price: i32,
+}
+
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+pub struct DeleteAccount {
+    /// The current password, to confirm the deletion
+    pub password: String,
+    #[serde(default)]
+    pub mode: crate::account::DeletionMode,
+    /// Who takes over the tournaments you own, defaults to the participant that joined each first
+    pub new_owner: Option<i32>,
+}
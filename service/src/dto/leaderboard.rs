@@ -0,0 +1,518 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use entity::prelude::*;
+use entity::sea_orm_active_enums::{CompetitionStatus, RoundTypeEnum};
+use entity::*;
+use itertools::Itertools;
+use rocket_okapi::JsonSchema;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+use super::{CompetitionLevel, Division, User};
+use crate::error::GenericError;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+pub enum LiveStatus {
+    NotStarted,
+    Running,
+    Finished,
+}
+
+impl From<CompetitionStatus> for LiveStatus {
+    fn from(status: CompetitionStatus) -> Self {
+        match status {
+            CompetitionStatus::NotStarted => Self::NotStarted,
+            CompetitionStatus::Running => Self::Running,
+            CompetitionStatus::Finished => Self::Finished,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+pub enum RoundType {
+    Round,
+    Playoff,
+    Final,
+    Unknown,
+}
+
+impl From<RoundTypeEnum> for RoundType {
+    fn from(round_type: RoundTypeEnum) -> Self {
+        match round_type {
+            RoundTypeEnum::Round => Self::Round,
+            RoundTypeEnum::Playoff => Self::Playoff,
+            RoundTypeEnum::Final => Self::Final,
+            RoundTypeEnum::Unknown => Self::Unknown,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct RoundLayout {
+    pub division: Division,
+    pub par: i32,
+    /// Course length in metres, if PDGA has the length of every hole
+    pub course_length: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct RoundDetails {
+    pub round_number: i32,
+    /// Start of the round, in the timezone of the competition when known
+    pub date: DateTimeWithTimeZone,
+    pub status: LiveStatus,
+    pub round_type: RoundType,
+    /// The layout of each division, empty until the round has been fetched
+    pub layouts: Vec<RoundLayout>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct CompetitionDetails {
+    pub competition_id: i32,
+    pub name: String,
+    pub level: CompetitionLevel,
+    pub status: LiveStatus,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub ended_at: Option<DateTimeWithTimeZone>,
+    /// IANA name of the timezone the competition is played in
+    pub timezone: Option<String>,
+    pub rounds: Vec<RoundDetails>,
+    pub divisions: Vec<Division>,
+}
+
+async fn find_competition(
+    db: &impl ConnectionTrait,
+    competition_id: i32,
+) -> Result<competition::Model, GenericError> {
+    Competition::find_by_id(competition_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competition from database"))?
+        .ok_or(GenericError::NotFound("Competition not found"))
+}
+
+/// The holes of every round, by division and round number.
+async fn round_holes(
+    db: &impl ConnectionTrait,
+    competition_id: i32,
+) -> Result<HashMap<(Division, i32), Vec<round_hole::Model>>, GenericError> {
+    Ok(RoundHole::find()
+        .filter(round_hole::Column::CompetitionId.eq(competition_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round layouts from database"))?
+        .into_iter()
+        .into_group_map_by(|h| (h.division.clone().into(), h.round)))
+}
+
+impl CompetitionDetails {
+    pub async fn from_db(db: &impl ConnectionTrait, competition_id: i32) -> Result<Self, GenericError> {
+        let competition = find_competition(db, competition_id).await?;
+        let timezone = competition
+            .timezone
+            .as_deref()
+            .and_then(|tz| tz.parse::<chrono_tz::Tz>().ok());
+        let holes = round_holes(db, competition_id).await?;
+        let rounds = Round::find()
+            .filter(round::Column::CompetitionId.eq(competition_id))
+            .order_by_asc(round::Column::RoundNumber)
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get rounds from database"))?
+            .into_iter()
+            .map(|round| {
+                let layouts = holes
+                    .iter()
+                    .filter(|((_, round_number), _)| *round_number == round.round_number)
+                    .map(|((division, _), holes)| RoundLayout {
+                        division: *division,
+                        par: holes.iter().map(|h| h.par).sum(),
+                        course_length: holes.iter().map(|h| h.length).sum(),
+                    })
+                    .sorted_by_key(|l| l.division as u8)
+                    .collect_vec();
+                RoundDetails {
+                    round_number: round.round_number,
+                    date: timezone
+                        .map(|tz| round.date.with_timezone(&tz).fixed_offset())
+                        .unwrap_or(round.date),
+                    status: round.status.into(),
+                    round_type: round.round_type.map_or(RoundType::Unknown, RoundType::from),
+                    layouts,
+                }
+            })
+            .collect_vec();
+        let divisions = PlayerInCompetition::find()
+            .filter(player_in_competition::Column::CompetitionId.eq(competition_id))
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get players in competition from database"))?
+            .into_iter()
+            .map(|p| Division::from(p.division))
+            .sorted_by_key(|d| *d as u8)
+            .dedup()
+            .collect_vec();
+
+        Ok(Self {
+            competition_id,
+            name: competition.name,
+            level: competition.level.into(),
+            status: competition.status.into(),
+            start_date: competition.start_date,
+            end_date: rounds.last().map(|r| r.date.date_naive()),
+            ended_at: competition.ended_at,
+            timezone: competition.timezone,
+            rounds,
+            divisions,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    /// Placement after the latest round the player has a score in
+    pub placement: i32,
+    pub tied: bool,
+    pub pdga_number: i32,
+    pub name: String,
+    pub division: Division,
+    /// Throws in each round, starting with round 1
+    pub rounds: Vec<Option<i32>>,
+    pub throws: i32,
+    /// Score relative to par, if the layout of every played round is known
+    pub to_par: Option<i32>,
+    /// Users that have the player on their roster in the given fantasy tournament
+    pub rostered_by: Vec<User>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct Leaderboard {
+    pub competition_id: i32,
+    pub status: LiveStatus,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    pub async fn from_db(
+        db: &impl ConnectionTrait,
+        competition_id: i32,
+        division: Option<Division>,
+        fantasy_tournament_id: Option<i32>,
+    ) -> Result<Self, GenericError> {
+        let competition = find_competition(db, competition_id).await?;
+        let mut filter = player_round_score::Column::CompetitionId.eq(competition_id);
+        if let Some(division) = division {
+            filter = filter
+                .and(player_round_score::Column::Division.eq(sea_orm_active_enums::Division::from(division)));
+        }
+        let round_scores = PlayerRoundScore::find()
+            .filter(filter)
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?;
+        let pdga_numbers = round_scores.iter().map(|s| s.pdga_number).unique().collect_vec();
+
+        let pars = round_holes(db, competition_id)
+            .await?
+            .into_iter()
+            .map(|(key, holes)| (key, holes.into_iter().map(|h| (h.hole_number, h.par)).collect()))
+            .collect::<HashMap<(Division, i32), HashMap<i32, i32>>>();
+        let hole_scores = PlayerHoleScore::find()
+            .filter(
+                player_hole_score::Column::CompetitionId
+                    .eq(competition_id)
+                    .and(player_hole_score::Column::PdgaNumber.is_in(pdga_numbers.clone())),
+            )
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get hole scores from database"))?
+            .into_iter()
+            .into_group_map_by(|s| (s.pdga_number, s.round));
+        let names: HashMap<i32, String> = Player::find()
+            .filter(player::Column::PdgaNumber.is_in(pdga_numbers.clone()))
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get players from database"))?
+            .into_iter()
+            .map(|p| (p.pdga_number, format!("{} {}", p.first_name, p.last_name)))
+            .collect();
+        let rostered_by = match fantasy_tournament_id {
+            Some(tournament_id) => rostered_by(db, tournament_id, pdga_numbers).await?,
+            None => HashMap::new(),
+        };
+
+        let players = round_scores.into_iter().into_group_map_by(|s| s.pdga_number);
+        let latest = players
+            .values()
+            .filter_map(|rounds| rounds.iter().max_by_key(|r| r.round))
+            .collect_vec();
+        let amount_of_rounds = players
+            .values()
+            .flatten()
+            .map(|s| s.round)
+            .max()
+            .unwrap_or_default();
+
+        let entries = latest
+            .iter()
+            .map(|score| {
+                let rounds = &players[&score.pdga_number];
+                let entry = LeaderboardEntry {
+                    placement: score.placement,
+                    tied: latest.iter().any(|other| {
+                        other.division == score.division
+                            && other.round == score.round
+                            && other.placement == score.placement
+                            && other.pdga_number != score.pdga_number
+                    }),
+                    pdga_number: score.pdga_number,
+                    name: names.get(&score.pdga_number).cloned().unwrap_or_default(),
+                    division: score.division.clone().into(),
+                    rounds: (1..=amount_of_rounds)
+                        .map(|round| rounds.iter().find(|r| r.round == round).map(|r| r.throws))
+                        .collect(),
+                    throws: rounds.iter().map(|r| r.throws).sum(),
+                    to_par: rounds
+                        .iter()
+                        .map(|r| {
+                            round_to_par(
+                                r,
+                                hole_scores.get(&(r.pdga_number, r.round)),
+                                pars.get(&(r.division.clone().into(), r.round)),
+                            )
+                        })
+                        .sum(),
+                    rostered_by: rostered_by.get(&score.pdga_number).cloned().unwrap_or_default(),
+                };
+                (score.round, entry)
+            })
+            // Players that made it further rank above those cut in earlier rounds
+            .sorted_by_key(|(round, e)| {
+                (
+                    e.division as u8,
+                    Reverse(*round),
+                    e.placement,
+                    e.throws,
+                    e.pdga_number,
+                )
+            })
+            .map(|(_, entry)| entry)
+            .collect_vec();
+
+        Ok(Self {
+            competition_id,
+            status: competition.status.into(),
+            entries,
+        })
+    }
+}
+
+/// Score relative to par of a round. Uses the holes played when they are known, so
+/// players still on the course aren't compared to the par of the full layout.
+fn round_to_par(
+    score: &player_round_score::Model,
+    hole_scores: Option<&Vec<player_hole_score::Model>>,
+    pars: Option<&HashMap<i32, i32>>,
+) -> Option<i32> {
+    let pars = pars?;
+    match hole_scores {
+        Some(holes) if !holes.is_empty() => holes
+            .iter()
+            .map(|h| pars.get(&h.hole_number).map(|par| h.throws - par))
+            .sum(),
+        _ => Some(score.throws - pars.values().sum::<i32>()),
+    }
+}
+
+/// Users in the tournament that have each of the players on their roster.
+async fn rostered_by(
+    db: &impl ConnectionTrait,
+    fantasy_tournament_id: i32,
+    pdga_numbers: Vec<i32>,
+) -> Result<HashMap<i32, Vec<User>>, GenericError> {
+    let picks = FantasyPick::find()
+        .filter(
+            fantasy_pick::Column::FantasyTournamentId
+                .eq(fantasy_tournament_id)
+                .and(fantasy_pick::Column::Player.is_in(pdga_numbers)),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?;
+    let users: HashMap<i32, user::Model> = entity::user::Entity::find()
+        .filter(user::Column::Id.is_in(picks.iter().map(|p| p.user).unique()))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get users from database"))?
+        .into_iter()
+        .map(|u| (u.id, u))
+        .collect();
+    Ok(picks
+        .into_iter()
+        .filter_map(|pick| {
+            users.get(&pick.user).map(|u| {
+                (
+                    pick.player,
+                    User {
+                        id: u.id,
+                        username: u.name.clone(),
+                    },
+                )
+            })
+        })
+        .into_group_map()
+        .into_iter()
+        .map(|(player, users)| (player, users.into_iter().sorted_by_key(|u| u.id).collect()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::Scorecard;
+    use crate::test_db;
+    use chrono::Utc;
+    use entity::sea_orm_active_enums::Division as DbDivision;
+    use sea_orm::ActiveValue::{NotSet, Set};
+
+    fn round_score(throws: i32) -> player_round_score::Model {
+        player_round_score::Model {
+            id: 0,
+            pdga_number: 1,
+            competition_id: 1,
+            round: 1,
+            throws,
+            division: DbDivision::Mpo,
+            placement: 1,
+        }
+    }
+
+    fn hole_score(hole_number: i32, throws: i32) -> player_hole_score::Model {
+        player_hole_score::Model {
+            id: 0,
+            pdga_number: 1,
+            competition_id: 1,
+            round: 1,
+            hole_number,
+            throws,
+        }
+    }
+
+    #[test]
+    fn to_par_counts_only_played_holes() {
+        let pars = HashMap::from([(1, 3), (2, 4), (3, 5)]);
+        let holes = vec![hole_score(1, 2), hole_score(2, 5)];
+        assert_eq!(round_to_par(&round_score(7), Some(&holes), Some(&pars)), Some(0));
+        // Without hole scores the whole layout is assumed to be played
+        assert_eq!(round_to_par(&round_score(14), None, Some(&pars)), Some(2));
+        assert_eq!(round_to_par(&round_score(14), None, None), None);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn divisions_keep_their_own_layouts() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        let competition_id = 990_000_101;
+        Competition::insert(competition::ActiveModel {
+            id: Set(competition_id),
+            name: Set("Test competition".to_string()),
+            status: Set(CompetitionStatus::Running),
+            rounds: Set(1),
+            level: Set(entity::sea_orm_active_enums::CompetitionLevel::Elite),
+            ended_at: Set(None),
+            start_date: Set(Utc::now().date_naive()),
+            timezone: Set(None),
+        })
+        .exec_without_returning(&txn)
+        .await
+        .unwrap();
+        Round::insert(round::ActiveModel {
+            id: NotSet,
+            round_number: Set(1),
+            competition_id: Set(competition_id),
+            date: Set(Utc::now().fixed_offset()),
+            status: Set(CompetitionStatus::Running),
+            round_type: Set(None),
+        })
+        .exec_without_returning(&txn)
+        .await
+        .unwrap();
+
+        // MPO plays two par 3s, FPO plays the same hole numbers as par 4s
+        let players = [(1, DbDivision::Mpo, 3), (2, DbDivision::Fpo, 4)];
+        for (player, division, par) in players.clone() {
+            test_db::player(&txn, tournament, player).await;
+            let pdga_number = 99_000_000 + player;
+            for hole_number in 1..=2 {
+                RoundHole::insert(round_hole::ActiveModel {
+                    id: NotSet,
+                    competition_id: Set(competition_id),
+                    round: Set(1),
+                    division: Set(division.clone()),
+                    hole_number: Set(hole_number),
+                    par: Set(par),
+                    length: Set(Some(100 * par)),
+                })
+                .exec_without_returning(&txn)
+                .await
+                .unwrap();
+                PlayerHoleScore::insert(player_hole_score::ActiveModel {
+                    id: NotSet,
+                    pdga_number: Set(pdga_number),
+                    competition_id: Set(competition_id),
+                    round: Set(1),
+                    hole_number: Set(hole_number),
+                    throws: Set(par),
+                })
+                .exec_without_returning(&txn)
+                .await
+                .unwrap();
+            }
+            PlayerRoundScore::insert(player_round_score::ActiveModel {
+                id: NotSet,
+                pdga_number: Set(pdga_number),
+                competition_id: Set(competition_id),
+                round: Set(1),
+                throws: Set(2 * par),
+                division: Set(division),
+                placement: Set(1),
+            })
+            .exec_without_returning(&txn)
+            .await
+            .unwrap();
+        }
+
+        let scorecard = Scorecard::from_db(&txn, competition_id, 1).await.unwrap();
+        assert_eq!(
+            scorecard
+                .layouts
+                .iter()
+                .map(|l| (l.division, l.par))
+                .collect_vec(),
+            [(Division::MPO, 6), (Division::FPO, 8)]
+        );
+        assert!(scorecard.players.iter().all(|p| p.round_to_par == 0));
+
+        let leaderboard = Leaderboard::from_db(&txn, competition_id, None, None)
+            .await
+            .unwrap();
+        assert_eq!(leaderboard.entries.len(), 2);
+        assert!(leaderboard.entries.iter().all(|e| e.to_par == Some(0)));
+
+        let details = CompetitionDetails::from_db(&txn, competition_id).await.unwrap();
+        assert_eq!(
+            details.rounds[0]
+                .layouts
+                .iter()
+                .map(|l| (l.division, l.par, l.course_length))
+                .collect_vec(),
+            [(Division::MPO, 6, Some(600)), (Division::FPO, 8, Some(800))]
+        );
+    }
+}
@@ -10,18 +10,25 @@ use std::fmt::Debug;
 use strum_macros::EnumIter;
 
 use entity::*;
+pub use leaderboard::{
+    CompetitionDetails, Leaderboard, LeaderboardEntry, LiveStatus, RoundDetails, RoundLayout, RoundType,
+};
 pub use pdga::{CompetitionInfo, RoundInformation};
+pub use scorecard::{HoleScore, PlayerScorecard, Scorecard, ScorecardHole, ScorecardLayout};
 pub use scoring_visualisation::{user_competition_scores, AttributeCompetitionScores, CompetitionScores};
 
 use crate::error::GenericError;
 
 pub mod forms;
+mod leaderboard;
 mod mutation;
 mod pdga;
 mod player_trading;
 mod query;
+mod scorecard;
 mod scoring_visualisation;
 mod user_attribute;
+pub(crate) use pdga::fantasy_points;
 pub use pdga::RoundLabel;
 pub use user_attribute::{AttributeName, UserDataCombination};
 pub mod traits {
@@ -101,6 +108,7 @@ impl Competition {
     }
 }
 
+pub(crate) use player_trading::describe_trade;
 pub use player_trading::{FantasyPick, FantasyPicks, PlayerTradesLog};
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
@@ -127,6 +135,7 @@ pub enum InvitationStatus {
     Accepted,
     Pending,
     Declined,
+    Requested,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Hash, Eq)]
@@ -154,7 +163,18 @@ pub struct LoginInput {
 }
 
 #[derive(
-    Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, FromFormField, EnumIter, Default, Copy,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    FromFormField,
+    EnumIter,
+    Default,
+    Copy,
 )]
 pub enum Division {
     MPO,
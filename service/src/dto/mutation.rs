@@ -7,8 +7,7 @@ use rocket::warn;
 use sea_orm::sea_query::OnConflict;
 use sea_orm::ActiveValue::{Set, Unchanged};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait, NotSet, QueryFilter,
-    SqlErr, TransactionTrait,
+    ActiveModelTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait, NotSet, SqlErr, TransactionTrait,
 };
 
 use entity::prelude::{
@@ -148,6 +147,7 @@ impl CreateTournament {
             user_id: Set(owner_id),
             fantasy_tournament_id: Set(tour.last_insert_id),
             invitation_status: Set(FantasyTournamentInvitationStatus::Accepted),
+            role: Set(entity::sea_orm_active_enums::TournamentRole::Owner),
         })
         .exec(db)
         .await
@@ -354,12 +354,17 @@ impl CompetitionInfo {
     }
 
     async fn make_sure_all_players_exist_in_db(&self, db: &impl ConnectionTrait) -> Result<(), GenericError> {
-        let players = self.get_all_player_active_models();
+        // Players show up once per round, and a row can only be updated once per statement
+        let players = self
+            .get_all_player_active_models()
+            .into_iter()
+            .unique_by(|p| p.pdga_number.clone().unwrap())
+            .collect_vec();
 
         player::Entity::insert_many(players)
             .on_conflict(
                 OnConflict::column(player::Column::PdgaNumber)
-                    .do_nothing()
+                    .update_columns([player::Column::Country, player::Column::Rating])
                     .to_owned(),
             )
             .do_nothing()
@@ -419,20 +424,36 @@ impl CompetitionInfo {
 
         self.make_sure_all_players_exist_in_db(db).await?;
 
-        let player_round_scores = self
+        let rounds = self
             .rounds
             .iter()
             .filter(|r| r.status() != RoundStatus::Pending)
-            .flat_map(|r| {
-                r.all_player_round_score_active_models(r.round_number as i32, self.competition_id as i32)
-                    .into_iter()
-            })
+            .collect_vec();
+        let competition_id = self.competition_id as i32;
+        let player_round_scores = rounds
+            .iter()
+            .flat_map(|r| r.all_player_round_score_active_models(r.round_number as i32, competition_id))
             .collect_vec();
         if !player_round_scores.is_empty() {
-            super::super::update_or_insert_many_player_round_scores(db, player_round_scores).await
-        } else {
-            Ok(())
+            super::super::update_or_insert_many_player_round_scores(db, player_round_scores).await?;
         }
+
+        let holes = rounds
+            .iter()
+            .flat_map(|r| r.hole_active_models(r.round_number as i32, competition_id))
+            .collect_vec();
+        super::super::update_or_insert_many_round_holes(db, holes).await?;
+        let hole_scores = rounds
+            .iter()
+            .flat_map(|r| r.all_player_hole_score_active_models(r.round_number as i32, competition_id))
+            .collect_vec();
+        super::super::update_or_insert_many_player_hole_scores(db, hole_scores).await?;
+
+        let withdrawals = rounds
+            .iter()
+            .flat_map(|r| r.all_player_withdrawal_active_models(r.round_number as i32, competition_id))
+            .collect_vec();
+        super::super::update_or_insert_many_player_withdrawals(db, withdrawals).await
     }
 
     pub async fn save_competition_in_db(
@@ -464,56 +485,4 @@ impl CompetitionInfo {
         add_players(db, players?, fantasy_tournament_id).await?;
         Ok(())
     }
-
-    pub async fn save_user_scores(
-        &self,
-        db: &impl ConnectionTrait,
-        fantasy_tournament_id: u32,
-    ) -> Result<(), GenericError> {
-        self.make_sure_all_players_exist_in_db(db).await?;
-        let mut user_scores = self.get_user_scores(db, fantasy_tournament_id).await?;
-        if !user_scores.is_empty() {
-            user_competition_score_in_fantasy_tournament::Entity::delete_many()
-                .filter(
-                    user_competition_score_in_fantasy_tournament::Column::FantasyTournamentId
-                        .eq(fantasy_tournament_id as i32)
-                        .and(
-                            user_competition_score_in_fantasy_tournament::Column::CompetitionId
-                                .eq(self.competition_id as i32),
-                        ),
-                )
-                .exec(db)
-                .await
-                .map_err(|e| {
-                    error!("Unable to delete user scores from competition {:#?}", e);
-                    GenericError::UnknownError("Unable to delete user scores from competition")
-                })?;
-
-            user_scores.dedup_by(|a, b| a.pdga_num == b.pdga_num);
-
-            let mut new_scores: Vec<UserScore> = Vec::new();
-            for score in &user_scores {
-                if !new_scores
-                    .iter()
-                    .any(|new_score| new_score.pdga_num == score.pdga_num)
-                {
-                    new_scores.push(score.clone());
-                }
-            }
-
-            let scores = new_scores
-                .into_iter()
-                .map(|p| p.into_active_model(self.competition_id as i32))
-                .dedup()
-                .collect_vec();
-            user_competition_score_in_fantasy_tournament::Entity::insert_many(scores)
-                .exec(db)
-                .await
-                .map_err(|e| {
-                    error!("Unable to insert user scores into database: {:#?}", e);
-                    GenericError::UnknownError("Unable to insert user score from competition into database")
-                })?;
-        }
-        Ok(())
-    }
 }
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+use log::warn;
+use reqwest::StatusCode;
+use tokio::sync::{OnceCell, Semaphore};
+
+use crate::error::GenericError;
+use crate::metrics;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Minimum time between two requests to the same host.
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(200);
+const MAX_CONCURRENT_REQUESTS: usize = 4;
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Consecutive failed requests before the circuit opens.
+const CIRCUIT_FAILURE_THRESHOLD: u32 = 5;
+const CIRCUIT_COOLDOWN: Duration = Duration::from_secs(120);
+/// Responses are shared between callers asking for the same url within this time,
+/// which is shorter than the score refresh interval.
+const DEDUP_TTL: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    static ref CLIENT: PdgaClient = PdgaClient::new();
+}
+
+/// Fetches `url` through the shared PDGA client, returning the response body.
+///
+/// `endpoint` is only used to label metrics.
+pub(crate) async fn get(endpoint: &'static str, url: String) -> Result<Arc<String>, GenericError> {
+    CLIENT.get(endpoint, url).await
+}
+
+struct PdgaClient {
+    http: reqwest::Client,
+    concurrency: Semaphore,
+    rate_limiter: Mutex<RateLimiter>,
+    circuit: Mutex<CircuitBreaker>,
+    responses: Mutex<HashMap<String, CachedResponse>>,
+}
+
+struct CachedResponse {
+    created: Instant,
+    body: Arc<OnceCell<Arc<String>>>,
+}
+
+enum AttemptError {
+    Retryable(String),
+    Fatal(String),
+}
+
+impl PdgaClient {
+    fn new() -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .pool_idle_timeout(Duration::from_secs(90))
+                .build()
+                .expect("Able to build PDGA http client"),
+            concurrency: Semaphore::new(MAX_CONCURRENT_REQUESTS),
+            rate_limiter: Mutex::new(RateLimiter::new(MIN_REQUEST_INTERVAL)),
+            circuit: Mutex::new(CircuitBreaker::new(CIRCUIT_FAILURE_THRESHOLD, CIRCUIT_COOLDOWN)),
+            responses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn get(&self, endpoint: &'static str, url: String) -> Result<Arc<String>, GenericError> {
+        let cell = {
+            let mut responses = self.responses.lock().unwrap();
+            let now = Instant::now();
+            responses.retain(|_, r| now.duration_since(r.created) < DEDUP_TTL);
+            responses
+                .entry(url.clone())
+                .or_insert_with(|| CachedResponse {
+                    created: now,
+                    body: Arc::new(OnceCell::new()),
+                })
+                .body
+                .clone()
+        };
+        // Concurrent callers wait for the first one, failures are not shared.
+        cell.get_or_try_init(|| self.fetch(endpoint, &url)).await.cloned()
+    }
+
+    async fn fetch(&self, endpoint: &'static str, url: &str) -> Result<Arc<String>, GenericError> {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_owned))
+            .unwrap_or_default();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            if !self.circuit.lock().unwrap().allows(Instant::now()) {
+                return Err(GenericError::PdgaGaveUp(
+                    "Requests to PDGA are paused after repeated failures",
+                ));
+            }
+            let result = {
+                let _permit = self
+                    .concurrency
+                    .acquire()
+                    .await
+                    .expect("Semaphore is never closed");
+                let wait = self.rate_limiter.lock().unwrap().reserve(&host, Instant::now());
+                tokio::time::sleep(wait).await;
+                let start = Instant::now();
+                let result = self.attempt(url).await;
+                let outcome = match &result {
+                    Ok(_) => "ok",
+                    Err(AttemptError::Retryable(_)) => "retryable_error",
+                    Err(AttemptError::Fatal(_)) => "error",
+                };
+                metrics::observe_pdga_request(endpoint, outcome, start.elapsed().as_secs_f64());
+                result
+            };
+            let error = {
+                let mut circuit = self.circuit.lock().unwrap();
+                match result {
+                    Ok(body) => {
+                        circuit.record_success();
+                        metrics::set_pdga_circuit_open(false);
+                        return Ok(Arc::new(body));
+                    }
+                    Err(AttemptError::Fatal(e)) => {
+                        // PDGA answered, so it is up even if it didn't like this request
+                        circuit.record_success();
+                        metrics::set_pdga_circuit_open(false);
+                        warn!("PDGA request to {} failed: {}", url, e);
+                        return Err(GenericError::PdgaGaveUp("PDGA rejected the request"));
+                    }
+                    Err(AttemptError::Retryable(e)) => {
+                        if circuit.record_failure(Instant::now()) {
+                            warn!("Pausing PDGA requests after repeated failures");
+                            metrics::set_pdga_circuit_open(true);
+                        }
+                        e
+                    }
+                }
+            };
+            if attempt >= MAX_ATTEMPTS {
+                warn!(
+                    "PDGA request to {} failed after {} attempts: {}",
+                    url, attempt, error
+                );
+                return Err(GenericError::PdgaGaveUp(
+                    "Internal error while fetching from PDGA",
+                ));
+            }
+            tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+        }
+    }
+
+    async fn attempt(&self, url: &str) -> Result<String, AttemptError> {
+        let response = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| AttemptError::Retryable(e.to_string()))?;
+        let status = response.status();
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(AttemptError::Retryable(format!("PDGA responded with {status}")));
+        }
+        if !status.is_success() {
+            return Err(AttemptError::Fatal(format!("PDGA responded with {status}")));
+        }
+        response
+            .text()
+            .await
+            .map_err(|e| AttemptError::Retryable(e.to_string()))
+    }
+}
+
+/// Spaces out requests to the same host.
+struct RateLimiter {
+    interval: Duration,
+    next_allowed: HashMap<String, Instant>,
+}
+
+impl RateLimiter {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_allowed: HashMap::new(),
+        }
+    }
+
+    /// Reserves the next free slot for `host`, returning how long to wait for it.
+    fn reserve(&mut self, host: &str, now: Instant) -> Duration {
+        let slot = self
+            .next_allowed
+            .get(host)
+            .copied()
+            .filter(|next| *next > now)
+            .unwrap_or(now);
+        self.next_allowed.insert(host.to_string(), slot + self.interval);
+        slot - now
+    }
+}
+
+/// Stops requests for a while after too many consecutive failures.
+struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            consecutive_failures: 0,
+            open_until: None,
+        }
+    }
+
+    fn allows(&self, now: Instant) -> bool {
+        self.open_until.is_none_or(|until| now >= until)
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.open_until = None;
+    }
+
+    /// Returns true if this failure opened the circuit.
+    fn record_failure(&mut self, now: Instant) -> bool {
+        self.consecutive_failures += 1;
+        // After the cooldown a single failing request is enough to open it again
+        if self.consecutive_failures >= self.threshold {
+            let was_closed = self.allows(now);
+            self.open_until = Some(now + self.cooldown);
+            was_closed
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves `statuses` in order, one per request, and counts the requests.
+    async fn server(statuses: Vec<u16>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/event", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 4096];
+                let _ = socket.read(&mut buf).await.unwrap();
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let status = statuses.get(n).copied().unwrap_or(200);
+                let response =
+                    format!("HTTP/1.1 {status} Status\r\nconnection: close\r\ncontent-length: 2\r\n\r\nok");
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_are_deduplicated() {
+        let client = PdgaClient::new();
+        let (url, requests) = server(vec![]).await;
+        let (a, b) = tokio::join!(client.get("test", url.clone()), client.get("test", url.clone()));
+        assert_eq!(a.unwrap().as_str(), "ok");
+        assert_eq!(b.unwrap().as_str(), "ok");
+        client.get("test", url).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried() {
+        let client = PdgaClient::new();
+        let (url, requests) = server(vec![503, 500]).await;
+        assert_eq!(client.get("test", url).await.unwrap().as_str(), "ok");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let client = PdgaClient::new();
+        let (url, requests) = server(vec![404]).await;
+        assert!(client.get("test", url).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn rate_limiter_spaces_requests_per_host() {
+        let mut limiter = RateLimiter::new(Duration::from_millis(200));
+        let now = Instant::now();
+        assert_eq!(limiter.reserve("pdga.com", now), Duration::ZERO);
+        assert_eq!(limiter.reserve("pdga.com", now), Duration::from_millis(200));
+        assert_eq!(limiter.reserve("pdga.com", now), Duration::from_millis(400));
+        assert_eq!(limiter.reserve("other.com", now), Duration::ZERO);
+        assert_eq!(
+            limiter.reserve("pdga.com", now + Duration::from_secs(1)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn circuit_opens_after_repeated_failures() {
+        let mut circuit = CircuitBreaker::new(3, Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(!circuit.record_failure(now));
+        assert!(!circuit.record_failure(now));
+        assert!(circuit.allows(now));
+        assert!(circuit.record_failure(now));
+        assert!(!circuit.allows(now + Duration::from_secs(30)));
+
+        // Half open after the cooldown, one more failure opens it again
+        let later = now + Duration::from_secs(60);
+        assert!(circuit.allows(later));
+        assert!(circuit.record_failure(later));
+        assert!(!circuit.allows(later + Duration::from_secs(1)));
+
+        circuit.record_success();
+        assert!(circuit.allows(later + Duration::from_secs(1)));
+    }
+}
@@ -15,6 +15,10 @@ pub struct ApiPlayer {
     pub name: String,
     #[serde(rename = "AvatarURL")]
     pub avatar: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub rating: Option<u16>,
     pub division: Division,
     #[serde(rename = "RoundtoPar")]
     pub round_to_par: i16,
@@ -210,8 +210,7 @@ pub struct CompetitionInfo {
 impl CompetitionInfo {
     pub async fn from_web(competition_id: u32) -> Result<Self, GenericError> {
         let mut info = Self::get_pdga_competition_info(competition_id).await?;
-        info.round_labels
-            .sort_by(|a, b| a.round_number.cmp(&b.round_number));
+        info.round_labels.sort_by_key(|a| a.round_number);
         let date_range = DateRange::from_api_comp_info(&info).await.unwrap();
 
         let divs = info
@@ -262,20 +261,14 @@ impl CompetitionInfo {
 
     async fn get_pdga_competition_info(competition_id: u32) -> Result<ApiCompetitionInfo, GenericError> {
         let url = format!("https://www.pdga.com/apps/tournament/live-api/live_results_fetch_event.php?TournID={competition_id}");
-        let resp: CompetitionInfoResponse = reqwest::get(url)
-            .await
-            .map_err(|e| {
-                error!("Unable to fetch competition from PDGA: {}", e);
-                GenericError::PdgaGaveUp("Internal error while fetching competition from PDGA")
-            })?
-            .json()
-            .await
-            .map_err(|e| {
-                error!("PDGA issue while converting to json: {:#?}", e);
-                GenericError::PdgaGaveUp(
-                    "Internal error while converting PDGA competition to internal format",
-                )
-            })?;
+        let body = super::client::get("event", url).await.map_err(|e| {
+            error!("Unable to fetch competition from PDGA: {:?}", e);
+            GenericError::PdgaGaveUp("Internal error while fetching competition from PDGA")
+        })?;
+        let resp: CompetitionInfoResponse = serde_json::from_str(&body).map_err(|e| {
+            error!("PDGA issue while converting to json: {:#?}", e);
+            GenericError::PdgaGaveUp("Internal error while converting PDGA competition to internal format")
+        })?;
         Ok(resp.data)
     }
 }
@@ -1,3 +1,4 @@
+mod client;
 mod fetch_people;
 mod get_competition;
 mod player_scoring;
@@ -6,4 +7,5 @@ pub use fetch_people::{add_players, ApiPlayer};
 
 pub use get_competition::{CompetitionInfo, RoundLabel};
 
+pub(crate) use player_scoring::fantasy_points;
 pub use player_scoring::{PlayerScore, RoundInformation, RoundStatus};
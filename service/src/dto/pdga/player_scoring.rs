@@ -1,15 +1,14 @@
-use crate::dto::{CompetitionLevel, Division, UserScore};
+use crate::dto::{CompetitionLevel, Division};
 use entity::player_round_score::ActiveModel;
-use entity::{fantasy_pick, player_round_score, user};
+use entity::sea_orm_active_enums::WithdrawalReason;
+use entity::{player_hole_score, player_round_score, player_withdrawal, round_hole};
 
 use crate::dto::pdga::ApiPlayer;
 use crate::error::GenericError;
-use entity::prelude::{FantasyPick, User};
 use itertools::Itertools;
 use log::warn;
 use sea_orm::prelude::DateTimeWithTimeZone;
 use sea_orm::ActiveValue::Set;
-use sea_orm::ModelTrait;
 use sea_orm::{ColumnTrait, QueryFilter};
 use sea_orm::{ConnectionTrait, DbErr, EntityTrait, IntoActiveModel, NotSet};
 
@@ -88,7 +87,8 @@ enum Tied {
 #[derive(Debug, PartialEq, Clone)]
 pub struct PlayerScore {
     pub pdga_number: u32,
-    pub hole_scores: Vec<u8>,
+    /// Throws per hole in layout order, `None` for holes not played yet
+    pub hole_scores: Vec<Option<u8>>,
     pub throws: u8,
     pub round_to_par: i16,
     pub placement: u16,
@@ -98,6 +98,8 @@ pub struct PlayerScore {
     pub(crate) first_name: String,
     pub(crate) last_name: String,
     pub(crate) avatar: Option<String>,
+    pub(crate) country: Option<String>,
+    pub(crate) rating: Option<u16>,
     tied: Tied,
 }
 
@@ -117,6 +119,16 @@ fn placement_to_score(placement: u16) -> u16 {
     }
 }
 
+/// Fantasy points for a placement, players tied with `tied` others share the points of the places they cover.
+pub(crate) fn fantasy_points(placement: u16, tied: usize, level: CompetitionLevel) -> u16 {
+    let mut score: u32 = 0;
+    for i in 0..=tied {
+        score += placement_to_score(placement + i as u16) as u32;
+    }
+    score /= (tied + 1) as u32;
+    (score as f64 * level.multiplier()).round() as u16
+}
+
 impl PlayerScore {
     fn from_api(api: &ApiPlayer, tied: Tied) -> Self {
         let status: PlayerStatus = PlayerStatus::from(api);
@@ -127,7 +139,7 @@ impl PlayerScore {
             hole_scores: api
                 .hole_scores
                 .iter()
-                .filter_map(|s| s.parse::<u8>().ok())
+                .map(|s| s.parse::<u8>().ok().filter(|throws| *throws > 0))
                 .collect(),
             placement: api.running_place.unwrap_or(0),
             started: status,
@@ -139,6 +151,8 @@ impl PlayerScore {
                 .avatar
                 .clone()
                 .map(|s| "https://www.pdga.com".to_string() + &s),
+            country: api.country.clone(),
+            rating: api.rating,
             tied,
         }
     }
@@ -149,6 +163,8 @@ impl PlayerScore {
             first_name: self.first_name.to_owned(),
             last_name: self.last_name.to_owned(),
             avatar: self.avatar.to_owned(),
+            country: self.country.to_owned(),
+            rating: self.rating.map(|r| r as i32),
         }
         .into_active_model()
     }
@@ -185,78 +201,49 @@ impl PlayerScore {
         }
     }
 
-    fn get_user_score(&self, level: CompetitionLevel) -> u16 {
-        let score = match self.tied {
-            Tied::Tied(tied) => {
-                let mut tied_score: u32 = 0;
-                for i in 0..=tied {
-                    tied_score += placement_to_score(self.placement + i as u16) as u32;
-                }
-                tied_score /= (tied + 1) as u32;
-                tied_score
-            }
-            Tied::NotTied => placement_to_score(self.placement) as u32,
-        };
-        (score as f64 * level.multiplier()).round() as u16
-    }
-
-    pub(crate) async fn get_user_fantasy_score(
+    /// Returns ActiveModel if the player didn't start or finish the round, otherwise None
+    pub(crate) fn withdrawal_active_model(
         &self,
-        db: &impl ConnectionTrait,
-        fantasy_tournament_id: u32,
-        competition_id: u32,
-    ) -> Result<Option<UserScore>, GenericError> {
-        let competition_level = if let Some(competition) = entity::competition::Entity::find()
-            .filter(entity::competition::Column::Id.eq(competition_id as i32))
-            .one(db)
-            .await
-            .map_err(|_| GenericError::UnknownError("Unable to find competition"))?
-        {
-            competition.level.into()
-        } else {
-            return Err(GenericError::NotFound("Competition not found"));
+        round: i32,
+        competition_id: i32,
+    ) -> Option<player_withdrawal::ActiveModel> {
+        let reason = match self.started {
+            PlayerStatus::DidNotStart => WithdrawalReason::DidNotStart,
+            PlayerStatus::DidNotFinish => WithdrawalReason::DidNotFinish,
+            _ => return None,
         };
-        let score = self.get_user_score(competition_level) as i32;
-        if let Ok(Some((user, benched, slot, division))) = self.get_user(db, fantasy_tournament_id).await {
-            Ok(Some(UserScore {
-                user: user.id,
-                score,
-                competition_id,
-                pdga_num: self.pdga_number,
-                fantasy_tournament_id,
-                benched,
-                slot,
-                division,
-            }))
-        } else {
-            Ok(None)
-        }
+        Some(player_withdrawal::ActiveModel {
+            id: NotSet,
+            pdga_number: Set(self.pdga_number as i32),
+            competition_id: Set(competition_id),
+            round: Set(round),
+            reason: Set(reason),
+        })
     }
 
-    /// Returns user and if the player is benched and slot number
-    async fn get_user(
+    /// Hole scores of a player that has started, numbered after the holes of the round
+    pub(crate) fn hole_score_active_models(
         &self,
-        db: &impl ConnectionTrait,
-        fantasy_id: u32,
-    ) -> Result<Option<(user::Model, bool, u8, Division)>, GenericError> {
-        if let Some(pick) = FantasyPick::find()
-            .filter(
-                fantasy_pick::Column::Player
-                    .eq(self.pdga_number)
-                    .and(fantasy_pick::Column::FantasyTournamentId.eq(fantasy_id)),
-            )
-            .one(db)
-            .await
-            .map_err(|_| GenericError::UnknownError("Pick not found due to unknown database error"))?
-        {
-            pick.find_related(User)
-                .one(db)
-                .await
-                .map_err(|_| GenericError::UnknownError("User not found due to unknown database error"))
-                .map(|u| u.map(|u| (u, pick.benched, pick.pick_number as u8, pick.division.into())))
-        } else {
-            Ok(None)
+        round: i32,
+        competition_id: i32,
+        holes: &[Hole],
+    ) -> Vec<player_hole_score::ActiveModel> {
+        if !matches!(self.started, PlayerStatus::Started | PlayerStatus::Finished) {
+            return vec![];
         }
+        self.hole_scores
+            .iter()
+            .enumerate()
+            .filter_map(|(i, throws)| throws.map(|throws| (i, throws)))
+            .map(|(i, throws)| player_hole_score::ActiveModel {
+                id: NotSet,
+                pdga_number: Set(self.pdga_number as i32),
+                competition_id: Set(competition_id),
+                round: Set(round),
+                hole_number: Set(holes.get(i).map_or(i as u32 + 1, |h| h.hole_number) as i32),
+                throws: Set(throws as i32),
+            })
+            .collect()
     }
 }
 
@@ -274,6 +261,18 @@ struct RoundFromApi {
     div: Division,
 }
 
+fn layout_holes(layout: &Layout) -> Vec<Hole> {
+    layout
+        .holes
+        .iter()
+        .map(|h| Hole {
+            par: h.par,
+            hole_number: h.hole_number,
+            length: fix_length(h.length, layout.unit),
+        })
+        .collect_vec()
+}
+
 fn fix_length(length: Option<u32>, unit: Option<Unit>) -> Option<u32> {
     match (unit, length) {
         (Some(Unit::Feet), Some(length)) => Some((length as f64 * 0.3048).round() as u32),
@@ -285,6 +284,8 @@ fn fix_length(length: Option<u32>, unit: Option<Unit>) -> Option<u32> {
 #[derive(Debug, PartialEq)]
 pub struct RoundInformation {
     pub holes: Vec<Hole>,
+    /// The holes played by each division, which can be on different layouts
+    pub division_holes: Vec<(Division, Vec<Hole>)>,
     pub players: Vec<PlayerScore>,
     pub course_length: u32,
     pub round_number: usize,
@@ -338,6 +339,10 @@ impl RoundInformation {
                 .unwrap()
                 .to_owned();
             let divisions = divs.iter().map(|d| d.div.to_owned()).collect();
+            let division_holes = divs
+                .iter()
+                .filter_map(|d| d.layouts.first().map(|layout| (d.div, layout_holes(layout))))
+                .collect_vec();
 
             let player_scores: Vec<PlayerScore> = divs
                 .into_iter()
@@ -354,6 +359,7 @@ impl RoundInformation {
             Ok(Self::make_self(
                 player_scores,
                 layout,
+                division_holes,
                 competition_id,
                 round_label.get_round_number_from_label(all_round_labels),
                 divisions,
@@ -373,6 +379,7 @@ impl RoundInformation {
     ) -> Self {
         RoundInformation {
             holes: vec![],
+            division_holes: vec![],
             players: vec![],
             course_length: 0,
             round_number: round_label_info.get_round_number_from_label(round_labels),
@@ -386,20 +393,13 @@ impl RoundInformation {
     fn make_self(
         player_scores: Vec<PlayerScore>,
         layout: Layout,
+        division_holes: Vec<(Division, Vec<Hole>)>,
         competition_id: usize,
         round_number: usize,
         divs: Vec<Division>,
         label: RoundLabelInfo,
     ) -> Self {
-        let holes = layout
-            .holes
-            .iter()
-            .map(|h| Hole {
-                par: h.par,
-                hole_number: h.hole_number,
-                length: fix_length(h.length, layout.unit),
-            })
-            .collect_vec();
+        let holes = layout_holes(&layout);
 
         let length = match layout.unit {
             Some(Unit::Feet) => (layout.length.unwrap_or_default() as f64 * 0.3048).round() as u32,
@@ -409,6 +409,7 @@ impl RoundInformation {
 
         RoundInformation {
             holes,
+            division_holes,
             players: player_scores
                 .into_iter()
                 .filter_map(|p| {
@@ -438,15 +439,11 @@ impl RoundInformation {
         //dbg!(&url);
         //tokio::time::sleep(std::time::Duration::from_millis(250)).await;
 
-        let mut resp: ApiRes = reqwest::get(url)
-            .await
-            .map_err(|_| GenericError::UnknownError("Internal error while fetching round from PDGA"))?
-            .json()
-            .await
-            .map_err(|e| {
-                warn!("Unable to parse PDGA round response: {}", e);
-                GenericError::UnknownError("Internal error while converting PDGA round to internal format")
-            })?;
+        let body = super::client::get("round", url).await?;
+        let mut resp: ApiRes = serde_json::from_str(&body).map_err(|e| {
+            warn!("Unable to parse PDGA round response: {}", e);
+            GenericError::UnknownError("Internal error while converting PDGA round to internal format")
+        })?;
         resp.data.div = div;
         Ok(resp.data)
     }
@@ -462,6 +459,52 @@ impl RoundInformation {
             .collect()
     }
 
+    pub fn all_player_hole_score_active_models(
+        &self,
+        round: i32,
+        competition_id: i32,
+    ) -> Vec<player_hole_score::ActiveModel> {
+        self.players
+            .iter()
+            .flat_map(|p| p.hole_score_active_models(round, competition_id, self.holes_of(p.division)))
+            .collect()
+    }
+
+    /// The holes played by a division, or the first layout if PDGA didn't send one for it.
+    fn holes_of(&self, division: Division) -> &[Hole] {
+        self.division_holes
+            .iter()
+            .find(|(d, _)| *d == division)
+            .map_or(&self.holes, |(_, holes)| holes)
+    }
+
+    pub fn all_player_withdrawal_active_models(
+        &self,
+        round: i32,
+        competition_id: i32,
+    ) -> Vec<player_withdrawal::ActiveModel> {
+        self.players
+            .iter()
+            .filter_map(|p| p.withdrawal_active_model(round, competition_id))
+            .collect()
+    }
+
+    pub fn hole_active_models(&self, round: i32, competition_id: i32) -> Vec<round_hole::ActiveModel> {
+        self.division_holes
+            .iter()
+            .flat_map(|(division, holes)| holes.iter().map(move |h| (division, h)))
+            .map(|(division, h)| round_hole::ActiveModel {
+                id: NotSet,
+                competition_id: Set(competition_id),
+                round: Set(round),
+                division: Set(division.into()),
+                hole_number: Set(h.hole_number as i32),
+                par: Set(h.par as i32),
+                length: Set(h.length.map(|l| l as i32)),
+            })
+            .collect()
+    }
+
     pub fn all_player_active_models(&self) -> Vec<entity::player::ActiveModel> {
         self.players.iter().map(|p| p.to_active_model()).collect()
     }
@@ -2143,4 +2186,47 @@ mod tests {
         let resp: ApiRes = serde_json::from_str(test_string).unwrap();
         dbg!(resp);
     }
+
+    #[test]
+    fn hole_scores_keep_their_hole_numbers() {
+        use super::*;
+        use sea_orm::ActiveValue;
+
+        let holes = (1..=4)
+            .map(|hole_number| Hole {
+                par: 3,
+                hole_number,
+                length: None,
+            })
+            .collect_vec();
+        let player = PlayerScore {
+            pdga_number: 1,
+            // Shotgun start on hole 3
+            hole_scores: vec![None, None, Some(3), Some(4)],
+            throws: 7,
+            round_to_par: 1,
+            placement: 1,
+            started: PlayerStatus::Started,
+            division: Division::MPO,
+            name: String::new(),
+            first_name: String::new(),
+            last_name: String::new(),
+            avatar: None,
+            country: None,
+            rating: None,
+            tied: Tied::NotTied,
+        };
+        let scores = player
+            .hole_score_active_models(1, 1, &holes)
+            .into_iter()
+            .map(|s| (s.hole_number, s.throws))
+            .collect_vec();
+        assert_eq!(
+            scores,
+            vec![
+                (ActiveValue::Set(3), ActiveValue::Set(3)),
+                (ActiveValue::Set(4), ActiveValue::Set(4))
+            ]
+        );
+    }
 }
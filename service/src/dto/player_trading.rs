@@ -1,11 +1,10 @@
 use crate::dto::Division;
 use crate::error::{GenericError, PlayerError};
 use crate::player_exists;
-use chrono::TimeZone;
 use chrono::Utc;
-use chrono_tz::Tz;
 use entity::{
-    fantasy_pick, player, player_division_in_fantasy_tournament, player_trade, sea_orm_active_enums,
+    fantasy_pick, player, player_division_in_fantasy_tournament, player_trade, player_trade_revert,
+    sea_orm_active_enums,
 };
 use itertools::Itertools;
 use rayon::prelude::*;
@@ -18,7 +17,7 @@ use sea_orm::{
     QueryFilter,
 };
 use serde_derive::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Deserialize, Serialize, JsonSchema, Debug)]
 pub struct FantasyPick {
@@ -29,7 +28,23 @@ pub struct FantasyPick {
     pub benched: bool,
 }
 impl FantasyPick {
-    pub async fn change_or_insert(
+    /// Changes or inserts the picks of a user in a division, then refuses the result if it is over
+    /// the budget of the division. Every pick change has to go through here so the budget can't be skipped.
+    pub async fn change_or_insert_all(
+        picks: &[Self],
+        db: &impl ConnectionTrait,
+        user_id: i32,
+        tournament_id: i32,
+        div: Division,
+    ) -> Result<(), GenericError> {
+        for pick in picks {
+            pick.change_or_insert(db, user_id, tournament_id, div).await?;
+        }
+        // Checked once all picks are in, a swap can go over the budget half-way through
+        crate::salary_cap::assure_within_budget(db, user_id, tournament_id, div).await
+    }
+
+    async fn change_or_insert(
         &self,
         db: &impl ConnectionTrait,
         user_id: i32,
@@ -125,7 +140,7 @@ impl FantasyPick {
                         GenericError::UnknownError("Unable to insert pick")
                     })?;
                     new_pick.id = Set(other_pick.id.take().unwrap());
-                    player_trade::ActiveModel {
+                    let trade = player_trade::ActiveModel {
                         id: NotSet,
                         user: Set(user_id),
                         player: Set(self.pdga_number),
@@ -135,13 +150,16 @@ impl FantasyPick {
                         is_local_swap: Set(true),
                         other_player: Set(previous_placement_of_player.player.clone().take()),
                         other_slot: Set(previous_placement_of_player.pick_number.clone().take()),
+                        is_removal: Set(false),
+                        is_revert: Set(false),
                     }
-                    .save(db)
+                    .insert(db)
                     .await
                     .map_err(|e| {
                         warn!("Unable to insert pick: {:#?}", e);
                         GenericError::UnknownError("Unable to insert pick")
                     })?;
+                    crate::webhooks::trade_logged(db, &trade).await?;
                     previous_placement_of_player.save(db).await.map_err(|e| {
                         warn!("Unable to insert pick: {:#?}", e);
                         GenericError::UnknownError("Unable to insert pick")
@@ -162,7 +180,7 @@ impl FantasyPick {
                     .take()
                     .is_some_and(|num| num != self.slot)
                 {
-                    player_trade::ActiveModel {
+                    let trade = player_trade::ActiveModel {
                         id: NotSet,
                         user: Set(user_id),
                         player: Set(self.pdga_number),
@@ -172,13 +190,16 @@ impl FantasyPick {
                         is_local_swap: Set(false),
                         other_player: Set(None),
                         other_slot: Set(previous_placement_of_player.pick_number.clone().take()),
+                        is_removal: Set(false),
+                        is_revert: Set(false),
                     }
-                    .save(db)
+                    .insert(db)
                     .await
                     .map_err(|e| {
                         warn!("Unable to insert pick: {:#?}", e);
                         GenericError::UnknownError("Unable to insert pick")
                     })?;
+                    crate::webhooks::trade_logged(db, &trade).await?;
                     previous_placement_of_player.pick_number = Set(self.slot);
                     previous_placement_of_player.save(db).await.map_err(|e| {
                         warn!("Unable to insert pick: {:#?}", e);
@@ -190,7 +211,7 @@ impl FantasyPick {
 
             // Insert new pick when there is no pick in the new slot
             (Ok(None), Ok(None)) => {
-                player_trade::ActiveModel {
+                let trade = player_trade::ActiveModel {
                     id: NotSet,
                     user: Set(user_id),
                     player: Set(self.pdga_number),
@@ -200,13 +221,16 @@ impl FantasyPick {
                     is_local_swap: Set(false),
                     other_player: Set(None),
                     other_slot: Set(None),
+                    is_removal: Set(false),
+                    is_revert: Set(false),
                 }
-                .save(db)
+                .insert(db)
                 .await
                 .map_err(|e| {
                     warn!("Unable to insert pick: {:#?}", e);
                     GenericError::UnknownError("Unable to insert pick")
                 })?;
+                crate::webhooks::trade_logged(db, &trade).await?;
                 fantasy_pick::Entity::insert(new_pick)
                     .exec(db)
                     .await
@@ -219,7 +243,7 @@ impl FantasyPick {
 
             // Insert new pick when there is a pick in the new slot
             (Ok(None), Ok(Some(mut other_pick))) => {
-                player_trade::ActiveModel {
+                let trade = player_trade::ActiveModel {
                     id: NotSet,
                     user: Set(user_id),
                     player: Set(self.pdga_number),
@@ -229,13 +253,16 @@ impl FantasyPick {
                     is_local_swap: Set(false),
                     other_player: Set(other_pick.player.clone().take()),
                     other_slot: Set(other_pick.pick_number.clone().take()),
+                    is_removal: Set(false),
+                    is_revert: Set(false),
                 }
-                .save(db)
+                .insert(db)
                 .await
                 .map_err(|e| {
                     warn!("Unable to insert pick: {:#?}", e);
                     GenericError::UnknownError("Unable to insert pick")
                 })?;
+                crate::webhooks::trade_logged(db, &trade).await?;
                 other_pick.player = Set(self.pdga_number);
                 other_pick.save(db).await.map_err(|e| {
                     warn!("Unable to insert pick: {:#?}", e);
@@ -259,11 +286,14 @@ pub struct FantasyPicks {
 
 #[derive(Debug)]
 struct PlayerTradeLog {
+    id: i32,
     user: i32,
     player: i32,
     slot: usize,
     action: PlayerTradingAction,
     timestamp: DateTimeWithTimeZone,
+    is_revert: bool,
+    reverted: bool,
 }
 
 impl PlayerTradeLog {
@@ -284,20 +314,41 @@ impl PlayerTradeLog {
             .get(&self.user)
             .map(|s| s.to_owned())
             .unwrap_or(self.user.to_string());
+        let action = self.describe_action(players);
+
+        let marker = match (self.is_revert, self.reverted) {
+            (true, _) => " [Revert]",
+            (false, true) => " [Reverted]",
+            (false, false) => "",
+        };
+
+        // TODO: Send timestamp data with TZ to frontend to display based on local timezone
+        format!(
+            "#{} {}: {} - At {}{}",
+            self.id,
+            user,
+            action,
+            self.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            marker
+        )
+    }
+
+    fn describe_action(&self, players: &HashMap<i32, String>) -> String {
         let player = players
             .get(&self.player)
             .map(|s| s.to_owned())
             .unwrap_or(self.player.to_string());
 
-        let action = match self.action {
+        match &self.action {
             PlayerTradingAction::Add => format!("Added {} to slot {}", player, self.slot),
+            PlayerTradingAction::Remove => format!("Removed {} from slot {}", player, self.slot),
             PlayerTradingAction::Swap(swap) => match swap {
                 PlayerTradingSwapType::Local {
                     other_slot,
                     other_player,
                 } => {
                     let other_player = players
-                        .get(&other_player)
+                        .get(other_player)
                         .map(|s| s.to_owned())
                         .unwrap_or(swap.get_player().to_string());
                     format!(
@@ -307,26 +358,28 @@ impl PlayerTradeLog {
                 }
                 PlayerTradingSwapType::Tournament { other_player } => {
                     let other_player = players
-                        .get(&other_player)
+                        .get(other_player)
                         .map(|s| s.to_owned())
                         .unwrap_or(swap.get_player().to_string());
                     format!("Swapped {} in slot {} with {}", player, self.slot, other_player,)
                 }
             },
-        };
+        }
+    }
 
-        // TODO: Send timestamp data with TZ to frontend to display based on local timezone
-        format!(
-            "{}: {} - At {}",
-            user,
-            action,
-            self.timestamp.format("%Y-%m-%d %H:%M:%S")
-        )
+    fn action_kind(&self) -> &'static str {
+        match &self.action {
+            PlayerTradingAction::Add => "add",
+            PlayerTradingAction::Remove => "remove",
+            PlayerTradingAction::Swap(PlayerTradingSwapType::Local { .. }) => "swap",
+            PlayerTradingAction::Swap(PlayerTradingSwapType::Tournament { .. }) => "replace",
+        }
     }
 }
 #[derive(Debug)]
 enum PlayerTradingAction {
     Add,
+    Remove,
     Swap(PlayerTradingSwapType),
 }
 #[derive(Debug)]
@@ -344,12 +397,14 @@ impl PlayerTradingSwapType {
     }
 }
 
-impl From<player_trade::Model> for PlayerTradeLog {
-    fn from(trade: player_trade::Model) -> Self {
+impl PlayerTradeLog {
+    fn new(trade: player_trade::Model, reverted: bool) -> Self {
         let user = trade.user;
         let player = trade.player;
         let slot = trade.slot as usize;
-        let action = if trade.is_local_swap {
+        let action = if trade.is_removal {
+            PlayerTradingAction::Remove
+        } else if trade.is_local_swap {
             PlayerTradingAction::Swap(PlayerTradingSwapType::Local {
                 other_slot: trade
                     .other_slot
@@ -366,11 +421,14 @@ impl From<player_trade::Model> for PlayerTradeLog {
         };
 
         Self {
+            id: trade.id,
             user,
             player,
             slot,
             action,
             timestamp: trade.timestamp,
+            is_revert: trade.is_revert,
+            reverted,
         }
     }
 }
@@ -379,18 +437,28 @@ pub struct PlayerTradesLog(Vec<PlayerTradeLog>);
 
 impl PlayerTradesLog {
     pub async fn get(db: &impl ConnectionTrait, tournament_id: i32) -> Self {
-        let mut trades = player_trade::Entity::find()
+        let trades = player_trade::Entity::find()
             .filter(player_trade::Column::FantasyTournamentId.eq(tournament_id))
             .all(db)
             .await
             .unwrap_or_default();
+        let reverted = player_trade_revert::Entity::find()
+            .filter(player_trade_revert::Column::TradeId.is_in(trades.iter().map(|t| t.id)))
+            .all(db)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| r.trade_id)
+            .collect::<HashSet<_>>();
 
         Self(
             trades
                 .into_iter()
                 .sorted_by(|a, b| b.timestamp.cmp(&a.timestamp))
-                .map(PlayerTradeLog::from)
-                .map(|mut trade| trade)
+                .map(|t| {
+                    let is_reverted = reverted.contains(&t.id);
+                    PlayerTradeLog::new(t, is_reverted)
+                })
                 .collect_vec(),
         )
     }
@@ -425,3 +493,18 @@ impl PlayerTradesLog {
             .collect()
     }
 }
+
+/// Describes a single logged trade, returning the kind of change and a readable summary of it.
+pub(crate) async fn describe_trade(
+    db: &impl ConnectionTrait,
+    trade: &player_trade::Model,
+) -> (&'static str, String) {
+    let trade = PlayerTradeLog::new(trade.clone(), false);
+    let mut player_map = HashMap::new();
+    for player_id in trade.players() {
+        if let Ok(Some(player)) = entity::player::Entity::find_by_id(player_id).one(db).await {
+            player_map.insert(player_id, format!("{} {}", player.first_name, player.last_name));
+        }
+    }
+    (trade.action_kind(), trade.describe_action(&player_map))
+}
@@ -12,6 +12,7 @@ impl UserLogin {
             id: NotSet,
             name: Set(self.username.clone()),
             admin: Set(false),
+            moderator: Set(false),
         }
     }
     pub(super) fn active_authentication(
@@ -56,6 +57,19 @@ impl CreateTournament {
                 Some(v) => Set(v),
                 None => NotSet,
             },
+            archived_at: NotSet,
+            join_requests_enabled: NotSet,
+            captain_multiplier_percent: NotSet,
+            vice_captains_enabled: NotSet,
+            substitution_policy: NotSet,
+            min_fpo_picks: NotSet,
+            max_fpo_picks: NotSet,
+            max_picks_per_country: NotSet,
+            top_rated_pool_size: NotSet,
+            max_top_rated_picks: NotSet,
+            budget_per_division: NotSet,
+            visibility: NotSet,
+            public_slug: NotSet,
         }
     }
 }
@@ -70,17 +84,20 @@ impl CompetitionInfo {
             .one(db)
             .await
         {
-            let status = model.status.clone();
+            let status_changed =
+                model.status != entity::sea_orm_active_enums::CompetitionStatus::from(self.status());
+            // Competitions stored before the timezone was tracked get it on their next refresh
+            let timezone_missing = model.timezone.is_none();
             let mut model = model.into_active_model();
 
-            Ok(
-                if status != entity::sea_orm_active_enums::CompetitionStatus::from(self.status()) {
-                    model.status = Set(self.status().into());
-                    Some(model)
-                } else {
-                    None
-                },
-            )
+            if status_changed {
+                model.status = Set(self.status().into());
+                model.ended_at = Set(self.status_to_finished());
+            }
+            if timezone_missing {
+                model.timezone = Set(Some(self.date_range.timezone().name().to_string()));
+            }
+            Ok((status_changed || timezone_missing).then_some(model))
         } else if let Some(level) = level {
             Ok(Some(competition::ActiveModel {
                 id: Set(self.competition_id as i32),
@@ -90,6 +107,7 @@ impl CompetitionInfo {
                 level: Set(level),
                 ended_at: Set(self.status_to_finished()),
                 start_date: Set(self.date_range.start_date()),
+                timezone: Set(Some(self.date_range.timezone().name().to_string())),
             }))
         } else {
             Err(GenericError::UnknownError(
@@ -204,57 +222,6 @@ impl CompetitionInfo {
         }
     }
 
-    pub(super) async fn get_user_scores(
-        &self,
-        db: &impl ConnectionTrait,
-        fantasy_tournament_id: u32,
-    ) -> Result<Vec<UserScore>, GenericError> {
-        let mut user_scores: Vec<UserScore> = Vec::new();
-        let players = self.get_current_player_scores()?;
-
-        for player in players {
-            let score = player
-                .get_user_fantasy_score(db, fantasy_tournament_id, self.competition_id)
-                .await?;
-            if let Some(score) = score {
-                user_scores.push(score);
-            }
-        }
-        let max_picks = super::super::query::max_picks(db, fantasy_tournament_id as i32).await?;
-        let bench_size =
-            super::super::query::get_tournament_bench_size(db, fantasy_tournament_id as i32).await?;
-        let mut filtered_scores = Vec::new();
-        let divs = user_scores
-            .iter()
-            .map(|score| score.division)
-            .dedup()
-            .collect_vec();
-
-        let mut user_ids = user_scores.iter().map(|score| score.user).collect_vec();
-        user_ids.dedup();
-
-        for user_id in user_ids {
-            for div in &divs {
-                let played_picks = user_scores
-                    .clone()
-                    .into_iter()
-                    .filter(|score| score.user == user_id && &score.division == div)
-                    .collect_vec();
-                let amount_of_played_picks = played_picks.len();
-                for pick in played_picks {
-                    if (pick.slot as isize)
-                        <= max_picks as isize
-                            - (bench_size as isize - (max_picks as isize - amount_of_played_picks as isize))
-                    {
-                        filtered_scores.push(pick);
-                    }
-                }
-            }
-        }
-
-        Ok(filtered_scores)
-    }
-
     pub fn status(&self) -> CompetitionStatus {
         // Add so that it has to be 6 in the morning, day after competition should end, to count as finished.
         if self.rounds.iter().all(|r| r.status() == RoundStatus::Finished)
@@ -304,6 +271,29 @@ impl From<CompetitionStatus> for sea_orm_active_enums::CompetitionStatus {
 }
 
 impl PhantomCompetition {
+    pub(crate) fn new(name: String, start_date: NaiveDate) -> Self {
+        Self {
+            name,
+            competition_id: None,
+            start_date,
+        }
+    }
+
+    /// Inserts the placeholder and keeps its id, so it can be added to a tournament afterwards.
+    pub(crate) async fn save(
+        &mut self,
+        db: &impl ConnectionTrait,
+        level: sea_orm_active_enums::CompetitionLevel,
+    ) -> Result<i32, GenericError> {
+        let id = phantom_competition::Entity::insert(self.active_model(level))
+            .exec(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to insert phantom competition"))?
+            .last_insert_id;
+        self.competition_id = Some(id as u32);
+        Ok(id)
+    }
+
     pub(crate) fn active_model(
         &self,
         level: sea_orm_active_enums::CompetitionLevel,
@@ -313,6 +303,7 @@ impl PhantomCompetition {
             name: Set(self.name.clone()),
             date: Set(self.start_date),
             level: Set(level),
+            linked_competition_id: NotSet,
         }
     }
 }
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use entity::prelude::*;
+use entity::*;
+use itertools::Itertools;
+use rocket_okapi::JsonSchema;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+use super::Division;
+use crate::error::GenericError;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct ScorecardHole {
+    pub hole_number: i32,
+    pub par: i32,
+    /// Length in metres, if PDGA knows it
+    pub length: Option<i32>,
+}
+
+/// The holes a division played in the round.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct ScorecardLayout {
+    pub division: Division,
+    pub par: i32,
+    pub holes: Vec<ScorecardHole>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct HoleScore {
+    pub hole_number: i32,
+    pub throws: i32,
+    pub to_par: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct PlayerScorecard {
+    pub pdga_number: i32,
+    pub name: String,
+    pub division: Division,
+    pub placement: i32,
+    pub throws: i32,
+    /// Score relative to par over the holes played so far
+    pub round_to_par: i32,
+    pub holes: Vec<HoleScore>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct Scorecard {
+    pub competition_id: i32,
+    pub round: i32,
+    /// Divisions can play different layouts in the same round
+    pub layouts: Vec<ScorecardLayout>,
+    pub players: Vec<PlayerScorecard>,
+}
+
+impl Scorecard {
+    pub async fn from_db(
+        db: &impl ConnectionTrait,
+        competition_id: i32,
+        round: i32,
+    ) -> Result<Self, GenericError> {
+        let holes = RoundHole::find()
+            .filter(
+                round_hole::Column::CompetitionId
+                    .eq(competition_id)
+                    .and(round_hole::Column::Round.eq(round)),
+            )
+            .order_by_asc(round_hole::Column::HoleNumber)
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get round layout from database"))?;
+        let round_scores = PlayerRoundScore::find()
+            .filter(
+                player_round_score::Column::CompetitionId
+                    .eq(competition_id)
+                    .and(player_round_score::Column::Round.eq(round)),
+            )
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?;
+        if holes.is_empty() && round_scores.is_empty() {
+            return Err(GenericError::NotFound("Round not found"));
+        }
+        let mut hole_scores = entity::player_hole_score::Entity::find()
+            .filter(
+                player_hole_score::Column::CompetitionId
+                    .eq(competition_id)
+                    .and(player_hole_score::Column::Round.eq(round)),
+            )
+            .order_by_asc(player_hole_score::Column::HoleNumber)
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get hole scores from database"))?
+            .into_iter()
+            .into_group_map_by(|s| s.pdga_number);
+        let players: HashMap<i32, player::Model> = Player::find()
+            .filter(player::Column::PdgaNumber.is_in(round_scores.iter().map(|s| s.pdga_number)))
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get players from database"))?
+            .into_iter()
+            .map(|p| (p.pdga_number, p))
+            .collect();
+
+        let pars: HashMap<(Division, i32), i32> = holes
+            .iter()
+            .map(|h| ((h.division.clone().into(), h.hole_number), h.par))
+            .collect();
+        let players = round_scores
+            .into_iter()
+            .map(|score| {
+                let division = Division::from(score.division);
+                let holes = hole_scores
+                    .remove(&score.pdga_number)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|h| HoleScore {
+                        hole_number: h.hole_number,
+                        throws: h.throws,
+                        to_par: pars.get(&(division, h.hole_number)).map(|par| h.throws - par),
+                    })
+                    .collect_vec();
+                PlayerScorecard {
+                    pdga_number: score.pdga_number,
+                    name: players
+                        .get(&score.pdga_number)
+                        .map(|p| format!("{} {}", p.first_name, p.last_name))
+                        .unwrap_or_default(),
+                    division,
+                    placement: score.placement,
+                    throws: score.throws,
+                    round_to_par: holes.iter().filter_map(|h| h.to_par).sum(),
+                    holes,
+                }
+            })
+            .sorted_by_key(|p| (p.division as u8, p.placement, p.pdga_number))
+            .collect_vec();
+
+        Ok(Self {
+            competition_id,
+            round,
+            layouts: holes
+                .into_iter()
+                .into_group_map_by(|h| Division::from(h.division.clone()))
+                .into_iter()
+                .map(|(division, holes)| ScorecardLayout {
+                    division,
+                    par: holes.iter().map(|h| h.par).sum(),
+                    holes: holes
+                        .into_iter()
+                        .map(|h| ScorecardHole {
+                            hole_number: h.hole_number,
+                            par: h.par,
+                            length: h.length,
+                        })
+                        .collect(),
+                })
+                .sorted_by_key(|l| l.division as u8)
+                .collect(),
+            players,
+        })
+    }
+}
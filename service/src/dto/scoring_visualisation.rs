@@ -64,7 +64,7 @@ impl PlayerCompetitionScore {
                         error!("Unable to get player round scores from db {:#?}", e);
                         GenericError::UnknownError("Unable to get player round scores from db")
                     })?;
-            players.sort_by(|a, b| a.round.cmp(&b.round));
+            players.sort_by_key(|a| a.round);
 
             if players.is_empty() {
                 warn!("Player does not have any round scores");
@@ -114,7 +114,7 @@ impl CompetitionScores {
                 Some(score) => scores.push(score),
             }
         }
-        scores.sort_by(|a, b| a.placement.cmp(&b.placement));
+        scores.sort_by_key(|a| a.placement);
         Ok(Self(scores))
     }
 
@@ -1,5 +1,4 @@
 use crate::dto::User;
-use lazy_static::lazy_static;
 use rocket_okapi::JsonSchema;
 use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
@@ -81,7 +80,7 @@ macro_rules! make_dto_user_attribute {
         }
 
         paste::paste! {
-        impl crate::dto::AttributeName for paste::paste! {[<Attribute $name>]} {
+        impl $crate::dto::AttributeName for paste::paste! {[<Attribute $name>]} {
             const NAME: &'static str = concat!("UserWith", stringify!($name));
             const FIELD_NAME: &'static str = stringify!([<$name:snake>]);
         }}
@@ -33,6 +33,9 @@ pub enum GenericError {
     Conflict(&'static str),
     #[response(status = 400)]
     BadRequest(&'static str),
+    /// The roster violations, as a JSON list
+    #[response(status = 422)]
+    InvalidRoster(String),
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Responder)]
@@ -107,6 +110,7 @@ pub enum InviteError {
     TournamentNotFound,
     UserNotFound,
     NotOwner,
+    TournamentArchived,
 }
 impl From<InviteError> for GenericError {
     fn from(e: InviteError) -> Self {
@@ -119,6 +123,7 @@ impl From<InviteError> for GenericError {
             NotOwner => GenericError::TournamentError(TournamentError::NotPermitted(
                 "You are not the owner of this tournament",
             )),
+            TournamentArchived => GenericError::Conflict("Tournament is archived"),
         }
     }
 }
@@ -16,6 +16,9 @@ pub async fn is_user_allowed_to_exchange(
     tournament_id: i32,
 ) -> Result<bool, GenericError> {
     if let Some(tournament) = get_fantasy_tournament_model(db, tournament_id).await? {
+        if tournament.archived_at.is_some() {
+            return Ok(false);
+        }
         let users = see_which_users_can_exchange(db, &tournament).await?;
         Ok(!any_competitions_running(db, tournament.id).await? && users.iter().any(|u| u.user.id == user_id))
     } else {
@@ -61,6 +64,9 @@ pub async fn see_which_users_can_exchange(
 
 pub async fn has_exchange_begun(db: &impl ConnectionTrait, tournament_id: i32) -> Result<bool, GenericError> {
     if let Some(tournament) = get_fantasy_tournament_model(db, tournament_id).await? {
+        if tournament.archived_at.is_some() {
+            return Ok(false);
+        }
         let first_exchange_window = get_first_exchange_window_time(db, &tournament).await?;
         error!("{:#?}", &first_exchange_window);
         Ok(first_exchange_window
@@ -115,11 +121,19 @@ async fn next_competition_beginning(
     tournament: &entity::fantasy_tournament::Model,
 ) -> Result<Option<DateTime<FixedOffset>>, GenericError> {
     let comps = get_competitions_in_fantasy_tournament(db, tournament.id).await?;
-    let next_competition = comps
+    // Placeholders count as competitions until their PDGA event is linked
+    let placeholders = crate::placeholders::unlinked_dates(db, tournament.id).await?;
+    let next_start = comps
         .iter()
         .filter(|comp| comp.status == CompetitionStatus::NotStarted)
-        .min_by_key(|c| c.start_date);
-    Ok(next_competition.map(|c| c.start_date.and_time(Time::default()).and_utc().fixed_offset()))
+        .map(|c| c.start_date)
+        .chain(
+            placeholders
+                .into_iter()
+                .filter(|date| *date >= chrono::Utc::now().date_naive()),
+        )
+        .min();
+    Ok(next_start.map(|date| date.and_time(Time::default()).and_utc().fixed_offset()))
 }
 
 pub async fn see_when_users_can_exchange(
@@ -151,7 +165,7 @@ async fn get_sorted_users(
     tournament_id: i32,
 ) -> Result<Vec<crate::dto::UserWithScore>, GenericError> {
     let mut users = get_user_participants_in_tournament(db, tournament_id).await?;
-    users.sort_by(|a, b| b.score.cmp(&a.score));
+    users.sort_by_key(|a| std::cmp::Reverse(a.score));
     Ok(users)
 }
 
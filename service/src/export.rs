@@ -0,0 +1,346 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+use entity::prelude::*;
+use entity::*;
+use itertools::Itertools;
+use rocket::FromFormField;
+use rocket_okapi::JsonSchema;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::Division;
+use crate::error::GenericError;
+
+/// Rows serialized per chunk of a streamed export
+const CHUNK_ROWS: usize = 500;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, FromFormField, Default)]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema, Debug, PartialEq)]
+pub struct StandingRow {
+    /// Users with the same score share a rank
+    pub rank: usize,
+    pub user_id: i32,
+    pub username: String,
+    pub score: i32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ScoreRow {
+    pub competition_id: i32,
+    pub competition_name: String,
+    pub start_date: NaiveDate,
+    pub user_id: i32,
+    pub username: String,
+    pub pdga_number: i32,
+    pub player_name: String,
+    pub score: i32,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct RosterRow {
+    pub user_id: i32,
+    pub username: String,
+    pub division: Division,
+    pub slot: i32,
+    pub pdga_number: i32,
+    pub player_name: String,
+    pub benched: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TradeRow {
+    pub id: i32,
+    pub timestamp: String,
+    pub user_id: i32,
+    pub username: String,
+    pub slot: i32,
+    pub pdga_number: i32,
+    pub player_name: String,
+    pub other_pdga_number: Option<i32>,
+    pub other_slot: Option<i32>,
+    pub is_local_swap: bool,
+    pub is_removal: bool,
+    pub is_revert: bool,
+}
+
+/// Serializes the rows a chunk at a time, so large exports can be streamed chunk by chunk. Every chunk
+/// is written before any is returned, a row that can't be serialized fails the export instead of cutting it short.
+pub fn encode<T: Serialize>(rows: &[T], format: ExportFormat) -> Result<Vec<Vec<u8>>, GenericError> {
+    let mut chunks = rows.chunks(CHUNK_ROWS).collect_vec();
+    if chunks.is_empty() {
+        // An empty JSON export still needs its brackets
+        chunks.push(&[]);
+    }
+    let last = chunks.len() - 1;
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| encode_chunk(chunk, format, index == 0, index == last))
+        .collect()
+}
+
+fn encode_chunk<T: Serialize>(
+    chunk: &[T],
+    format: ExportFormat,
+    first: bool,
+    last: bool,
+) -> Result<Vec<u8>, GenericError> {
+    let mut out = Vec::new();
+    match format {
+        ExportFormat::Json => {
+            out.push(if first { b'[' } else { b',' });
+            for (index, row) in chunk.iter().enumerate() {
+                if index > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut out, row)
+                    .map_err(|_| GenericError::UnknownError("Unable to serialize export"))?;
+            }
+            if last {
+                out.push(b']');
+            }
+        }
+        ExportFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new().has_headers(first).from_writer(out);
+            for row in chunk {
+                writer
+                    .serialize(row)
+                    .map_err(|_| GenericError::UnknownError("Unable to serialize export"))?;
+            }
+            out = writer
+                .into_inner()
+                .map_err(|_| GenericError::UnknownError("Unable to serialize export"))?;
+        }
+    }
+    Ok(out)
+}
+
+async fn usernames(
+    db: &impl ConnectionTrait,
+    user_ids: HashSet<i32>,
+) -> Result<HashMap<i32, String>, GenericError> {
+    Ok(User::find()
+        .filter(user::Column::Id.is_in(user_ids))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get users from database"))?
+        .into_iter()
+        .map(|u| (u.id, u.name))
+        .collect())
+}
+
+async fn player_names(
+    db: &impl ConnectionTrait,
+    pdga_numbers: HashSet<i32>,
+) -> Result<HashMap<i32, String>, GenericError> {
+    Ok(Player::find()
+        .filter(player::Column::PdgaNumber.is_in(pdga_numbers))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get players from database"))?
+        .into_iter()
+        .map(|p| (p.pdga_number, format!("{} {}", p.first_name, p.last_name)))
+        .collect())
+}
+
+fn name(names: &HashMap<i32, String>, id: i32) -> String {
+    names.get(&id).cloned().unwrap_or_default()
+}
+
+pub async fn standings(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<Vec<StandingRow>, GenericError> {
+    let users = crate::get_user_participants_in_tournament(db, tournament_id)
+        .await?
+        .into_iter()
+        .map(|u| (u.user.id, u.user.username, u.score))
+        .collect_vec();
+    Ok(ranked(users))
+}
+
+/// Sorts users by score, best first, giving tied users the same rank.
+fn ranked(users: Vec<(i32, String, i32)>) -> Vec<StandingRow> {
+    let users = users
+        .into_iter()
+        .sorted_by(|(a_id, _, a), (b_id, _, b)| b.cmp(a).then(a_id.cmp(b_id)))
+        .collect_vec();
+    let mut rows: Vec<StandingRow> = Vec::with_capacity(users.len());
+    for (index, (user_id, username, score)) in users.into_iter().enumerate() {
+        let rank = match rows.last() {
+            Some(previous) if previous.score == score => previous.rank,
+            _ => index + 1,
+        };
+        rows.push(StandingRow {
+            rank,
+            user_id,
+            username,
+            score,
+        });
+    }
+    rows
+}
+
+pub async fn competition_scores(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<Vec<ScoreRow>, GenericError> {
+    let scores = UserCompetitionScoreInFantasyTournament::find()
+        .filter(user_competition_score_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .find_also_related(Competition)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get user scores from database"))?;
+    let users = usernames(db, scores.iter().map(|(s, _)| s.user).collect()).await?;
+    let players = player_names(db, scores.iter().map(|(s, _)| s.pdga_number).collect()).await?;
+    Ok(scores
+        .into_iter()
+        .filter_map(|(score, competition)| {
+            competition.map(|c| ScoreRow {
+                competition_id: c.id,
+                competition_name: c.name,
+                start_date: c.start_date,
+                user_id: score.user,
+                username: name(&users, score.user),
+                pdga_number: score.pdga_number,
+                player_name: name(&players, score.pdga_number),
+                score: score.score,
+            })
+        })
+        .sorted_by_key(|r| (r.start_date, r.competition_id, r.user_id, r.pdga_number))
+        .collect())
+}
+
+pub async fn rosters(db: &impl ConnectionTrait, tournament_id: i32) -> Result<Vec<RosterRow>, GenericError> {
+    let picks = FantasyPick::find()
+        .filter(fantasy_pick::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?;
+    let users = usernames(db, picks.iter().map(|p| p.user).collect()).await?;
+    let players = player_names(db, picks.iter().map(|p| p.player).collect()).await?;
+    Ok(picks
+        .into_iter()
+        .map(|p| RosterRow {
+            user_id: p.user,
+            username: name(&users, p.user),
+            division: p.division.into(),
+            slot: p.pick_number,
+            pdga_number: p.player,
+            player_name: name(&players, p.player),
+            benched: p.benched,
+        })
+        .sorted_by_key(|r| (r.user_id, r.division as u8, r.slot))
+        .collect())
+}
+
+pub async fn trades(db: &impl ConnectionTrait, tournament_id: i32) -> Result<Vec<TradeRow>, GenericError> {
+    let trades = PlayerTrade::find()
+        .filter(player_trade::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get trades from database"))?;
+    let users = usernames(db, trades.iter().map(|t| t.user).collect()).await?;
+    let players = player_names(db, trades.iter().map(|t| t.player).collect()).await?;
+    Ok(trades
+        .into_iter()
+        .sorted_by_key(|t| (t.timestamp, t.id))
+        .map(|t| TradeRow {
+            id: t.id,
+            timestamp: t.timestamp.to_rfc3339(),
+            user_id: t.user,
+            username: name(&users, t.user),
+            slot: t.slot,
+            pdga_number: t.player,
+            player_name: name(&players, t.player),
+            other_pdga_number: t.other_player,
+            other_slot: t.other_slot,
+            is_local_swap: t.is_local_swap,
+            is_removal: t.is_removal,
+            is_revert: t.is_revert,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn standing(rank: usize, user_id: i32, score: i32) -> StandingRow {
+        StandingRow {
+            rank,
+            user_id,
+            username: format!("user{user_id}"),
+            score,
+        }
+    }
+
+    #[test]
+    fn tied_users_share_rank() {
+        let users = [(1, 10), (2, 30), (3, 10), (4, 5)]
+            .map(|(id, score)| (id, format!("user{id}"), score))
+            .to_vec();
+        assert_eq!(
+            ranked(users),
+            vec![
+                standing(1, 2, 30),
+                standing(2, 1, 10),
+                standing(2, 3, 10),
+                standing(4, 4, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_join_into_one_document() {
+        let rows = (0..CHUNK_ROWS + 1)
+            .map(|i| standing(i, i as i32, 0))
+            .collect_vec();
+        let chunks = encode(&rows, ExportFormat::Json).unwrap();
+        assert_eq!(chunks.len(), 2);
+        let parsed: Vec<serde_json::Value> = serde_json::from_slice(&chunks.concat()).unwrap();
+        assert_eq!(parsed.len(), CHUNK_ROWS + 1);
+
+        let csv = encode(&rows, ExportFormat::Csv).unwrap().concat();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), CHUNK_ROWS + 2);
+        assert_eq!(csv.matches("rank,user_id").count(), 1);
+
+        let csv = encode(&[standing(1, 1, 3), standing(2, 2, 1)], ExportFormat::Csv)
+            .unwrap()
+            .concat();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "rank,user_id,username,score\n1,1,user1,3\n2,2,user2,1\n"
+        );
+        assert_eq!(
+            encode(&Vec::<StandingRow>::new(), ExportFormat::Json)
+                .unwrap()
+                .concat(),
+            b"[]"
+        );
+    }
+
+    #[test]
+    fn rows_that_cant_be_written_fail_the_export() {
+        // CSV has no way to write nested maps
+        let rows = vec![HashMap::from([("nested", HashMap::from([("a", 1)]))])];
+        assert!(encode(&rows, ExportFormat::Csv).is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use rocket_okapi::JsonSchema;
+use sea_orm::DatabaseConnection;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::metrics::{seconds_since_last_run, Job};
+
+/// The score refresh runs every minute, so a few missed runs mean it has stalled.
+const MAX_SCORE_REFRESH_AGE_SECONDS: i64 = 5 * 60;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct HealthStatus {
+    pub ok: bool,
+    pub database: bool,
+    /// Seconds since the score refresh loop last finished a run.
+    pub score_refresh_age_seconds: Option<i64>,
+}
+
+/// Checks that the database answers.
+pub async fn check_health(db: &DatabaseConnection) -> HealthStatus {
+    let database = db.ping().await.is_ok();
+    HealthStatus {
+        ok: database,
+        database,
+        score_refresh_age_seconds: seconds_since_last_run(Job::RefreshScores),
+    }
+}
+
+/// Checks that the database answers and that the score refresh loop has run recently.
+pub async fn check_readiness(db: &DatabaseConnection) -> HealthStatus {
+    let mut status = check_health(db).await;
+    status.ok &= status
+        .score_refresh_age_seconds
+        .is_some_and(|age| age <= MAX_SCORE_REFRESH_AGE_SECONDS);
+    status
+}
@@ -0,0 +1,332 @@
+use chrono::Utc;
+use entity::prelude::*;
+use entity::sea_orm_active_enums::FantasyTournamentInvitationStatus;
+use entity::*;
+use itertools::Itertools;
+use log::error;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket_okapi::JsonSchema;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, IntoActiveModel, ModelTrait,
+    NotSet, QueryFilter, QueryOrder,
+};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::dto::forms::CreateInviteCode;
+use crate::error::GenericError;
+use crate::query::get_fantasy_tournament_model;
+use crate::visibility::Visibility;
+use crate::{dto, notifications};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct InviteCode {
+    pub code: String,
+    pub created_at: DateTimeWithTimeZone,
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+}
+
+impl From<fantasy_tournament_invite_code::Model> for InviteCode {
+    fn from(code: fantasy_tournament_invite_code::Model) -> Self {
+        Self {
+            code: code.code,
+            created_at: code.created_at,
+            expires_at: code.expires_at,
+            max_uses: code.max_uses,
+            uses: code.uses,
+        }
+    }
+}
+
+async fn get_open_tournament(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<fantasy_tournament::Model, GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    get_fantasy_tournament_model(db, tournament_id)
+        .await?
+        .ok_or(GenericError::NotFound("Tournament not found"))
+}
+
+async fn get_membership(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<Option<user_in_fantasy_tournament::Model>, GenericError> {
+    UserInFantasyTournament::find()
+        .filter(
+            user_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(user_in_fantasy_tournament::Column::UserId.eq(user_id)),
+        )
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get user in tournament"))
+}
+
+/// Inserts or updates the membership row of a user in a tournament.
+async fn set_membership(
+    db: &impl ConnectionTrait,
+    membership: Option<user_in_fantasy_tournament::Model>,
+    tournament_id: i32,
+    user_id: i32,
+    status: FantasyTournamentInvitationStatus,
+) -> Result<(), GenericError> {
+    let mut active = match membership {
+        Some(m) => m.into_active_model(),
+        None => user_in_fantasy_tournament::ActiveModel {
+            id: NotSet,
+            user_id: Set(user_id),
+            fantasy_tournament_id: Set(tournament_id),
+            invitation_status: NotSet,
+            role: NotSet,
+        },
+    };
+    active.invitation_status = Set(status);
+    active.save(db).await.map_err(|e| {
+        error!("Unable to save user in tournament: {:#?}", e);
+        GenericError::UnknownError("Unable to save user in tournament")
+    })?;
+    Ok(())
+}
+
+pub async fn create_invite_code(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    created_by: i32,
+    options: CreateInviteCode,
+) -> Result<InviteCode, GenericError> {
+    get_open_tournament(db, tournament_id).await?;
+    if options.max_uses.is_some_and(|uses| uses < 1) {
+        Err(GenericError::BadRequest("Max uses must be at least 1"))?
+    }
+    let now = Utc::now().fixed_offset();
+    if options.expires_at.is_some_and(|expires| expires <= now) {
+        Err(GenericError::BadRequest("Expiry must be in the future"))?
+    }
+
+    let code: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(char::from)
+        .collect();
+    fantasy_tournament_invite_code::ActiveModel {
+        id: NotSet,
+        fantasy_tournament_id: Set(tournament_id),
+        code: Set(code),
+        created_by: Set(created_by),
+        created_at: Set(now),
+        expires_at: Set(options.expires_at),
+        max_uses: Set(options.max_uses),
+        uses: Set(0),
+    }
+    .insert(db)
+    .await
+    .map(InviteCode::from)
+    .map_err(|e| {
+        error!("Unable to insert invite code: {:#?}", e);
+        GenericError::UnknownError("Unable to create invite code")
+    })
+}
+
+pub async fn get_invite_codes(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<Vec<InviteCode>, GenericError> {
+    Ok(FantasyTournamentInviteCode::find()
+        .filter(fantasy_tournament_invite_code::Column::FantasyTournamentId.eq(tournament_id))
+        .order_by_desc(fantasy_tournament_invite_code::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get invite codes"))?
+        .into_iter()
+        .map(InviteCode::from)
+        .collect_vec())
+}
+
+pub async fn delete_invite_code(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    code: &str,
+) -> Result<(), GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let deleted = FantasyTournamentInviteCode::delete_many()
+        .filter(
+            fantasy_tournament_invite_code::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(fantasy_tournament_invite_code::Column::Code.eq(code)),
+        )
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete invite code"))?;
+    if deleted.rows_affected == 0 {
+        Err(GenericError::NotFound("Invite code not found"))?
+    }
+    Ok(())
+}
+
+/// Joins the tournament an invite code belongs to, returning the id of the tournament.
+///
+/// Any earlier invite, request or decline of the user is turned into an accepted membership.
+/// Should be run inside a transaction.
+pub async fn join_with_code(
+    db: &impl ConnectionTrait,
+    code: &str,
+    user_id: i32,
+) -> Result<i32, GenericError> {
+    let invite_code = FantasyTournamentInviteCode::find()
+        .filter(fantasy_tournament_invite_code::Column::Code.eq(code))
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get invite code"))?
+        .ok_or(GenericError::NotFound("Invite code not found"))?;
+    if invite_code
+        .expires_at
+        .is_some_and(|expires| expires <= Utc::now().fixed_offset())
+    {
+        Err(GenericError::Conflict("Invite code has expired"))?
+    }
+    if invite_code.max_uses.is_some_and(|max| invite_code.uses >= max) {
+        Err(GenericError::Conflict("Invite code has been used up"))?
+    }
+    let tournament_id = invite_code.fantasy_tournament_id;
+    get_open_tournament(db, tournament_id).await?;
+
+    let membership = get_membership(db, tournament_id, user_id).await?;
+    if membership
+        .as_ref()
+        .is_some_and(|m| m.invitation_status == FantasyTournamentInvitationStatus::Accepted)
+    {
+        Err(GenericError::Conflict("Already in tournament"))?
+    }
+    set_membership(
+        db,
+        membership,
+        tournament_id,
+        user_id,
+        FantasyTournamentInvitationStatus::Accepted,
+    )
+    .await?;
+
+    // Counted in the database so that concurrent joins can't go past the use limit
+    let counted = FantasyTournamentInviteCode::update_many()
+        .col_expr(
+            fantasy_tournament_invite_code::Column::Uses,
+            Expr::col(fantasy_tournament_invite_code::Column::Uses).add(1),
+        )
+        .filter(
+            Condition::all()
+                .add(fantasy_tournament_invite_code::Column::Id.eq(invite_code.id))
+                .add(
+                    Condition::any()
+                        .add(fantasy_tournament_invite_code::Column::MaxUses.is_null())
+                        .add(
+                            Expr::col(fantasy_tournament_invite_code::Column::Uses)
+                                .lt(Expr::col(fantasy_tournament_invite_code::Column::MaxUses)),
+                        ),
+                ),
+        )
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to update invite code"))?;
+    if counted.rows_affected == 0 {
+        Err(GenericError::Conflict("Invite code has been used up"))?
+    }
+    Ok(tournament_id)
+}
+
+/// Asks to join a tournament that has join requests enabled.
+pub async fn request_to_join(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<(), GenericError> {
+    let tournament = get_open_tournament(db, tournament_id).await?;
+    if !tournament.join_requests_enabled || Visibility::from(tournament.visibility) == Visibility::Private {
+        Err(GenericError::NotPermitted(
+            "Tournament does not accept join requests",
+        ))?
+    }
+    let membership = get_membership(db, tournament_id, user_id).await?;
+    match membership.as_ref().map(|m| &m.invitation_status) {
+        Some(FantasyTournamentInvitationStatus::Accepted) => {
+            Err(GenericError::Conflict("Already in tournament"))?
+        }
+        Some(FantasyTournamentInvitationStatus::Pending) => Err(GenericError::Conflict(
+            "You are already invited, answer the invite instead",
+        ))?,
+        Some(FantasyTournamentInvitationStatus::Requested) => {
+            Err(GenericError::Conflict("You have already asked to join"))?
+        }
+        Some(FantasyTournamentInvitationStatus::Declined) | None => (),
+    }
+    set_membership(
+        db,
+        membership,
+        tournament_id,
+        user_id,
+        FantasyTournamentInvitationStatus::Requested,
+    )
+    .await?;
+    notifications::notify_join_request(db, tournament_id, user_id).await
+}
+
+pub async fn get_join_requests(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<Vec<dto::User>, GenericError> {
+    let requests = UserInFantasyTournament::find()
+        .filter(
+            user_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(
+                    user_in_fantasy_tournament::Column::InvitationStatus
+                        .eq(FantasyTournamentInvitationStatus::Requested),
+                ),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get join requests"))?;
+
+    let mut users = Vec::new();
+    for request in requests {
+        if let Some(user) = request
+            .find_related(User)
+            .one(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get user"))?
+        {
+            users.push(dto::User {
+                id: user.id,
+                username: user.name,
+            });
+        }
+    }
+    Ok(users)
+}
+
+/// Approves or declines a pending join request.
+pub async fn answer_join_request(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+    accepted: bool,
+) -> Result<(), GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let membership = get_membership(db, tournament_id, user_id)
+        .await?
+        .filter(|m| m.invitation_status == FantasyTournamentInvitationStatus::Requested)
+        .ok_or(GenericError::NotFound("Join request not found"))?;
+    let status = if accepted {
+        FantasyTournamentInvitationStatus::Accepted
+    } else {
+        FantasyTournamentInvitationStatus::Declined
+    };
+    set_membership(db, Some(membership), tournament_id, user_id, status).await?;
+    notifications::notify_join_request_answered(db, tournament_id, user_id, accepted).await
+}
@@ -1,8 +1,33 @@
+pub mod account;
+pub mod audit;
+pub mod backup;
+pub mod captains;
 pub mod dto;
 pub mod error;
 pub mod exchange_windows;
+pub mod export;
+pub mod health;
+pub mod invites;
+pub mod metrics;
 pub mod mutation;
+pub mod notifications;
+pub mod performance_index;
+pub mod placeholders;
+pub mod projections;
 pub mod query;
+pub mod refresh;
+pub mod roles;
+pub mod roster_rules;
+pub mod salary_cap;
+pub mod schedule_import;
+pub mod simulation;
+pub mod substitutions;
+#[cfg(test)]
+mod test_db;
+pub mod tournament_management;
+pub mod trade_rollback;
+pub mod visibility;
+pub mod webhooks;
 
 pub use mutation::*;
 pub use query::*;
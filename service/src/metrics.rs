@@ -0,0 +1,208 @@
+use std::future::Future;
+use std::time::Instant;
+
+use chrono::Utc;
+use entity::prelude::*;
+use entity::sea_orm_active_enums::CompetitionStatus;
+use entity::*;
+use lazy_static::lazy_static;
+use prometheus::{
+    register_gauge_vec, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Encoder, GaugeVec, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    TextEncoder,
+};
+use sea_orm::sea_query::Expr;
+use sea_orm::{DatabaseConnection, EntityTrait, Iterable, QuerySelect};
+
+use crate::error::GenericError;
+
+lazy_static! {
+    static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "ctp_http_requests_total",
+        "Handled HTTP requests",
+        &["method", "route", "status"]
+    )
+    .unwrap();
+    static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "ctp_http_request_duration_seconds",
+        "Time spent handling HTTP requests",
+        &["method", "route"]
+    )
+    .unwrap();
+    static ref PDGA_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "ctp_pdga_requests_total",
+        "Requests made to the PDGA live API",
+        &["endpoint", "outcome"]
+    )
+    .unwrap();
+    static ref PDGA_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "ctp_pdga_request_duration_seconds",
+        "Time spent waiting on the PDGA live API",
+        &["endpoint"]
+    )
+    .unwrap();
+    static ref PDGA_CIRCUIT_OPEN: IntGauge = register_int_gauge!(
+        "ctp_pdga_circuit_open",
+        "Whether requests to the PDGA are paused after repeated failures"
+    )
+    .unwrap();
+    static ref JOB_RUNS: IntCounterVec = register_int_counter_vec!(
+        "ctp_job_runs_total",
+        "Runs of background jobs",
+        &["job", "outcome"]
+    )
+    .unwrap();
+    static ref JOB_DURATION: HistogramVec = register_histogram_vec!(
+        "ctp_job_duration_seconds",
+        "Time spent running background jobs",
+        &["job"],
+        vec![0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]
+    )
+    .unwrap();
+    static ref JOB_LAST_RUN: GaugeVec = register_gauge_vec!(
+        "ctp_job_last_run_timestamp_seconds",
+        "Unix time of when background jobs last finished a run",
+        &["job"]
+    )
+    .unwrap();
+    static ref DB_POOL_CONNECTIONS: IntGauge =
+        register_int_gauge!("ctp_db_pool_connections", "Open database connections").unwrap();
+    static ref DB_POOL_IDLE_CONNECTIONS: IntGauge =
+        register_int_gauge!("ctp_db_pool_idle_connections", "Idle database connections").unwrap();
+    static ref COMPETITIONS: IntGaugeVec = register_int_gauge_vec!(
+        "ctp_competitions",
+        "Competitions in the database by status",
+        &["status"]
+    )
+    .unwrap();
+}
+
+/// Background jobs that are timed, named after the loop they run in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Job {
+    RefreshScores,
+    NotifyExchangeWindows,
+    InsertPlayers,
+    PricePlayers,
+    LinkPlaceholders,
+    DeliverWebhooks,
+}
+
+impl Job {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::RefreshScores => "refresh_scores",
+            Self::NotifyExchangeWindows => "notify_exchange_windows",
+            Self::InsertPlayers => "insert_players",
+            Self::PricePlayers => "price_players",
+            Self::LinkPlaceholders => "link_placeholders",
+            Self::DeliverWebhooks => "deliver_webhooks",
+        }
+    }
+}
+
+fn outcome<T>(result: &Result<T, GenericError>) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(GenericError::PdgaGaveUp(_)) => "pdga_gave_up",
+        Err(_) => "error",
+    }
+}
+
+pub fn observe_request(method: &str, route: &str, status: u16, seconds: f64) {
+    HTTP_REQUESTS
+        .with_label_values(&[method, route, &status.to_string()])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[method, route])
+        .observe(seconds);
+}
+
+pub(crate) fn observe_pdga_request(endpoint: &str, outcome: &str, seconds: f64) {
+    PDGA_REQUEST_DURATION
+        .with_label_values(&[endpoint])
+        .observe(seconds);
+    PDGA_REQUESTS.with_label_values(&[endpoint, outcome]).inc();
+}
+
+pub(crate) fn set_pdga_circuit_open(open: bool) {
+    PDGA_CIRCUIT_OPEN.set(open as i64);
+}
+
+/// Times a run of a background job, and records when it last finished.
+pub async fn time_job<T>(
+    job: Job,
+    run: impl Future<Output = Result<T, GenericError>>,
+) -> Result<T, GenericError> {
+    let start = Instant::now();
+    let result = run.await;
+    JOB_DURATION
+        .with_label_values(&[job.label()])
+        .observe(start.elapsed().as_secs_f64());
+    JOB_RUNS.with_label_values(&[job.label(), outcome(&result)]).inc();
+    JOB_LAST_RUN
+        .with_label_values(&[job.label()])
+        .set(Utc::now().timestamp() as f64);
+    result
+}
+
+/// Seconds since the job last finished a run, or `None` if it hasn't yet.
+pub fn seconds_since_last_run(job: Job) -> Option<i64> {
+    let last = JOB_LAST_RUN.with_label_values(&[job.label()]).get() as i64;
+    (last > 0).then(|| Utc::now().timestamp() - last)
+}
+
+async fn update_competition_counts(db: &DatabaseConnection) -> Result<(), GenericError> {
+    let counts: Vec<(CompetitionStatus, i64)> = Competition::find()
+        .select_only()
+        .column(competition::Column::Status)
+        .column_as(Expr::col(competition::Column::Id).count(), "count")
+        .group_by(competition::Column::Status)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to count competitions"))?;
+    for status in CompetitionStatus::iter() {
+        let count = counts.iter().find(|(s, _)| *s == status).map_or(0, |(_, c)| *c);
+        COMPETITIONS
+            .with_label_values(&[&format!("{status:?}")])
+            .set(count);
+    }
+    Ok(())
+}
+
+/// Renders all metrics in the Prometheus text format.
+pub async fn render(db: &DatabaseConnection) -> Result<String, GenericError> {
+    update_competition_counts(db).await?;
+    let pool = db.get_postgres_connection_pool();
+    DB_POOL_CONNECTIONS.set(pool.size() as i64);
+    DB_POOL_IDLE_CONNECTIONS.set(pool.num_idle() as i64);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .map_err(|_| GenericError::UnknownError("Unable to encode metrics"))?;
+    String::from_utf8(buffer).map_err(|_| GenericError::UnknownError("Unable to encode metrics"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn timed_job_records_last_run() {
+        assert_eq!(seconds_since_last_run(Job::InsertPlayers), None);
+        let result = time_job(Job::InsertPlayers, async {
+            Err::<(), _>(GenericError::PdgaGaveUp("test"))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(seconds_since_last_run(Job::InsertPlayers), Some(0));
+        assert_eq!(
+            JOB_RUNS
+                .with_label_values(&["insert_players", "pdga_gave_up"])
+                .get(),
+            1
+        );
+    }
+}
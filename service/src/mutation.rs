@@ -1,9 +1,7 @@
 use bcrypt::{hash, DEFAULT_COST};
 use entity::prelude::*;
-use entity::sea_orm_active_enums::{CompetitionStatus, FantasyTournamentInvitationStatus};
+use entity::sea_orm_active_enums::FantasyTournamentInvitationStatus;
 use entity::*;
-use fantasy_tournament::Entity as FantasyTournament;
-use itertools::Itertools;
 use log::{error, warn};
 use rand::distributions::Alphanumeric;
 use rand::Rng;
@@ -11,13 +9,13 @@ use rocket::http::{Cookie, CookieJar};
 use sea_orm::ActiveValue::*;
 use sea_orm::{
     sea_query, ActiveModelTrait, ConnectionTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
-    ModelTrait, TransactionTrait,
+    ModelTrait,
 };
 use sea_orm::{ColumnTrait, QueryFilter};
 
 use crate::dto::traits::InsertCompetition;
 use crate::error::{GenericError, InviteError};
-use crate::{dto, query};
+use crate::{dto, notifications};
 
 pub async fn generate_cookie(
     db: &DatabaseConnection,
@@ -53,10 +51,16 @@ pub async fn generate_cookie(
 }
 
 pub async fn create_invite(
-    db: &DatabaseConnection,
+    db: &impl ConnectionTrait,
     receiver_name: String,
     fantasy_tournament_id: i32,
 ) -> Result<(), InviteError> {
+    crate::tournament_management::assure_not_archived(db, fantasy_tournament_id)
+        .await
+        .map_err(|e| match e {
+            GenericError::Conflict(_) => InviteError::TournamentArchived,
+            _ => InviteError::TournamentNotFound,
+        })?;
     let invited_user = if let Ok(Some(u)) = crate::get_user_by_name(db, receiver_name).await {
         u
     } else {
@@ -67,6 +71,7 @@ pub async fn create_invite(
         fantasy_tournament_id: Set(fantasy_tournament_id),
         user_id: Set(invited_user.id),
         invitation_status: Set(FantasyTournamentInvitationStatus::Pending),
+        role: NotSet,
     };
 
     if user_in_fantasy_tournament::Entity::insert(invite)
@@ -74,22 +79,37 @@ pub async fn create_invite(
         .await
         .is_ok()
     {
+        if let Err(e) = notifications::notify_invitation(db, invited_user.id, fantasy_tournament_id).await {
+            warn!("Unable to notify invited user: {:#?}", e);
+        }
         Ok(())
     } else {
         Err(InviteError::UserNotFound)
     }
 }
 pub async fn answer_invite(
-    db: &DatabaseConnection,
+    db: &impl ConnectionTrait,
     user: &user::Model,
     fantasy_tournament_id: i32,
     invitation_status: bool,
 ) -> Result<(), InviteError> {
+    crate::tournament_management::assure_not_archived(db, fantasy_tournament_id)
+        .await
+        .map_err(|e| match e {
+            GenericError::Conflict(_) => InviteError::TournamentArchived,
+            _ => InviteError::TournamentNotFound,
+        })?;
     let mut invite = if let Ok(Some(i)) = UserInFantasyTournament::find()
         .filter(
             user_in_fantasy_tournament::Column::FantasyTournamentId
                 .eq(fantasy_tournament_id)
-                .and(user_in_fantasy_tournament::Column::UserId.eq(user.id)),
+                .and(user_in_fantasy_tournament::Column::UserId.eq(user.id))
+                // Only open invites can be answered. Join requests are answered by the owner,
+                // and a declined join request must not be accepted by the one who asked
+                .and(
+                    user_in_fantasy_tournament::Column::InvitationStatus
+                        .eq(FantasyTournamentInvitationStatus::Pending),
+                ),
         )
         .one(db)
         .await
@@ -111,50 +131,6 @@ pub async fn answer_invite(
     }
 }
 
-pub async fn update_active_competitions(db: &DatabaseConnection) -> Result<(), GenericError> {
-    let competitions = query::active_competitions(db).await?;
-
-    for competition in competitions {
-        if let Ok(txn) = db.begin().await.map_err(|e| {
-            warn!("Unable to start transaction: {:#?}", e);
-        }) {
-            let _ = competition.save_round_scores(&txn).await;
-            let _ = competition.save_competition_in_db(&txn, None).await;
-            let _ = txn.commit().await.map_err(|e| {
-                warn!("Unable to commit transaction: {:#?}", e);
-            });
-        }
-    }
-
-    Ok(())
-}
-
-// TODO: Refactor out the saving to DB
-pub async fn refresh_user_scores_in_fantasy(
-    db: &impl ConnectionTrait,
-    fantasy_tournament_id: u32,
-) -> Result<(), GenericError> {
-    let competition_ids = crate::get_competitions_in_fantasy_tournament(db, fantasy_tournament_id as i32)
-        .await?
-        .into_iter()
-        .filter(|comp| comp.status == CompetitionStatus::Running)
-        .map(|c| c.id as u32)
-        .collect_vec();
-
-    for id in competition_ids {
-        match dto::CompetitionInfo::from_web(id).await {
-            Err(GenericError::PdgaGaveUp(_)) => {
-                tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
-                //let comp = dto::CompetitionInfo::from_web(id).await?;
-                //comp.save_user_scores(db, fantasy_tournament_id).await?;
-            }
-            Ok(comp) => comp.save_user_scores(db, fantasy_tournament_id).await?,
-            Err(e) => Err(e)?,
-        }
-    }
-    Ok(())
-}
-
 pub async fn refresh_player_scores_in_active_competitions(
     db: &impl ConnectionTrait,
 ) -> Result<(), GenericError> {
@@ -166,19 +142,6 @@ pub async fn refresh_player_scores_in_active_competitions(
     Ok(())
 }
 
-pub async fn refresh_user_scores_in_all(db: &DatabaseConnection) -> Result<(), GenericError> {
-    let fantasy_tournaments = FantasyTournament::find()
-        .all(db)
-        .await
-        .map_err(|_| GenericError::UnknownError("database error on fantasy tournament"))?;
-    for tournament in fantasy_tournaments {
-        let transaction = db.begin().await.unwrap();
-        refresh_user_scores_in_fantasy(&transaction, tournament.id as u32).await?;
-        transaction.commit().await.unwrap();
-    }
-    Ok(())
-}
-
 /*pub async fn set_activity_status_on_competitions(db: &impl ConnectionTrait) -> Result<(), GenericError> {
     let active_comps = crate::get_active_competitions(db).await?;
     for comp in active_comps {
@@ -212,12 +175,93 @@ pub async fn update_or_insert_many_player_round_scores(
     Ok(())
 }
 
+/// Rows per insert, keeping the bind parameters of a statement well below the Postgres limit
+pub(crate) const INSERT_CHUNK_SIZE: usize = 1000;
+
+pub async fn update_or_insert_many_round_holes(
+    db: &impl ConnectionTrait,
+    holes: Vec<round_hole::ActiveModel>,
+) -> Result<(), GenericError> {
+    for chunk in holes.chunks(INSERT_CHUNK_SIZE) {
+        round_hole::Entity::insert_many(chunk.to_vec())
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    round_hole::Column::CompetitionId,
+                    round_hole::Column::Round,
+                    round_hole::Column::Division,
+                    round_hole::Column::HoleNumber,
+                ])
+                .update_columns([round_hole::Column::Par, round_hole::Column::Length])
+                .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await
+            .map_err(|e| {
+                error!("Unable to insert round holes into database: {:#?}", e);
+                GenericError::UnknownError("Unable to insert round holes into database")
+            })?;
+    }
+    Ok(())
+}
+
+pub async fn update_or_insert_many_player_hole_scores(
+    db: &impl ConnectionTrait,
+    scores: Vec<player_hole_score::ActiveModel>,
+) -> Result<(), GenericError> {
+    for chunk in scores.chunks(INSERT_CHUNK_SIZE) {
+        player_hole_score::Entity::insert_many(chunk.to_vec())
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    player_hole_score::Column::PdgaNumber,
+                    player_hole_score::Column::CompetitionId,
+                    player_hole_score::Column::Round,
+                    player_hole_score::Column::HoleNumber,
+                ])
+                .update_column(player_hole_score::Column::Throws)
+                .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await
+            .map_err(|e| {
+                error!("Unable to insert hole scores into database: {:#?}", e);
+                GenericError::UnknownError("Unable to insert hole scores into database")
+            })?;
+    }
+    Ok(())
+}
+
+pub async fn update_or_insert_many_player_withdrawals(
+    db: &impl ConnectionTrait,
+    withdrawals: Vec<player_withdrawal::ActiveModel>,
+) -> Result<(), GenericError> {
+    for chunk in withdrawals.chunks(INSERT_CHUNK_SIZE) {
+        player_withdrawal::Entity::insert_many(chunk.to_vec())
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    player_withdrawal::Column::PdgaNumber,
+                    player_withdrawal::Column::CompetitionId,
+                    player_withdrawal::Column::Round,
+                ])
+                .update_column(player_withdrawal::Column::Reason)
+                .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await
+            .map_err(|e| {
+                error!("Unable to insert withdrawals into database: {:#?}", e);
+                GenericError::UnknownError("Unable to insert withdrawals into database")
+            })?;
+    }
+    Ok(())
+}
+
 pub async fn insert_competition_in_fantasy(
     db: &impl ConnectionTrait,
     fantasy_tournament_id: u32,
     competition_id: u32,
     level: dto::CompetitionLevel,
 ) -> Result<(), GenericError> {
+    crate::tournament_management::assure_not_archived(db, fantasy_tournament_id as i32).await?;
     match Competition::find_by_id(competition_id as i32)
         .one(db)
         .await
@@ -257,32 +301,100 @@ pub async fn insert_competition_in_fantasy(
         }
         None => {
             let competition = dto::CompetitionInfo::from_web(competition_id).await?;
-            competition.insert_in_db(db, level.into()).await?;
-
-            competition.insert_in_fantasy(db, fantasy_tournament_id).await?;
-            competition
-                .insert_players(db, Some(fantasy_tournament_id as i32))
-                .await?;
-            Ok(())
+            insert_fetched_competition(db, fantasy_tournament_id, &competition, level).await
         }
     }
 }
 
+/// Stores a competition fetched from PDGA and adds it to a fantasy tournament.
+pub(crate) async fn insert_fetched_competition(
+    db: &impl ConnectionTrait,
+    fantasy_tournament_id: u32,
+    competition: &dto::CompetitionInfo,
+    level: dto::CompetitionLevel,
+) -> Result<(), GenericError> {
+    competition.insert_in_db(db, level.into()).await?;
+
+    competition.insert_in_fantasy(db, fantasy_tournament_id).await?;
+    competition
+        .insert_players(db, Some(fantasy_tournament_id as i32))
+        .await?;
+    Ok(())
+}
+
+/// Sets the password of a user, also for users that don't have one yet, like the ones created by a restore.
 pub async fn update_password(
-    db: &DatabaseConnection,
+    db: &impl ConnectionTrait,
     user_id: i32,
     new_password: String,
 ) -> Result<(), GenericError> {
-    let hashed_password = hash(new_password, DEFAULT_COST).expect("hashing should work");
-    let mut user = UserAuthentication::find_by_id(user_id)
+    User::find_by_id(user_id)
         .one(db)
         .await
         .map_err(|_| GenericError::UnknownError("Unable to find user"))?
-        .ok_or(GenericError::NotFound("User not found"))?
-        .into_active_model();
-    user.hashed_password = Set(hashed_password);
-    user.save(db)
-        .await
-        .map_err(|_| GenericError::UnknownError("Unable to update password"))?;
+        .ok_or(GenericError::NotFound("User not found"))?;
+    let hashed_password = hash(new_password, DEFAULT_COST).expect("hashing should work");
+    UserAuthentication::insert(user_authentication::ActiveModel {
+        user_id: Set(user_id),
+        hashed_password: Set(hashed_password),
+    })
+    .on_conflict(
+        sea_query::OnConflict::column(user_authentication::Column::UserId)
+            .update_column(user_authentication::Column::HashedPassword)
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await
+    .map_err(|_| GenericError::UnknownError("Unable to update password"))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+    use entity::sea_orm_active_enums::TournamentRole;
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn only_pending_invites_can_be_answered() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let invited = test_db::user(&txn, "invited").await;
+        let declined_requester = test_db::user(&txn, "requester").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        for (user, status) in [
+            (&invited, FantasyTournamentInvitationStatus::Pending),
+            (&declined_requester, FantasyTournamentInvitationStatus::Declined),
+        ] {
+            test_db::member(&txn, tournament, user.id, status, TournamentRole::Member).await;
+        }
+
+        assert!(answer_invite(&txn, &declined_requester, tournament, true)
+            .await
+            .is_err());
+        assert!(answer_invite(&txn, &invited, tournament, true).await.is_ok());
+        assert!(answer_invite(&txn, &invited, tournament, false).await.is_err());
+        let role = crate::roles::participant_role(&txn, tournament, declined_requester.id)
+            .await
+            .unwrap();
+        assert_eq!(role, None);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn users_without_a_login_can_be_given_a_password() {
+        let txn = test_db::begin().await;
+        let restored = test_db::user(&txn, "restored").await;
+        update_password(&txn, restored.id, "new password".to_string())
+            .await
+            .unwrap();
+        let login = UserAuthentication::find_by_id(restored.id)
+            .one(&txn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(bcrypt::verify("new password", &login.hashed_password).unwrap());
+        assert!(update_password(&txn, -1, "password".to_string()).await.is_err());
+    }
+}
@@ -0,0 +1,457 @@
+use chrono::{Duration, Utc};
+use entity::prelude::*;
+use entity::sea_orm_active_enums::{
+    FantasyTournamentInvitationStatus, NotificationKind as DbNotificationKind,
+};
+use entity::*;
+use itertools::Itertools;
+use log::error;
+use rocket_okapi::JsonSchema;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, NotSet, QueryFilter, QueryOrder, QuerySelect};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::GenericError;
+use crate::exchange_windows::see_when_users_can_exchange;
+use crate::query::get_fantasy_tournament_model;
+
+/// Windows that opened longer ago than this are not notified about,
+/// so that a restart doesn't flood users with old news.
+const EXCHANGE_WINDOW_NOTIFY_WINDOW_HOURS: i64 = 12;
+
+const NOTIFICATION_LIST_LIMIT: u64 = 100;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub enum NotificationKind {
+    Invitation,
+    JoinRequest,
+    ExchangeWindowOpened,
+    Trade,
+    CompetitionFinished,
+}
+
+impl From<DbNotificationKind> for NotificationKind {
+    fn from(kind: DbNotificationKind) -> Self {
+        match kind {
+            DbNotificationKind::Invitation => Self::Invitation,
+            DbNotificationKind::JoinRequest => Self::JoinRequest,
+            DbNotificationKind::ExchangeWindowOpened => Self::ExchangeWindowOpened,
+            DbNotificationKind::Trade => Self::Trade,
+            DbNotificationKind::CompetitionFinished => Self::CompetitionFinished,
+        }
+    }
+}
+
+impl From<NotificationKind> for DbNotificationKind {
+    fn from(kind: NotificationKind) -> Self {
+        match kind {
+            NotificationKind::Invitation => Self::Invitation,
+            NotificationKind::JoinRequest => Self::JoinRequest,
+            NotificationKind::ExchangeWindowOpened => Self::ExchangeWindowOpened,
+            NotificationKind::Trade => Self::Trade,
+            NotificationKind::CompetitionFinished => Self::CompetitionFinished,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct Notification {
+    pub id: i32,
+    pub kind: NotificationKind,
+    pub fantasy_tournament_id: Option<i32>,
+    pub message: String,
+    pub created_at: DateTimeWithTimeZone,
+    pub read: bool,
+}
+
+impl From<notification::Model> for Notification {
+    fn from(n: notification::Model) -> Self {
+        Self {
+            id: n.id,
+            kind: n.kind.into(),
+            fantasy_tournament_id: n.fantasy_tournament_id,
+            message: n.message,
+            created_at: n.created_at,
+            read: n.read_at.is_some(),
+        }
+    }
+}
+
+/// Stores a notification for a user.
+///
+/// When a `dedup_key` is given, the notification is only stored if the user
+/// hasn't already gotten one with the same key.
+pub async fn notify(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    tournament_id: Option<i32>,
+    kind: NotificationKind,
+    message: String,
+    dedup_key: Option<String>,
+) -> Result<(), GenericError> {
+    notification::Entity::insert(notification::ActiveModel {
+        id: NotSet,
+        user_id: Set(user_id),
+        fantasy_tournament_id: Set(tournament_id),
+        kind: Set(kind.into()),
+        message: Set(message),
+        dedup_key: Set(dedup_key),
+        created_at: Set(Utc::now().fixed_offset()),
+        read_at: Set(None),
+    })
+    .on_conflict(
+        OnConflict::columns([notification::Column::UserId, notification::Column::DedupKey])
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await
+    .map_err(|e| {
+        error!("Unable to insert notification: {:#?}", e);
+        GenericError::UnknownError("Unable to insert notification")
+    })?;
+    Ok(())
+}
+
+/// Notifies every accepted participant of a tournament, except `except`.
+pub async fn notify_participants(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    except: Option<i32>,
+    kind: NotificationKind,
+    message: String,
+) -> Result<(), GenericError> {
+    let participants = UserInFantasyTournament::find()
+        .filter(
+            user_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(
+                    user_in_fantasy_tournament::Column::InvitationStatus
+                        .eq(FantasyTournamentInvitationStatus::Accepted),
+                ),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get participants from database"))?;
+    for participant in participants.into_iter().filter(|p| Some(p.user_id) != except) {
+        notify(
+            db,
+            participant.user_id,
+            Some(tournament_id),
+            kind.clone(),
+            message.clone(),
+            None,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn tournament_name(db: &impl ConnectionTrait, tournament_id: i32) -> Result<String, GenericError> {
+    Ok(get_fantasy_tournament_model(db, tournament_id)
+        .await?
+        .map(|t| t.name)
+        .unwrap_or_else(|| tournament_id.to_string()))
+}
+
+async fn user_name(db: &impl ConnectionTrait, user_id: i32) -> Result<String, GenericError> {
+    Ok(User::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get user from database"))?
+        .map(|u| u.name)
+        .unwrap_or_else(|| user_id.to_string()))
+}
+
+pub async fn notify_invitation(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    tournament_id: i32,
+) -> Result<(), GenericError> {
+    let name = tournament_name(db, tournament_id).await?;
+    notify(
+        db,
+        user_id,
+        Some(tournament_id),
+        NotificationKind::Invitation,
+        format!("You have been invited to {name}"),
+        None,
+    )
+    .await
+}
+
+pub async fn notify_join_request(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<(), GenericError> {
+    let tournament = get_fantasy_tournament_model(db, tournament_id)
+        .await?
+        .ok_or(GenericError::NotFound("Tournament not found"))?;
+    let user = user_name(db, user_id).await?;
+    notify(
+        db,
+        tournament.owner,
+        Some(tournament_id),
+        NotificationKind::JoinRequest,
+        format!("{user} wants to join {}", tournament.name),
+        None,
+    )
+    .await
+}
+
+pub async fn notify_join_request_answered(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+    accepted: bool,
+) -> Result<(), GenericError> {
+    let name = tournament_name(db, tournament_id).await?;
+    let answer = if accepted { "accepted" } else { "declined" };
+    notify(
+        db,
+        user_id,
+        Some(tournament_id),
+        NotificationKind::JoinRequest,
+        format!("Your request to join {name} was {answer}"),
+        None,
+    )
+    .await
+}
+
+/// Lets the other participants know that a user has changed their picks.
+pub async fn notify_trade(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<(), GenericError> {
+    let user = user_name(db, user_id).await?;
+    let name = tournament_name(db, tournament_id).await?;
+    notify_participants(
+        db,
+        tournament_id,
+        Some(user_id),
+        NotificationKind::Trade,
+        format!("{user} changed their picks in {name}"),
+    )
+    .await
+}
+
+pub async fn notify_trades_reverted(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<(), GenericError> {
+    let name = tournament_name(db, tournament_id).await?;
+    notify(
+        db,
+        user_id,
+        Some(tournament_id),
+        NotificationKind::Trade,
+        format!("Some of your pick changes in {name} were reverted by the owner"),
+        None,
+    )
+    .await
+}
+
+/// Notifies the participants of every tournament a competition is part of that it has finished.
+pub async fn notify_competition_finished(
+    db: &impl ConnectionTrait,
+    competition_id: i32,
+) -> Result<(), GenericError> {
+    let competition = Competition::find_by_id(competition_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competition from database"))?
+        .ok_or(GenericError::NotFound("Competition not found"))?;
+    let tournaments = CompetitionInFantasyTournament::find()
+        .filter(competition_in_fantasy_tournament::Column::CompetitionId.eq(competition_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournaments from database"))?;
+    for tournament in tournaments {
+        notify_participants(
+            db,
+            tournament.fantasy_tournament_id,
+            None,
+            NotificationKind::CompetitionFinished,
+            format!("{} has finished, scores are final", competition.name),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Notifies users whose exchange window has opened since the last check.
+pub async fn notify_opened_exchange_windows(db: &impl ConnectionTrait) -> Result<(), GenericError> {
+    let tournaments = FantasyTournament::find()
+        .filter(fantasy_tournament::Column::ArchivedAt.is_null())
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournaments from database"))?;
+    let now = Utc::now().fixed_offset();
+    let oldest = now - Duration::try_hours(EXCHANGE_WINDOW_NOTIFY_WINDOW_HOURS).unwrap();
+    for tournament in tournaments {
+        for (user, opens_at) in see_when_users_can_exchange(db, tournament.id).await? {
+            if opens_at <= now && opens_at > oldest {
+                notify(
+                    db,
+                    user.user.id,
+                    Some(tournament.id),
+                    NotificationKind::ExchangeWindowOpened,
+                    format!("Your exchange window in {} is now open", tournament.name),
+                    Some(format!(
+                        "exchange-window:{}:{}",
+                        tournament.id,
+                        opens_at.timestamp()
+                    )),
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn get_notifications(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    unread_only: bool,
+) -> Result<Vec<Notification>, GenericError> {
+    let mut filter = notification::Column::UserId.eq(user_id);
+    if unread_only {
+        filter = filter.and(notification::Column::ReadAt.is_null());
+    }
+    Ok(notification::Entity::find()
+        .filter(filter)
+        .order_by_desc(notification::Column::CreatedAt)
+        .order_by_desc(notification::Column::Id)
+        .limit(NOTIFICATION_LIST_LIMIT)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get notifications from database"))?
+        .into_iter()
+        .map(Notification::from)
+        .collect_vec())
+}
+
+/// Marks notifications of a user as read, either a single one or all of them. Notifications that
+/// were already read stay as they were.
+pub async fn mark_read(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    notification_id: Option<i32>,
+) -> Result<(), GenericError> {
+    let mut filter = notification::Column::UserId
+        .eq(user_id)
+        .and(notification::Column::ReadAt.is_null());
+    if let Some(id) = notification_id {
+        filter = filter.and(notification::Column::Id.eq(id));
+    }
+    let updated = notification::Entity::update_many()
+        .col_expr(
+            notification::Column::ReadAt,
+            Expr::value(Utc::now().fixed_offset()),
+        )
+        .filter(filter)
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to mark notifications as read"))?;
+    // Marking a notification that is already read is fine, as long as it is the user's
+    if let (Some(id), 0) = (notification_id, updated.rows_affected) {
+        notification::Entity::find_by_id(id)
+            .filter(notification::Column::UserId.eq(user_id))
+            .one(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get notification from database"))?
+            .ok_or(GenericError::NotFound("Notification not found"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+    use entity::sea_orm_active_enums::TournamentRole;
+
+    async fn notify_with_key(db: &impl ConnectionTrait, user_id: i32, dedup_key: Option<&str>) {
+        notify(
+            db,
+            user_id,
+            None,
+            NotificationKind::ExchangeWindowOpened,
+            "Your exchange window is now open".to_string(),
+            dedup_key.map(str::to_string),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn dedup_key_is_only_notified_once_per_user() {
+        let txn = test_db::begin().await;
+        let user = test_db::user(&txn, "user").await;
+        let other = test_db::user(&txn, "other").await;
+        for key in [Some("window:1"), Some("window:1"), Some("window:2"), None, None] {
+            notify_with_key(&txn, user.id, key).await;
+        }
+        notify_with_key(&txn, other.id, Some("window:1")).await;
+
+        assert_eq!(get_notifications(&txn, user.id, false).await.unwrap().len(), 4);
+        assert_eq!(get_notifications(&txn, other.id, false).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn notifications_are_marked_read_one_or_all_at_a_time() {
+        let txn = test_db::begin().await;
+        let user = test_db::user(&txn, "user").await;
+        let other = test_db::user(&txn, "other").await;
+        for _ in 0..3 {
+            notify_with_key(&txn, user.id, None).await;
+        }
+        let first = get_notifications(&txn, user.id, true).await.unwrap()[0].id;
+
+        assert!(matches!(
+            mark_read(&txn, other.id, Some(first)).await,
+            Err(GenericError::NotFound(_))
+        ));
+        mark_read(&txn, user.id, Some(first)).await.unwrap();
+        let unread = get_notifications(&txn, user.id, true).await.unwrap();
+        assert_eq!(unread.len(), 2);
+        assert!(unread.iter().all(|n| n.id != first && !n.read));
+        mark_read(&txn, user.id, Some(first)).await.unwrap();
+
+        mark_read(&txn, user.id, None).await.unwrap();
+        assert!(get_notifications(&txn, user.id, true).await.unwrap().is_empty());
+        let all = get_notifications(&txn, user.id, false).await.unwrap();
+        assert!(all.len() == 3 && all.iter().all(|n| n.read));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn trades_notify_everyone_but_the_trader() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        let mut members = Vec::new();
+        for status in [
+            FantasyTournamentInvitationStatus::Accepted,
+            FantasyTournamentInvitationStatus::Pending,
+        ] {
+            let member = test_db::user(&txn, "member").await;
+            test_db::member(&txn, tournament, member.id, status, TournamentRole::Member).await;
+            members.push(member.id);
+        }
+
+        notify_trade(&txn, tournament, members[0]).await.unwrap();
+        let count = |user_id| get_notifications(&txn, user_id, true);
+        assert_eq!(count(owner.id).await.unwrap().len(), 1);
+        assert!(count(members[0]).await.unwrap().is_empty());
+        assert!(count(members[1]).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, Utc};
+use entity::prelude::*;
+use entity::sea_orm_active_enums::CompetitionStatus;
+use entity::*;
+use itertools::Itertools;
+use log::error;
+use rocket::FromFormField;
+use rocket_okapi::JsonSchema;
+use sea_orm::sea_query::{Expr, Order, SimpleExpr};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    sea_query, ColumnTrait, ConnectionTrait, EntityTrait, NotSet, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::{CompetitionLevel, Division};
+use crate::error::GenericError;
+
+/// Days after which a competition counts half as much towards the index
+const HALF_LIFE_DAYS: f64 = 365.0;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct PerformanceIndex {
+    pub competition_id: i32,
+    pub competition_name: String,
+    pub start_date: NaiveDate,
+    /// Strokes per round better than the average of the division in this competition
+    pub strokes_gained: f64,
+    /// The rolling index after this competition
+    pub value: f64,
+    pub competitions_counted: i32,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct PlayerSearchResult {
+    pub pdga_number: i32,
+    pub first_name: String,
+    pub last_name: String,
+    pub rating: Option<i32>,
+    pub performance_index: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, FromFormField, Default)]
+pub enum PlayerSort {
+    #[default]
+    Name,
+    Rating,
+    PerformanceIndex,
+}
+
+/// A round played by a player, as stored in `player_round_score`
+pub(crate) struct PlayedRound {
+    pub pdga_number: i32,
+    pub round: i32,
+    pub division: Division,
+    pub throws: i32,
+}
+
+/// A competition a player has an index row for, used to roll the index forward
+pub(crate) struct PastPerformance {
+    pub strokes_gained: f64,
+    pub level: CompetitionLevel,
+    pub days_ago: i64,
+}
+
+/// Average strokes per round each player was better than the rest of their division in the same round.
+pub(crate) fn strokes_gained(rounds: &[PlayedRound]) -> HashMap<i32, f64> {
+    let field_average: HashMap<_, f64> = rounds
+        .iter()
+        .filter(|r| r.throws > 0)
+        .into_group_map_by(|r| (r.round, r.division as u8))
+        .into_iter()
+        .map(|(key, rounds)| {
+            let average = rounds.iter().map(|r| r.throws as f64).sum::<f64>() / rounds.len() as f64;
+            (key, average)
+        })
+        .collect();
+    rounds
+        .iter()
+        .filter(|r| r.throws > 0)
+        .into_group_map_by(|r| r.pdga_number)
+        .into_iter()
+        .map(|(pdga_number, rounds)| {
+            let gained = rounds
+                .iter()
+                .map(|r| field_average[&(r.round, r.division as u8)] - r.throws as f64)
+                .sum::<f64>()
+                / rounds.len() as f64;
+            (pdga_number, gained)
+        })
+        .collect()
+}
+
+/// Weighted average of the strokes gained in each competition.
+///
+/// Competitions weigh by the multiplier of their level, and lose half their weight every year.
+pub(crate) fn rolling_index(performances: &[PastPerformance]) -> f64 {
+    let (sum, weights) = performances.iter().fold((0.0, 0.0), |(sum, weights), p| {
+        let weight = p.level.multiplier() * 0.5_f64.powf(p.days_ago.max(0) as f64 / HALF_LIFE_DAYS);
+        (sum + p.strokes_gained * weight, weights + weight)
+    });
+    if weights > 0.0 {
+        sum / weights
+    } else {
+        0.0
+    }
+}
+
+/// Computes the index of every player after each finished competition that hasn't been indexed yet,
+/// oldest competition first so each index builds on the previous ones.
+pub async fn index_finished_competitions(db: &impl ConnectionTrait) -> Result<(), GenericError> {
+    let indexed: HashSet<i32> = PlayerPerformanceIndex::find()
+        .select_only()
+        .column(player_performance_index::Column::CompetitionId)
+        .distinct()
+        .into_tuple::<i32>()
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get performance indexes from database"))?
+        .into_iter()
+        .collect();
+    let scored: HashSet<i32> = PlayerRoundScore::find()
+        .select_only()
+        .column(player_round_score::Column::CompetitionId)
+        .distinct()
+        .into_tuple::<i32>()
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?
+        .into_iter()
+        .collect();
+    let competitions = Competition::find()
+        .filter(competition::Column::Status.eq(CompetitionStatus::Finished))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competitions from database"))?
+        .into_iter()
+        .filter(|c| scored.contains(&c.id) && !indexed.contains(&c.id))
+        .sorted_by_key(|c| (c.start_date, c.id))
+        .collect_vec();
+    for competition in competitions {
+        index_competition(db, &competition).await?;
+    }
+    Ok(())
+}
+
+async fn index_competition(
+    db: &impl ConnectionTrait,
+    competition: &competition::Model,
+) -> Result<(), GenericError> {
+    let rounds = PlayerRoundScore::find()
+        .filter(player_round_score::Column::CompetitionId.eq(competition.id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?
+        .into_iter()
+        .map(|r| PlayedRound {
+            pdga_number: r.pdga_number,
+            round: r.round,
+            division: r.division.into(),
+            throws: r.throws,
+        })
+        .collect_vec();
+    let gained = strokes_gained(&rounds);
+    if gained.is_empty() {
+        return Ok(());
+    }
+
+    let history = PlayerPerformanceIndex::find()
+        .filter(player_performance_index::Column::PdgaNumber.is_in(gained.keys().copied().collect_vec()))
+        .find_also_related(Competition)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get performance indexes from database"))?
+        .into_iter()
+        .filter_map(|(index, c)| c.map(|c| (index, c)))
+        .filter(|(_, c)| c.start_date <= competition.start_date && c.id != competition.id)
+        .into_group_map_by(|(index, _)| index.pdga_number);
+
+    let level: CompetitionLevel = competition.level.clone().into();
+    let models = gained
+        .into_iter()
+        .map(|(pdga_number, strokes_gained)| {
+            let mut performances = history
+                .get(&pdga_number)
+                .into_iter()
+                .flatten()
+                .map(|(index, c)| PastPerformance {
+                    strokes_gained: index.strokes_gained,
+                    level: c.level.clone().into(),
+                    days_ago: (competition.start_date - c.start_date).num_days(),
+                })
+                .collect_vec();
+            performances.push(PastPerformance {
+                strokes_gained,
+                level: level.clone(),
+                days_ago: 0,
+            });
+            player_performance_index::ActiveModel {
+                id: NotSet,
+                pdga_number: Set(pdga_number),
+                competition_id: Set(competition.id),
+                strokes_gained: Set(strokes_gained),
+                value: Set(rolling_index(&performances)),
+                competitions_counted: Set(performances.len() as i32),
+                computed_at: Set(Utc::now().fixed_offset()),
+            }
+        })
+        .collect_vec();
+    for chunk in models.chunks(crate::mutation::INSERT_CHUNK_SIZE) {
+        PlayerPerformanceIndex::insert_many(chunk.to_vec())
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    player_performance_index::Column::PdgaNumber,
+                    player_performance_index::Column::CompetitionId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await
+            .map_err(|e| {
+                error!("Unable to save performance indexes: {:#?}", e);
+                GenericError::UnknownError("Unable to save performance indexes")
+            })?;
+    }
+    Ok(())
+}
+
+/// The index of a player after each competition, newest first.
+pub async fn performance_history(
+    db: &impl ConnectionTrait,
+    pdga_number: i32,
+) -> Result<Vec<PerformanceIndex>, GenericError> {
+    Ok(PlayerPerformanceIndex::find()
+        .filter(player_performance_index::Column::PdgaNumber.eq(pdga_number))
+        .find_also_related(Competition)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get performance indexes from database"))?
+        .into_iter()
+        .filter_map(|(index, c)| {
+            c.map(|c| PerformanceIndex {
+                competition_id: c.id,
+                competition_name: c.name,
+                start_date: c.start_date,
+                strokes_gained: index.strokes_gained,
+                value: index.value,
+                competitions_counted: index.competitions_counted,
+            })
+        })
+        .sorted_by_key(|i| std::cmp::Reverse((i.start_date, i.competition_id)))
+        .collect())
+}
+
+/// Escapes the wildcards of a LIKE pattern, so that searched text only matches itself.
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Players whose name contains `name`, best first when sorting by rating or index.
+pub async fn search_players(
+    db: &impl ConnectionTrait,
+    name: Option<&str>,
+    sort: PlayerSort,
+    limit: u64,
+) -> Result<Vec<PlayerSearchResult>, GenericError> {
+    let mut query = Player::find();
+    if let Some(name) = name.map(str::trim).filter(|n| !n.is_empty()) {
+        for part in name.split_whitespace() {
+            let pattern = format!("%{}%", escape_like(&part.to_lowercase()));
+            let matches = |column: player::Column| {
+                sea_query::Expr::expr(sea_query::Func::lower(sea_query::Expr::col(column)))
+                    .like(sea_query::LikeExpr::new(pattern.clone()).escape('\\'))
+            };
+            query = query.filter(
+                sea_query::Condition::any()
+                    .add(matches(player::Column::FirstName))
+                    .add(matches(player::Column::LastName)),
+            );
+        }
+    }
+    // The index after the player's latest indexed competition
+    let latest_index = sea_query::Query::select()
+        .column((PlayerPerformanceIndex, player_performance_index::Column::Value))
+        .from(PlayerPerformanceIndex)
+        .inner_join(
+            Competition,
+            Expr::col((Competition, competition::Column::Id)).equals((
+                PlayerPerformanceIndex,
+                player_performance_index::Column::CompetitionId,
+            )),
+        )
+        .and_where(
+            Expr::col((
+                PlayerPerformanceIndex,
+                player_performance_index::Column::PdgaNumber,
+            ))
+            .equals((Player, player::Column::PdgaNumber)),
+        )
+        .order_by((Competition, competition::Column::StartDate), Order::Desc)
+        .order_by((Competition, competition::Column::Id), Order::Desc)
+        .limit(1)
+        .to_owned();
+    let performance_index = SimpleExpr::SubQuery(None, Box::new(latest_index.into_sub_query_statement()));
+    let mut query = query
+        .select_only()
+        .columns([
+            player::Column::PdgaNumber,
+            player::Column::FirstName,
+            player::Column::LastName,
+            player::Column::Rating,
+        ])
+        .expr(performance_index.clone());
+    let best_first = match sort {
+        PlayerSort::Name => None,
+        PlayerSort::Rating => Some(Expr::col(player::Column::Rating).into()),
+        PlayerSort::PerformanceIndex => Some(performance_index),
+    };
+    if let Some(best_first) = best_first {
+        // Players without a rating or index last
+        query = query
+            .order_by(Expr::expr(best_first.clone()).is_null(), Order::Asc)
+            .order_by(best_first, Order::Desc);
+    }
+    Ok(query
+        .order_by_asc(player::Column::LastName)
+        .order_by_asc(player::Column::FirstName)
+        .order_by_asc(player::Column::PdgaNumber)
+        .limit(limit)
+        .into_tuple::<(i32, String, String, Option<i32>, Option<f64>)>()
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get players from database"))?
+        .into_iter()
+        .map(
+            |(pdga_number, first_name, last_name, rating, performance_index)| PlayerSearchResult {
+                pdga_number,
+                first_name,
+                last_name,
+                rating,
+                performance_index,
+            },
+        )
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+
+    fn played(pdga_number: i32, round: i32, division: Division, throws: i32) -> PlayedRound {
+        PlayedRound {
+            pdga_number,
+            round,
+            division,
+            throws,
+        }
+    }
+
+    #[test]
+    fn strokes_gained_compares_within_division_and_round() {
+        let rounds = [
+            played(1, 1, Division::MPO, 50),
+            played(2, 1, Division::MPO, 54),
+            played(1, 2, Division::MPO, 56),
+            played(2, 2, Division::MPO, 56),
+            played(3, 1, Division::FPO, 60),
+            played(4, 1, Division::FPO, 0),
+        ];
+        let gained = strokes_gained(&rounds);
+        assert_eq!(gained[&1], 1.0);
+        assert_eq!(gained[&2], -1.0);
+        assert_eq!(gained[&3], 0.0);
+        assert!(!gained.contains_key(&4));
+    }
+
+    #[test]
+    fn rolling_index_weighs_level_and_recency() {
+        let performance = |strokes_gained, level, days_ago| PastPerformance {
+            strokes_gained,
+            level,
+            days_ago,
+        };
+        let index = rolling_index(&[
+            performance(4.0, CompetitionLevel::Major, 0),
+            performance(1.0, CompetitionLevel::Elite, 0),
+        ]);
+        assert_eq!(index, 3.0);
+
+        let index = rolling_index(&[
+            performance(3.0, CompetitionLevel::Elite, 0),
+            performance(0.0, CompetitionLevel::Elite, 365),
+        ]);
+        assert_eq!(index, 2.0);
+        assert_eq!(rolling_index(&[]), 0.0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn search_sorts_by_latest_index_and_limits() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        for player in [4242001, 4242002, 4242003] {
+            test_db::player(&txn, tournament, player).await;
+        }
+        for (id, start_date) in [(990_000_001, (2024, 5, 1)), (990_000_002, (2024, 6, 1))] {
+            Competition::insert(competition::ActiveModel {
+                id: Set(id),
+                name: Set(format!("Test competition {id}")),
+                status: Set(CompetitionStatus::Finished),
+                rounds: Set(3),
+                level: Set(entity::sea_orm_active_enums::CompetitionLevel::Elite),
+                ended_at: Set(None),
+                start_date: Set(NaiveDate::from_ymd_opt(start_date.0, start_date.1, start_date.2).unwrap()),
+                timezone: Set(None),
+            })
+            .exec_without_returning(&txn)
+            .await
+            .unwrap();
+        }
+        // The first player was better in the older competition, only the newer one counts
+        for (player, competition_id, value) in [
+            (4242001, 990_000_002, 1.0),
+            (4242001, 990_000_001, 5.0),
+            (4242002, 990_000_001, 3.0),
+        ] {
+            PlayerPerformanceIndex::insert(player_performance_index::ActiveModel {
+                id: NotSet,
+                pdga_number: Set(99_000_000 + player),
+                competition_id: Set(competition_id),
+                strokes_gained: Set(value),
+                value: Set(value),
+                competitions_counted: Set(1),
+                computed_at: Set(Utc::now().fixed_offset()),
+            })
+            .exec_without_returning(&txn)
+            .await
+            .unwrap();
+        }
+
+        let search = |sort, limit| search_players(&txn, Some("player 424200"), sort, limit);
+        let found = search(PlayerSort::PerformanceIndex, 10).await.unwrap();
+        assert_eq!(
+            found
+                .iter()
+                .map(|p| (p.pdga_number - 99_000_000, p.performance_index))
+                .collect_vec(),
+            [(4242002, Some(3.0)), (4242001, Some(1.0)), (4242003, None)]
+        );
+        assert_eq!(search(PlayerSort::Rating, 10).await.unwrap().len(), 3);
+        let found = search(PlayerSort::Name, 2).await.unwrap();
+        assert_eq!(
+            found.iter().map(|p| p.pdga_number - 99_000_000).collect_vec(),
+            [4242001, 4242002]
+        );
+        // Wildcards in the search are matched literally
+        let found = search_players(&txn, Some("player 42420_1"), PlayerSort::Name, 10)
+            .await
+            .unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn like_wildcards_are_escaped() {
+        assert_eq!(escape_like("100%_a\\b"), "100\\%\\_a\\\\b");
+        assert_eq!(escape_like("plain"), "plain");
+    }
+}
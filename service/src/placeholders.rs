@@ -0,0 +1,376 @@
+use chrono::NaiveDate;
+use entity::prelude::*;
+use entity::*;
+use itertools::Itertools;
+use log::{info, warn};
+use rocket_okapi::JsonSchema;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, ModelTrait, QueryFilter, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::traits::InsertCompetition;
+use crate::dto::{CompetitionInfo, CompetitionLevel, PhantomCompetition};
+use crate::error::GenericError;
+
+/// How many days the date of a placeholder may be off from the start of the real event
+const MAX_DATE_DIFFERENCE: i64 = 3;
+
+/// Words that tell what kind of event it is rather than which one
+const GENERIC_WORDS: [&str; 12] = [
+    "open",
+    "classic",
+    "championship",
+    "championships",
+    "cup",
+    "tour",
+    "series",
+    "invitational",
+    "disc",
+    "golf",
+    "the",
+    "of",
+];
+
+/// A competition on the schedule of a tournament before PDGA has published it
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct Placeholder {
+    pub id: i32,
+    pub name: String,
+    pub date: NaiveDate,
+    pub level: CompetitionLevel,
+    /// The PDGA event the placeholder was replaced with
+    pub linked_competition_id: Option<i32>,
+}
+
+impl From<phantom_competition::Model> for Placeholder {
+    fn from(model: phantom_competition::Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            date: model.date,
+            level: model.level.into(),
+            linked_competition_id: model.linked_competition_id,
+        }
+    }
+}
+
+pub async fn add_placeholder(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    name: &str,
+    date: NaiveDate,
+    level: CompetitionLevel,
+) -> Result<i32, GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let name = name.trim();
+    if name.is_empty() {
+        Err(GenericError::BadRequest("Placeholder name cannot be empty"))?
+    }
+    let mut phantom = PhantomCompetition::new(name.to_string(), date);
+    let id = phantom.save(db, level.into()).await?;
+    phantom.insert_in_fantasy(db, tournament_id as u32).await?;
+    Ok(id)
+}
+
+/// Placeholders of a tournament, linked ones included, in schedule order.
+pub async fn get_placeholders(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<Vec<Placeholder>, GenericError> {
+    Ok(placeholder_models(db, tournament_id)
+        .await?
+        .into_iter()
+        .sorted_by_key(|p| (p.date, p.id))
+        .map(Placeholder::from)
+        .collect())
+}
+
+async fn placeholder_models(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<Vec<phantom_competition::Model>, GenericError> {
+    Ok(PhantomCompetitionInFantasyTournament::find()
+        .filter(phantom_competition_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .find_also_related(entity::prelude::PhantomCompetition)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get placeholders from database"))?
+        .into_iter()
+        .filter_map(|(_, phantom)| phantom)
+        .collect())
+}
+
+/// Start dates of the placeholders in a tournament that are still waiting for their PDGA event
+pub(crate) async fn unlinked_dates(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<Vec<NaiveDate>, GenericError> {
+    Ok(placeholder_models(db, tournament_id)
+        .await?
+        .into_iter()
+        .filter(|p| p.linked_competition_id.is_none())
+        .map(|p| p.date)
+        .collect())
+}
+
+pub async fn remove_placeholder(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    placeholder_id: i32,
+) -> Result<(), GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let removed = PhantomCompetitionInFantasyTournament::delete_many()
+        .filter(
+            phantom_competition_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(
+                    phantom_competition_in_fantasy_tournament::Column::PhantomCompetitionId
+                        .eq(placeholder_id),
+                ),
+        )
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to remove placeholder"))?
+        .rows_affected;
+    if removed == 0 {
+        Err(GenericError::NotFound("Placeholder not found in tournament"))?
+    }
+    entity::prelude::PhantomCompetition::delete_by_id(placeholder_id)
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to remove placeholder"))?;
+    Ok(())
+}
+
+/// Fetches the PDGA event of a link if it isn't stored yet. Meant to run before the transaction
+/// of [`link_placeholder`] is opened, so that it isn't held open while waiting for PDGA.
+pub async fn fetch_unstored_competition(
+    db: &impl ConnectionTrait,
+    competition_id: i32,
+) -> Result<Option<CompetitionInfo>, GenericError> {
+    let stored = Competition::find_by_id(competition_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competition from database"))?
+        .is_some();
+    if stored {
+        Ok(None)
+    } else {
+        CompetitionInfo::from_web(competition_id as u32).await.map(Some)
+    }
+}
+
+/// Replaces a placeholder with its PDGA event in every tournament it is scheduled in.
+///
+/// `fetched` is the event from [`fetch_unstored_competition`], it is stored along with the first
+/// tournament that isn't archived.
+pub async fn link_placeholder(
+    db: &impl ConnectionTrait,
+    placeholder_id: i32,
+    competition_id: i32,
+    mut fetched: Option<CompetitionInfo>,
+) -> Result<(), GenericError> {
+    let phantom = entity::prelude::PhantomCompetition::find_by_id(placeholder_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get placeholder from database"))?
+        .ok_or(GenericError::NotFound("Placeholder not found"))?;
+    if phantom.linked_competition_id.is_some() {
+        Err(GenericError::Conflict("Placeholder is already linked"))?
+    }
+    let tournaments = phantom
+        .find_related(PhantomCompetitionInFantasyTournament)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get placeholder tournaments from database"))?;
+    let level: CompetitionLevel = phantom.level.clone().into();
+    for tournament in tournaments {
+        let tournament_id = tournament.fantasy_tournament_id;
+        let added = match crate::tournament_management::assure_not_archived(db, tournament_id).await {
+            Err(e) => Err(e),
+            Ok(()) => match fetched.take() {
+                Some(competition) => {
+                    crate::mutation::insert_fetched_competition(
+                        db,
+                        tournament_id as u32,
+                        &competition,
+                        level.clone(),
+                    )
+                    .await
+                }
+                None => {
+                    crate::mutation::insert_competition_in_fantasy(
+                        db,
+                        tournament_id as u32,
+                        competition_id as u32,
+                        level.clone(),
+                    )
+                    .await
+                }
+            },
+        };
+        match added {
+            Ok(()) | Err(GenericError::Conflict(_)) => (),
+            Err(e) => Err(e)?,
+        }
+    }
+    let mut phantom = phantom.into_active_model();
+    phantom.linked_competition_id = Set(Some(competition_id));
+    entity::prelude::PhantomCompetition::update(phantom)
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to link placeholder"))?;
+    Ok(())
+}
+
+/// Links placeholders to a stored competition with a matching name that starts within a few days of them.
+/// Placeholders with several candidates are left for an admin.
+///
+/// Only competitions that are already stored are matched. PDGA events nobody has added to a tournament
+/// yet aren't looked up, so their placeholders wait for an admin to link them. Each placeholder is linked in a transaction
+/// of its own, so one that fails doesn't keep the others from being linked.
+pub async fn link_matching_placeholders(
+    db: &(impl ConnectionTrait + TransactionTrait),
+) -> Result<(), GenericError> {
+    let placeholders = entity::prelude::PhantomCompetition::find()
+        .filter(phantom_competition::Column::LinkedCompetitionId.is_null())
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get placeholders from database"))?;
+    if placeholders.is_empty() {
+        return Ok(());
+    }
+    let earliest = placeholders.iter().map(|p| p.date).min().unwrap_or_default();
+    let competitions = Competition::find()
+        .filter(competition::Column::StartDate.gte(earliest - chrono::Duration::days(MAX_DATE_DIFFERENCE)))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competitions from database"))?;
+    for placeholder in placeholders {
+        let candidates = competitions
+            .iter()
+            .filter(|c| {
+                (c.start_date - placeholder.date).num_days().abs() <= MAX_DATE_DIFFERENCE
+                    && names_match(&placeholder.name, &c.name)
+            })
+            .collect_vec();
+        if let [competition] = candidates[..] {
+            let txn = db
+                .begin()
+                .await
+                .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+            let linked = match link_placeholder(&txn, placeholder.id, competition.id, None).await {
+                Ok(()) => txn
+                    .commit()
+                    .await
+                    .map_err(|_| GenericError::UnknownError("transaction failed")),
+                Err(e) => Err(e),
+            };
+            match linked {
+                Ok(()) => info!(
+                    "Linked placeholder {} to competition {}",
+                    placeholder.id, competition.id
+                ),
+                Err(e) => warn!("Unable to link placeholder {}: {:?}", placeholder.id, e),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The words of a name in lower case, leaving out numbers like the year
+fn words(name: &str) -> Vec<String> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !w.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Whether every word of the placeholder is part of the competition name, and at least one of them
+/// names the event rather than its kind, so a placeholder like "Open" doesn't match every open.
+///
+/// PDGA names often carry a sponsor or year that the placeholder left out.
+pub(crate) fn names_match(placeholder: &str, competition: &str) -> bool {
+    let (placeholder, competition) = (words(placeholder), words(competition));
+    placeholder.iter().any(|w| !GENERIC_WORDS.contains(&w.as_str()))
+        && placeholder.iter().all(|w| competition.contains(w))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+    use entity::sea_orm_active_enums::CompetitionStatus;
+
+    #[test]
+    fn names_match_ignoring_sponsors_and_case() {
+        assert!(names_match(
+            "European Open",
+            "2024 European Open presented by Discmania"
+        ));
+        assert!(names_match("USDGC", "usdgc"));
+        assert!(!names_match("European Open", "European Masters"));
+        assert!(!names_match("", "European Open"));
+        assert!(names_match("2024 European Open", "European Open"));
+        assert!(!names_match("Open", "European Open"));
+        assert!(!names_match("The Open", "2024 The Open"));
+        assert!(!names_match("European Open Masters", "European Open"));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn placeholders_link_to_the_competition_matching_them() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        let date = NaiveDate::from_ymd_opt(2030, 7, 1).unwrap();
+        let mut placeholders = Vec::new();
+        for name in ["European Open", "Open"] {
+            placeholders.push(
+                add_placeholder(&txn, tournament, name, date, CompetitionLevel::Major)
+                    .await
+                    .unwrap(),
+            );
+        }
+        Competition::insert(competition::ActiveModel {
+            id: Set(990_000_101),
+            name: Set("2030 European Open presented by Discmania".to_string()),
+            status: Set(CompetitionStatus::NotStarted),
+            rounds: Set(4),
+            level: Set(entity::sea_orm_active_enums::CompetitionLevel::Major),
+            ended_at: Set(None),
+            start_date: Set(date + chrono::Duration::days(1)),
+            timezone: Set(None),
+        })
+        .exec_without_returning(&txn)
+        .await
+        .unwrap();
+
+        link_matching_placeholders(&txn).await.unwrap();
+        let linked = |id| entity::prelude::PhantomCompetition::find_by_id(id).one(&txn);
+        assert_eq!(
+            linked(placeholders[0])
+                .await
+                .unwrap()
+                .unwrap()
+                .linked_competition_id,
+            Some(990_000_101)
+        );
+        assert_eq!(
+            linked(placeholders[1])
+                .await
+                .unwrap()
+                .unwrap()
+                .linked_competition_id,
+            None
+        );
+        let scheduled = CompetitionInFantasyTournament::find()
+            .filter(competition_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament))
+            .all(&txn)
+            .await
+            .unwrap();
+        assert_eq!(scheduled.len(), 1);
+    }
+}
@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+
+use entity::prelude::*;
+use entity::sea_orm_active_enums::CompetitionStatus;
+use entity::*;
+use itertools::Itertools;
+use rocket_okapi::JsonSchema;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::captains::CaptainMultiplier;
+use crate::dto::{fantasy_points, CompetitionLevel, User};
+use crate::error::GenericError;
+use crate::query;
+use crate::refresh::counted_user_scores;
+
+/// Round-to-round moves a player needs before their own history outweighs the field's.
+const PRIOR_WEIGHT: f64 = 5.0;
+/// Spread of placement moves per round, used before any history is stored.
+const DEFAULT_SPREAD: f64 = 4.0;
+/// Standard normal deciles, used to average points over the likely final placements.
+const DECILES: [f64; 9] = [
+    -1.2816, -0.8416, -0.5244, -0.2533, 0.0, 0.2533, 0.5244, 0.8416, 1.2816,
+];
+/// The range covers the 10th to 90th percentile of final placements.
+const RANGE_Z: f64 = 1.2816;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct PlayerProjection {
+    pub pdga_number: i32,
+    pub competition_id: i32,
+    pub current_placement: i32,
+    pub projected_placement: f64,
+    pub rounds_remaining: i32,
+    pub current_points: i32,
+    pub expected_points: f64,
+    pub low_points: i32,
+    pub high_points: i32,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct UserProjection {
+    pub user: User,
+    pub current_score: i32,
+    pub expected_score: f64,
+    /// Score if every counted player ends up at the bad end of their range
+    pub low_score: i32,
+    /// Score if every counted player ends up at the good end of their range
+    pub high_score: i32,
+    pub current_rank: usize,
+    pub expected_rank: usize,
+    /// Counted players, captains with the multiplier applied to their points
+    pub players: Vec<PlayerProjection>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct TournamentProjection {
+    pub fantasy_tournament_id: i32,
+    /// Running competitions the projection is based on
+    pub competitions: Vec<i32>,
+    /// Users ordered by expected score
+    pub standings: Vec<UserProjection>,
+}
+
+/// How much a player's placement tends to move from one round to the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Movement {
+    /// Average change in placement per round, negative is moving up
+    drift: f64,
+    /// Standard deviation of the change in placement per round
+    spread: f64,
+}
+
+impl Movement {
+    /// Blends the player's own history with the field's, trusting it more the more rounds it covers.
+    fn from_history(deltas: &[i32], prior_spread: f64) -> Self {
+        let n = deltas.len() as f64;
+        if deltas.is_empty() {
+            return Self {
+                drift: 0.0,
+                spread: prior_spread,
+            };
+        }
+        let mean = deltas.iter().sum::<i32>() as f64 / n;
+        let variance = deltas.iter().map(|d| (*d as f64 - mean).powi(2)).sum::<f64>() / n;
+        Self {
+            drift: mean * n / (n + PRIOR_WEIGHT),
+            spread: ((variance * n + prior_spread.powi(2) * PRIOR_WEIGHT) / (n + PRIOR_WEIGHT)).sqrt(),
+        }
+    }
+
+    fn field_spread(deltas: &[i32]) -> f64 {
+        if deltas.len() < 2 {
+            return DEFAULT_SPREAD;
+        }
+        let n = deltas.len() as f64;
+        let mean = deltas.iter().sum::<i32>() as f64 / n;
+        (deltas.iter().map(|d| (*d as f64 - mean).powi(2)).sum::<f64>() / n)
+            .sqrt()
+            .max(1.0)
+    }
+}
+
+/// Changes in placement between consecutive rounds of the same competition.
+fn placement_deltas(round_scores: &[player_round_score::Model]) -> Vec<i32> {
+    round_scores
+        .iter()
+        .into_group_map_by(|s| s.competition_id)
+        .into_values()
+        .flat_map(|rounds| {
+            rounds
+                .into_iter()
+                .sorted_by_key(|s| s.round)
+                .tuple_windows()
+                .filter(|(a, b)| b.round == a.round + 1)
+                .map(|(a, b)| b.placement - a.placement)
+                .collect_vec()
+        })
+        .collect()
+}
+
+struct Projected {
+    placement: f64,
+    expected_points: f64,
+    low_points: i32,
+    high_points: i32,
+}
+
+fn project(
+    current_placement: i32,
+    rounds_remaining: i32,
+    movement: Movement,
+    field_size: i32,
+    level: &CompetitionLevel,
+) -> Projected {
+    let remaining = rounds_remaining.max(0) as f64;
+    let center = current_placement as f64 + movement.drift * remaining;
+    let spread = movement.spread * remaining.sqrt();
+    let points_at = |z: f64| {
+        let placement = (center + z * spread).round().clamp(1.0, field_size.max(1) as f64);
+        fantasy_points(placement as u16, 0, level.clone()) as i32
+    };
+    Projected {
+        placement: center.clamp(1.0, field_size.max(1) as f64),
+        expected_points: DECILES.iter().map(|z| points_at(*z) as f64).sum::<f64>() / DECILES.len() as f64,
+        // Higher placements give fewer points
+        low_points: points_at(RANGE_Z),
+        high_points: points_at(-RANGE_Z),
+    }
+}
+
+/// Projects the final points of every player in a running competition.
+async fn project_competition(
+    db: &impl ConnectionTrait,
+    competition: &competition::Model,
+) -> Result<Vec<PlayerProjection>, GenericError> {
+    let level: CompetitionLevel = competition.level.clone().into();
+    let round_scores = PlayerRoundScore::find()
+        .filter(player_round_score::Column::CompetitionId.eq(competition.id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?;
+    let latest = round_scores
+        .iter()
+        .into_group_map_by(|s| s.pdga_number)
+        .into_values()
+        .filter_map(|scores| scores.into_iter().max_by_key(|s| s.round))
+        .collect_vec();
+    let Some(current_round) = latest.iter().map(|s| s.round).max() else {
+        return Ok(Vec::new());
+    };
+
+    let history = PlayerRoundScore::find()
+        .filter(player_round_score::Column::PdgaNumber.is_in(latest.iter().map(|s| s.pdga_number)))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round history from database"))?
+        .into_iter()
+        .into_group_map_by(|s| s.pdga_number);
+    let prior_spread = Movement::field_spread(
+        &history
+            .values()
+            .flat_map(|rounds| placement_deltas(rounds))
+            .collect_vec(),
+    );
+
+    Ok(latest
+        .iter()
+        .map(|score| {
+            let field_size = latest.iter().filter(|s| s.division == score.division).count() as i32;
+            let tied = latest
+                .iter()
+                .filter(|s| {
+                    s.division == score.division
+                        && s.round == score.round
+                        && s.placement == score.placement
+                        && s.pdga_number != score.pdga_number
+                })
+                .count();
+            // Players more than a round behind the field have been cut
+            let rounds_remaining = if score.round + 1 < current_round {
+                0
+            } else {
+                competition.rounds - score.round
+            };
+            let movement = Movement::from_history(
+                &history
+                    .get(&score.pdga_number)
+                    .map(|rounds| placement_deltas(rounds))
+                    .unwrap_or_default(),
+                prior_spread,
+            );
+            let projected = project(score.placement, rounds_remaining, movement, field_size, &level);
+            PlayerProjection {
+                pdga_number: score.pdga_number,
+                competition_id: competition.id,
+                current_placement: score.placement,
+                projected_placement: projected.placement,
+                rounds_remaining: rounds_remaining.max(0),
+                current_points: fantasy_points(score.placement as u16, tied, level.clone()) as i32,
+                expected_points: projected.expected_points,
+                low_points: projected.low_points,
+                high_points: projected.high_points,
+            }
+        })
+        .collect())
+}
+
+/// The projection of a captain, whose points are multiplied the same way as the saved scores.
+fn captained(projection: PlayerProjection, captains: &CaptainMultiplier) -> PlayerProjection {
+    PlayerProjection {
+        current_points: captains.apply(projection.current_points),
+        expected_points: captains.apply_expected(projection.expected_points),
+        low_points: captains.apply(projection.low_points),
+        high_points: captains.apply(projection.high_points),
+        ..projection
+    }
+}
+
+/// Projects the final scores and standings of a tournament from its running competitions.
+pub async fn project_tournament(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<TournamentProjection, GenericError> {
+    let running = query::get_competitions_in_fantasy_tournament(db, tournament_id)
+        .await?
+        .into_iter()
+        .filter(|c| c.status == CompetitionStatus::Running)
+        .collect_vec();
+
+    let mut counted: HashMap<i32, Vec<PlayerProjection>> = HashMap::new();
+    for competition in &running {
+        let projections = project_competition(db, competition).await?;
+        let points = projections
+            .iter()
+            .map(|p| (p.pdga_number, p.current_points))
+            .collect();
+        let by_player: HashMap<i32, &PlayerProjection> =
+            projections.iter().map(|p| (p.pdga_number, p)).collect();
+        // Bench rules only depend on who played, so the current points pick the counted players
+        let scores = counted_user_scores(db, tournament_id, competition.id, &points).await?;
+        let captains = CaptainMultiplier::load(db, tournament_id, competition.id, &points).await?;
+        let multiplied =
+            captains.multiplied(&scores.iter().map(|s| (s.user, s.pdga_num as i32)).collect_vec());
+        for score in scores {
+            if let Some(projection) = by_player.get(&(score.pdga_num as i32)) {
+                let mut projection = (*projection).clone();
+                if multiplied.contains(&(score.user, projection.pdga_number)) {
+                    projection = captained(projection, &captains);
+                }
+                counted.entry(score.user).or_default().push(projection);
+            }
+        }
+    }
+
+    let running_ids = running.iter().map(|c| c.id).collect_vec();
+    let settled: HashMap<i32, i32> = UserCompetitionScoreInFantasyTournament::find()
+        .filter(
+            user_competition_score_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(
+                    user_competition_score_in_fantasy_tournament::Column::CompetitionId
+                        .is_not_in(running_ids.clone()),
+                ),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get user scores from database"))?
+        .into_iter()
+        .into_group_map_by(|s| s.user)
+        .into_iter()
+        .map(|(user, scores)| (user, scores.iter().map(|s| s.score).sum()))
+        .collect();
+
+    let mut standings = query::get_user_participants_in_tournament(db, tournament_id)
+        .await?
+        .into_iter()
+        .sorted_by_key(|u| (std::cmp::Reverse(u.score), u.user.id))
+        .enumerate()
+        .map(|(rank, u)| {
+            let settled = settled.get(&u.user.id).copied().unwrap_or_default();
+            let players = counted
+                .remove(&u.user.id)
+                .unwrap_or_default()
+                .into_iter()
+                .sorted_by(|a, b| b.expected_points.total_cmp(&a.expected_points))
+                .collect_vec();
+            UserProjection {
+                current_score: u.score,
+                expected_score: settled as f64 + players.iter().map(|p| p.expected_points).sum::<f64>(),
+                low_score: settled + players.iter().map(|p| p.low_points).sum::<i32>(),
+                high_score: settled + players.iter().map(|p| p.high_points).sum::<i32>(),
+                current_rank: rank + 1,
+                expected_rank: 0,
+                user: u.user,
+                players,
+            }
+        })
+        .collect_vec();
+    standings.sort_by(|a, b| {
+        b.expected_score
+            .total_cmp(&a.expected_score)
+            .then(a.user.id.cmp(&b.user.id))
+    });
+    for (rank, user) in standings.iter_mut().enumerate() {
+        user.expected_rank = rank + 1;
+    }
+
+    Ok(TournamentProjection {
+        fantasy_tournament_id: tournament_id,
+        competitions: running_ids,
+        standings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+    use chrono::Utc;
+    use entity::sea_orm_active_enums::Division;
+    use sea_orm::sea_query::Expr;
+    use sea_orm::ActiveValue::{NotSet, Set};
+
+    fn round_score(competition_id: i32, round: i32, placement: i32) -> player_round_score::Model {
+        player_round_score::Model {
+            id: 0,
+            pdga_number: 1,
+            competition_id,
+            round,
+            throws: 50,
+            division: Division::Mpo,
+            placement,
+        }
+    }
+
+    #[test]
+    fn deltas_stay_within_competitions() {
+        let deltas = placement_deltas(&[
+            round_score(1, 1, 10),
+            round_score(1, 2, 4),
+            round_score(2, 1, 3),
+            round_score(2, 3, 8),
+        ]);
+        assert_eq!(deltas, vec![-6]);
+    }
+
+    #[test]
+    fn short_history_leans_on_the_field() {
+        let movement = Movement::from_history(&[-10], 4.0);
+        assert!(movement.drift > -10.0 && movement.drift < 0.0);
+        let volatile = Movement::from_history(&[-10, 10], 4.0);
+        assert_eq!(volatile.drift, 0.0);
+        assert!(volatile.spread > 4.0 && volatile.spread < 10.0);
+        assert_eq!(
+            Movement::from_history(&[], 4.0),
+            Movement {
+                drift: 0.0,
+                spread: 4.0
+            }
+        );
+    }
+
+    #[test]
+    fn finished_players_keep_their_points() {
+        let movement = Movement {
+            drift: 0.0,
+            spread: 5.0,
+        };
+        let done = project(3, 0, movement, 50, &CompetitionLevel::Elite);
+        assert_eq!(done.low_points, done.high_points);
+        assert_eq!(done.expected_points, done.low_points as f64);
+
+        let open = project(3, 2, movement, 50, &CompetitionLevel::Elite);
+        assert!(open.low_points < open.high_points);
+        assert!(
+            open.low_points as f64 <= open.expected_points && open.expected_points <= open.high_points as f64
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn captains_are_projected_like_saved_scores() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        FantasyTournament::update_many()
+            .col_expr(
+                fantasy_tournament::Column::CaptainMultiplierPercent,
+                Expr::value(200),
+            )
+            .filter(fantasy_tournament::Column::Id.eq(tournament))
+            .exec(&txn)
+            .await
+            .unwrap();
+        let competition_id = 990_000_201;
+        Competition::insert(competition::ActiveModel {
+            id: Set(competition_id),
+            name: Set("Test competition".to_string()),
+            status: Set(CompetitionStatus::Running),
+            rounds: Set(3),
+            level: Set(entity::sea_orm_active_enums::CompetitionLevel::Elite),
+            ended_at: Set(None),
+            start_date: Set(Utc::now().date_naive()),
+            timezone: Set(None),
+        })
+        .exec_without_returning(&txn)
+        .await
+        .unwrap();
+        CompetitionInFantasyTournament::insert(competition_in_fantasy_tournament::ActiveModel {
+            id: NotSet,
+            competition_id: Set(competition_id),
+            fantasy_tournament_id: Set(tournament),
+        })
+        .exec_without_returning(&txn)
+        .await
+        .unwrap();
+        for player in [1, 2] {
+            test_db::player(&txn, tournament, player).await;
+            test_db::pick(&txn, tournament, owner.id, player, player).await;
+            PlayerRoundScore::insert(player_round_score::ActiveModel {
+                id: NotSet,
+                pdga_number: Set(99_000_000 + player),
+                competition_id: Set(competition_id),
+                round: Set(1),
+                throws: Set(50 + player),
+                division: Set(Division::Mpo),
+                placement: Set(player),
+            })
+            .exec_without_returning(&txn)
+            .await
+            .unwrap();
+        }
+        FantasyCaptain::insert(fantasy_captain::ActiveModel {
+            id: NotSet,
+            fantasy_tournament_id: Set(tournament),
+            user_id: Set(owner.id),
+            competition_id: Set(competition_id),
+            division: Set(Division::Mpo),
+            captain: Set(99_000_001),
+            vice_captain: Set(None),
+            updated_at: Set(Utc::now().fixed_offset()),
+        })
+        .exec_without_returning(&txn)
+        .await
+        .unwrap();
+        crate::refresh::refresh_user_scores_in_fantasy(&txn, tournament)
+            .await
+            .unwrap();
+
+        let projection = project_tournament(&txn, tournament).await.unwrap();
+        let standing = &projection.standings[0];
+        let captain = standing
+            .players
+            .iter()
+            .find(|p| p.pdga_number == 99_000_001)
+            .unwrap();
+        assert_eq!(
+            captain.current_points,
+            2 * fantasy_points(1, 0, CompetitionLevel::Elite) as i32
+        );
+        assert_eq!(
+            standing.current_score,
+            standing.players.iter().map(|p| p.current_points).sum::<i32>()
+        );
+    }
+}
@@ -10,11 +10,9 @@ use log::{error, warn};
 
 use rocket_okapi::okapi::schemars::JsonSchema;
 use sea_orm::entity::prelude::*;
-use sea_orm::ActiveValue::Set;
-use sea_orm::IntoActiveModel;
 
 use crate::dto;
-use crate::dto::{CompetitionInfo, FantasyPicks};
+use crate::dto::FantasyPicks;
 use crate::error::GenericError;
 
 pub enum Auth {
@@ -35,7 +33,7 @@ pub async fn authenticate(db: &DatabaseConnection, username: String, auth: Auth)
                     .one(db)
                     .await?;
                 if let Some(user_auth) = user_auth {
-                    Ok(verify(&password, &user_auth.hashed_password).is_ok())
+                    Ok(password_matches(&password, &user_auth.hashed_password))
                 } else {
                     Ok(false)
                 }
@@ -54,6 +52,11 @@ pub async fn authenticate(db: &DatabaseConnection, username: String, auth: Auth)
     }
 }
 
+/// `verify` only errors on malformed hashes, a wrong password is `Ok(false)`.
+fn password_matches(password: &str, hashed_password: &str) -> bool {
+    verify(password, hashed_password).unwrap_or(false)
+}
+
 pub async fn get_player_image_path(
     db: &DatabaseConnection,
     pdga_number: i32,
@@ -95,6 +98,10 @@ pub struct SimpleFantasyTournament {
     name: String,
     pub(crate) owner_id: i32,
     invitation_status: InvitationStatus,
+    archived: bool,
+    visibility: crate::visibility::Visibility,
+    /// Where the read-only page of a public tournament is shared from
+    public_slug: Option<String>,
 }
 
 impl From<sea_orm_active_enums::FantasyTournamentInvitationStatus> for InvitationStatus {
@@ -103,6 +110,7 @@ impl From<sea_orm_active_enums::FantasyTournamentInvitationStatus> for Invitatio
             sea_orm_active_enums::FantasyTournamentInvitationStatus::Accepted => InvitationStatus::Accepted,
             sea_orm_active_enums::FantasyTournamentInvitationStatus::Pending => InvitationStatus::Pending,
             sea_orm_active_enums::FantasyTournamentInvitationStatus::Declined => InvitationStatus::Declined,
+            sea_orm_active_enums::FantasyTournamentInvitationStatus::Requested => InvitationStatus::Requested,
         }
     }
 }
@@ -125,6 +133,9 @@ pub async fn get_users_fantasy_tournaments(
                 name: t.name.to_string(),
                 invitation_status: InvitationStatus::Accepted,
                 owner_id: t.owner,
+                archived: t.archived_at.is_some(),
+                visibility: t.visibility.clone().into(),
+                public_slug: crate::visibility::public_slug(t),
             })
             .collect();
         Ok(a)
@@ -154,6 +165,9 @@ pub async fn get_users_fantasy_tournaments(
                     name: tournament.name.to_string(),
                     invitation_status: user_in_tournament.invitation_status.into(),
                     owner_id: tournament.owner,
+                    archived: tournament.archived_at.is_some(),
+                    visibility: tournament.visibility.clone().into(),
+                    public_slug: crate::visibility::public_slug(&tournament),
                 });
             }
         }
@@ -161,10 +175,49 @@ pub async fn get_users_fantasy_tournaments(
     }
 }
 
+/// Tournaments the user has been invited to but not yet answered.
+pub async fn get_pending_invitations(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+) -> Result<Vec<SimpleFantasyTournament>, GenericError> {
+    let invitations = UserInFantasyTournament::find()
+        .filter(
+            user_in_fantasy_tournament::Column::UserId.eq(user_id).and(
+                user_in_fantasy_tournament::Column::InvitationStatus
+                    .eq(sea_orm_active_enums::FantasyTournamentInvitationStatus::Pending),
+            ),
+        )
+        .find_also_related(fantasy_tournament::Entity)
+        .all(db)
+        .await
+        .map_err(|e| {
+            error!("Error while getting invitations: {:#?}", e);
+            GenericError::UnknownError("Unknown error while getting invitations")
+        })?;
+
+    Ok(invitations
+        .into_iter()
+        .filter_map(|(invitation, tournament)| {
+            tournament
+                .filter(|t| t.archived_at.is_none())
+                .map(|t| SimpleFantasyTournament {
+                    visibility: t.visibility.clone().into(),
+                    public_slug: crate::visibility::public_slug(&t),
+                    id: t.id,
+                    name: t.name,
+                    invitation_status: invitation.invitation_status.into(),
+                    owner_id: t.owner,
+                    archived: false,
+                })
+        })
+        .collect())
+}
+
+/// `acting_owner` is shown as the owner, for users that manage the tournament through their role.
 pub async fn get_fantasy_tournament(
     db: &impl ConnectionTrait,
     tournament_id: i32,
-    user_admin_id: Option<i32>,
+    acting_owner: Option<i32>,
 ) -> Result<Option<SimpleFantasyTournament>, GenericError> {
     let t = FantasyTournament::find_by_id(tournament_id)
         .one(db)
@@ -176,7 +229,10 @@ pub async fn get_fantasy_tournament(
             id: t.id,
             name: t.name.to_string(),
             invitation_status: InvitationStatus::Accepted,
-            owner_id: user_admin_id.unwrap_or(t.owner),
+            owner_id: acting_owner.unwrap_or(t.owner),
+            archived: t.archived_at.is_some(),
+            visibility: t.visibility.clone().into(),
+            public_slug: crate::visibility::public_slug(&t),
         }))
     } else {
         Ok(None)
@@ -203,6 +259,7 @@ pub async fn get_user_participants_in_tournament(
             user_in_fantasy_tournament::Column::InvitationStatus
                 .eq(sea_orm_active_enums::FantasyTournamentInvitationStatus::Accepted),
         )
+        .filter(user_in_fantasy_tournament::Column::Role.ne(sea_orm_active_enums::TournamentRole::Spectator))
         .all(db)
         .await
         .map_err(|_| GenericError::UnknownError("Unable to recieve users from database"))?;
@@ -241,7 +298,7 @@ pub async fn get_user_participants_in_tournament(
 }
 
 pub async fn get_user_by_name(
-    db: &DatabaseConnection,
+    db: &impl ConnectionTrait,
     username: String,
 ) -> Result<Option<user::Model>, DbErr> {
     User::find().filter(user::Column::Name.eq(username)).one(db).await
@@ -322,7 +379,7 @@ async fn get_player_name(db: &DatabaseConnection, player_id: i32) -> Result<Stri
 
 pub async fn get_user_picks_in_tournament_division(
     db: &impl ConnectionTrait,
-    requester_id: i32,
+    requester_id: Option<i32>,
     user_id: i32,
     tournament_id: i32,
     div: &dto::Division,
@@ -341,7 +398,7 @@ pub async fn get_user_picks_in_tournament_division(
             warn!("Error while getting picks: {:#?}", e);
             GenericError::UnknownError("Unknown error while getting picks")
         })?;
-    let owner = requester_id == user_id;
+    let owner = requester_id == Some(user_id);
 
     Ok(FantasyPicks {
         picks: {
@@ -434,7 +491,7 @@ pub async fn check_if_user_in_tournament(
 }
 
 pub async fn get_tournament_divisions(
-    db: &DatabaseConnection,
+    db: &impl ConnectionTrait,
     tournament_id: i32,
 ) -> Result<Vec<dto::Division>, DbErr> {
     let picks = FantasyTournamentDivision::find()
@@ -460,49 +517,6 @@ pub async fn active_rounds(db: &impl ConnectionTrait) -> Result<Vec<round::Model
         .await
 }
 
-pub async fn active_competitions(db: &impl ConnectionTrait) -> Result<Vec<CompetitionInfo>, GenericError> {
-    let competition_models = Competition::find()
-        .filter(
-            competition::Column::Status
-                .eq(CompetitionStatus::Running)
-                .or(competition::Column::Status
-                    .eq(CompetitionStatus::NotStarted)
-                    .and(competition::Column::StartDate.lte(chrono::Utc::now().date_naive()))),
-        )
-        .all(db)
-        .await
-        .map_err(|_| GenericError::UnknownError("Unknown error while trying to find active competitions"))?;
-
-    let mut competitions = Vec::new();
-
-    for comp_model in competition_models {
-        match CompetitionInfo::from_web(comp_model.id as u32).await {
-            Ok(comp) => {
-                if comp_model.status != comp.status().into() {
-                    let mut model = comp_model.into_active_model();
-                    let status: CompetitionStatus = comp.status().into();
-                    if status == CompetitionStatus::Finished {
-                        model.ended_at = Set(Some(chrono::Utc::now().fixed_offset()));
-                    }
-                    model.status = Set(comp.status().into());
-
-                    if let Err(e) = model.save(db).await {
-                        error!("Encountered db err: {:?}", e.sql_err());
-                    }
-                }
-                competitions.push(comp);
-            }
-            Err(e) => {
-                warn!("Unable to fetch competition from PDGA: {:?}", e);
-                Err(GenericError::PdgaGaveUp(
-                    "Internal error while fetching competition from PDGA",
-                ))?
-            }
-        }
-    }
-    Ok(competitions)
-}
-
 pub async fn get_player_division_in_tournament(
     db: &impl ConnectionTrait,
     player_id: i32,
@@ -538,7 +552,7 @@ pub async fn get_player_positions_in_round(
         .all(db)
         .await?;
 
-    player_round_scores.sort_by(|a, b| a.throws.cmp(&b.throws));
+    player_round_scores.sort_by_key(|a| a.throws);
     player_round_scores.reverse();
 
     Ok(player_round_scores)
@@ -629,3 +643,16 @@ pub async fn get_users_in_tournament(
     }
     Ok(out_things)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrong_passwords_are_refused() {
+        let hashed_password = bcrypt::hash("correct horse", 4).unwrap();
+        assert!(password_matches("correct horse", &hashed_password));
+        assert!(!password_matches("battery staple", &hashed_password));
+        assert!(!password_matches("correct horse", "not a bcrypt hash"));
+    }
+}
@@ -0,0 +1,339 @@
+use std::collections::{HashMap, HashSet};
+
+use entity::prelude::*;
+use entity::sea_orm_active_enums::CompetitionStatus;
+use entity::*;
+use itertools::Itertools;
+use log::{error, warn};
+use sea_orm::{ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait};
+
+use crate::dto::{fantasy_points, CompetitionInfo, CompetitionLevel, UserScore, UserWithScore};
+use crate::error::GenericError;
+use crate::{captains, notifications, query, substitutions, webhooks};
+
+/// What a refresh did, mostly for logging.
+#[derive(Debug, Default)]
+pub struct RefreshSummary {
+    /// Competitions fetched from PDGA and stored.
+    pub competitions: Vec<i32>,
+    /// Competitions that couldn't be fetched this time.
+    pub failed: Vec<i32>,
+    /// Tournaments whose user scores were recomputed.
+    pub tournaments: Vec<i32>,
+    /// Tournaments whose user scores couldn't be recomputed, they are retried on the next refresh.
+    pub failed_tournaments: Vec<i32>,
+}
+
+/// Fetches every active competition from PDGA once and stores it, then recomputes the
+/// user scores of every tournament containing one of them from the stored round scores.
+pub async fn refresh_active_competitions(db: &DatabaseConnection) -> Result<RefreshSummary, GenericError> {
+    let mut summary = RefreshSummary::default();
+    for competition in competitions_to_refresh(db).await? {
+        match refresh_competition(db, competition.id).await {
+            Ok(()) => summary.competitions.push(competition.id),
+            Err(e) => {
+                warn!("Unable to refresh competition {}: {:?}", competition.id, e);
+                summary.failed.push(competition.id);
+            }
+        }
+    }
+
+    let tournaments = tournaments_containing(db, &summary.competitions).await?;
+    // One tournament failing must not hold back the scores of the others
+    for (tournament_id, competition_ids) in tournaments {
+        match refresh_tournament(db, tournament_id, &competition_ids).await {
+            Ok(()) => summary.tournaments.push(tournament_id),
+            Err(e) => {
+                error!(
+                    "Unable to save user scores in tournament {}: {:?}",
+                    tournament_id, e
+                );
+                summary.failed_tournaments.push(tournament_id);
+            }
+        }
+    }
+
+    if let Err(e) = crate::performance_index::index_finished_competitions(db).await {
+        warn!("Unable to update performance indexes: {:?}", e);
+    }
+    Ok(summary)
+}
+
+async fn refresh_tournament(
+    db: &DatabaseConnection,
+    tournament_id: i32,
+    competition_ids: &[i32],
+) -> Result<(), GenericError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    save_user_scores_in_tournament(&txn, tournament_id, competition_ids).await?;
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))
+}
+
+/// Competitions that are running, or should have started by now.
+async fn competitions_to_refresh(db: &impl ConnectionTrait) -> Result<Vec<competition::Model>, GenericError> {
+    Competition::find()
+        .filter(
+            competition::Column::Status
+                .eq(CompetitionStatus::Running)
+                .or(competition::Column::Status
+                    .eq(CompetitionStatus::NotStarted)
+                    .and(competition::Column::StartDate.lte(chrono::Utc::now().date_naive()))),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unknown error while trying to find active competitions"))
+}
+
+/// Fetches a competition from PDGA and stores its round scores and status.
+pub async fn refresh_competition(db: &DatabaseConnection, competition_id: i32) -> Result<(), GenericError> {
+    let competition = CompetitionInfo::from_web(competition_id as u32).await?;
+
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    let status_before = competition_status(&txn, competition_id).await;
+    competition.save_round_scores(&txn).await?;
+    competition.save_competition_in_db(&txn, None).await?;
+    if let Some(status) = competition_status(&txn, competition_id).await {
+        if status == CompetitionStatus::Finished && status_before != Some(CompetitionStatus::Finished) {
+            if let Err(e) = notifications::notify_competition_finished(&txn, competition_id).await {
+                warn!("Unable to notify about finished competition: {:#?}", e);
+            }
+        }
+        if let Err(e) =
+            webhooks::competition_status_changed(&txn, competition_id, status_before, status).await
+        {
+            warn!("Unable to queue competition webhooks: {:#?}", e);
+        }
+    }
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))
+}
+
+async fn competition_status(db: &impl ConnectionTrait, competition_id: i32) -> Option<CompetitionStatus> {
+    Competition::find_by_id(competition_id)
+        .one(db)
+        .await
+        .ok()
+        .flatten()
+        .map(|c| c.status)
+}
+
+/// Groups the competitions by the unarchived tournaments they are part of.
+async fn tournaments_containing(
+    db: &impl ConnectionTrait,
+    competition_ids: &[i32],
+) -> Result<Vec<(i32, Vec<i32>)>, GenericError> {
+    if competition_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let archived = FantasyTournament::find()
+        .filter(fantasy_tournament::Column::ArchivedAt.is_not_null())
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("database error on fantasy tournament"))?
+        .into_iter()
+        .map(|t| t.id)
+        .collect::<HashSet<_>>();
+    Ok(CompetitionInFantasyTournament::find()
+        .filter(
+            competition_in_fantasy_tournament::Column::CompetitionId.is_in(competition_ids.iter().copied()),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournaments from database"))?
+        .into_iter()
+        .filter(|c| !archived.contains(&c.fantasy_tournament_id))
+        .map(|c| (c.fantasy_tournament_id, c.competition_id))
+        .into_group_map()
+        .into_iter()
+        .sorted_by_key(|(tournament, _)| *tournament)
+        .collect_vec())
+}
+
+/// Recomputes the user scores of a tournament in the given competitions from the stored
+/// round scores, and queues a webhook if the standings changed. Should be run inside a transaction.
+pub async fn save_user_scores_in_tournament(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_ids: &[i32],
+) -> Result<(), GenericError> {
+    let standings_before = query::get_user_participants_in_tournament(db, tournament_id).await?;
+    for competition_id in competition_ids {
+        save_user_scores(db, tournament_id, *competition_id).await?;
+    }
+    let standings_after = query::get_user_participants_in_tournament(db, tournament_id).await?;
+    if let Err(e) = webhooks::standings_changed(
+        db,
+        tournament_id,
+        &sorted_standings(standings_before),
+        &sorted_standings(standings_after),
+    )
+    .await
+    {
+        warn!("Unable to queue standings webhooks: {:#?}", e);
+    }
+    Ok(())
+}
+
+/// Recomputes the user scores of every running competition in a tournament from the stored round scores.
+pub async fn refresh_user_scores_in_fantasy(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<(), GenericError> {
+    let competition_ids = query::get_competitions_in_fantasy_tournament(db, tournament_id)
+        .await?
+        .into_iter()
+        .filter(|comp| comp.status == CompetitionStatus::Running)
+        .map(|c| c.id)
+        .collect_vec();
+    save_user_scores_in_tournament(db, tournament_id, &competition_ids).await
+}
+
+fn sorted_standings(mut standings: Vec<UserWithScore>) -> Vec<UserWithScore> {
+    standings.sort_by_key(|u| (std::cmp::Reverse(u.score), u.user.id));
+    standings
+}
+
+/// Replaces the user scores of a tournament in a competition.
+///
+/// Every player is scored on their latest stored round, so playoff players
+/// replace their score from the last regular round.
+async fn save_user_scores(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+) -> Result<(), GenericError> {
+    let level: CompetitionLevel = Competition::find_by_id(competition_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to find competition"))?
+        .ok_or(GenericError::NotFound("Competition not found"))?
+        .level
+        .into();
+    let round_scores = PlayerRoundScore::find()
+        .filter(player_round_score::Column::CompetitionId.eq(competition_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?;
+    let player_scores = score_latest_rounds(round_scores, level);
+    let lineup = substitutions::lineup(db, tournament_id, competition_id).await?;
+    substitutions::save_substitutions(db, tournament_id, competition_id, &lineup.substitutions).await?;
+    let mut scores = lineup.user_scores(tournament_id, competition_id, &player_scores);
+    captains::CaptainMultiplier::load(db, tournament_id, competition_id, &player_scores)
+        .await?
+        .apply_to_scores(&mut scores);
+
+    UserCompetitionScoreInFantasyTournament::delete_many()
+        .filter(
+            user_competition_score_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(user_competition_score_in_fantasy_tournament::Column::CompetitionId.eq(competition_id)),
+        )
+        .exec(db)
+        .await
+        .map_err(|e| {
+            error!("Unable to delete user scores from competition {:#?}", e);
+            GenericError::UnknownError("Unable to delete user scores from competition")
+        })?;
+    if !scores.is_empty() {
+        UserCompetitionScoreInFantasyTournament::insert_many(
+            scores.into_iter().map(|s| s.into_active_model(competition_id)),
+        )
+        .exec(db)
+        .await
+        .map_err(|e| {
+            error!("Unable to insert user scores into database: {:#?}", e);
+            GenericError::UnknownError("Unable to insert user score from competition into database")
+        })?;
+    }
+    Ok(())
+}
+
+/// Turns the points of each player into the scores of the users that picked them,
+/// counting only the starters and the bench players substituted in for them.
+pub(crate) async fn counted_user_scores(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+    player_scores: &HashMap<i32, i32>,
+) -> Result<Vec<UserScore>, GenericError> {
+    Ok(substitutions::lineup(db, tournament_id, competition_id)
+        .await?
+        .user_scores(tournament_id, competition_id, player_scores))
+}
+
+/// Fantasy points of every player, based on their placement in the latest round they have a score in.
+pub(crate) fn score_latest_rounds(
+    round_scores: Vec<player_round_score::Model>,
+    level: CompetitionLevel,
+) -> HashMap<i32, i32> {
+    let latest = round_scores
+        .into_iter()
+        .into_group_map_by(|s| s.pdga_number)
+        .into_values()
+        .filter_map(|scores| scores.into_iter().max_by_key(|s| s.round))
+        .collect_vec();
+    latest
+        .iter()
+        .map(|score| {
+            let tied = latest
+                .iter()
+                .filter(|other| {
+                    other.division == score.division
+                        && other.placement == score.placement
+                        && other.pdga_number != score.pdga_number
+                })
+                .count();
+            (
+                score.pdga_number,
+                fantasy_points(score.placement as u16, tied, level.clone()) as i32,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::sea_orm_active_enums::Division;
+
+    fn round_score(pdga_number: i32, round: i32, placement: i32) -> player_round_score::Model {
+        player_round_score::Model {
+            id: 0,
+            pdga_number,
+            competition_id: 1,
+            round,
+            throws: 50,
+            division: Division::Mpo,
+            placement,
+        }
+    }
+
+    #[test]
+    fn latest_round_decides_score() {
+        let scores = score_latest_rounds(
+            vec![round_score(1, 1, 1), round_score(1, 2, 3), round_score(2, 1, 2)],
+            CompetitionLevel::Elite,
+        );
+        assert_eq!(scores[&1], fantasy_points(3, 0, CompetitionLevel::Elite) as i32);
+        assert_eq!(scores[&2], fantasy_points(2, 0, CompetitionLevel::Elite) as i32);
+    }
+
+    #[test]
+    fn tied_players_share_points() {
+        let scores = score_latest_rounds(
+            vec![round_score(1, 1, 1), round_score(2, 1, 1)],
+            CompetitionLevel::Elite,
+        );
+        assert_eq!(scores[&1], scores[&2]);
+        assert_eq!(scores[&1], (100 + 85) / 2);
+    }
+}
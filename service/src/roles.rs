@@ -0,0 +1,275 @@
+use entity::prelude::*;
+use entity::sea_orm_active_enums::{self, FantasyTournamentInvitationStatus};
+use entity::*;
+use rocket::request::FromParam;
+use rocket_okapi::JsonSchema;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveEnum, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::error::GenericError;
+
+/// The role of a user in a tournament. Every role can do everything the roles before it can.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TournamentRole {
+    /// Follows the tournament without picking
+    Spectator,
+    Member,
+    /// Helps the owner run the tournament
+    CoOwner,
+    Owner,
+}
+
+impl FromParam<'_> for TournamentRole {
+    type Error = GenericError;
+
+    fn from_param(param: &'_ str) -> Result<Self, Self::Error> {
+        match param {
+            "Spectator" => Ok(Self::Spectator),
+            "Member" => Ok(Self::Member),
+            "CoOwner" => Ok(Self::CoOwner),
+            "Owner" => Ok(Self::Owner),
+            _ => Err(GenericError::BadRequest("Invalid role")),
+        }
+    }
+}
+
+/// The role of a user on the whole server
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SiteRole {
+    User,
+    /// Has the powers of a co-owner in every tournament, to fix picks and settle disputes
+    Moderator,
+    /// Has the powers of the owner in every tournament
+    Admin,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct Member {
+    pub user_id: i32,
+    pub username: String,
+    pub role: TournamentRole,
+}
+
+impl From<sea_orm_active_enums::TournamentRole> for TournamentRole {
+    fn from(role: sea_orm_active_enums::TournamentRole) -> Self {
+        match role {
+            sea_orm_active_enums::TournamentRole::Spectator => Self::Spectator,
+            sea_orm_active_enums::TournamentRole::Member => Self::Member,
+            sea_orm_active_enums::TournamentRole::CoOwner => Self::CoOwner,
+            sea_orm_active_enums::TournamentRole::Owner => Self::Owner,
+        }
+    }
+}
+
+impl From<TournamentRole> for sea_orm_active_enums::TournamentRole {
+    fn from(role: TournamentRole) -> Self {
+        match role {
+            TournamentRole::Spectator => Self::Spectator,
+            TournamentRole::Member => Self::Member,
+            TournamentRole::CoOwner => Self::CoOwner,
+            TournamentRole::Owner => Self::Owner,
+        }
+    }
+}
+
+pub fn site_role(user: &user::Model) -> SiteRole {
+    if user.admin {
+        SiteRole::Admin
+    } else if user.moderator {
+        SiteRole::Moderator
+    } else {
+        SiteRole::User
+    }
+}
+
+/// The role a user acts with in a tournament, taking their role on the server into account.
+pub fn effective_role(site: SiteRole, membership: Option<TournamentRole>) -> Option<TournamentRole> {
+    match site {
+        SiteRole::Admin => Some(TournamentRole::Owner),
+        SiteRole::Moderator => membership.max(Some(TournamentRole::CoOwner)),
+        SiteRole::User => membership,
+    }
+}
+
+/// The role of a user in a tournament, `None` when they haven't joined it.
+pub async fn role_in_tournament(
+    db: &impl ConnectionTrait,
+    user: &user::Model,
+    tournament_id: i32,
+) -> Result<Option<TournamentRole>, GenericError> {
+    let membership = participant_role(db, tournament_id, user.id).await?;
+    Ok(effective_role(site_role(user), membership))
+}
+
+/// The role a user was given in a tournament, ignoring their role on the server.
+pub async fn participant_role(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<Option<TournamentRole>, GenericError> {
+    let tournament = FantasyTournament::find_by_id(tournament_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament from database"))?
+        .ok_or(GenericError::NotFound("Tournament not found"))?;
+    if tournament.owner == user_id {
+        return Ok(Some(TournamentRole::Owner));
+    }
+    Ok(accepted_membership(db, tournament_id, user_id)
+        .await?
+        .map(|m| m.role.into()))
+}
+
+async fn accepted_membership(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<Option<user_in_fantasy_tournament::Model>, GenericError> {
+    UserInFantasyTournament::find()
+        .filter(
+            user_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(user_in_fantasy_tournament::Column::UserId.eq(user_id))
+                .and(
+                    user_in_fantasy_tournament::Column::InvitationStatus
+                        .eq(FantasyTournamentInvitationStatus::Accepted),
+                ),
+        )
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get participant from database"))
+}
+
+pub async fn get_members(db: &impl ConnectionTrait, tournament_id: i32) -> Result<Vec<Member>, GenericError> {
+    let mut members: Vec<Member> = UserInFantasyTournament::find()
+        .filter(
+            user_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(
+                    user_in_fantasy_tournament::Column::InvitationStatus
+                        .eq(FantasyTournamentInvitationStatus::Accepted),
+                ),
+        )
+        .find_also_related(User)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get participants from database"))?
+        .into_iter()
+        .filter_map(|(membership, user)| {
+            user.map(|u| Member {
+                user_id: u.id,
+                username: u.name,
+                role: membership.role.into(),
+            })
+        })
+        .collect();
+    members.sort_by(|a, b| b.role.cmp(&a.role).then(a.user_id.cmp(&b.user_id)));
+    Ok(members)
+}
+
+/// Changes the role of a participant. Making someone the owner hands the tournament over to them,
+/// and the previous owner stays on as a co-owner. Should be run inside a transaction.
+pub async fn set_role(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+    role: TournamentRole,
+) -> Result<(), GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let tournament = FantasyTournament::find_by_id(tournament_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament from database"))?
+        .ok_or(GenericError::NotFound("Tournament not found"))?;
+    if tournament.owner == user_id {
+        Err(GenericError::BadRequest(
+            "The owner keeps their role until ownership is handed to someone else",
+        ))?
+    }
+    let membership = accepted_membership(db, tournament_id, user_id)
+        .await?
+        .ok_or(GenericError::NotFound("User not found in tournament"))?;
+    if role == TournamentRole::Owner {
+        return transfer_ownership(db, tournament_id, user_id).await;
+    }
+    let mut membership = membership.into_active_model();
+    membership.role = Set(role.into());
+    UserInFantasyTournament::update(membership)
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to change role"))?;
+    Ok(())
+}
+
+/// Makes a participant the owner of a tournament, the previous owner becomes a co-owner.
+pub(crate) async fn transfer_ownership(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    new_owner: i32,
+) -> Result<(), GenericError> {
+    let tournament = FantasyTournament::find_by_id(tournament_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament from database"))?
+        .ok_or(GenericError::NotFound("Tournament not found"))?;
+    let previous_owner = tournament.owner;
+    for (user_id, role) in [
+        (previous_owner, sea_orm_active_enums::TournamentRole::CoOwner),
+        (new_owner, sea_orm_active_enums::TournamentRole::Owner),
+    ] {
+        UserInFantasyTournament::update_many()
+            .col_expr(user_in_fantasy_tournament::Column::Role, role.as_enum())
+            .filter(
+                user_in_fantasy_tournament::Column::FantasyTournamentId
+                    .eq(tournament_id)
+                    .and(user_in_fantasy_tournament::Column::UserId.eq(user_id)),
+            )
+            .exec(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to change role"))?;
+    }
+    let mut tournament = tournament.into_active_model();
+    tournament.owner = Set(new_owner);
+    FantasyTournament::update(tournament)
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to transfer tournament"))?;
+    Ok(())
+}
+
+pub async fn set_moderator(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    moderator: bool,
+) -> Result<(), GenericError> {
+    let user = User::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get user from database"))?
+        .ok_or(GenericError::NotFound("User not found"))?;
+    let mut user = user.into_active_model();
+    user.moderator = Set(moderator);
+    User::update(user)
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to change moderator status"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn site_roles_raise_tournament_roles() {
+        use TournamentRole::*;
+        assert_eq!(effective_role(SiteRole::User, None), None);
+        assert_eq!(effective_role(SiteRole::User, Some(Spectator)), Some(Spectator));
+        assert_eq!(effective_role(SiteRole::Moderator, None), Some(CoOwner));
+        assert_eq!(effective_role(SiteRole::Moderator, Some(Member)), Some(CoOwner));
+        assert_eq!(effective_role(SiteRole::Moderator, Some(Owner)), Some(Owner));
+        assert_eq!(effective_role(SiteRole::Admin, Some(Spectator)), Some(Owner));
+        assert!(Spectator < Member && Member < CoOwner && CoOwner < Owner);
+    }
+}
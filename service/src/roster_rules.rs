@@ -0,0 +1,478 @@
+use std::collections::HashSet;
+
+use entity::prelude::*;
+use entity::*;
+use itertools::Itertools;
+use rocket_okapi::JsonSchema;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::Division;
+use crate::error::GenericError;
+
+/// Roster rules of a tournament, rules left out aren't enforced
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+pub struct RosterRules {
+    pub min_fpo_picks: Option<i32>,
+    pub max_fpo_picks: Option<i32>,
+    /// Most players from the same country on one roster
+    pub max_picks_per_country: Option<i32>,
+    pub top_rated: Option<TopRatedLimit>,
+}
+
+/// Limits how many of the highest rated players of each division a roster can hold
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+pub struct TopRatedLimit {
+    pub pool_size: i32,
+    pub max_picks: i32,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(tag = "rule")]
+pub enum RosterViolation {
+    TooFewFpo {
+        min: i32,
+        picked: i32,
+    },
+    TooManyFpo {
+        max: i32,
+        picked: i32,
+    },
+    TooManyFromCountry {
+        country: String,
+        max: i32,
+        picked: i32,
+    },
+    TooManyTopRated {
+        pool_size: i32,
+        max: i32,
+        players: Vec<i32>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RosterPlayer {
+    pub pdga_number: i32,
+    pub division: Division,
+    pub country: Option<String>,
+}
+
+pub(crate) struct Roster {
+    pub players: Vec<RosterPlayer>,
+    /// Slots the user can still fill with FPO players, so that minimums aren't enforced on rosters being built
+    pub open_slots: i32,
+}
+
+impl Roster {
+    fn fpo_picks(&self) -> i32 {
+        self.players
+            .iter()
+            .filter(|p| p.division == Division::FPO)
+            .count() as i32
+    }
+}
+
+pub(crate) trait RosterRule: Send + Sync {
+    fn check(&self, roster: &Roster) -> Vec<RosterViolation>;
+}
+
+struct FpoQuota {
+    min: Option<i32>,
+    max: Option<i32>,
+}
+
+impl RosterRule for FpoQuota {
+    fn check(&self, roster: &Roster) -> Vec<RosterViolation> {
+        let picked = roster.fpo_picks();
+        let mut violations = Vec::new();
+        if let Some(min) = self.min.filter(|min| picked + roster.open_slots < *min) {
+            violations.push(RosterViolation::TooFewFpo { min, picked });
+        }
+        if let Some(max) = self.max.filter(|max| picked > *max) {
+            violations.push(RosterViolation::TooManyFpo { max, picked });
+        }
+        violations
+    }
+}
+
+struct CountryLimit {
+    max: i32,
+}
+
+impl RosterRule for CountryLimit {
+    fn check(&self, roster: &Roster) -> Vec<RosterViolation> {
+        roster
+            .players
+            .iter()
+            .filter_map(|p| p.country.clone())
+            .counts()
+            .into_iter()
+            .filter(|(_, picked)| *picked as i32 > self.max)
+            .sorted()
+            .map(|(country, picked)| RosterViolation::TooManyFromCountry {
+                country,
+                max: self.max,
+                picked: picked as i32,
+            })
+            .collect()
+    }
+}
+
+struct TopRatedRule {
+    limit: TopRatedLimit,
+    top_rated: HashSet<i32>,
+}
+
+impl RosterRule for TopRatedRule {
+    fn check(&self, roster: &Roster) -> Vec<RosterViolation> {
+        let players = roster
+            .players
+            .iter()
+            .map(|p| p.pdga_number)
+            .filter(|p| self.top_rated.contains(p))
+            .sorted()
+            .collect_vec();
+        if players.len() as i32 > self.limit.max_picks {
+            vec![RosterViolation::TooManyTopRated {
+                pool_size: self.limit.pool_size,
+                max: self.limit.max_picks,
+                players,
+            }]
+        } else {
+            vec![]
+        }
+    }
+}
+
+impl RosterRules {
+    fn from_model(tournament: &fantasy_tournament::Model) -> Self {
+        Self {
+            min_fpo_picks: tournament.min_fpo_picks,
+            max_fpo_picks: tournament.max_fpo_picks,
+            max_picks_per_country: tournament.max_picks_per_country,
+            top_rated: tournament
+                .top_rated_pool_size
+                .zip(tournament.max_top_rated_picks)
+                .map(|(pool_size, max_picks)| TopRatedLimit { pool_size, max_picks }),
+        }
+    }
+
+    pub(crate) fn validate(&self, max_picks: i32) -> Result<(), GenericError> {
+        let counts = [
+            self.min_fpo_picks,
+            self.max_fpo_picks,
+            self.max_picks_per_country,
+            self.top_rated.map(|t| t.max_picks),
+        ];
+        if counts.into_iter().flatten().any(|c| c < 0) {
+            Err(GenericError::BadRequest("Roster rules can't be negative"))?
+        }
+        if self.min_fpo_picks.is_some_and(|min| min > max_picks) {
+            Err(GenericError::BadRequest(
+                "Minimum FPO picks can't exceed max picks",
+            ))?
+        }
+        if let (Some(min), Some(max)) = (self.min_fpo_picks, self.max_fpo_picks) {
+            if min > max {
+                Err(GenericError::BadRequest(
+                    "Minimum FPO picks can't exceed the maximum",
+                ))?
+            }
+        }
+        if self.top_rated.is_some_and(|t| t.pool_size < 1) {
+            Err(GenericError::BadRequest(
+                "Top rated pool needs at least one player",
+            ))?
+        }
+        Ok(())
+    }
+
+    pub(crate) fn apply(&self, tournament: &mut fantasy_tournament::ActiveModel) {
+        tournament.min_fpo_picks = Set(self.min_fpo_picks);
+        tournament.max_fpo_picks = Set(self.max_fpo_picks);
+        tournament.max_picks_per_country = Set(self.max_picks_per_country);
+        tournament.top_rated_pool_size = Set(self.top_rated.map(|t| t.pool_size));
+        tournament.max_top_rated_picks = Set(self.top_rated.map(|t| t.max_picks));
+    }
+
+    fn rules(&self, top_rated: HashSet<i32>) -> Vec<Box<dyn RosterRule>> {
+        let mut rules: Vec<Box<dyn RosterRule>> = Vec::new();
+        if self.min_fpo_picks.is_some() || self.max_fpo_picks.is_some() {
+            rules.push(Box::new(FpoQuota {
+                min: self.min_fpo_picks,
+                max: self.max_fpo_picks,
+            }));
+        }
+        if let Some(max) = self.max_picks_per_country {
+            rules.push(Box::new(CountryLimit { max }));
+        }
+        if let Some(limit) = self.top_rated {
+            rules.push(Box::new(TopRatedRule { limit, top_rated }));
+        }
+        rules
+    }
+}
+
+pub(crate) fn check_roster(rules: &[Box<dyn RosterRule>], roster: &Roster) -> Vec<RosterViolation> {
+    rules.iter().flat_map(|rule| rule.check(roster)).collect()
+}
+
+/// The `pool_size` highest rated players of each division, including everyone tied with the last of them.
+fn top_rated_players(players: Vec<(i32, Division, i32)>, pool_size: i32) -> HashSet<i32> {
+    players
+        .into_iter()
+        .into_group_map_by(|(_, division, _)| *division as u8)
+        .into_values()
+        .flat_map(|players| {
+            let players = players
+                .into_iter()
+                .sorted_by_key(|(_, _, rating)| std::cmp::Reverse(*rating))
+                .collect_vec();
+            let cutoff = players
+                .get(pool_size as usize - 1)
+                .or(players.last())
+                .map(|(_, _, rating)| *rating)
+                .unwrap_or_default();
+            players
+                .into_iter()
+                .take_while(move |(_, _, rating)| *rating >= cutoff)
+                .map(|(pdga_number, _, _)| pdga_number)
+        })
+        .collect()
+}
+
+async fn load_top_rated(db: &impl ConnectionTrait, pool_size: i32) -> Result<HashSet<i32>, GenericError> {
+    let players = Player::find()
+        .filter(player::Column::Rating.is_not_null())
+        .find_also_related(PlayerDivisionInFantasyTournament)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get player ratings from database"))?
+        .into_iter()
+        .filter_map(|(player, division)| {
+            Some((player.pdga_number, division?.division.into(), player.rating?))
+        })
+        .collect_vec();
+    Ok(top_rated_players(players, pool_size))
+}
+
+async fn tournament(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<fantasy_tournament::Model, GenericError> {
+    FantasyTournament::find_by_id(tournament_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament from database"))?
+        .ok_or(GenericError::NotFound("Tournament not found"))
+}
+
+pub async fn get_roster_rules(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<RosterRules, GenericError> {
+    Ok(RosterRules::from_model(&tournament(db, tournament_id).await?))
+}
+
+/// Checks the roster of a user against the rules of the tournament.
+///
+/// A lenient check lets rosters that aren't full yet stay below the FPO minimum.
+pub async fn roster_violations(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    tournament_id: i32,
+    lenient: bool,
+) -> Result<Vec<RosterViolation>, GenericError> {
+    let tournament = tournament(db, tournament_id).await?;
+    let rules = RosterRules::from_model(&tournament);
+    let top_rated = match rules.top_rated {
+        Some(limit) => load_top_rated(db, limit.pool_size).await?,
+        None => HashSet::new(),
+    };
+    let rules = rules.rules(top_rated);
+    if rules.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let picks = FantasyPick::find()
+        .filter(
+            fantasy_pick::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(fantasy_pick::Column::User.eq(user_id)),
+        )
+        .find_also_related(Player)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?;
+    let players = picks
+        .into_iter()
+        .map(|(pick, player)| RosterPlayer {
+            pdga_number: pick.player,
+            division: pick.division.into(),
+            country: player.and_then(|p| p.country),
+        })
+        .collect_vec();
+
+    let fpo_enabled = FantasyTournamentDivision::find()
+        .filter(
+            fantasy_tournament_division::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(fantasy_tournament_division::Column::Division.eq(sea_orm_active_enums::Division::Fpo)),
+        )
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament divisions from database"))?
+        .is_some();
+    let mut roster = Roster {
+        players,
+        open_slots: 0,
+    };
+    if lenient && fpo_enabled {
+        roster.open_slots = (tournament.max_picks_per_user - roster.players.len() as i32).max(0);
+    }
+    Ok(check_roster(&rules, &roster))
+}
+
+/// Refuses a roster that breaks the rules of the tournament, meant to run before committing pick changes.
+///
+/// The FPO minimum waits for the roster to fill up while the user's exchange window is open,
+/// changes made after it has closed have to meet it right away.
+pub async fn validate_roster(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    tournament_id: i32,
+) -> Result<(), GenericError> {
+    let window_open =
+        crate::exchange_windows::is_user_allowed_to_exchange(db, user_id, tournament_id).await?;
+    let violations = roster_violations(db, user_id, tournament_id, window_open).await?;
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(GenericError::InvalidRoster(
+            serde_json::to_string(&violations)
+                .map_err(|_| GenericError::UnknownError("Unable to describe roster violations"))?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+    use sea_orm::sea_query::Expr;
+    use sea_orm::ActiveValue::NotSet;
+
+    fn player(pdga_number: i32, division: Division, country: &str) -> RosterPlayer {
+        RosterPlayer {
+            pdga_number,
+            division,
+            country: Some(country.to_string()),
+        }
+    }
+
+    fn roster(open_slots: i32) -> Roster {
+        Roster {
+            players: vec![
+                player(1, Division::MPO, "US"),
+                player(2, Division::MPO, "US"),
+                player(3, Division::MPO, "FI"),
+                player(4, Division::FPO, "US"),
+            ],
+            open_slots,
+        }
+    }
+
+    #[test]
+    fn fpo_minimum_counts_open_slots() {
+        let rules = RosterRules {
+            min_fpo_picks: Some(2),
+            max_fpo_picks: Some(3),
+            ..Default::default()
+        }
+        .rules(HashSet::new());
+        assert!(check_roster(&rules, &roster(1)).is_empty());
+        assert_eq!(
+            check_roster(&rules, &roster(0)),
+            vec![RosterViolation::TooFewFpo { min: 2, picked: 1 }]
+        );
+    }
+
+    #[test]
+    fn country_and_top_rated_limits() {
+        let rules = RosterRules {
+            max_picks_per_country: Some(2),
+            top_rated: Some(TopRatedLimit {
+                pool_size: 2,
+                max_picks: 1,
+            }),
+            ..Default::default()
+        }
+        .rules(HashSet::from([1, 3]));
+        assert_eq!(
+            check_roster(&rules, &roster(0)),
+            vec![
+                RosterViolation::TooManyFromCountry {
+                    country: "US".to_string(),
+                    max: 2,
+                    picked: 3
+                },
+                RosterViolation::TooManyTopRated {
+                    pool_size: 2,
+                    max: 1,
+                    players: vec![1, 3]
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn top_rated_pool_is_per_division_and_keeps_ties() {
+        let players = vec![
+            (1, Division::MPO, 1050),
+            (2, Division::MPO, 1040),
+            (3, Division::MPO, 1040),
+            (4, Division::MPO, 1000),
+            (5, Division::FPO, 950),
+        ];
+        assert_eq!(top_rated_players(players, 2), HashSet::from([1, 2, 3, 5]));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn full_roster_needs_fpo_minimum() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        FantasyTournament::update_many()
+            .col_expr(fantasy_tournament::Column::MaxPicksPerUser, Expr::value(2))
+            .col_expr(fantasy_tournament::Column::MinFpoPicks, Expr::value(1))
+            .filter(fantasy_tournament::Column::Id.eq(tournament))
+            .exec(&txn)
+            .await
+            .unwrap();
+        FantasyTournamentDivision::insert(fantasy_tournament_division::ActiveModel {
+            id: NotSet,
+            fantasy_tournament_id: Set(tournament),
+            division: Set(sea_orm_active_enums::Division::Fpo),
+        })
+        .exec_without_returning(&txn)
+        .await
+        .unwrap();
+        for player in [1, 2] {
+            test_db::player(&txn, tournament, player).await;
+        }
+
+        test_db::pick(&txn, tournament, owner.id, 1, 1).await;
+        let violations = roster_violations(&txn, owner.id, tournament, true).await.unwrap();
+        assert!(violations.is_empty());
+
+        test_db::pick(&txn, tournament, owner.id, 2, 2).await;
+        let violations = roster_violations(&txn, owner.id, tournament, true).await.unwrap();
+        assert_eq!(violations, vec![RosterViolation::TooFewFpo { min: 1, picked: 0 }]);
+        assert!(matches!(
+            validate_roster(&txn, owner.id, tournament).await,
+            Err(GenericError::InvalidRoster(_))
+        ));
+    }
+}
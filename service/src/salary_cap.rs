@@ -0,0 +1,490 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, Utc};
+use entity::prelude::*;
+use entity::sea_orm_active_enums::CompetitionStatus;
+use entity::*;
+use itertools::Itertools;
+use log::error;
+use rocket_okapi::JsonSchema;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{sea_query, ColumnTrait, ConnectionTrait, EntityTrait, NotSet, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::{fantasy_points, CompetitionLevel, Division};
+use crate::error::GenericError;
+
+/// Price of players without a rating or recent results
+const MIN_PRICE: i32 = 5;
+/// Rating at which the rating part of the price bottoms out
+const RATING_FLOOR: i32 = 850;
+/// How many of the latest finished competitions make up the recent form
+const FORM_COMPETITIONS: usize = 5;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct PlayerPrice {
+    pub pdga_number: i32,
+    pub competition_id: i32,
+    pub competition_name: String,
+    pub start_date: NaiveDate,
+    pub price: i32,
+    /// Set by an admin instead of derived from rating and form
+    pub overridden: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct DivisionBudget {
+    pub division: Division,
+    pub budget: i32,
+    pub spent: i32,
+}
+
+/// Price of a player from their rating and the fantasy points of their latest competitions.
+///
+/// A 1050 rated player is priced at 100 from rating alone, and the average of both parts
+/// is used when the player has recent results.
+pub(crate) fn price(rating: Option<i32>, recent_points: &[i32]) -> i32 {
+    let rating_price = rating.map(|r| (r - RATING_FLOOR).max(0) / 2);
+    let form_price =
+        (!recent_points.is_empty()).then(|| recent_points.iter().sum::<i32>() / recent_points.len() as i32);
+    let price = match (rating_price, form_price) {
+        (Some(rating), Some(form)) => (rating + form) / 2,
+        (Some(price), None) | (None, Some(price)) => price,
+        (None, None) => MIN_PRICE,
+    };
+    price.max(MIN_PRICE)
+}
+
+/// Fantasy points of each player in their latest finished competitions, newest first.
+///
+/// Ties are left out, the placement of the latest round played is what counts.
+async fn recent_points(
+    db: &impl ConnectionTrait,
+    pdga_numbers: &[i32],
+) -> Result<HashMap<i32, Vec<i32>>, GenericError> {
+    let scores = PlayerRoundScore::find()
+        .filter(player_round_score::Column::PdgaNumber.is_in(pdga_numbers.to_vec()))
+        .find_also_related(Competition)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?;
+    Ok(scores
+        .into_iter()
+        .filter_map(|(score, competition)| {
+            competition
+                .filter(|c| c.status == CompetitionStatus::Finished)
+                .map(|c| (score, c))
+        })
+        .into_group_map_by(|(score, _)| score.pdga_number)
+        .into_iter()
+        .map(|(pdga_number, scores)| {
+            let points = scores
+                .into_iter()
+                .into_group_map_by(|(score, _)| score.competition_id)
+                .into_values()
+                .filter_map(|rounds| rounds.into_iter().max_by_key(|(score, _)| score.round))
+                .sorted_by_key(|(_, competition)| std::cmp::Reverse(competition.start_date))
+                .take(FORM_COMPETITIONS)
+                .map(|(score, competition)| {
+                    let level: CompetitionLevel = competition.level.into();
+                    fantasy_points(score.placement as u16, 0, level) as i32
+                })
+                .collect_vec();
+            (pdga_number, points)
+        })
+        .collect())
+}
+
+async fn derived_prices(
+    db: &impl ConnectionTrait,
+    pdga_numbers: &[i32],
+) -> Result<HashMap<i32, i32>, GenericError> {
+    let ratings: HashMap<i32, Option<i32>> = Player::find()
+        .filter(player::Column::PdgaNumber.is_in(pdga_numbers.to_vec()))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get players from database"))?
+        .into_iter()
+        .map(|p| (p.pdga_number, p.rating))
+        .collect();
+    let recent = recent_points(db, pdga_numbers).await?;
+    Ok(pdga_numbers
+        .iter()
+        .map(|p| {
+            let rating = ratings.get(p).copied().flatten();
+            let points = recent.get(p).map(Vec::as_slice).unwrap_or_default();
+            (*p, price(rating, points))
+        })
+        .collect())
+}
+
+/// Recomputes the prices of every upcoming competition in a tournament with a budget,
+/// leaving prices set by an admin alone. Prices stop changing once the competition starts.
+pub async fn price_upcoming_competitions(db: &impl ConnectionTrait) -> Result<(), GenericError> {
+    let tournaments = FantasyTournament::find()
+        .filter(
+            fantasy_tournament::Column::BudgetPerDivision
+                .is_not_null()
+                .and(fantasy_tournament::Column::ArchivedAt.is_null()),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournaments from database"))?
+        .into_iter()
+        .map(|t| t.id)
+        .collect_vec();
+    if tournaments.is_empty() {
+        return Ok(());
+    }
+    let competitions = CompetitionInFantasyTournament::find()
+        .filter(competition_in_fantasy_tournament::Column::FantasyTournamentId.is_in(tournaments.clone()))
+        .find_also_related(Competition)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competitions from database"))?
+        .into_iter()
+        .filter_map(|(_, competition)| competition)
+        .filter(|c| c.status == CompetitionStatus::NotStarted)
+        .map(|c| c.id)
+        .unique()
+        .collect_vec();
+    let picked = FantasyPick::find()
+        .filter(fantasy_pick::Column::FantasyTournamentId.is_in(tournaments))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?
+        .into_iter()
+        .map(|p| p.player)
+        .collect::<HashSet<_>>();
+
+    for competition_id in competitions {
+        let mut players = PlayerInCompetition::find()
+            .filter(player_in_competition::Column::CompetitionId.eq(competition_id))
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get players in competition from database"))?
+            .into_iter()
+            .map(|p| p.pdga_number)
+            .collect::<HashSet<_>>();
+        players.extend(picked.iter().copied());
+        let players = players.into_iter().collect_vec();
+        save_prices(db, competition_id, derived_prices(db, &players).await?).await?;
+    }
+    Ok(())
+}
+
+async fn save_prices(
+    db: &impl ConnectionTrait,
+    competition_id: i32,
+    prices: HashMap<i32, i32>,
+) -> Result<(), GenericError> {
+    let overridden = player_price::Entity::find()
+        .filter(
+            player_price::Column::CompetitionId
+                .eq(competition_id)
+                .and(player_price::Column::Overridden.eq(true)),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get prices from database"))?
+        .into_iter()
+        .map(|p| p.pdga_number)
+        .collect::<HashSet<_>>();
+    let models = prices
+        .into_iter()
+        .filter(|(pdga_number, _)| !overridden.contains(pdga_number))
+        .map(|(pdga_number, price)| player_price::ActiveModel {
+            id: NotSet,
+            pdga_number: Set(pdga_number),
+            competition_id: Set(competition_id),
+            price: Set(price),
+            overridden: Set(false),
+            updated_at: Set(Utc::now().fixed_offset()),
+        })
+        .collect_vec();
+    for chunk in models.chunks(crate::mutation::INSERT_CHUNK_SIZE) {
+        player_price::Entity::insert_many(chunk.to_vec())
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    player_price::Column::PdgaNumber,
+                    player_price::Column::CompetitionId,
+                ])
+                .update_columns([player_price::Column::Price, player_price::Column::UpdatedAt])
+                .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await
+            .map_err(|e| {
+                error!("Unable to save player prices: {:#?}", e);
+                GenericError::UnknownError("Unable to save player prices")
+            })?;
+    }
+    Ok(())
+}
+
+/// Sets the price of a player in a competition by hand, or hands it back to the derived price.
+pub async fn override_price(
+    db: &impl ConnectionTrait,
+    competition_id: i32,
+    pdga_number: i32,
+    price: Option<i32>,
+) -> Result<(), GenericError> {
+    if price.is_some_and(|p| p < 0) {
+        Err(GenericError::BadRequest("Price can't be negative"))?
+    }
+    let (price, overridden) = match price {
+        Some(price) => (price, true),
+        None => (
+            derived_prices(db, &[pdga_number])
+                .await?
+                .get(&pdga_number)
+                .copied()
+                .unwrap_or(MIN_PRICE),
+            false,
+        ),
+    };
+    player_price::Entity::insert(player_price::ActiveModel {
+        id: NotSet,
+        pdga_number: Set(pdga_number),
+        competition_id: Set(competition_id),
+        price: Set(price),
+        overridden: Set(overridden),
+        updated_at: Set(Utc::now().fixed_offset()),
+    })
+    .on_conflict(
+        sea_query::OnConflict::columns([
+            player_price::Column::PdgaNumber,
+            player_price::Column::CompetitionId,
+        ])
+        .update_columns([
+            player_price::Column::Price,
+            player_price::Column::Overridden,
+            player_price::Column::UpdatedAt,
+        ])
+        .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await
+    .map_err(|e| match e.sql_err() {
+        Some(sea_orm::SqlErr::ForeignKeyConstraintViolation(_)) => {
+            GenericError::NotFound("Player or competition not found")
+        }
+        _ => {
+            error!("Unable to save player price: {:#?}", e);
+            GenericError::UnknownError("Unable to save player price")
+        }
+    })?;
+    Ok(())
+}
+
+async fn prices_where(
+    db: &impl ConnectionTrait,
+    filter: sea_query::SimpleExpr,
+) -> Result<Vec<PlayerPrice>, GenericError> {
+    Ok(player_price::Entity::find()
+        .filter(filter)
+        .find_also_related(Competition)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get prices from database"))?
+        .into_iter()
+        .filter_map(|(price, competition)| {
+            competition.map(|c| PlayerPrice {
+                pdga_number: price.pdga_number,
+                competition_id: c.id,
+                competition_name: c.name,
+                start_date: c.start_date,
+                price: price.price,
+                overridden: price.overridden,
+            })
+        })
+        .sorted_by_key(|p| (std::cmp::Reverse(p.start_date), p.competition_id, p.pdga_number))
+        .collect())
+}
+
+/// Prices of a player over time, newest competition first.
+pub async fn price_history(
+    db: &impl ConnectionTrait,
+    pdga_number: i32,
+) -> Result<Vec<PlayerPrice>, GenericError> {
+    prices_where(db, player_price::Column::PdgaNumber.eq(pdga_number)).await
+}
+
+pub async fn competition_prices(
+    db: &impl ConnectionTrait,
+    competition_id: i32,
+) -> Result<Vec<PlayerPrice>, GenericError> {
+    prices_where(db, player_price::Column::CompetitionId.eq(competition_id)).await
+}
+
+/// Current price of each player in a tournament, from the latest of its competitions that priced them.
+/// Players that haven't been priced yet get their derived price.
+async fn current_prices(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    pdga_numbers: &[i32],
+) -> Result<HashMap<i32, i32>, GenericError> {
+    let competitions = CompetitionInFantasyTournament::find()
+        .filter(competition_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competitions from database"))?
+        .into_iter()
+        .map(|c| c.competition_id)
+        .collect_vec();
+    let mut prices: HashMap<i32, i32> = prices_where(
+        db,
+        player_price::Column::CompetitionId
+            .is_in(competitions)
+            .and(player_price::Column::PdgaNumber.is_in(pdga_numbers.to_vec())),
+    )
+    .await?
+    .into_iter()
+    .rev()
+    .map(|p| (p.pdga_number, p.price))
+    .collect();
+    let unpriced = pdga_numbers
+        .iter()
+        .copied()
+        .filter(|p| !prices.contains_key(p))
+        .collect_vec();
+    if !unpriced.is_empty() {
+        prices.extend(derived_prices(db, &unpriced).await?);
+    }
+    Ok(prices)
+}
+
+async fn budget_per_division(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<Option<i32>, GenericError> {
+    Ok(FantasyTournament::find_by_id(tournament_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament from database"))?
+        .ok_or(GenericError::NotFound("Tournament not found"))?
+        .budget_per_division)
+}
+
+async fn spent(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    tournament_id: i32,
+    division: Division,
+) -> Result<i32, GenericError> {
+    let picks = FantasyPick::find()
+        .filter(
+            fantasy_pick::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(fantasy_pick::Column::User.eq(user_id))
+                .and(fantasy_pick::Column::Division.eq(sea_orm_active_enums::Division::from(division))),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?
+        .into_iter()
+        .map(|p| p.player)
+        .collect_vec();
+    Ok(current_prices(db, tournament_id, &picks).await?.values().sum())
+}
+
+/// Refuses picks that put a user over the budget of a division, if the tournament has one.
+/// Meant to run once all pick changes are made, before committing them.
+pub async fn assure_within_budget(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    tournament_id: i32,
+    division: Division,
+) -> Result<(), GenericError> {
+    if let Some(budget) = budget_per_division(db, tournament_id).await? {
+        if spent(db, user_id, tournament_id, division).await? > budget {
+            Err(GenericError::Conflict("Picks exceed the budget of the division"))?
+        }
+    }
+    Ok(())
+}
+
+/// What a user has spent of the budget in each division, empty when the tournament has no budget.
+pub async fn budget_status(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    tournament_id: i32,
+) -> Result<Vec<DivisionBudget>, GenericError> {
+    let Some(budget) = budget_per_division(db, tournament_id).await? else {
+        return Ok(vec![]);
+    };
+    let mut divisions = Vec::new();
+    let divisions_in_tournament = crate::get_tournament_divisions(db, tournament_id)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get divisions from database"))?;
+    for division in divisions_in_tournament {
+        divisions.push(DivisionBudget {
+            division,
+            budget,
+            spent: spent(db, user_id, tournament_id, division).await?,
+        });
+    }
+    Ok(divisions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+    use sea_orm::sea_query::Expr;
+
+    #[test]
+    fn price_blends_rating_and_form() {
+        assert_eq!(price(Some(1050), &[]), 100);
+        assert_eq!(price(Some(1050), &[60, 40]), 75);
+        assert_eq!(price(None, &[30]), 30);
+        assert_eq!(price(None, &[]), MIN_PRICE);
+        assert_eq!(price(Some(800), &[]), MIN_PRICE);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn pick_changes_stay_within_budget() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        // Unrated players without results cost the minimum price
+        FantasyTournament::update_many()
+            .col_expr(
+                fantasy_tournament::Column::BudgetPerDivision,
+                Expr::value(MIN_PRICE * 3 / 2),
+            )
+            .filter(fantasy_tournament::Column::Id.eq(tournament))
+            .exec(&txn)
+            .await
+            .unwrap();
+        for player in [1, 2] {
+            test_db::player(&txn, tournament, player).await;
+        }
+        let pick = |slot, player| crate::dto::FantasyPick {
+            slot,
+            pdga_number: 99_000_000 + player,
+            name: None,
+            benched: false,
+        };
+
+        crate::dto::FantasyPick::change_or_insert_all(
+            &[pick(1, 1)],
+            &txn,
+            owner.id,
+            tournament,
+            Division::MPO,
+        )
+        .await
+        .unwrap();
+        let over_budget = crate::dto::FantasyPick::change_or_insert_all(
+            &[pick(2, 2)],
+            &txn,
+            owner.id,
+            tournament,
+            Division::MPO,
+        )
+        .await;
+        assert!(matches!(over_budget, Err(GenericError::Conflict(_))));
+    }
+}
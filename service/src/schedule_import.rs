@@ -0,0 +1,329 @@
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+use entity::prelude::*;
+use entity::*;
+use rocket::FromFormField;
+use rocket_okapi::JsonSchema;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, TransactionTrait};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::{CompetitionInfo, CompetitionLevel};
+use crate::error::GenericError;
+
+/// Most rows a single import may contain, a season rarely has more than a few dozen events
+const MAX_ROWS: usize = 200;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ScheduleEntry {
+    Competition {
+        competition_id: u32,
+        level: CompetitionLevel,
+    },
+    /// A competition PDGA hasn't published yet, see placeholders
+    Placeholder {
+        name: String,
+        date: NaiveDate,
+        level: CompetitionLevel,
+    },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, FromFormField, Default)]
+pub enum ScheduleFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct ImportedRow {
+    /// Position of the row in the import, starting at 1
+    pub row: usize,
+    pub entry: Option<ScheduleEntry>,
+    pub error: Option<GenericError>,
+}
+
+/// Nothing is imported unless every row is valid
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct ImportReport {
+    pub imported: bool,
+    pub rows: Vec<ImportedRow>,
+}
+
+/// A CSV row, with either a competition id or a name and date
+#[derive(Deserialize)]
+struct CsvRow {
+    #[serde(alias = "pdga_id")]
+    competition_id: Option<u32>,
+    name: Option<String>,
+    date: Option<NaiveDate>,
+    level: CompetitionLevel,
+}
+
+impl TryFrom<CsvRow> for ScheduleEntry {
+    type Error = GenericError;
+
+    fn try_from(row: CsvRow) -> Result<Self, Self::Error> {
+        let name = row.name.filter(|n| !n.trim().is_empty());
+        match (row.competition_id, name, row.date) {
+            (Some(competition_id), None, _) => Ok(Self::Competition {
+                competition_id,
+                level: row.level,
+            }),
+            (None, Some(name), Some(date)) => Ok(Self::Placeholder {
+                name,
+                date,
+                level: row.level,
+            }),
+            _ => Err(GenericError::BadRequest(
+                "Row needs a competition id, or a name and a date",
+            )),
+        }
+    }
+}
+
+/// Splits an import into its rows. Rows that can't be read are kept as errors so they show up in the report.
+///
+/// CSV imports need a header naming the columns `competition_id`, `name`, `date` and `level`.
+pub fn parse_schedule(
+    input: &str,
+    format: ScheduleFormat,
+) -> Result<Vec<Result<ScheduleEntry, GenericError>>, GenericError> {
+    let rows: Vec<Result<ScheduleEntry, GenericError>> = match format {
+        ScheduleFormat::Json => serde_json::from_str::<Vec<serde_json::Value>>(input)
+            .map_err(|_| GenericError::BadRequest("Schedule must be a JSON list"))?
+            .into_iter()
+            .map(|row| {
+                serde_json::from_value(row).map_err(|_| {
+                    GenericError::BadRequest(
+                        "Row needs a competition id and level, or a name, date and level",
+                    )
+                })
+            })
+            .collect(),
+        ScheduleFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .comment(Some(b'#'))
+                .from_reader(input.as_bytes());
+            let headers = reader
+                .headers()
+                .map_err(|_| GenericError::BadRequest("Schedule must start with a header row"))?;
+            if !headers.iter().any(|h| h == "level") {
+                Err(GenericError::BadRequest("Schedule is missing the level column"))?
+            }
+            reader
+                .deserialize::<CsvRow>()
+                .map(|row| {
+                    row.map_err(|_| {
+                        GenericError::BadRequest(
+                            "Row could not be read, check the competition id, date and level",
+                        )
+                    })
+                    .and_then(ScheduleEntry::try_from)
+                })
+                .collect()
+        }
+    };
+    if rows.is_empty() {
+        Err(GenericError::BadRequest("Schedule is empty"))?
+    }
+    if rows.len() > MAX_ROWS {
+        Err(GenericError::BadRequest("Schedule has too many rows"))?
+    }
+    Ok(rows)
+}
+
+/// Rows checked and fetched by [`prepare_schedule`], ready for [`import_schedule`]
+pub struct PreparedSchedule {
+    tournament_id: i32,
+    report: ImportReport,
+    to_insert: Vec<(usize, ScheduleEntry, Option<CompetitionInfo>)>,
+}
+
+/// Checks every row against the tournament and each other, and fetches the events that aren't stored yet.
+/// Nothing is written, so this should run before a transaction is opened for [`import_schedule`].
+pub async fn prepare_schedule(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    rows: Vec<Result<ScheduleEntry, GenericError>>,
+) -> Result<PreparedSchedule, GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let added: HashSet<i32> = CompetitionInFantasyTournament::find()
+        .filter(competition_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competitions from database"))?
+        .into_iter()
+        .map(|c| c.competition_id)
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut checked = Vec::with_capacity(rows.len());
+    for row in rows {
+        let row = match row {
+            Ok(ScheduleEntry::Competition { competition_id, .. }) if !seen.insert(competition_id) => {
+                Err(GenericError::Conflict("Competition is listed more than once"))
+            }
+            Ok(ScheduleEntry::Competition { competition_id, .. })
+                if added.contains(&(competition_id as i32)) =>
+            {
+                Err(GenericError::Conflict("Competition already added"))
+            }
+            Ok(ScheduleEntry::Placeholder { name, .. }) if name.trim().is_empty() => {
+                Err(GenericError::BadRequest("Placeholder name cannot be empty"))
+            }
+            row => row,
+        };
+        checked.push(row);
+    }
+
+    // Events PDGA has to be asked about, fetched before anything is written
+    let mut fetched = Vec::with_capacity(checked.len());
+    for row in &checked {
+        let info = match row {
+            Ok(ScheduleEntry::Competition { competition_id, .. }) => {
+                let stored = Competition::find_by_id(*competition_id as i32)
+                    .one(db)
+                    .await
+                    .map_err(|_| GenericError::UnknownError("Unable to get competition from database"))?
+                    .is_some();
+                if stored {
+                    None
+                } else {
+                    Some(CompetitionInfo::from_web(*competition_id).await)
+                }
+            }
+            _ => None,
+        };
+        fetched.push(info);
+    }
+
+    let mut report = ImportReport {
+        imported: false,
+        rows: Vec::with_capacity(checked.len()),
+    };
+    let mut to_insert = Vec::new();
+    for (index, (row, info)) in checked.into_iter().zip(fetched).enumerate() {
+        let (entry, error) = match (row, info) {
+            (Err(e), _) => (None, Some(e)),
+            (Ok(entry), Some(Err(e))) => (Some(entry), Some(e)),
+            (Ok(entry), info) => {
+                to_insert.push((index, entry.clone(), info.and_then(Result::ok)));
+                (Some(entry), None)
+            }
+        };
+        report.rows.push(ImportedRow {
+            row: index + 1,
+            entry,
+            error,
+        });
+    }
+    Ok(PreparedSchedule {
+        tournament_id,
+        report,
+        to_insert,
+    })
+}
+
+/// Inserts the rows of a prepared schedule, in one transaction, when none of them failed. Given a
+/// transaction, the rows are inserted in a savepoint of it.
+pub async fn import_schedule(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    prepared: PreparedSchedule,
+) -> Result<ImportReport, GenericError> {
+    let PreparedSchedule {
+        tournament_id,
+        mut report,
+        to_insert,
+    } = prepared;
+    if report.rows.iter().any(|r| r.error.is_some()) {
+        return Ok(report);
+    }
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction start failed"))?;
+    for (index, entry, info) in to_insert {
+        let inserted = match (entry, info) {
+            (ScheduleEntry::Competition { level, .. }, Some(info)) => {
+                crate::mutation::insert_fetched_competition(&txn, tournament_id as u32, &info, level).await
+            }
+            (
+                ScheduleEntry::Competition {
+                    competition_id,
+                    level,
+                },
+                None,
+            ) => {
+                crate::mutation::insert_competition_in_fantasy(
+                    &txn,
+                    tournament_id as u32,
+                    competition_id,
+                    level,
+                )
+                .await
+            }
+            (ScheduleEntry::Placeholder { name, date, level }, _) => {
+                crate::placeholders::add_placeholder(&txn, tournament_id, &name, date, level)
+                    .await
+                    .map(|_| ())
+            }
+        };
+        if let Err(e) = inserted {
+            // Dropping the transaction rolls back the rows inserted before this one
+            report.rows[index].error = Some(e);
+            return Ok(report);
+        }
+    }
+    txn.commit()
+        .await
+        .map_err(|_| GenericError::UnknownError("transaction failed"))?;
+    report.imported = true;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_rows_become_competitions_or_placeholders() {
+        let input = "competition_id,name,date,level\n\
+                     77758,,,Major\n\
+                     ,European Open,2030-07-01,ElitePlus\n\
+                     ,No date,,Elite\n\
+                     12,,,Gold\n";
+        let rows = parse_schedule(input, ScheduleFormat::Csv).unwrap();
+        assert_eq!(
+            rows[0].as_ref().unwrap(),
+            &ScheduleEntry::Competition {
+                competition_id: 77758,
+                level: CompetitionLevel::Major
+            }
+        );
+        assert_eq!(
+            rows[1].as_ref().unwrap(),
+            &ScheduleEntry::Placeholder {
+                name: "European Open".to_string(),
+                date: NaiveDate::from_ymd_opt(2030, 7, 1).unwrap(),
+                level: CompetitionLevel::ElitePlus
+            }
+        );
+        assert!(rows[2].is_err());
+        assert!(rows[3].is_err());
+    }
+
+    #[test]
+    fn json_rows_are_read_one_by_one() {
+        let input = r#"[{"competition_id": 1, "level": "Elite"}, {"name": "Open"}]"#;
+        let rows = parse_schedule(input, ScheduleFormat::Json).unwrap();
+        assert!(rows[0].is_ok());
+        assert!(rows[1].is_err());
+        assert!(parse_schedule("{}", ScheduleFormat::Json).is_err());
+        assert!(parse_schedule("[]", ScheduleFormat::Json).is_err());
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::{HashMap, HashSet};
+
+use entity::prelude::*;
+use entity::*;
+use itertools::Itertools;
+use rocket_okapi::JsonSchema;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::captains::{Captain, CaptainMultiplier};
+use crate::dto::forms::SetCaptain;
+use crate::dto::{CompetitionLevel, Division, FantasyPick};
+use crate::error::GenericError;
+use crate::query;
+use crate::refresh::score_latest_rounds;
+use crate::substitutions::{substitute, tournament_policy, LineupPick, SubstitutionPolicy};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct SimulatedPick {
+    pub slot: i32,
+    pub pdga_number: i32,
+    pub division: Division,
+    /// Whether the player has a score in the competition
+    pub played: bool,
+    /// Points of the player, multiplied if they are a counted captain
+    pub points: i32,
+    /// Whether the points count towards the total under the bench rules
+    pub counted: bool,
+    /// Whether the points were multiplied as the captain, or as the vice-captain stepping in
+    pub captain: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct LineupSimulation {
+    pub competition_id: i32,
+    pub total: i32,
+    pub picks: Vec<SimulatedPick>,
+    /// The best total the same players could have scored, ordering the slots after points
+    pub best_total: i32,
+    pub best_picks: Vec<SimulatedPick>,
+}
+
+/// Stands in for the user of a simulated lineup, which isn't tied to anyone.
+const SIMULATED_USER: i32 = 0;
+
+/// Scores a hypothetical lineup in a competition with the tournament's bench rules, without saving anything.
+pub async fn simulate_lineup(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+    picks: Vec<FantasyPick>,
+    captains: Vec<SetCaptain>,
+) -> Result<LineupSimulation, GenericError> {
+    let level: CompetitionLevel = Competition::find_by_id(competition_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to find competition"))?
+        .ok_or(GenericError::NotFound("Competition not found"))?
+        .level
+        .into();
+    let max_picks = query::max_picks(db, tournament_id).await?;
+    let bench_size = query::get_tournament_bench_size(db, tournament_id).await?;
+    let policy = tournament_policy(db, tournament_id).await?;
+    let pdga_numbers = picks.iter().map(|p| p.pdga_number).collect_vec();
+
+    let round_scores = PlayerRoundScore::find()
+        .filter(
+            player_round_score::Column::CompetitionId
+                .eq(competition_id)
+                .and(player_round_score::Column::PdgaNumber.is_in(pdga_numbers.clone())),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?;
+    // Ties are counted against the whole field, not just the simulated players
+    let field = PlayerRoundScore::find()
+        .filter(player_round_score::Column::CompetitionId.eq(competition_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?;
+    let points = score_latest_rounds(field, level);
+
+    let mut divisions: HashMap<i32, Division> = round_scores
+        .into_iter()
+        .map(|s| (s.pdga_number, s.division.into()))
+        .collect();
+    divisions.extend(
+        PlayerDivisionInFantasyTournament::find()
+            .filter(
+                player_division_in_fantasy_tournament::Column::FantasyTournamentId
+                    .eq(tournament_id)
+                    .and(player_division_in_fantasy_tournament::Column::PlayerPdgaNumber.is_in(pdga_numbers)),
+            )
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get player divisions from database"))?
+            .into_iter()
+            .map(|d| (d.player_pdga_number, d.division.into())),
+    );
+
+    let lineup = picks
+        .iter()
+        .map(|pick| SimulatedPick {
+            slot: pick.slot,
+            pdga_number: pick.pdga_number,
+            division: divisions.get(&pick.pdga_number).copied().unwrap_or_default(),
+            played: points.contains_key(&pick.pdga_number),
+            points: points.get(&pick.pdga_number).copied().unwrap_or_default(),
+            counted: false,
+            captain: false,
+        })
+        .collect_vec();
+    validate_lineup(&lineup, max_picks)?;
+    let captains = captains
+        .into_iter()
+        .map(|c| Captain {
+            user: SIMULATED_USER,
+            division: c.division,
+            captain: c.captain,
+            vice_captain: c.vice_captain,
+        })
+        .collect_vec();
+    let captains =
+        CaptainMultiplier::with_captains(db, tournament_id, competition_id, captains, &points).await?;
+
+    let picks = apply_captains(
+        apply_bench_rules(lineup.clone(), max_picks, bench_size, policy),
+        &captains,
+    );
+    let best_picks = apply_captains(
+        apply_bench_rules(best_lineup(lineup), max_picks, bench_size, policy),
+        &captains,
+    );
+    Ok(LineupSimulation {
+        competition_id,
+        total: total(&picks),
+        picks,
+        best_total: total(&best_picks),
+        best_picks,
+    })
+}
+
+fn validate_lineup(lineup: &[SimulatedPick], max_picks: i32) -> Result<(), GenericError> {
+    if lineup.iter().map(|p| p.pdga_number).unique().count() != lineup.len() {
+        return Err(GenericError::BadRequest("A player can only be picked once"));
+    }
+    if lineup.iter().any(|p| p.slot < 1 || p.slot > max_picks) {
+        return Err(GenericError::BadRequest("Slot is outside of the allowed picks"));
+    }
+    let mut slots = HashSet::new();
+    if !lineup.iter().all(|p| slots.insert((p.division as u8, p.slot))) {
+        return Err(GenericError::BadRequest(
+            "Two players share a slot in the same division",
+        ));
+    }
+    Ok(())
+}
+
+/// Marks which picks count, substituting bench players in for starters without a score
+/// the same way the saved user scores do.
+fn apply_bench_rules(
+    lineup: Vec<SimulatedPick>,
+    max_picks: i32,
+    bench_size: i32,
+    policy: SubstitutionPolicy,
+) -> Vec<SimulatedPick> {
+    let picks = lineup
+        .iter()
+        .map(|p| LineupPick {
+            user: SIMULATED_USER,
+            pdga_number: p.pdga_number,
+            division: p.division,
+            slot: p.slot,
+            benched: p.slot > max_picks - bench_size,
+        })
+        .collect_vec();
+    let did_not_start = lineup
+        .iter()
+        .filter(|p| !p.played)
+        .map(|p| p.pdga_number)
+        .collect::<HashSet<_>>();
+    let counted = substitute(picks, &did_not_start, policy)
+        .active
+        .into_iter()
+        .map(|p| p.pdga_number)
+        .collect::<HashSet<_>>();
+    lineup
+        .into_iter()
+        .map(|p| SimulatedPick {
+            counted: counted.contains(&p.pdga_number),
+            ..p
+        })
+        .sorted_by_key(|p| (p.division as u8, p.slot))
+        .collect()
+}
+
+/// Multiplies the points of the counted captain, the same way the saved user scores are.
+fn apply_captains(picks: Vec<SimulatedPick>, captains: &CaptainMultiplier) -> Vec<SimulatedPick> {
+    let multiplied = captains.multiplied(
+        &picks
+            .iter()
+            .filter(|p| p.counted)
+            .map(|p| (SIMULATED_USER, p.pdga_number))
+            .collect_vec(),
+    );
+    picks
+        .into_iter()
+        .map(|p| {
+            if multiplied.contains(&(SIMULATED_USER, p.pdga_number)) {
+                SimulatedPick {
+                    points: captains.apply(p.points),
+                    captain: true,
+                    ..p
+                }
+            } else {
+                p
+            }
+        })
+        .collect()
+}
+
+/// The same players with the slots of each division handed out by points, best first.
+fn best_lineup(lineup: Vec<SimulatedPick>) -> Vec<SimulatedPick> {
+    lineup
+        .into_iter()
+        .into_group_map_by(|p| p.division as u8)
+        .into_values()
+        .flat_map(|picks| {
+            let mut slots = picks.iter().map(|p| p.slot).sorted().collect_vec().into_iter();
+            picks
+                .into_iter()
+                .sorted_by_key(|p| (std::cmp::Reverse(p.points), p.slot))
+                .map(|p| SimulatedPick {
+                    slot: slots.next().unwrap_or(p.slot),
+                    ..p
+                })
+                .collect_vec()
+        })
+        .collect()
+}
+
+fn total(picks: &[SimulatedPick]) -> i32 {
+    picks.iter().filter(|p| p.counted).map(|p| p.points).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pick(slot: i32, pdga_number: i32, points: i32) -> SimulatedPick {
+        SimulatedPick {
+            slot,
+            pdga_number,
+            division: Division::MPO,
+            played: points > 0,
+            points,
+            counted: false,
+            captain: false,
+        }
+    }
+
+    #[test]
+    fn bench_only_counts_when_a_starter_is_missing() {
+        let all_played = apply_bench_rules(
+            vec![pick(1, 1, 50), pick(2, 2, 60), pick(3, 3, 90)],
+            3,
+            1,
+            SubstitutionPolicy::SameDivision,
+        );
+        assert_eq!(total(&all_played), 110);
+
+        let starter_missing = apply_bench_rules(
+            vec![pick(1, 1, 50), pick(2, 2, 0), pick(3, 3, 90)],
+            3,
+            1,
+            SubstitutionPolicy::SameDivision,
+        );
+        assert_eq!(total(&starter_missing), 140);
+    }
+
+    #[test]
+    fn best_lineup_benches_the_weakest() {
+        let best = apply_bench_rules(
+            best_lineup(vec![pick(1, 1, 50), pick(2, 2, 60), pick(3, 3, 90)]),
+            3,
+            1,
+            SubstitutionPolicy::SameDivision,
+        );
+        assert_eq!(total(&best), 150);
+        assert_eq!(best.iter().find(|p| p.pdga_number == 1).unwrap().slot, 3);
+    }
+
+    #[test]
+    fn counted_captains_are_multiplied() {
+        let captains = vec![Captain {
+            user: SIMULATED_USER,
+            division: Division::MPO,
+            captain: 2,
+            vice_captain: Some(1),
+        }];
+        let lineup = vec![pick(1, 1, 50), pick(2, 2, 60), pick(3, 3, 90)];
+
+        let captained = apply_captains(
+            apply_bench_rules(lineup.clone(), 3, 1, SubstitutionPolicy::SameDivision),
+            &CaptainMultiplier::new(200, true, captains.clone(), HashSet::new()),
+        );
+        assert_eq!(total(&captained), 170);
+        assert!(captained.iter().find(|p| p.pdga_number == 2).unwrap().captain);
+
+        // The vice-captain steps in for a captain that didn't start
+        let captained = apply_captains(
+            apply_bench_rules(lineup, 3, 1, SubstitutionPolicy::SameDivision),
+            &CaptainMultiplier::new(200, true, captains, HashSet::from([2])),
+        );
+        assert_eq!(total(&captained), 160);
+    }
+
+    #[test]
+    fn shared_slots_are_refused() {
+        assert!(validate_lineup(&[pick(1, 1, 50), pick(1, 2, 60)], 3).is_err());
+        assert!(validate_lineup(&[pick(1, 1, 50), pick(4, 2, 60)], 3).is_err());
+        assert!(validate_lineup(&[pick(1, 1, 50), pick(2, 1, 60)], 3).is_err());
+        assert!(validate_lineup(&[pick(1, 1, 50), pick(2, 2, 60)], 3).is_ok());
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use entity::prelude::*;
+use entity::sea_orm_active_enums::WithdrawalReason;
+use entity::*;
+use itertools::Itertools;
+use log::error;
+use rocket_okapi::JsonSchema;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, NotSet, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::{Division, UserScore};
+use crate::error::GenericError;
+
+/// Which bench players may replace a starter that didn't start
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SubstitutionPolicy {
+    Off,
+    #[default]
+    SameDivision,
+    /// Bench players of the same division are still preferred
+    AnyDivision,
+}
+
+impl From<sea_orm_active_enums::SubstitutionPolicy> for SubstitutionPolicy {
+    fn from(policy: sea_orm_active_enums::SubstitutionPolicy) -> Self {
+        match policy {
+            sea_orm_active_enums::SubstitutionPolicy::Off => SubstitutionPolicy::Off,
+            sea_orm_active_enums::SubstitutionPolicy::SameDivision => SubstitutionPolicy::SameDivision,
+            sea_orm_active_enums::SubstitutionPolicy::AnyDivision => SubstitutionPolicy::AnyDivision,
+        }
+    }
+}
+
+impl From<SubstitutionPolicy> for sea_orm_active_enums::SubstitutionPolicy {
+    fn from(policy: SubstitutionPolicy) -> Self {
+        match policy {
+            SubstitutionPolicy::Off => sea_orm_active_enums::SubstitutionPolicy::Off,
+            SubstitutionPolicy::SameDivision => sea_orm_active_enums::SubstitutionPolicy::SameDivision,
+            SubstitutionPolicy::AnyDivision => sea_orm_active_enums::SubstitutionPolicy::AnyDivision,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Substitution {
+    pub user: i32,
+    pub replaced_player: i32,
+    pub substitute_player: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LineupPick {
+    pub user: i32,
+    pub pdga_number: i32,
+    pub division: Division,
+    pub slot: i32,
+    pub benched: bool,
+}
+
+/// The picks that play for their users in a competition, after substitutions
+#[derive(Debug)]
+pub(crate) struct Lineup {
+    pub active: Vec<LineupPick>,
+    pub substitutions: Vec<Substitution>,
+}
+
+impl Lineup {
+    /// Scores of the active picks that have points in the competition.
+    pub(crate) fn user_scores(
+        &self,
+        tournament_id: i32,
+        competition_id: i32,
+        player_scores: &HashMap<i32, i32>,
+    ) -> Vec<UserScore> {
+        self.active
+            .iter()
+            .filter_map(|pick| {
+                player_scores.get(&pick.pdga_number).map(|score| UserScore {
+                    user: pick.user,
+                    score: *score,
+                    competition_id: competition_id as u32,
+                    pdga_num: pick.pdga_number as u32,
+                    fantasy_tournament_id: tournament_id as u32,
+                    benched: pick.benched,
+                    slot: pick.slot as u8,
+                    division: pick.division,
+                })
+            })
+            .sorted_by_key(|s| (s.user, s.pdga_num))
+            .collect()
+    }
+}
+
+/// Replaces every starter that didn't start with the first eligible bench player of the same user.
+///
+/// Bench players are tried in slot order, those of the starter's division first,
+/// and each of them can only come in once.
+pub(crate) fn substitute(
+    picks: Vec<LineupPick>,
+    did_not_start: &HashSet<i32>,
+    policy: SubstitutionPolicy,
+) -> Lineup {
+    let mut active = Vec::new();
+    let mut substitutions = Vec::new();
+    for (user, picks) in picks
+        .into_iter()
+        .into_group_map_by(|p| p.user)
+        .into_iter()
+        .sorted_by_key(|(u, _)| *u)
+    {
+        let (bench, starters): (Vec<_>, Vec<_>) = picks.into_iter().partition(|p| p.benched);
+        let mut available = bench
+            .into_iter()
+            .filter(|p| !did_not_start.contains(&p.pdga_number))
+            .collect_vec();
+        for starter in starters.into_iter().sorted_by_key(|p| (p.division as u8, p.slot)) {
+            if !did_not_start.contains(&starter.pdga_number) {
+                active.push(starter);
+                continue;
+            }
+            let substitute = available
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| match policy {
+                    SubstitutionPolicy::Off => false,
+                    SubstitutionPolicy::SameDivision => p.division == starter.division,
+                    SubstitutionPolicy::AnyDivision => true,
+                })
+                .min_by_key(|(_, p)| (p.division != starter.division, p.slot, p.division as u8))
+                .map(|(i, _)| i);
+            if let Some(i) = substitute {
+                let substitute = available.remove(i);
+                substitutions.push(Substitution {
+                    user,
+                    replaced_player: starter.pdga_number,
+                    substitute_player: substitute.pdga_number,
+                });
+                active.push(substitute);
+            }
+        }
+    }
+    Lineup {
+        active,
+        substitutions,
+    }
+}
+
+pub(crate) async fn tournament_policy(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<SubstitutionPolicy, GenericError> {
+    Ok(FantasyTournament::find_by_id(tournament_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament from database"))?
+        .ok_or(GenericError::NotFound("Tournament not found"))?
+        .substitution_policy
+        .into())
+}
+
+/// Picked players that didn't start the competition, either by being left out of the field
+/// or by being marked as not starting without having played a round.
+async fn did_not_start(
+    db: &impl ConnectionTrait,
+    competition_id: i32,
+    picked: &[i32],
+) -> Result<HashSet<i32>, GenericError> {
+    let field = PlayerInCompetition::find()
+        .filter(player_in_competition::Column::CompetitionId.eq(competition_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get players in competition from database"))?
+        .into_iter()
+        .map(|p| p.pdga_number)
+        .collect::<HashSet<_>>();
+    // The field isn't known until the competition has been fetched once
+    if field.is_empty() {
+        return Ok(HashSet::new());
+    }
+    let played = PlayerRoundScore::find()
+        .filter(player_round_score::Column::CompetitionId.eq(competition_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get round scores from database"))?
+        .into_iter()
+        .map(|s| s.pdga_number)
+        .collect::<HashSet<_>>();
+    let withdrawn = PlayerWithdrawal::find()
+        .filter(
+            player_withdrawal::Column::CompetitionId
+                .eq(competition_id)
+                .and(player_withdrawal::Column::Reason.eq(WithdrawalReason::DidNotStart)),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get withdrawals from database"))?
+        .into_iter()
+        .map(|w| w.pdga_number)
+        .collect::<HashSet<_>>();
+
+    Ok(picked
+        .iter()
+        .copied()
+        .filter(|p| !field.contains(p) || (withdrawn.contains(p) && !played.contains(p)))
+        .collect())
+}
+
+/// The lineups of every user in a tournament for a competition, with the tournament's substitution policy.
+pub(crate) async fn lineup(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+) -> Result<Lineup, GenericError> {
+    let policy = tournament_policy(db, tournament_id).await?;
+    let picks = FantasyPick::find()
+        .filter(fantasy_pick::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?
+        .into_iter()
+        .map(|p| LineupPick {
+            user: p.user,
+            pdga_number: p.player,
+            division: p.division.into(),
+            slot: p.pick_number,
+            benched: p.benched,
+        })
+        .collect_vec();
+    let did_not_start = did_not_start(
+        db,
+        competition_id,
+        &picks.iter().map(|p| p.pdga_number).collect_vec(),
+    )
+    .await?;
+    Ok(substitute(picks, &did_not_start, policy))
+}
+
+/// Replaces the recorded substitutions, keeping those that still apply untouched.
+pub(crate) async fn save_substitutions(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+    substitutions: &[Substitution],
+) -> Result<(), GenericError> {
+    let filter = pick_substitution::Column::FantasyTournamentId
+        .eq(tournament_id)
+        .and(pick_substitution::Column::CompetitionId.eq(competition_id));
+    let recorded = PickSubstitution::find()
+        .filter(filter.clone())
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get substitutions from database"))?;
+
+    let outdated = recorded
+        .iter()
+        .filter(|r| !substitutions.contains(&Substitution::from((*r).clone())))
+        .map(|r| r.id)
+        .collect_vec();
+    if !outdated.is_empty() {
+        PickSubstitution::delete_many()
+            .filter(pick_substitution::Column::Id.is_in(outdated))
+            .exec(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to delete substitutions"))?;
+    }
+
+    let recorded = recorded.into_iter().map(Substitution::from).collect_vec();
+    let new = substitutions
+        .iter()
+        .filter(|s| !recorded.contains(s))
+        .map(|s| pick_substitution::ActiveModel {
+            id: NotSet,
+            fantasy_tournament_id: Set(tournament_id),
+            competition_id: Set(competition_id),
+            user_id: Set(s.user),
+            replaced_player: Set(s.replaced_player),
+            substitute_player: Set(s.substitute_player),
+            created_at: Set(Utc::now().fixed_offset()),
+        })
+        .collect_vec();
+    if !new.is_empty() {
+        PickSubstitution::insert_many(new)
+            .exec_without_returning(db)
+            .await
+            .map_err(|e| {
+                error!("Unable to insert substitutions into database: {:#?}", e);
+                GenericError::UnknownError("Unable to insert substitutions into database")
+            })?;
+    }
+    Ok(())
+}
+
+impl From<pick_substitution::Model> for Substitution {
+    fn from(model: pick_substitution::Model) -> Self {
+        Self {
+            user: model.user_id,
+            replaced_player: model.replaced_player,
+            substitute_player: model.substitute_player,
+        }
+    }
+}
+
+pub async fn get_substitutions(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+) -> Result<Vec<Substitution>, GenericError> {
+    Ok(PickSubstitution::find()
+        .filter(
+            pick_substitution::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(pick_substitution::Column::CompetitionId.eq(competition_id)),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get substitutions from database"))?
+        .into_iter()
+        .map(Substitution::from)
+        .sorted_by_key(|s| (s.user, s.replaced_player))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pick(pdga_number: i32, division: Division, slot: i32, benched: bool) -> LineupPick {
+        LineupPick {
+            user: 1,
+            pdga_number,
+            division,
+            slot,
+            benched,
+        }
+    }
+
+    fn picks() -> Vec<LineupPick> {
+        vec![
+            pick(1, Division::MPO, 1, false),
+            pick(2, Division::MPO, 2, false),
+            pick(3, Division::FPO, 1, false),
+            pick(4, Division::MPO, 3, true),
+            pick(5, Division::FPO, 2, true),
+        ]
+    }
+
+    fn active(lineup: &Lineup) -> Vec<i32> {
+        lineup.active.iter().map(|p| p.pdga_number).sorted().collect()
+    }
+
+    #[test]
+    fn same_division_substitutes_only_within_the_division() {
+        let lineup = substitute(picks(), &HashSet::from([1, 4]), SubstitutionPolicy::SameDivision);
+        assert_eq!(active(&lineup), vec![2, 3]);
+        assert!(lineup.substitutions.is_empty());
+
+        let lineup = substitute(picks(), &HashSet::from([1]), SubstitutionPolicy::SameDivision);
+        assert_eq!(active(&lineup), vec![2, 3, 4]);
+        assert_eq!(
+            lineup.substitutions,
+            vec![Substitution {
+                user: 1,
+                replaced_player: 1,
+                substitute_player: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn any_division_prefers_the_same_division() {
+        let lineup = substitute(picks(), &HashSet::from([1, 2]), SubstitutionPolicy::AnyDivision);
+        assert_eq!(active(&lineup), vec![3, 4, 5]);
+        assert_eq!(lineup.substitutions[0].substitute_player, 4);
+        assert_eq!(lineup.substitutions[1].substitute_player, 5);
+    }
+
+    #[test]
+    fn nobody_is_substituted_when_off() {
+        let lineup = substitute(picks(), &HashSet::from([1]), SubstitutionPolicy::Off);
+        assert_eq!(active(&lineup), vec![2, 3]);
+        assert!(lineup.substitutions.is_empty());
+    }
+}
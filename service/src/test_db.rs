@@ -0,0 +1,125 @@
+//! Helpers for tests that need a database.
+//!
+//! Like the integration tests, they use the migrated database at `DEV_DATABASE_URL`. Every test runs
+//! in a transaction that is rolled back when it is dropped, so nothing is left behind. The tests are
+//! ignored by default, run them with `cargo test -- --ignored`.
+use entity::prelude::*;
+use entity::sea_orm_active_enums::{Division, FantasyTournamentInvitationStatus, TournamentRole};
+use entity::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    ConnectOptions, ConnectionTrait, Database, DatabaseTransaction, EntityTrait, TransactionTrait,
+};
+
+pub(crate) async fn begin() -> DatabaseTransaction {
+    dotenvy::dotenv().ok();
+    let url = std::env::var("DEV_DATABASE_URL").expect("DEV_DATABASE_URL not set");
+    let mut options = ConnectOptions::new(url);
+    options.sqlx_logging(false);
+    let db = Database::connect(options)
+        .await
+        .expect("DEV_DATABASE_URL should point to a migrated database");
+    db.begin().await.expect("transaction should start")
+}
+
+pub(crate) async fn user(db: &impl ConnectionTrait, name: &str) -> user::Model {
+    let id = User::insert(user::ActiveModel {
+        id: NotSet,
+        name: Set(format!("test-{name}-{}", crate::visibility::new_slug())),
+        admin: Set(false),
+        moderator: Set(false),
+    })
+    .exec(db)
+    .await
+    .expect("user should be inserted")
+    .last_insert_id;
+    User::find_by_id(id).one(db).await.unwrap().unwrap()
+}
+
+/// A tournament with `owner` as its accepted owner.
+pub(crate) async fn tournament(db: &impl ConnectionTrait, owner: i32) -> i32 {
+    let tournament = crate::dto::CreateTournament {
+        name: format!("test-{}", crate::visibility::new_slug()),
+        max_picks_per_user: None,
+        divisions: vec![],
+        amount_in_bench: None,
+    };
+    let id = FantasyTournament::insert(tournament.into_active_model(owner))
+        .exec(db)
+        .await
+        .expect("tournament should be inserted")
+        .last_insert_id;
+    member(
+        db,
+        id,
+        owner,
+        FantasyTournamentInvitationStatus::Accepted,
+        TournamentRole::Owner,
+    )
+    .await;
+    id
+}
+
+pub(crate) async fn member(
+    db: &impl ConnectionTrait,
+    fantasy_tournament_id: i32,
+    user_id: i32,
+    status: FantasyTournamentInvitationStatus,
+    role: TournamentRole,
+) {
+    UserInFantasyTournament::insert(user_in_fantasy_tournament::ActiveModel {
+        id: NotSet,
+        user_id: Set(user_id),
+        fantasy_tournament_id: Set(fantasy_tournament_id),
+        invitation_status: Set(status),
+        role: Set(role),
+    })
+    .exec(db)
+    .await
+    .expect("membership should be inserted");
+}
+
+/// A player in the MPO division of the tournament, PDGA numbers from 99 000 000 are never real.
+pub(crate) async fn player(db: &impl ConnectionTrait, fantasy_tournament_id: i32, pdga_number: i32) {
+    Player::insert(player::ActiveModel {
+        pdga_number: Set(99_000_000 + pdga_number),
+        first_name: Set("Test".to_string()),
+        last_name: Set(format!("Player {pdga_number}")),
+        avatar: Set(None),
+        country: Set(None),
+        rating: Set(None),
+    })
+    .exec_without_returning(db)
+    .await
+    .expect("player should be inserted");
+    PlayerDivisionInFantasyTournament::insert(player_division_in_fantasy_tournament::ActiveModel {
+        player_pdga_number: Set(99_000_000 + pdga_number),
+        fantasy_tournament_id: Set(fantasy_tournament_id),
+        division: Set(Division::Mpo),
+    })
+    .exec_without_returning(db)
+    .await
+    .expect("player division should be inserted");
+}
+
+/// A pick of a player made with [`player`], in the MPO division.
+pub(crate) async fn pick(
+    db: &impl ConnectionTrait,
+    fantasy_tournament_id: i32,
+    user_id: i32,
+    pdga_number: i32,
+    slot: i32,
+) {
+    FantasyPick::insert(fantasy_pick::ActiveModel {
+        id: NotSet,
+        user: Set(user_id),
+        player: Set(99_000_000 + pdga_number),
+        fantasy_tournament_id: Set(fantasy_tournament_id),
+        pick_number: Set(slot),
+        division: Set(Division::Mpo),
+        benched: Set(false),
+    })
+    .exec(db)
+    .await
+    .expect("pick should be inserted");
+}
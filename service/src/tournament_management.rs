@@ -0,0 +1,653 @@
+use chrono::Utc;
+use entity::prelude::*;
+use entity::*;
+use itertools::Itertools;
+use log::error;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, NotSet,
+    QueryFilter, SqlErr,
+};
+
+use crate::dto::forms::UpdateTournamentSettings;
+use crate::dto::Division;
+use crate::error::{GenericError, TournamentError};
+use crate::query::get_fantasy_tournament_model;
+use crate::roles::TournamentRole;
+use crate::visibility::Visibility;
+
+async fn get_tournament(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<fantasy_tournament::Model, GenericError> {
+    get_fantasy_tournament_model(db, tournament_id)
+        .await?
+        .ok_or(GenericError::NotFound("Tournament not found"))
+}
+
+/// Refuses changes to an archived tournament, which is read-only until it is unarchived.
+pub async fn assure_not_archived(db: &impl ConnectionTrait, tournament_id: i32) -> Result<(), GenericError> {
+    if get_tournament(db, tournament_id).await?.archived_at.is_some() {
+        Err(GenericError::Conflict("Tournament is archived"))?
+    }
+    Ok(())
+}
+
+/// Changes the settings of a tournament, making sure existing picks still fit.
+///
+/// Picks above the new max or in removed divisions are refused with a conflict, unless
+/// `trim_excess` is set, in which case they are removed and logged as removals.
+/// Should be run inside a transaction.
+pub async fn update_settings(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    settings: UpdateTournamentSettings,
+) -> Result<(), GenericError> {
+    assure_not_archived(db, tournament_id).await?;
+    let tournament = get_tournament(db, tournament_id).await?;
+
+    let max_picks = settings
+        .max_picks_per_user
+        .unwrap_or(tournament.max_picks_per_user);
+    let bench_size = settings.bench_size.unwrap_or(tournament.bench_size);
+    if max_picks < 1 {
+        Err(GenericError::BadRequest("Max picks must be at least 1"))?
+    }
+    if bench_size < 0 || bench_size >= max_picks {
+        Err(GenericError::BadRequest(
+            "Bench size must be positive and smaller than max picks",
+        ))?
+    }
+
+    let picks = FantasyPick::find()
+        .filter(fantasy_pick::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?;
+
+    let removed_divisions = if let Some(divisions) = &settings.divisions {
+        if divisions.is_empty() {
+            Err(GenericError::BadRequest("Tournament needs at least one division"))?
+        }
+        update_divisions(db, tournament_id, divisions).await?
+    } else {
+        Vec::new()
+    };
+
+    let excess_picks = picks
+        .into_iter()
+        .filter(|p| p.pick_number > max_picks || removed_divisions.contains(&p.division.clone().into()))
+        .collect_vec();
+    if !excess_picks.is_empty() {
+        if !settings.trim_excess {
+            Err(GenericError::Conflict(
+                "Existing picks do not fit the new settings, set trim_excess to remove them",
+            ))?
+        }
+        remove_picks(db, excess_picks).await?;
+    }
+
+    let tournament_slug = tournament.public_slug.clone();
+    let mut active = tournament.into_active_model();
+    if let Some(name) = settings.name {
+        let name = name.trim();
+        if name.is_empty() {
+            Err(GenericError::BadRequest("Tournament name cannot be empty"))?
+        }
+        active.name = Set(name.to_string());
+    }
+    if let Some(join_requests_enabled) = settings.join_requests_enabled {
+        active.join_requests_enabled = Set(join_requests_enabled);
+    }
+    if let Some(percent) = settings.captain_multiplier_percent {
+        if !(100..=300).contains(&percent) {
+            Err(GenericError::BadRequest(
+                "Captain multiplier must be between 100 and 300 percent",
+            ))?
+        }
+        active.captain_multiplier_percent = Set(percent);
+    }
+    if let Some(vice_captains_enabled) = settings.vice_captains_enabled {
+        active.vice_captains_enabled = Set(vice_captains_enabled);
+    }
+    if let Some(policy) = settings.substitution_policy {
+        active.substitution_policy = Set(policy.into());
+    }
+    if let Some(rules) = &settings.roster_rules {
+        rules.validate(max_picks)?;
+        rules.apply(&mut active);
+    }
+    if let Some(budget) = settings.budget_per_division {
+        if budget < 0 {
+            Err(GenericError::BadRequest("Budget can't be negative"))?
+        }
+        active.budget_per_division = Set((budget > 0).then_some(budget));
+    }
+    if let Some(visibility) = settings.visibility {
+        if visibility == Visibility::Public && tournament_slug.is_none() {
+            active.public_slug = Set(Some(crate::visibility::new_slug()));
+        }
+        active.visibility = Set(visibility.into());
+    }
+    active.max_picks_per_user = Set(max_picks);
+    active.bench_size = Set(bench_size);
+    active.save(db).await.map_err(|e| match e.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => {
+            TournamentError::TournamentNameConflict("Tournament name already taken").into()
+        }
+        _ => {
+            error!("Unable to update fantasy tournament: {:#?}", e);
+            GenericError::UnknownError("Unable to update fantasy tournament")
+        }
+    })?;
+
+    FantasyPick::update_many()
+        .col_expr(
+            fantasy_pick::Column::Benched,
+            Expr::col(fantasy_pick::Column::PickNumber).gt(max_picks - bench_size),
+        )
+        .filter(fantasy_pick::Column::FantasyTournamentId.eq(tournament_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to update benched picks"))?;
+    Ok(())
+}
+
+/// Replaces the divisions of a tournament, returning the divisions that were removed.
+async fn update_divisions(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    divisions: &[Division],
+) -> Result<Vec<Division>, GenericError> {
+    let current = FantasyTournamentDivision::find()
+        .filter(fantasy_tournament_division::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get divisions from database"))?;
+
+    let mut removed = Vec::new();
+    for division in &current {
+        let as_dto: Division = division.division.clone().into();
+        if !divisions.contains(&as_dto) {
+            FantasyTournamentDivision::delete_by_id(division.id)
+                .exec(db)
+                .await
+                .map_err(|_| GenericError::UnknownError("Unable to remove division"))?;
+            removed.push(as_dto);
+        }
+    }
+    let mut existing = current
+        .into_iter()
+        .map(|d| Division::from(d.division))
+        .collect_vec();
+    for division in divisions {
+        if !existing.contains(division) {
+            fantasy_tournament_division::ActiveModel {
+                id: NotSet,
+                fantasy_tournament_id: Set(tournament_id),
+                division: Set(division.into()),
+            }
+            .insert(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to add division"))?;
+            existing.push(*division);
+        }
+    }
+    Ok(removed)
+}
+
+/// Removes picks, logging each one as a removal in the trade log.
+async fn remove_picks(
+    db: &impl ConnectionTrait,
+    picks: Vec<fantasy_pick::Model>,
+) -> Result<(), GenericError> {
+    let now = Utc::now().fixed_offset();
+    for pick in picks {
+        let trade = player_trade::ActiveModel {
+            id: NotSet,
+            user: Set(pick.user),
+            player: Set(pick.player),
+            slot: Set(pick.pick_number),
+            fantasy_tournament_id: Set(pick.fantasy_tournament_id),
+            timestamp: Set(now),
+            is_local_swap: Set(false),
+            other_player: Set(None),
+            other_slot: Set(None),
+            is_removal: Set(true),
+            is_revert: Set(false),
+        }
+        .insert(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to log removed pick"))?;
+        crate::webhooks::trade_logged(db, &trade).await?;
+        FantasyPick::delete_by_id(pick.id)
+            .exec(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to remove pick"))?;
+    }
+    Ok(())
+}
+
+/// Archives or unarchives a tournament. Archived tournaments are read-only and no longer get scored.
+pub async fn set_archived(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    archived: bool,
+) -> Result<(), GenericError> {
+    let mut tournament = get_tournament(db, tournament_id).await?.into_active_model();
+    tournament.archived_at = Set(archived.then(|| Utc::now().fixed_offset()));
+    tournament
+        .save(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to update fantasy tournament"))?;
+    Ok(())
+}
+
+/// Deletes a tournament and everything belonging to it. Should be run inside a transaction.
+pub async fn delete_tournament(db: &impl ConnectionTrait, tournament_id: i32) -> Result<(), GenericError> {
+    get_tournament(db, tournament_id).await?;
+
+    let trade_ids = PlayerTrade::find()
+        .filter(player_trade::Column::FantasyTournamentId.eq(tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get trades from database"))?
+        .into_iter()
+        .map(|t| t.id)
+        .collect_vec();
+    PlayerTradeRevert::delete_many()
+        .filter(player_trade_revert::Column::TradeId.is_in(trade_ids))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete reverted trades"))?;
+    PlayerTrade::delete_many()
+        .filter(player_trade::Column::FantasyTournamentId.eq(tournament_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete trades"))?;
+    FantasyPick::delete_many()
+        .filter(fantasy_pick::Column::FantasyTournamentId.eq(tournament_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete picks"))?;
+    UserCompetitionScoreInFantasyTournament::delete_many()
+        .filter(user_competition_score_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete user scores"))?;
+    CompetitionInFantasyTournament::delete_many()
+        .filter(competition_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete competitions"))?;
+    PhantomCompetitionInFantasyTournament::delete_many()
+        .filter(phantom_competition_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete phantom competitions"))?;
+    FantasyTournamentDivision::delete_many()
+        .filter(fantasy_tournament_division::Column::FantasyTournamentId.eq(tournament_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete divisions"))?;
+    crate::webhooks::delete_webhooks_in_tournament(db, tournament_id).await?;
+    Notification::delete_many()
+        .filter(notification::Column::FantasyTournamentId.eq(tournament_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete notifications"))?;
+    FantasyTournamentInviteCode::delete_many()
+        .filter(fantasy_tournament_invite_code::Column::FantasyTournamentId.eq(tournament_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete invite codes"))?;
+    UserInFantasyTournament::delete_many()
+        .filter(user_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete participants"))?;
+    move_player_divisions(db, tournament_id).await?;
+
+    FantasyTournament::delete_by_id(tournament_id)
+        .exec(db)
+        .await
+        .map_err(|e| {
+            error!("Unable to delete fantasy tournament: {:#?}", e);
+            GenericError::UnknownError("Unable to delete fantasy tournament")
+        })?;
+    Ok(())
+}
+
+/// Player divisions are shared between all tournaments but still reference one, so they are handed
+/// over to another tournament that picked the player or has them in one of its competitions.
+/// Divisions of players no other tournament uses are deleted.
+async fn move_player_divisions(db: &impl ConnectionTrait, tournament_id: i32) -> Result<(), GenericError> {
+    let filter = player_division_in_fantasy_tournament::Column::FantasyTournamentId.eq(tournament_id);
+    PlayerDivisionInFantasyTournament::update_many()
+        .col_expr(
+            player_division_in_fantasy_tournament::Column::FantasyTournamentId,
+            Expr::cust(
+                "COALESCE(\
+                 (SELECT MIN(fantasy_pick.fantasy_tournament_id) FROM fantasy_pick \
+                 WHERE fantasy_pick.player = player_division_in_fantasy_tournament.player_pdga_number \
+                 AND fantasy_pick.fantasy_tournament_id <> player_division_in_fantasy_tournament.fantasy_tournament_id), \
+                 (SELECT MIN(competition_in_fantasy_tournament.fantasy_tournament_id) FROM player_in_competition \
+                 JOIN competition_in_fantasy_tournament \
+                 ON competition_in_fantasy_tournament.competition_id = player_in_competition.competition_id \
+                 WHERE player_in_competition.pdga_number = player_division_in_fantasy_tournament.player_pdga_number \
+                 AND competition_in_fantasy_tournament.fantasy_tournament_id \
+                 <> player_division_in_fantasy_tournament.fantasy_tournament_id), \
+                 player_division_in_fantasy_tournament.fantasy_tournament_id)",
+            ),
+        )
+        .filter(filter.clone())
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to move player divisions"))?;
+    PlayerDivisionInFantasyTournament::delete_many()
+        .filter(filter)
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete player divisions"))?;
+    Ok(())
+}
+
+/// Removes a competition from a tournament together with the scores it gave.
+pub async fn remove_competition(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    competition_id: i32,
+) -> Result<(), GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let deleted = CompetitionInFantasyTournament::delete_many()
+        .filter(
+            competition_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(competition_in_fantasy_tournament::Column::CompetitionId.eq(competition_id)),
+        )
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to remove competition"))?;
+    if deleted.rows_affected == 0 {
+        Err(GenericError::NotFound("Competition not found in tournament"))?
+    }
+    UserCompetitionScoreInFantasyTournament::delete_many()
+        .filter(
+            user_competition_score_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(user_competition_score_in_fantasy_tournament::Column::CompetitionId.eq(competition_id)),
+        )
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to remove competition scores"))?;
+    Ok(())
+}
+
+/// Removes a user on behalf of someone managing the tournament. Only users with a lower role can be
+/// removed, so co-owners and moderators can't remove co-owners.
+pub async fn kick_user(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    kicked_by: TournamentRole,
+    user_id: i32,
+) -> Result<(), GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    if crate::roles::participant_role(db, tournament_id, user_id).await? >= Some(kicked_by) {
+        Err(GenericError::NotPermitted(
+            "You can only remove users with a lower role than yours",
+        ))?
+    }
+    remove_user(db, tournament_id, user_id).await
+}
+
+/// Removes a participant from a tournament along with their picks and scores.
+///
+/// Their trades are kept and their picks are logged as removals, so the trade log stays complete.
+/// The owner can't be removed.
+/// Should be run inside a transaction.
+pub async fn remove_user(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<(), GenericError> {
+    let tournament = get_tournament(db, tournament_id).await?;
+    if tournament.owner == user_id {
+        Err(GenericError::BadRequest(
+            "The owner cannot leave or be removed from the tournament",
+        ))?
+    }
+    let deleted = UserInFantasyTournament::delete_many()
+        .filter(
+            user_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(user_in_fantasy_tournament::Column::UserId.eq(user_id)),
+        )
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to remove user from tournament"))?;
+    if deleted.rows_affected == 0 {
+        Err(GenericError::NotFound("User not found in tournament"))?
+    }
+    let picks = FantasyPick::find()
+        .filter(
+            fantasy_pick::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(fantasy_pick::Column::User.eq(user_id)),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks of user"))?;
+    remove_picks(db, picks).await?;
+    UserCompetitionScoreInFantasyTournament::delete_many()
+        .filter(
+            user_competition_score_in_fantasy_tournament::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(user_competition_score_in_fantasy_tournament::Column::User.eq(user_id)),
+        )
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to remove scores of user"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+    use entity::sea_orm_active_enums::{
+        FantasyTournamentInvitationStatus, TournamentRole as DbTournamentRole,
+    };
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn only_lower_roles_can_be_kicked() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        let mut users = Vec::new();
+        for role in [DbTournamentRole::CoOwner, DbTournamentRole::Member] {
+            let user = test_db::user(&txn, "member").await;
+            test_db::member(
+                &txn,
+                tournament,
+                user.id,
+                FantasyTournamentInvitationStatus::Accepted,
+                role,
+            )
+            .await;
+            users.push(user.id);
+        }
+        let (co_owner, member) = (users[0], users[1]);
+
+        for kicked in [owner.id, co_owner] {
+            assert!(kick_user(&txn, tournament, TournamentRole::CoOwner, kicked)
+                .await
+                .is_err());
+        }
+        kick_user(&txn, tournament, TournamentRole::CoOwner, member)
+            .await
+            .unwrap();
+        kick_user(&txn, tournament, TournamentRole::Owner, co_owner)
+            .await
+            .unwrap();
+        assert_eq!(
+            crate::roles::participant_role(&txn, tournament, co_owner)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    async fn removals(db: &impl ConnectionTrait, tournament_id: i32, user_id: i32) -> Vec<i32> {
+        PlayerTrade::find()
+            .filter(
+                player_trade::Column::FantasyTournamentId
+                    .eq(tournament_id)
+                    .and(player_trade::Column::User.eq(user_id))
+                    .and(player_trade::Column::IsRemoval.eq(true)),
+            )
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.player - 99_000_000)
+            .sorted()
+            .collect()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn removed_users_picks_are_logged_as_removals() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let member = test_db::user(&txn, "member").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        test_db::member(
+            &txn,
+            tournament,
+            member.id,
+            FantasyTournamentInvitationStatus::Accepted,
+            DbTournamentRole::Member,
+        )
+        .await;
+        for player in [1, 2] {
+            test_db::player(&txn, tournament, player).await;
+            test_db::pick(&txn, tournament, member.id, player, player).await;
+        }
+
+        assert!(remove_user(&txn, tournament, owner.id).await.is_err());
+        remove_user(&txn, tournament, member.id).await.unwrap();
+        assert_eq!(removals(&txn, tournament, member.id).await, [1, 2]);
+        let picks = FantasyPick::find()
+            .filter(fantasy_pick::Column::FantasyTournamentId.eq(tournament))
+            .all(&txn)
+            .await
+            .unwrap();
+        assert!(picks.is_empty());
+        assert!(remove_user(&txn, tournament, member.id).await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn picks_that_no_longer_fit_are_refused_or_trimmed() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        for player in [1, 2] {
+            test_db::player(&txn, tournament, player).await;
+            test_db::pick(&txn, tournament, owner.id, player, player).await;
+        }
+        let settings = |trim_excess| -> UpdateTournamentSettings {
+            serde_json::from_value(serde_json::json!({
+                "max_picks_per_user": 1,
+                "bench_size": 0,
+                "trim_excess": trim_excess,
+            }))
+            .unwrap()
+        };
+
+        assert!(matches!(
+            update_settings(&txn, tournament, settings(false)).await,
+            Err(GenericError::Conflict(_))
+        ));
+        update_settings(&txn, tournament, settings(true)).await.unwrap();
+        assert_eq!(removals(&txn, tournament, owner.id).await, [2]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn deleted_tournaments_hand_player_divisions_to_a_tournament_using_them() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let deleted = test_db::tournament(&txn, owner.id).await;
+        let unrelated = test_db::tournament(&txn, owner.id).await;
+        let picking = test_db::tournament(&txn, owner.id).await;
+        for player in [1, 2] {
+            test_db::player(&txn, deleted, player).await;
+        }
+        test_db::pick(&txn, picking, owner.id, 1, 1).await;
+
+        delete_tournament(&txn, deleted).await.unwrap();
+        let division = |player| PlayerDivisionInFantasyTournament::find_by_id(99_000_000 + player).one(&txn);
+        assert_eq!(division(1).await.unwrap().unwrap().fantasy_tournament_id, picking);
+        assert!(division(2).await.unwrap().is_none());
+        assert!(FantasyTournament::find_by_id(deleted)
+            .one(&txn)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(FantasyTournament::find_by_id(unrelated)
+            .one(&txn)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn archived_tournaments_refuse_changes() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let invited = test_db::user(&txn, "invited").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        let date = chrono::NaiveDate::from_ymd_opt(2030, 7, 1).unwrap();
+        let add_placeholder = || {
+            crate::placeholders::add_placeholder(
+                &txn,
+                tournament,
+                "European Open",
+                date,
+                crate::dto::CompetitionLevel::Major,
+            )
+        };
+        set_archived(&txn, tournament, true).await.unwrap();
+
+        assert!(matches!(add_placeholder().await, Err(GenericError::Conflict(_))));
+        let code = crate::dto::forms::CreateInviteCode {
+            expires_at: None,
+            max_uses: None,
+        };
+        assert!(matches!(
+            crate::invites::create_invite_code(&txn, tournament, owner.id, code).await,
+            Err(GenericError::Conflict(_))
+        ));
+        assert!(matches!(
+            crate::create_invite(&txn, invited.name.clone(), tournament).await,
+            Err(crate::error::InviteError::TournamentArchived)
+        ));
+        assert!(matches!(
+            crate::mutation::insert_competition_in_fantasy(
+                &txn,
+                tournament as u32,
+                1,
+                crate::dto::CompetitionLevel::Major
+            )
+            .await,
+            Err(GenericError::Conflict(_))
+        ));
+
+        set_archived(&txn, tournament, false).await.unwrap();
+        add_placeholder().await.unwrap();
+        assert!(crate::create_invite(&txn, invited.name, tournament).await.is_ok());
+    }
+}
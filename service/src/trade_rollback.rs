@@ -0,0 +1,558 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use entity::{fantasy_pick, player_division_in_fantasy_tournament, player_trade, player_trade_revert};
+use itertools::Itertools;
+use log::error;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, NotSet, QueryFilter, QueryOrder};
+
+use crate::dto::Division;
+use crate::error::GenericError;
+
+/// The change a single `player_trade` row made to a roster.
+///
+/// Every row records both the slot the player ended up in and enough of the previous state
+/// (`other_player`/`other_slot`) to be undone again.
+#[derive(Debug, Clone, PartialEq)]
+enum TradeChange {
+    Add {
+        player: i32,
+        slot: i32,
+    },
+    Move {
+        player: i32,
+        slot: i32,
+        from_slot: i32,
+    },
+    Swap {
+        player: i32,
+        slot: i32,
+        other_player: i32,
+        other_slot: i32,
+    },
+    Replace {
+        player: i32,
+        slot: i32,
+        replaced_player: i32,
+    },
+    Remove {
+        player: i32,
+        slot: i32,
+    },
+}
+
+impl From<&player_trade::Model> for TradeChange {
+    fn from(trade: &player_trade::Model) -> Self {
+        let (player, slot) = (trade.player, trade.slot);
+        if trade.is_removal {
+            Self::Remove { player, slot }
+        } else if let (true, Some(other_player), Some(other_slot)) =
+            (trade.is_local_swap, trade.other_player, trade.other_slot)
+        {
+            Self::Swap {
+                player,
+                slot,
+                other_player,
+                other_slot,
+            }
+        } else if let Some(replaced_player) = trade.other_player {
+            Self::Replace {
+                player,
+                slot,
+                replaced_player,
+            }
+        } else if let Some(from_slot) = trade.other_slot {
+            Self::Move {
+                player,
+                slot,
+                from_slot,
+            }
+        } else {
+            Self::Add { player, slot }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RosterPick {
+    division: Division,
+    slot: i32,
+    player: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Roster(Vec<RosterPick>);
+
+impl Roster {
+    fn player_in_slot(&self, division: Division, slot: i32) -> Option<i32> {
+        self.0
+            .iter()
+            .find(|p| p.division == division && p.slot == slot)
+            .map(|p| p.player)
+    }
+
+    fn slot_of_player(&self, player: i32) -> Option<i32> {
+        self.0.iter().find(|p| p.player == player).map(|p| p.slot)
+    }
+
+    fn remove_player(&mut self, player: i32) {
+        self.0.retain(|p| p.player != player);
+    }
+
+    fn clear_slot(&mut self, division: Division, slot: i32) {
+        self.0.retain(|p| !(p.division == division && p.slot == slot));
+    }
+
+    fn set(&mut self, division: Division, slot: i32, player: i32) {
+        self.remove_player(player);
+        self.clear_slot(division, slot);
+        self.0.push(RosterPick {
+            division,
+            slot,
+            player,
+        });
+    }
+
+    /// Places `player` in `slot` the same way `FantasyPick::insert` does:
+    /// swapping, moving, adding or replacing depending on the current roster.
+    fn put(&mut self, division: Division, player: i32, slot: i32) {
+        match (self.slot_of_player(player), self.player_in_slot(division, slot)) {
+            (Some(previous_slot), Some(other_player)) if previous_slot != slot => {
+                self.set(division, previous_slot, other_player);
+                self.set(division, slot, player);
+            }
+            (Some(_), Some(_)) => {}
+            (_, _) => self.set(division, slot, player),
+        }
+    }
+
+    fn apply(&mut self, division: Division, change: &TradeChange) {
+        match *change {
+            TradeChange::Add { player, slot }
+            | TradeChange::Move { player, slot, .. }
+            | TradeChange::Swap { player, slot, .. }
+            | TradeChange::Replace { player, slot, .. } => self.put(division, player, slot),
+            TradeChange::Remove { player, .. } => self.remove_player(player),
+        }
+    }
+
+    fn undo(&mut self, division: Division, change: &TradeChange) {
+        match *change {
+            TradeChange::Add { player, .. } => self.remove_player(player),
+            TradeChange::Move {
+                player, from_slot, ..
+            } => self.set(division, from_slot, player),
+            TradeChange::Swap {
+                player,
+                slot,
+                other_player,
+                other_slot,
+            } => {
+                self.set(division, other_slot, player);
+                self.set(division, slot, other_player);
+            }
+            TradeChange::Replace {
+                player,
+                slot,
+                replaced_player,
+            } => {
+                self.remove_player(player);
+                self.set(division, slot, replaced_player);
+            }
+            TradeChange::Remove { player, slot } => self.set(division, slot, player),
+        }
+    }
+
+    fn picks_not_in(&self, other: &Roster) -> Vec<RosterPick> {
+        self.0
+            .iter()
+            .filter(|p| !other.0.contains(p))
+            .cloned()
+            .collect_vec()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LoggedTrade {
+    id: i32,
+    division: Division,
+    change: TradeChange,
+}
+
+/// Rebuilds what `current` would have looked like if the trades in `reverted` never happened.
+///
+/// All trades from the earliest reverted one and onwards are undone in reverse order,
+/// after which the trades that are kept get applied again on top of the older roster.
+fn rebuild_roster(current: &Roster, log: &[LoggedTrade], reverted: &HashSet<i32>) -> Roster {
+    let Some(first_reverted) = log.iter().position(|t| reverted.contains(&t.id)) else {
+        return current.clone();
+    };
+    let mut roster = current.clone();
+    for trade in log[first_reverted..].iter().rev() {
+        roster.undo(trade.division, &trade.change);
+    }
+    for trade in log[first_reverted..].iter().filter(|t| !reverted.contains(&t.id)) {
+        roster.apply(trade.division, &trade.change);
+    }
+    roster
+}
+
+/// Reverts a single trade, and rebuilds the roster of the user that made it.
+pub async fn revert_trade(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    trade_id: i32,
+    reverted_by: i32,
+) -> Result<usize, GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let trade = player_trade::Entity::find_by_id(trade_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get trade from database"))?
+        .filter(|t| t.fantasy_tournament_id == tournament_id)
+        .ok_or(GenericError::NotFound("Trade not found in tournament"))?;
+    revert_trades(db, tournament_id, trade.user, vec![trade_id], reverted_by).await
+}
+
+/// Reverts every trade a user made after `after` that has not already been reverted.
+pub async fn revert_trades_after(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+    after: DateTimeWithTimeZone,
+    reverted_by: i32,
+) -> Result<usize, GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let log = get_trade_log(db, tournament_id, user_id).await?;
+    let already_reverted = get_reverted_trade_ids(db, &log).await?;
+    let trade_ids = log
+        .iter()
+        .filter(|t| !t.is_revert && t.timestamp > after && !already_reverted.contains(&t.id))
+        .map(|t| t.id)
+        .collect_vec();
+    if trade_ids.is_empty() {
+        return Ok(0);
+    }
+    revert_trades(db, tournament_id, user_id, trade_ids, reverted_by).await
+}
+
+/// Reverts the given trades of a user in a tournament.
+///
+/// The roster is rebuilt from the trade log, the picks are updated to match it and
+/// compensating trades are logged, so that the trade log itself is never rewritten.
+/// Should be run inside a transaction.
+pub async fn revert_trades(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+    trade_ids: Vec<i32>,
+    reverted_by: i32,
+) -> Result<usize, GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let log = get_trade_log(db, tournament_id, user_id).await?;
+    let already_reverted = get_reverted_trade_ids(db, &log).await?;
+
+    for trade_id in &trade_ids {
+        match log.iter().find(|t| t.id == *trade_id) {
+            None => Err(GenericError::NotFound("Trade not found for user in tournament"))?,
+            Some(t) if t.is_revert => Err(GenericError::BadRequest("Cannot revert a revert"))?,
+            Some(_) if already_reverted.contains(trade_id) => {
+                Err(GenericError::Conflict("Trade has already been reverted"))?
+            }
+            Some(_) => (),
+        }
+    }
+
+    let picks = fantasy_pick::Entity::find()
+        .filter(
+            fantasy_pick::Column::User
+                .eq(user_id)
+                .and(fantasy_pick::Column::FantasyTournamentId.eq(tournament_id)),
+        )
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get picks from database"))?;
+    let current = Roster(
+        picks
+            .iter()
+            .map(|p| RosterPick {
+                division: p.division.clone().into(),
+                slot: p.pick_number,
+                player: p.player,
+            })
+            .collect_vec(),
+    );
+
+    let divisions = get_player_divisions(db, &picks, &log).await?;
+    let logged_trades = log
+        .iter()
+        .map(|t| LoggedTrade {
+            id: t.id,
+            division: divisions.get(&t.player).copied().unwrap_or_default(),
+            change: TradeChange::from(t),
+        })
+        .collect_vec();
+    let reverted = trade_ids.iter().copied().collect::<HashSet<_>>();
+    let rebuilt = rebuild_roster(&current, &logged_trades, &reverted);
+
+    let removed = current.picks_not_in(&rebuilt);
+    let added = rebuilt.picks_not_in(&current);
+    let bench_limit = crate::get_tournament_bench_limit(db, tournament_id).await?;
+    let now = Utc::now().fixed_offset();
+
+    for pick in &removed {
+        fantasy_pick::Entity::delete_many()
+            .filter(
+                fantasy_pick::Column::User
+                    .eq(user_id)
+                    .and(fantasy_pick::Column::FantasyTournamentId.eq(tournament_id))
+                    .and(fantasy_pick::Column::Player.eq(pick.player)),
+            )
+            .exec(db)
+            .await
+            .map_err(|e| {
+                error!("Unable to remove pick while reverting: {:#?}", e);
+                GenericError::UnknownError("Unable to remove pick while reverting")
+            })?;
+        let trade = compensating_trade(user_id, tournament_id, pick, true, now)
+            .insert(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to log reverted trade"))?;
+        crate::webhooks::trade_logged(db, &trade).await?;
+    }
+    for pick in &added {
+        fantasy_pick::ActiveModel {
+            id: NotSet,
+            user: Set(user_id),
+            player: Set(pick.player),
+            fantasy_tournament_id: Set(tournament_id),
+            pick_number: Set(pick.slot),
+            division: Set((&pick.division).into()),
+            benched: Set(pick.slot > bench_limit),
+        }
+        .insert(db)
+        .await
+        .map_err(|e| {
+            error!("Unable to restore pick while reverting: {:#?}", e);
+            GenericError::UnknownError("Unable to restore pick while reverting")
+        })?;
+        let trade = compensating_trade(user_id, tournament_id, pick, false, now)
+            .insert(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to log reverted trade"))?;
+        crate::webhooks::trade_logged(db, &trade).await?;
+    }
+    // Restored players can cost more now than when they were traded away
+    let divisions = added.iter().map(|p| p.division).unique().collect_vec();
+    for division in divisions {
+        crate::salary_cap::assure_within_budget(db, user_id, tournament_id, division).await?;
+    }
+
+    player_trade_revert::Entity::insert_many(trade_ids.iter().map(|id| player_trade_revert::ActiveModel {
+        id: NotSet,
+        trade_id: Set(*id),
+        reverted_by: Set(reverted_by),
+        timestamp: Set(now),
+    }))
+    .exec(db)
+    .await
+    .map_err(|e| {
+        error!("Unable to mark trades as reverted: {:#?}", e);
+        GenericError::UnknownError("Unable to mark trades as reverted")
+    })?;
+
+    if reverted_by != user_id {
+        crate::notifications::notify_trades_reverted(db, tournament_id, user_id).await?;
+    }
+    Ok(trade_ids.len())
+}
+
+fn compensating_trade(
+    user_id: i32,
+    tournament_id: i32,
+    pick: &RosterPick,
+    is_removal: bool,
+    timestamp: DateTimeWithTimeZone,
+) -> player_trade::ActiveModel {
+    player_trade::ActiveModel {
+        id: NotSet,
+        user: Set(user_id),
+        player: Set(pick.player),
+        slot: Set(pick.slot),
+        fantasy_tournament_id: Set(tournament_id),
+        timestamp: Set(timestamp),
+        is_local_swap: Set(false),
+        other_player: Set(None),
+        other_slot: Set(None),
+        is_removal: Set(is_removal),
+        is_revert: Set(true),
+    }
+}
+
+async fn get_trade_log(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    user_id: i32,
+) -> Result<Vec<player_trade::Model>, GenericError> {
+    player_trade::Entity::find()
+        .filter(
+            player_trade::Column::FantasyTournamentId
+                .eq(tournament_id)
+                .and(player_trade::Column::User.eq(user_id)),
+        )
+        .order_by_asc(player_trade::Column::Timestamp)
+        .order_by_asc(player_trade::Column::Id)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get trade log from database"))
+}
+
+async fn get_reverted_trade_ids(
+    db: &impl ConnectionTrait,
+    log: &[player_trade::Model],
+) -> Result<HashSet<i32>, GenericError> {
+    Ok(player_trade_revert::Entity::find()
+        .filter(player_trade_revert::Column::TradeId.is_in(log.iter().map(|t| t.id)))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get reverted trades from database"))?
+        .into_iter()
+        .map(|r| r.trade_id)
+        .collect())
+}
+
+/// Trades don't store the division, so it is taken from the picks or the division the player is known by.
+async fn get_player_divisions(
+    db: &impl ConnectionTrait,
+    picks: &[fantasy_pick::Model],
+    log: &[player_trade::Model],
+) -> Result<HashMap<i32, Division>, GenericError> {
+    let mut divisions: HashMap<i32, Division> = picks
+        .iter()
+        .map(|p| (p.player, p.division.clone().into()))
+        .collect();
+    let unknown = log
+        .iter()
+        .map(|t| t.player)
+        .filter(|p| !divisions.contains_key(p))
+        .unique()
+        .collect_vec();
+    if !unknown.is_empty() {
+        for player_division in player_division_in_fantasy_tournament::Entity::find()
+            .filter(player_division_in_fantasy_tournament::Column::PlayerPdgaNumber.is_in(unknown))
+            .all(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get player divisions from database"))?
+        {
+            divisions.insert(
+                player_division.player_pdga_number,
+                player_division.division.into(),
+            );
+        }
+    }
+    Ok(divisions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(id: i32, change: TradeChange) -> LoggedTrade {
+        LoggedTrade {
+            id,
+            division: Division::MPO,
+            change,
+        }
+    }
+
+    fn roster(picks: &[(i32, i32)]) -> Roster {
+        Roster(
+            picks
+                .iter()
+                .map(|(slot, player)| RosterPick {
+                    division: Division::MPO,
+                    slot: *slot,
+                    player: *player,
+                })
+                .collect(),
+        )
+    }
+
+    fn sorted(roster: Roster) -> Vec<(i32, i32)> {
+        roster
+            .0
+            .into_iter()
+            .map(|p| (p.slot, p.player))
+            .sorted()
+            .collect()
+    }
+
+    #[test]
+    fn reverting_swap_restores_slots() {
+        let log = vec![
+            trade(1, TradeChange::Add { player: 10, slot: 1 }),
+            trade(2, TradeChange::Add { player: 20, slot: 2 }),
+            trade(
+                3,
+                TradeChange::Swap {
+                    player: 20,
+                    slot: 1,
+                    other_player: 10,
+                    other_slot: 2,
+                },
+            ),
+        ];
+        let current = roster(&[(1, 20), (2, 10)]);
+        let rebuilt = rebuild_roster(&current, &log, &HashSet::from([3]));
+        assert_eq!(sorted(rebuilt), vec![(1, 10), (2, 20)]);
+    }
+
+    #[test]
+    fn reverting_add_keeps_later_trades() {
+        let log = vec![
+            trade(1, TradeChange::Add { player: 10, slot: 1 }),
+            trade(2, TradeChange::Add { player: 20, slot: 2 }),
+            trade(
+                3,
+                TradeChange::Move {
+                    player: 10,
+                    slot: 3,
+                    from_slot: 1,
+                },
+            ),
+        ];
+        let current = roster(&[(2, 20), (3, 10)]);
+        let rebuilt = rebuild_roster(&current, &log, &HashSet::from([2]));
+        assert_eq!(sorted(rebuilt), vec![(3, 10)]);
+    }
+
+    #[test]
+    fn reverting_replace_brings_back_player() {
+        let log = vec![trade(
+            7,
+            TradeChange::Replace {
+                player: 30,
+                slot: 1,
+                replaced_player: 10,
+            },
+        )];
+        // Picks made before trades were logged must survive the rebuild
+        let current = roster(&[(1, 30), (2, 40)]);
+        let rebuilt = rebuild_roster(&current, &log, &HashSet::from([7]));
+        assert_eq!(sorted(rebuilt), vec![(1, 10), (2, 40)]);
+    }
+
+    #[test]
+    fn compensating_removal_is_undone_when_reverting_earlier_trade() {
+        let log = vec![
+            trade(1, TradeChange::Add { player: 10, slot: 1 }),
+            trade(2, TradeChange::Add { player: 20, slot: 2 }),
+            trade(3, TradeChange::Remove { player: 20, slot: 2 }),
+        ];
+        let current = roster(&[(1, 10)]);
+        let rebuilt = rebuild_roster(&current, &log, &HashSet::from([1]));
+        assert!(rebuilt.0.is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+use entity::prelude::*;
+use entity::sea_orm_active_enums;
+use entity::*;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket_okapi::JsonSchema;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::dto::{Competition, Division};
+use crate::error::GenericError;
+use crate::export::{RosterRow, StandingRow};
+use crate::roles::TournamentRole;
+
+/// Who can read a tournament
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Visibility {
+    /// Only members can read it, and it can only be joined through an invite
+    Private,
+    /// Only members can read it, others can ask to join when join requests are enabled
+    #[default]
+    MembersOnly,
+    /// Anyone can read it without signing in, and it has a shareable read-only page
+    Public,
+}
+
+impl From<sea_orm_active_enums::TournamentVisibility> for Visibility {
+    fn from(visibility: sea_orm_active_enums::TournamentVisibility) -> Self {
+        match visibility {
+            sea_orm_active_enums::TournamentVisibility::Private => Self::Private,
+            sea_orm_active_enums::TournamentVisibility::MembersOnly => Self::MembersOnly,
+            sea_orm_active_enums::TournamentVisibility::Public => Self::Public,
+        }
+    }
+}
+
+impl From<Visibility> for sea_orm_active_enums::TournamentVisibility {
+    fn from(visibility: Visibility) -> Self {
+        match visibility {
+            Visibility::Private => Self::Private,
+            Visibility::MembersOnly => Self::MembersOnly,
+            Visibility::Public => Self::Public,
+        }
+    }
+}
+
+/// The read-only page of a public tournament, shared through its slug
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct PublicTournament {
+    pub id: i32,
+    pub name: String,
+    pub archived: bool,
+    pub divisions: Vec<Division>,
+    pub competitions: Vec<Competition>,
+    pub standings: Vec<StandingRow>,
+    pub rosters: Vec<RosterRow>,
+}
+
+/// Members, and users that act as one through their role on the server, can always read a tournament.
+pub fn can_view(visibility: Visibility, role: Option<TournamentRole>) -> bool {
+    role.is_some() || visibility == Visibility::Public
+}
+
+/// Makes sure the user, or anyone when signed out, can read the tournament.
+///
+/// # Returns
+///
+/// The role of the user in the tournament
+pub async fn assure_can_view(
+    db: &impl ConnectionTrait,
+    user: Option<&user::Model>,
+    tournament_id: i32,
+) -> Result<Option<TournamentRole>, GenericError> {
+    let tournament = FantasyTournament::find_by_id(tournament_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament from database"))?
+        .ok_or(GenericError::NotFound("Tournament not found"))?;
+    let role = match user {
+        Some(user) => crate::roles::role_in_tournament(db, user, tournament_id).await?,
+        None => None,
+    };
+    if can_view(tournament.visibility.into(), role) {
+        Ok(role)
+    } else {
+        Err(GenericError::NotPermitted("Only members can see this tournament"))
+    }
+}
+
+/// The slug of the public page, only shown while the tournament is public.
+pub fn public_slug(tournament: &fantasy_tournament::Model) -> Option<String> {
+    (Visibility::from(tournament.visibility.clone()) == Visibility::Public)
+        .then(|| tournament.public_slug.clone())
+        .flatten()
+}
+
+/// Slugs are kept when a tournament stops being public, so links shared earlier work again
+/// if it is made public again.
+pub(crate) fn new_slug() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+pub async fn get_public_tournament(
+    db: &impl ConnectionTrait,
+    slug: &str,
+) -> Result<PublicTournament, GenericError> {
+    let tournament = FantasyTournament::find()
+        .filter(fantasy_tournament::Column::PublicSlug.eq(slug))
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournament from database"))?
+        .filter(|t| Visibility::from(t.visibility.clone()) == Visibility::Public)
+        .ok_or(GenericError::NotFound("Tournament not found"))?;
+    Ok(PublicTournament {
+        id: tournament.id,
+        name: tournament.name,
+        archived: tournament.archived_at.is_some(),
+        divisions: crate::get_tournament_divisions(db, tournament.id)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to get divisions from database"))?,
+        competitions: Competition::all_in_fantasy_tournament(db, tournament.id).await?,
+        standings: crate::export::standings(db, tournament.id).await?,
+        rosters: crate::export::rosters(db, tournament.id).await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_tournaments_are_readable_by_everyone() {
+        for visibility in [Visibility::Private, Visibility::MembersOnly, Visibility::Public] {
+            assert!(can_view(visibility, Some(TournamentRole::Spectator)));
+        }
+        assert!(!can_view(Visibility::Private, None));
+        assert!(!can_view(Visibility::MembersOnly, None));
+        assert!(can_view(Visibility::Public, None));
+    }
+
+    #[test]
+    fn slugs_are_url_safe() {
+        let slug = new_slug();
+        assert_eq!(slug.len(), 16);
+        assert!(slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+    }
+}
@@ -0,0 +1,965 @@
+use chrono::{Duration, Utc};
+use entity::prelude::*;
+use entity::sea_orm_active_enums::{
+    CompetitionStatus, WebhookDeliveryStatus as DbWebhookDeliveryStatus, WebhookFormat as DbWebhookFormat,
+};
+use entity::*;
+use hmac::{Hmac, Mac};
+use itertools::Itertools;
+use log::{error, warn};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket_okapi::JsonSchema;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, NotSet, QueryFilter,
+    QueryOrder, QuerySelect,
+};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::dto::forms::CreateWebhook;
+use crate::dto::UserWithScore;
+use crate::error::GenericError;
+use crate::query::get_fantasy_tournament_model;
+
+const MAX_WEBHOOKS_PER_TOURNAMENT: usize = 5;
+const MAX_DELIVERY_ATTEMPTS: i32 = 6;
+/// Delay before the first retry, doubled for every attempt after that.
+const RETRY_BASE_SECONDS: i64 = 30;
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+const DELIVERY_BATCH_SIZE: u64 = 50;
+const DELIVERY_LIST_LIMIT: u64 = 100;
+
+pub const SIGNATURE_HEADER: &str = "X-CTP-Signature";
+pub const EVENT_HEADER: &str = "X-CTP-Event";
+pub const DELIVERY_HEADER: &str = "X-CTP-Delivery";
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+pub enum WebhookFormat {
+    /// The event itself, as JSON.
+    Generic,
+    /// A Discord webhook message with the event as an embed.
+    Discord,
+    /// A Slack incoming webhook message.
+    Slack,
+}
+
+impl From<DbWebhookFormat> for WebhookFormat {
+    fn from(format: DbWebhookFormat) -> Self {
+        match format {
+            DbWebhookFormat::Generic => Self::Generic,
+            DbWebhookFormat::Discord => Self::Discord,
+            DbWebhookFormat::Slack => Self::Slack,
+        }
+    }
+}
+
+impl From<WebhookFormat> for DbWebhookFormat {
+    fn from(format: WebhookFormat) -> Self {
+        match format {
+            WebhookFormat::Generic => Self::Generic,
+            WebhookFormat::Discord => Self::Discord,
+            WebhookFormat::Slack => Self::Slack,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl From<DbWebhookDeliveryStatus> for WebhookDeliveryStatus {
+    fn from(status: DbWebhookDeliveryStatus) -> Self {
+        match status {
+            DbWebhookDeliveryStatus::Pending => Self::Pending,
+            DbWebhookDeliveryStatus::Delivered => Self::Delivered,
+            DbWebhookDeliveryStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub format: WebhookFormat,
+    /// Used to sign the body of every request, see [`SIGNATURE_HEADER`].
+    /// Only returned when the webhook is created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl From<webhook_subscription::Model> for Webhook {
+    fn from(webhook: webhook_subscription::Model) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            format: webhook.format.into(),
+            secret: None,
+            created_at: webhook.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub event: String,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+    pub next_attempt_at: DateTimeWithTimeZone,
+    pub delivered_at: Option<DateTimeWithTimeZone>,
+}
+
+impl From<webhook_delivery::Model> for WebhookDelivery {
+    fn from(delivery: webhook_delivery::Model) -> Self {
+        Self {
+            id: delivery.id,
+            event: delivery.event,
+            status: delivery.status.into(),
+            attempts: delivery.attempts,
+            last_status_code: delivery.last_status_code,
+            last_error: delivery.last_error,
+            created_at: delivery.created_at,
+            next_attempt_at: delivery.next_attempt_at,
+            delivered_at: delivery.delivered_at,
+        }
+    }
+}
+
+/// Something that happened in a tournament, sent to every webhook of it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WebhookEvent {
+    pub event: String,
+    pub fantasy_tournament_id: i32,
+    /// Readable description of the event, used for chat formats.
+    pub summary: String,
+    pub data: Value,
+    pub timestamp: DateTimeWithTimeZone,
+}
+
+impl WebhookEvent {
+    fn new(event: &str, fantasy_tournament_id: i32, summary: String, data: Value) -> Self {
+        Self {
+            event: event.to_string(),
+            fantasy_tournament_id,
+            summary,
+            data,
+            timestamp: Utc::now().fixed_offset(),
+        }
+    }
+
+    /// Builds the request body for a webhook format.
+    fn body(&self, format: WebhookFormat, tournament_name: &str) -> Value {
+        match format {
+            WebhookFormat::Generic => json!(self),
+            WebhookFormat::Discord => json!({
+                "username": "CTP Fantasy",
+                "embeds": [{
+                    "title": tournament_name,
+                    "description": self.summary,
+                    "color": discord_colour(&self.event),
+                    "footer": { "text": self.event },
+                    "timestamp": self.timestamp.to_rfc3339(),
+                }],
+            }),
+            WebhookFormat::Slack => json!({
+                "text": format!("*{}*: {}", tournament_name, self.summary),
+            }),
+        }
+    }
+}
+
+fn discord_colour(event: &str) -> u32 {
+    match event {
+        "pick.changed" => 0x3498db,
+        "competition.started" => 0xf1c40f,
+        "competition.finished" => 0x2ecc71,
+        "standings.changed" => 0x9b59b6,
+        _ => 0x95a5a6,
+    }
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn generate_secret() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+async fn get_webhook(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    webhook_id: i32,
+) -> Result<webhook_subscription::Model, GenericError> {
+    WebhookSubscription::find_by_id(webhook_id)
+        .filter(webhook_subscription::Column::FantasyTournamentId.eq(tournament_id))
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get webhook from database"))?
+        .ok_or(GenericError::NotFound("Webhook not found"))
+}
+
+pub async fn create_webhook(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    created_by: i32,
+    form: CreateWebhook,
+) -> Result<Webhook, GenericError> {
+    crate::tournament_management::assure_not_archived(db, tournament_id).await?;
+    let url = form.url.trim();
+    resolve_public_url(url, &allowed_hosts())
+        .await
+        .map_err(GenericError::BadRequest)?;
+    if get_webhooks(db, tournament_id).await?.len() >= MAX_WEBHOOKS_PER_TOURNAMENT {
+        Err(GenericError::Conflict(
+            "Tournament already has the maximum amount of webhooks",
+        ))?
+    }
+    let webhook = webhook_subscription::ActiveModel {
+        id: NotSet,
+        fantasy_tournament_id: Set(tournament_id),
+        url: Set(url.to_string()),
+        format: Set(form.format.into()),
+        secret: Set(generate_secret()),
+        created_by: Set(created_by),
+        created_at: Set(Utc::now().fixed_offset()),
+    }
+    .insert(db)
+    .await
+    .map_err(|e| {
+        error!("Unable to insert webhook: {:#?}", e);
+        GenericError::UnknownError("Unable to insert webhook")
+    })?;
+    Ok(Webhook {
+        secret: Some(webhook.secret.clone()),
+        ..webhook.into()
+    })
+}
+
+pub async fn get_webhooks(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<Vec<Webhook>, GenericError> {
+    Ok(WebhookSubscription::find()
+        .filter(webhook_subscription::Column::FantasyTournamentId.eq(tournament_id))
+        .order_by_asc(webhook_subscription::Column::Id)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get webhooks from database"))?
+        .into_iter()
+        .map(Webhook::from)
+        .collect_vec())
+}
+
+pub async fn delete_webhook(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    webhook_id: i32,
+) -> Result<(), GenericError> {
+    let webhook = get_webhook(db, tournament_id, webhook_id).await?;
+    webhook_delivery::Entity::delete_many()
+        .filter(webhook_delivery::Column::SubscriptionId.eq(webhook.id))
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete webhook deliveries"))?;
+    WebhookSubscription::delete_by_id(webhook.id)
+        .exec(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to delete webhook"))?;
+    Ok(())
+}
+
+/// Deletes every webhook of a tournament along with their delivery logs.
+pub(crate) async fn delete_webhooks_in_tournament(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+) -> Result<(), GenericError> {
+    for webhook in get_webhooks(db, tournament_id).await? {
+        delete_webhook(db, tournament_id, webhook.id).await?;
+    }
+    Ok(())
+}
+
+pub async fn get_deliveries(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    webhook_id: i32,
+) -> Result<Vec<WebhookDelivery>, GenericError> {
+    let webhook = get_webhook(db, tournament_id, webhook_id).await?;
+    Ok(webhook_delivery::Entity::find()
+        .filter(webhook_delivery::Column::SubscriptionId.eq(webhook.id))
+        .order_by_desc(webhook_delivery::Column::Id)
+        .limit(DELIVERY_LIST_LIMIT)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get webhook deliveries from database"))?
+        .into_iter()
+        .map(WebhookDelivery::from)
+        .collect_vec())
+}
+
+/// Queues an event for every webhook of the tournament.
+///
+/// Deliveries are stored in the same transaction as the change that caused them,
+/// and are sent later by [`deliver_pending_webhooks`].
+pub async fn enqueue(db: &impl ConnectionTrait, event: WebhookEvent) -> Result<(), GenericError> {
+    let webhooks = WebhookSubscription::find()
+        .filter(webhook_subscription::Column::FantasyTournamentId.eq(event.fantasy_tournament_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get webhooks from database"))?;
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+    let now = Utc::now().fixed_offset();
+    let payload = json!(event);
+    webhook_delivery::Entity::insert_many(webhooks.into_iter().map(|webhook| {
+        webhook_delivery::ActiveModel {
+            id: NotSet,
+            subscription_id: Set(webhook.id),
+            event: Set(event.event.clone()),
+            payload: Set(payload.clone()),
+            status: Set(DbWebhookDeliveryStatus::Pending),
+            attempts: Set(0),
+            next_attempt_at: Set(now),
+            last_status_code: Set(None),
+            last_error: Set(None),
+            created_at: Set(now),
+            delivered_at: Set(None),
+        }
+    }))
+    .exec_without_returning(db)
+    .await
+    .map_err(|e| {
+        error!("Unable to queue webhook deliveries: {:#?}", e);
+        GenericError::UnknownError("Unable to queue webhook deliveries")
+    })?;
+    Ok(())
+}
+
+/// Queues a ping to a single webhook, so that owners can check that it is set up correctly.
+pub async fn send_test_event(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    webhook_id: i32,
+) -> Result<(), GenericError> {
+    let webhook = get_webhook(db, tournament_id, webhook_id).await?;
+    let event = WebhookEvent::new(
+        "ping",
+        tournament_id,
+        "Webhook is set up correctly".to_string(),
+        json!({ "webhook_id": webhook.id }),
+    );
+    let now = Utc::now().fixed_offset();
+    webhook_delivery::ActiveModel {
+        id: NotSet,
+        subscription_id: Set(webhook.id),
+        event: Set(event.event.clone()),
+        payload: Set(json!(event)),
+        status: Set(DbWebhookDeliveryStatus::Pending),
+        attempts: Set(0),
+        next_attempt_at: Set(now),
+        last_status_code: Set(None),
+        last_error: Set(None),
+        created_at: Set(now),
+        delivered_at: Set(None),
+    }
+    .insert(db)
+    .await
+    .map_err(|_| GenericError::UnknownError("Unable to queue webhook delivery"))?;
+    Ok(())
+}
+
+/// Queues a `pick.changed` event for a newly logged trade.
+pub async fn trade_logged(
+    db: &impl ConnectionTrait,
+    trade: &player_trade::Model,
+) -> Result<(), GenericError> {
+    let user = User::find_by_id(trade.user)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get user from database"))?
+        .map(|u| u.name)
+        .unwrap_or_else(|| trade.user.to_string());
+    let (action, description) = crate::dto::describe_trade(db, trade).await;
+    let summary = if trade.is_revert {
+        format!("{user}: {description} (reverted by the owner)")
+    } else {
+        format!("{user}: {description}")
+    };
+    enqueue(
+        db,
+        WebhookEvent::new(
+            "pick.changed",
+            trade.fantasy_tournament_id,
+            summary,
+            json!({
+                "trade_id": trade.id,
+                "user_id": trade.user,
+                "user": user,
+                "action": action,
+                "player": trade.player,
+                "slot": trade.slot,
+                "other_player": trade.other_player,
+                "other_slot": trade.other_slot,
+                "is_revert": trade.is_revert,
+            }),
+        ),
+    )
+    .await
+}
+
+/// Queues a `competition.started` or `competition.finished` event in every tournament
+/// the competition is part of. Other status changes are ignored.
+pub async fn competition_status_changed(
+    db: &impl ConnectionTrait,
+    competition_id: i32,
+    from: Option<CompetitionStatus>,
+    to: CompetitionStatus,
+) -> Result<(), GenericError> {
+    if from.as_ref() == Some(&to) {
+        return Ok(());
+    }
+    let event = match to {
+        CompetitionStatus::Running => "competition.started",
+        CompetitionStatus::Finished => "competition.finished",
+        _ => return Ok(()),
+    };
+    let competition = Competition::find_by_id(competition_id)
+        .one(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get competition from database"))?
+        .ok_or(GenericError::NotFound("Competition not found"))?;
+    let summary = if to == CompetitionStatus::Running {
+        format!("{} has started", competition.name)
+    } else {
+        format!("{} has finished, scores are final", competition.name)
+    };
+    let tournaments = CompetitionInFantasyTournament::find()
+        .filter(competition_in_fantasy_tournament::Column::CompetitionId.eq(competition_id))
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get tournaments from database"))?;
+    for tournament in tournaments {
+        enqueue(
+            db,
+            WebhookEvent::new(
+                event,
+                tournament.fantasy_tournament_id,
+                summary.clone(),
+                json!({
+                    "competition_id": competition.id,
+                    "name": competition.name,
+                    "status": format!("{:?}", to),
+                }),
+            ),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Queues a `standings.changed` event if the scores or order of the participants differ.
+pub async fn standings_changed(
+    db: &impl ConnectionTrait,
+    tournament_id: i32,
+    before: &[UserWithScore],
+    after: &[UserWithScore],
+) -> Result<(), GenericError> {
+    let key = |standings: &[UserWithScore]| standings.iter().map(|u| (u.user.id, u.score)).collect_vec();
+    if key(before) == key(after) {
+        return Ok(());
+    }
+    let summary = after
+        .iter()
+        .take(3)
+        .enumerate()
+        .map(|(i, u)| format!("{}. {} ({})", i + 1, u.user.username, u.score))
+        .join(", ");
+    enqueue(
+        db,
+        WebhookEvent::new(
+            "standings.changed",
+            tournament_id,
+            format!("Standings updated: {summary}"),
+            json!({
+                "standings": after
+                    .iter()
+                    .enumerate()
+                    .map(|(i, u)| json!({
+                        "rank": i + 1,
+                        "user_id": u.user.id,
+                        "user": u.user.username,
+                        "score": u.score,
+                    }))
+                    .collect_vec(),
+            }),
+        ),
+    )
+    .await
+}
+
+/// Whether an address is reachable on the public internet. Webhook urls are chosen by tournament
+/// owners, so they must not be able to reach the server itself or the network it runs in.
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // "This network", carrier-grade NAT, benchmarking and reserved ranges
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (18..20).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+                    // Documentation range
+                    || ip.segments()[..2] == [0x2001, 0xdb8])
+            }
+        },
+    }
+}
+
+/// Hosts that webhooks may be sent to even though they are local or use plain http, from the
+/// comma separated `WEBHOOK_ALLOWED_HOSTS` variable. Meant for development and tests, empty by default.
+fn allowed_hosts() -> Vec<String> {
+    std::env::var("WEBHOOK_ALLOWED_HOSTS")
+        .map(|hosts| {
+            hosts
+                .split(',')
+                .map(|host| host.trim().to_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect_vec()
+        })
+        .unwrap_or_default()
+}
+
+/// Checks that a webhook url is https and that every address its host resolves to is public,
+/// unless the host is one of `allowed_hosts`.
+///
+/// # Returns
+///
+/// The resolved addresses, so that deliveries connect to exactly the addresses that were checked
+/// and can't be sent elsewhere by changing the DNS records in between.
+async fn resolve_public_url(
+    url: &str,
+    allowed_hosts: &[String],
+) -> Result<(reqwest::Url, Vec<SocketAddr>), &'static str> {
+    let url = reqwest::Url::parse(url).map_err(|_| "Webhook url must be a valid https url")?;
+    let host = url.host_str().ok_or("Webhook url must be a valid https url")?;
+    let allowed = allowed_hosts.iter().any(|h| h.eq_ignore_ascii_case(host));
+    if !(url.scheme() == "https" || allowed && url.scheme() == "http") {
+        return Err("Webhook url must be a valid https url");
+    }
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addresses = match host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| "Webhook url host could not be resolved")?
+            .collect_vec(),
+    };
+    if addresses.is_empty() {
+        return Err("Webhook url host could not be resolved");
+    }
+    if !allowed && !addresses.iter().all(|address| is_public_address(address.ip())) {
+        return Err("Webhook url must not point to a private, loopback or link-local address");
+    }
+    Ok((url, addresses))
+}
+
+/// A client that can only connect to the checked addresses of the webhook, and doesn't follow
+/// redirects since they could point anywhere.
+async fn delivery_client(url: &str, allowed_hosts: &[String]) -> Result<reqwest::Client, String> {
+    let (url, addresses) = resolve_public_url(url, allowed_hosts)
+        .await
+        .map_err(str::to_string)?;
+    let mut client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+        .redirect(reqwest::redirect::Policy::none());
+    if let Some(domain) = url.domain() {
+        client = client.resolve_to_addrs(domain, &addresses);
+    }
+    client.build().map_err(|e| e.to_string())
+}
+
+/// Result of posting a single delivery, the status code if a response was received.
+type PostResult = Result<u16, (Option<u16>, String)>;
+
+async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    event: &str,
+    id: i32,
+    body: String,
+) -> PostResult {
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, sign(secret, body.as_bytes()))
+        .header(EVENT_HEADER, event)
+        .header(DELIVERY_HEADER, id.to_string())
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| (None, e.to_string()))?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(status.as_u16())
+    } else {
+        Err((Some(status.as_u16()), format!("Endpoint responded with {status}")))
+    }
+}
+
+/// Sends every delivery that is due, retrying failed ones with exponential backoff
+/// until [`MAX_DELIVERY_ATTEMPTS`] is reached.
+pub async fn deliver_pending_webhooks(db: &impl ConnectionTrait) -> Result<(), GenericError> {
+    deliver_due(db, &allowed_hosts()).await
+}
+
+async fn deliver_due(db: &impl ConnectionTrait, allowed_hosts: &[String]) -> Result<(), GenericError> {
+    let due = webhook_delivery::Entity::find()
+        .filter(
+            webhook_delivery::Column::Status
+                .eq(DbWebhookDeliveryStatus::Pending)
+                .and(webhook_delivery::Column::NextAttemptAt.lte(Utc::now().fixed_offset())),
+        )
+        .order_by_asc(webhook_delivery::Column::NextAttemptAt)
+        .limit(DELIVERY_BATCH_SIZE)
+        .find_also_related(WebhookSubscription)
+        .all(db)
+        .await
+        .map_err(|_| GenericError::UnknownError("Unable to get webhook deliveries from database"))?;
+
+    for (delivery, webhook) in due {
+        let Some(webhook) = webhook else { continue };
+        let result = match serde_json::from_value::<WebhookEvent>(delivery.payload.clone()) {
+            Ok(event) => {
+                let tournament_name =
+                    match get_fantasy_tournament_model(db, webhook.fantasy_tournament_id).await {
+                        Ok(tournament) => tournament.map(|t| t.name).unwrap_or_default(),
+                        Err(e) => {
+                            // Left pending, it is picked up again by the next run
+                            error!(
+                                "Unable to get tournament for webhook delivery {}: {:?}",
+                                delivery.id, e
+                            );
+                            continue;
+                        }
+                    };
+                let body = event
+                    .body(webhook.format.clone().into(), &tournament_name)
+                    .to_string();
+                // Checked again for every delivery, the host may resolve elsewhere since it was added
+                match delivery_client(&webhook.url, allowed_hosts).await {
+                    Ok(client) => {
+                        post(
+                            &client,
+                            &webhook.url,
+                            &webhook.secret,
+                            &event.event,
+                            delivery.id,
+                            body,
+                        )
+                        .await
+                    }
+                    Err(e) => Err((None, e)),
+                }
+            }
+            Err(e) => Err((None, format!("Invalid payload: {e}"))),
+        };
+
+        let attempts = delivery.attempts + 1;
+        let mut delivery = delivery.into_active_model();
+        delivery.attempts = Set(attempts);
+        match result {
+            Ok(status) => {
+                delivery.status = Set(DbWebhookDeliveryStatus::Delivered);
+                delivery.last_status_code = Set(Some(status as i32));
+                delivery.last_error = Set(None);
+                delivery.delivered_at = Set(Some(Utc::now().fixed_offset()));
+            }
+            Err((status, message)) => {
+                warn!("Webhook delivery to {} failed: {}", webhook.url, message);
+                delivery.last_status_code = Set(status.map(|s| s as i32));
+                delivery.last_error = Set(Some(message));
+                if attempts >= MAX_DELIVERY_ATTEMPTS {
+                    delivery.status = Set(DbWebhookDeliveryStatus::Failed);
+                } else {
+                    delivery.next_attempt_at = Set(Utc::now().fixed_offset() + retry_delay(attempts));
+                }
+            }
+        }
+        delivery
+            .save(db)
+            .await
+            .map_err(|_| GenericError::UnknownError("Unable to update webhook delivery"))?;
+    }
+    Ok(())
+}
+
+fn retry_delay(attempts: i32) -> Duration {
+    Duration::try_seconds(RETRY_BASE_SECONDS << (attempts - 1).clamp(0, 16)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn event() -> WebhookEvent {
+        WebhookEvent::new(
+            "pick.changed",
+            1,
+            "alice: Added Some Player to slot 1".to_string(),
+            json!({ "trade_id": 1 }),
+        )
+    }
+
+    /// Accepts a single request and answers it with `status`, returning the raw request.
+    async fn sink(status: u16) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|l| {
+                            l.to_lowercase()
+                                .strip_prefix("content-length: ")
+                                .map(str::to_owned)
+                        })
+                        .and_then(|l| l.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            socket
+                .write_all(format!("HTTP/1.1 {status} Status\r\ncontent-length: 0\r\n\r\n").as_bytes())
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_request() {
+        let (url, handle) = sink(204).await;
+        let body = event().body(WebhookFormat::Generic, "League").to_string();
+        let result = post(
+            &reqwest::Client::new(),
+            &url,
+            "secret",
+            "pick.changed",
+            7,
+            body.clone(),
+        )
+        .await;
+        assert_eq!(result, Ok(204));
+
+        let request = handle.await.unwrap();
+        let (head, received) = request.split_once("\r\n\r\n").unwrap();
+        let head = head.to_lowercase();
+        assert_eq!(received, body);
+        assert!(head.contains(&format!(
+            "{}: {}",
+            SIGNATURE_HEADER.to_lowercase(),
+            sign("secret", body.as_bytes())
+        )));
+        assert!(head.contains("x-ctp-event: pick.changed"));
+        assert!(head.contains("x-ctp-delivery: 7"));
+    }
+
+    #[tokio::test]
+    async fn reports_failed_request() {
+        let (url, handle) = sink(500).await;
+        let result = post(
+            &reqwest::Client::new(),
+            &url,
+            "secret",
+            "ping",
+            1,
+            "{}".to_string(),
+        )
+        .await;
+        handle.await.unwrap();
+        assert_eq!(result.unwrap_err().0, Some(500));
+    }
+
+    #[test]
+    fn formats_bodies() {
+        let event = event();
+        let generic = event.body(WebhookFormat::Generic, "League");
+        assert_eq!(serde_json::from_value::<WebhookEvent>(generic).unwrap(), event);
+
+        let discord = event.body(WebhookFormat::Discord, "League");
+        assert_eq!(discord["embeds"][0]["title"], "League");
+        assert_eq!(discord["embeds"][0]["description"], event.summary);
+
+        let slack = event.body(WebhookFormat::Slack, "League");
+        assert_eq!(slack["text"], format!("*League*: {}", event.summary));
+    }
+
+    #[test]
+    fn signature_matches_known_value() {
+        // HMAC-SHA256 test vector from RFC 4231, test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn only_public_addresses_are_allowed() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{ip} should be refused");
+        }
+        for ip in ["1.1.1.1", "93.184.216.34", "2606:4700:4700::1111"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{ip} should be allowed");
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_local_and_plain_http_urls() {
+        for url in [
+            "http://1.1.1.1/hook",
+            "https://127.0.0.1/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]:8000/hook",
+            "https://localhost/hook",
+            "ftp://example.com/hook",
+        ] {
+            assert!(
+                resolve_public_url(url, &[]).await.is_err(),
+                "{url} should be refused"
+            );
+        }
+        let (_, addresses) = resolve_public_url("https://1.1.1.1/hook", &[]).await.unwrap();
+        assert_eq!(addresses, vec!["1.1.1.1:443".parse().unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn allowed_hosts_may_be_local() {
+        let allowed = vec!["127.0.0.1".to_string()];
+        let (_, addresses) = resolve_public_url("http://127.0.0.1:8000/hook", &allowed)
+            .await
+            .unwrap();
+        assert_eq!(addresses, vec!["127.0.0.1:8000".parse().unwrap()]);
+        assert!(resolve_public_url("http://localhost/hook", &allowed)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the database at DEV_DATABASE_URL"]
+    async fn delivers_queued_events() {
+        let txn = test_db::begin().await;
+        let owner = test_db::user(&txn, "owner").await;
+        let tournament = test_db::tournament(&txn, owner.id).await;
+        let (url, handle) = sink(200).await;
+        let allowed = vec!["127.0.0.1".to_string()];
+        let webhook = webhook_subscription::ActiveModel {
+            id: NotSet,
+            fantasy_tournament_id: Set(tournament),
+            url: Set(url),
+            format: Set(DbWebhookFormat::Generic),
+            secret: Set("secret".to_string()),
+            created_by: Set(owner.id),
+            created_at: Set(Utc::now().fixed_offset()),
+        }
+        .insert(&txn)
+        .await
+        .unwrap();
+        assert!(get_webhooks(&txn, tournament).await.unwrap()[0].secret.is_none());
+
+        let event = WebhookEvent::new("ping", tournament, "Test".to_string(), json!({}));
+        enqueue(&txn, event.clone()).await.unwrap();
+        deliver_due(&txn, &allowed).await.unwrap();
+
+        let request = handle.await.unwrap();
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        assert_eq!(serde_json::from_str::<WebhookEvent>(body).unwrap(), event);
+        assert!(head
+            .to_lowercase()
+            .contains(&format!("x-ctp-signature: {}", sign("secret", body.as_bytes()))));
+
+        let delivery = get_deliveries(&txn, tournament, webhook.id)
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(delivery.status, WebhookDeliveryStatus::Delivered);
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.last_status_code, Some(200));
+    }
+
+    #[test]
+    fn retries_back_off() {
+        assert_eq!(retry_delay(1), Duration::try_seconds(30).unwrap());
+        assert_eq!(retry_delay(2), Duration::try_seconds(60).unwrap());
+        assert_eq!(retry_delay(5), Duration::try_seconds(480).unwrap());
+    }
+}
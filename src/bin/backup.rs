@@ -0,0 +1,79 @@
+//! Backs up a fantasy tournament to an archive file, or restores one as a new tournament.
+//!
+//! Usage: `backup export <tournament id> <archive.json>` or `backup restore <archive.json> [name]`
+use dotenvy::dotenv;
+use sea_orm::TransactionTrait;
+use service::backup::{backup_tournament, parse_archive, restore_tournament};
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "Usage: backup export <tournament id> <archive.json> | backup restore <archive.json> [name]";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("export") => {
+            let (Some(tournament_id), Some(path)) =
+                (args.get(2).and_then(|id| id.parse::<i32>().ok()), args.get(3))
+            else {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            };
+            let db = api::get_db().await;
+            let archive = match backup_tournament(&db, tournament_id).await {
+                Ok(archive) => archive,
+                Err(e) => {
+                    eprintln!("Unable to back up tournament: {e:?}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let json = serde_json::to_string_pretty(&archive).expect("archive is always serializable");
+            if let Err(e) = std::fs::write(path, json) {
+                eprintln!("Unable to write {path}: {e}");
+                return ExitCode::FAILURE;
+            }
+            println!("Backed up tournament {tournament_id} to {path}");
+            ExitCode::SUCCESS
+        }
+        Some("restore") => {
+            let Some(path) = args.get(2) else {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            };
+            let archive = match std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|input| parse_archive(&input).map_err(|e| format!("{e:?}")))
+            {
+                Ok(archive) => archive,
+                Err(e) => {
+                    eprintln!("Unable to read {path}: {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let db = api::get_db().await;
+            let restored = match db.begin().await {
+                Ok(txn) => match restore_tournament(&txn, archive, args.get(3).cloned()).await {
+                    Ok(id) => txn.commit().await.map(|_| id).map_err(|e| format!("{e:?}")),
+                    Err(e) => Err(format!("{e:?}")),
+                },
+                Err(e) => Err(format!("{e:?}")),
+            };
+            match restored {
+                Ok(id) => {
+                    println!("Restored as tournament {id}");
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("Unable to restore tournament: {e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
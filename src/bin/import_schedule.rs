@@ -0,0 +1,59 @@
+//! Imports a season schedule into a fantasy tournament from a CSV or JSON file.
+//!
+//! Usage: `import_schedule <tournament id> <schedule.csv|schedule.json>`
+use dotenvy::dotenv;
+use service::schedule_import::{import_schedule, parse_schedule, prepare_schedule, ScheduleFormat};
+use std::process::ExitCode;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
+    let args: Vec<String> = std::env::args().collect();
+    let (Some(tournament_id), Some(path)) = (args.get(1).and_then(|id| id.parse::<i32>().ok()), args.get(2))
+    else {
+        eprintln!("Usage: import_schedule <tournament id> <schedule.csv|schedule.json>");
+        return ExitCode::FAILURE;
+    };
+    let format = if path.ends_with(".csv") {
+        ScheduleFormat::Csv
+    } else {
+        ScheduleFormat::Json
+    };
+    let input = match std::fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("Unable to read {path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let db = api::get_db().await;
+    let report = match parse_schedule(&input, format) {
+        Ok(rows) => match prepare_schedule(&db, tournament_id, rows).await {
+            Ok(prepared) => import_schedule(&db, prepared).await,
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    match report {
+        Ok(report) => {
+            for row in &report.rows {
+                match &row.error {
+                    None => println!("row {}: ok {:?}", row.row, row.entry),
+                    Some(e) => println!("row {}: {:?} {:?}", row.row, e, row.entry),
+                }
+            }
+            if report.imported {
+                println!("Imported {} rows", report.rows.len());
+                ExitCode::SUCCESS
+            } else {
+                println!("Nothing was imported");
+                ExitCode::FAILURE
+            }
+        }
+        Err(e) => {
+            eprintln!("Unable to import schedule: {e:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
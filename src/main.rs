@@ -3,26 +3,46 @@ use dotenvy::dotenv;
 use sea_orm::DatabaseConnection;
 
 use chrono::Days;
-use rocket::error;
+use rocket::{error, warn};
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use service::dto::CompetitionInfo;
+use service::error::GenericError;
+use service::metrics::{time_job, Job};
 use std::time::Duration;
 
 #[rocket::main]
+#[allow(clippy::result_large_err)]
 async fn main() -> Result<(), rocket::Error> {
     dotenv().ok();
     let mut round_update_interval = tokio::time::interval(Duration::from_secs(60));
     let mut player_insert_interval = tokio::time::interval(Duration::from_secs(60 * 10));
+    let mut webhook_delivery_interval = tokio::time::interval(Duration::from_secs(15));
 
     // Start the background task to update the scores of all active competitions
     tokio::spawn(async move {
         let db = api::get_db().await;
         loop {
             round_update_interval.tick().await;
-            if let Err(e) = service::mutation::refresh_user_scores_in_all(&db).await {
-                error!("Unable to refresh global user scores {:#?}", e);
+            match time_job(
+                Job::RefreshScores,
+                service::refresh::refresh_active_competitions(&db),
+            )
+            .await
+            {
+                Ok(summary) if !summary.failed.is_empty() => {
+                    warn!("Unable to refresh competitions {:?}", summary.failed)
+                }
+                Ok(_) => (),
+                Err(e) => error!("Unable to refresh active competitions {:#?}", e),
+            }
+            if let Err(e) = time_job(
+                Job::NotifyExchangeWindows,
+                service::notifications::notify_opened_exchange_windows(&db),
+            )
+            .await
+            {
+                error!("Unable to notify about opened exchange windows {:#?}", e);
             }
-            check_active_rounds(&db).await;
         }
     });
 
@@ -30,35 +50,67 @@ async fn main() -> Result<(), rocket::Error> {
     tokio::spawn(async move {
         let db = api::get_db().await;
         loop {
-            if let Ok(comps) = entity::competition::Entity::find()
-                .filter(
-                    entity::competition::Column::Status
-                        .eq(entity::sea_orm_active_enums::CompetitionStatus::NotStarted),
-                )
-                .all(&db)
-                .await
+            if let Err(e) = time_job(Job::InsertPlayers, insert_upcoming_players(&db)).await {
+                error!("Unable to insert players of upcoming competitions {:#?}", e);
+            }
+            if let Err(e) = time_job(
+                Job::PricePlayers,
+                service::salary_cap::price_upcoming_competitions(&db),
+            )
+            .await
             {
-                for comp in comps {
-                    if chrono::Local::now().naive_local().date()
-                        > comp.start_date.checked_sub_days(Days::new(2)).unwrap()
-                    {
-                        let comp_info = CompetitionInfo::from_web(comp.id as u32).await.unwrap();
-                        comp_info.save_round_scores(&db).await.unwrap();
-                    }
-                }
-            };
+                error!("Unable to price players of upcoming competitions {:#?}", e);
+            }
+            if let Err(e) = time_job(
+                Job::LinkPlaceholders,
+                service::placeholders::link_matching_placeholders(&db),
+            )
+            .await
+            {
+                error!("Unable to link placeholders to competitions {:#?}", e);
+            }
             player_insert_interval.tick().await;
         }
     });
 
+    // Start the background task to send queued webhook deliveries
+    tokio::spawn(async move {
+        let db = api::get_db().await;
+        loop {
+            webhook_delivery_interval.tick().await;
+            if let Err(e) = time_job(
+                Job::DeliverWebhooks,
+                service::webhooks::deliver_pending_webhooks(&db),
+            )
+            .await
+            {
+                error!("Unable to deliver webhooks {:#?}", e);
+            }
+        }
+    });
+
     launch().await.launch().await.unwrap();
 
     Ok(())
 }
-async fn check_active_rounds(db: &DatabaseConnection) {
-    let _ = service::mutation::update_active_competitions(db)
+
+async fn insert_upcoming_players(db: &DatabaseConnection) -> Result<(), GenericError> {
+    if let Ok(comps) = entity::competition::Entity::find()
+        .filter(
+            entity::competition::Column::Status
+                .eq(entity::sea_orm_active_enums::CompetitionStatus::NotStarted),
+        )
+        .all(db)
         .await
-        .map_err(|e| {
-            error!("Unable to update active competitions {:#?}", e);
-        });
+    {
+        for comp in comps {
+            if chrono::Local::now().naive_local().date()
+                > comp.start_date.checked_sub_days(Days::new(2)).unwrap()
+            {
+                let comp_info = CompetitionInfo::from_web(comp.id as u32).await?;
+                comp_info.save_round_scores(db).await?;
+            }
+        }
+    };
+    Ok(())
 }
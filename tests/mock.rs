@@ -118,7 +118,8 @@ mod tests {
         !competitions.is_empty()
     }
 
-    pub async fn add_pick(client: &Client, player: i32, div: Division, slot: u8) -> LocalResponse {
+    #[allow(dead_code)]
+    pub async fn add_pick(client: &Client, player: i32, div: Division, slot: u8) -> LocalResponse<'_> {
         let div = div.to_string().to_uppercase();
 
         client
@@ -142,7 +143,6 @@ mod tests {
     }
 
     use service::dto::{CompetitionLevel, Division};
-    use service::refresh_user_scores_in_all;
 
     #[async_test]
     async fn make_score_test() {
@@ -164,6 +164,8 @@ mod tests {
             avatar: Set(None),
             first_name: Set("Haley".to_string()),
             last_name: Set("King".to_string()),
+            country: Set(None),
+            rating: Set(None),
         }
         .insert(&db)
         .await
@@ -235,18 +237,14 @@ mod tests {
         // Shouldn't be able to switch pick due to above competition just ended (ended goes by when it was checked)
         //assert_eq!(add_pick(&client, 7438, Division::FPO, 3).await.status().code, 403);
 
-        let _ = refresh_user_scores_in_all(&db).await;
-
-        let _ = service::mutation::update_active_competitions(&db).await;
+        let _ = service::refresh::refresh_active_competitions(&db).await;
 
         assert_eq!(amount_of_results(&db).await, 2);
 
         /*let mut pick = pick.into_active_model();
         pick.player = Set(122356);
         pick.save(&db).await.unwrap();
-        let _ = refresh_user_scores_in_all(&db).await;
-
-        let _ = service::mutation::update_active_competitions(&db).await;
+        let _ = service::refresh::refresh_active_competitions(&db).await;
         assert_eq!(amount_of_results(&db).await, 2);
         //assert!(any_user_scores(&db).await);
 